@@ -974,10 +974,28 @@ pub mod functions {
     use super::*;
     use super::types::*;
 
+    // The debug feature checks glGetError after every call and reports failures together with
+    // the call's arguments, which are only formatted on error. The trace feature records every
+    // call while a trace is running. Without either feature this is a plain call.
     macro_rules! func {
         ($fun:ident, $ret:ty, $($name:ident: $typ:ty),*) => {
             #[inline] pub unsafe fn $fun($($name: $typ),*) -> $ret {
-                transmute::<_, extern "system" fn($($typ),*) -> $ret>(storage::$fun.ptr)($($name),*)
+                #[cfg(feature = "trace")]
+                let trace_start = super::trace::begin_call();
+                let result = transmute::<_, extern "system" fn($($typ),*) -> $ret>(storage::$fun.ptr)($($name),*);
+                #[cfg(feature = "trace")]
+                {
+                    if let Some(trace_start) = trace_start {
+                        super::trace::record_call(stringify!($fun), trace_start, &[$((stringify!($name), super::trace::TraceArg::trace_value(&$name))),*]);
+                    }
+                }
+                #[cfg(feature = "debug")]
+                {
+                    if stringify!($fun) != "GetError" {
+                        super::debug::check_errors(stringify!($fun), || vec![$(format!("{}: {:?}", stringify!($name), $name)),*]);
+                    }
+                }
+                result
             }
         }
     }
@@ -1331,6 +1349,645 @@ pub mod functions {
     
 }
 
+#[cfg(feature = "debug")]
+pub mod debug {
+    use std::mem::transmute;
+    use std::sync::Mutex;
+
+    use super::storage;
+    use super::types::*;
+    use super::enumerations::*;
+
+    /// A GL error raised by a call, as reported to the error callback
+    pub struct ErrorReport<'a> {
+        pub function: &'static str,
+        pub arguments: &'a [String],
+        pub error: GLenum,
+    }
+
+    static CALLBACK: Mutex<Option<fn(&ErrorReport)>> = Mutex::new(None);
+
+    /// Replaces the default handler, which prints each error to stderr
+    pub fn set_error_callback(callback: fn(&ErrorReport)) {
+        *CALLBACK.lock().unwrap() = Some(callback);
+    }
+
+    /// Returns the name of a glGetError code, e.g. "GL_INVALID_OPERATION"
+    pub fn error_name(error: GLenum) -> &'static str {
+        match error {
+            NO_ERROR => "GL_NO_ERROR",
+            INVALID_ENUM => "GL_INVALID_ENUM",
+            INVALID_VALUE => "GL_INVALID_VALUE",
+            INVALID_OPERATION => "GL_INVALID_OPERATION",
+            INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+            OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            0x0503 => "GL_STACK_OVERFLOW",
+            0x0504 => "GL_STACK_UNDERFLOW",
+            _ => "unknown GL error",
+        }
+    }
+
+    pub(super) fn check_errors<F: FnOnce() -> Vec<String>>(function: &'static str, arguments: F) {
+        let get_error = unsafe { storage::GetError };
+        if !get_error.is_loaded {
+            return;
+        }
+
+        // Several errors can be queued up, so drain all of them
+        let mut arguments = Some(arguments);
+        let mut formatted: Vec<String> = Vec::new();
+        loop {
+            let error = unsafe { transmute::<_, extern "system" fn() -> GLenum>(get_error.ptr)() };
+            if error == NO_ERROR {
+                return;
+            }
+            if let Some(arguments) = arguments.take() {
+                formatted = arguments();
+            }
+
+            let report = ErrorReport { function, arguments: &formatted, error };
+            let callback = *CALLBACK.lock().unwrap();
+            match callback {
+                Some(callback) => callback(&report),
+                None => eprintln!(
+                    "gl{}({}) raised {} (0x{:04X})",
+                    function,
+                    formatted.join(", "),
+                    error_name(error),
+                    error
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+pub mod trace {
+    //! Records GL calls to a file while a trace is running.
+    //!
+    //! The file holds one JSON object per line. Calls look like
+    //! {"frame":0,"t":1234,"dur":3,"fn":"BindBuffer","args":{"target":34962,"buffer":1}}
+    //! where t is microseconds since the trace started and dur is the call's duration in
+    //! microseconds. Calls that upload memory also carry a "payload" object with the byte count,
+    //! an FNV-1a hash and, when capturing in full, the base64 encoded bytes. Texture uploads
+    //! sourced from a bound pixel unpack buffer read no client memory and have no payload.
+    //! end_frame() writes {"frame_end":0,"t":...}.
+
+    use std::fs::File;
+    use std::io::{self, BufWriter, Write};
+    use std::path::Path;
+    use std::slice;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use super::enumerations::*;
+
+    /// How much of the memory passed to upload calls (BufferData, TexImage2D, ...) is kept
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum PayloadCapture {
+        /// Only the byte count
+        SizeOnly,
+        /// The byte count and a hash, enough to tell whether uploads changed between frames
+        Hash,
+        /// The bytes themselves, which makes traces large but replayable
+        Full,
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum TraceValue {
+        Int(i64),
+        Uint(u64),
+        Float(f32),
+        Double(f64),
+        Pointer(usize),
+    }
+
+    impl TraceValue {
+        fn as_usize(&self) -> usize {
+            match *self {
+                TraceValue::Int(value) => value.max(0) as usize,
+                TraceValue::Uint(value) => value as usize,
+                TraceValue::Float(value) => value.max(0.0) as usize,
+                TraceValue::Double(value) => value.max(0.0) as usize,
+                TraceValue::Pointer(value) => value,
+            }
+        }
+    }
+
+    pub trait TraceArg {
+        fn trace_value(&self) -> TraceValue;
+    }
+
+    macro_rules! trace_arg {
+        ($variant:ident, $as:ty, $($typ:ty),*) => {
+            $(impl TraceArg for $typ {
+                fn trace_value(&self) -> TraceValue { TraceValue::$variant(*self as $as) }
+            })*
+        }
+    }
+
+    trace_arg!(Int, i64, i8, i16, i32, i64, isize);
+    trace_arg!(Uint, u64, u8, u16, u32, u64, usize);
+    trace_arg!(Float, f32, f32);
+    trace_arg!(Double, f64, f64);
+
+    impl<T: ?Sized> TraceArg for *const T {
+        fn trace_value(&self) -> TraceValue { TraceValue::Pointer(*self as *const u8 as usize) }
+    }
+
+    impl<T: ?Sized> TraceArg for *mut T {
+        fn trace_value(&self) -> TraceValue { TraceValue::Pointer(*self as *const u8 as usize) }
+    }
+
+    struct Recorder {
+        writer: BufWriter<File>,
+        start: Instant,
+        frame: u64,
+        payloads: PayloadCapture,
+        unpack_alignment: usize,
+    }
+
+    static RECORDING: AtomicBool = AtomicBool::new(false);
+    static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+    /// Starts recording every GL call to the file at path, replacing any running trace
+    pub fn start(path: &Path, payloads: PayloadCapture) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        *RECORDER.lock().unwrap() = Some(Recorder {
+            writer,
+            start: Instant::now(),
+            frame: 0,
+            payloads,
+            unpack_alignment: 4,
+        });
+        RECORDING.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Stops recording and flushes the file
+    pub fn stop() -> io::Result<()> {
+        RECORDING.store(false, Ordering::Release);
+        match RECORDER.lock().unwrap().take() {
+            Some(mut recorder) => recorder.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording() -> bool {
+        RECORDING.load(Ordering::Acquire)
+    }
+
+    /// Marks the end of a frame, normally right after swapping buffers
+    pub fn end_frame() {
+        if !is_recording() {
+            return;
+        }
+        if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+            let t = recorder.start.elapsed().as_micros();
+            let _ = writeln!(recorder.writer, "{{\"frame_end\":{},\"t\":{}}}", recorder.frame, t);
+            let _ = recorder.writer.flush();
+            recorder.frame += 1;
+        }
+    }
+
+    #[inline]
+    pub(super) fn begin_call() -> Option<Instant> {
+        if is_recording() { Some(Instant::now()) } else { None }
+    }
+
+    pub(super) fn record_call(function: &'static str, call_start: Instant, args: &[(&'static str, TraceValue)]) {
+        let duration = call_start.elapsed().as_micros();
+        let mut guard = RECORDER.lock().unwrap();
+        let recorder = match guard.as_mut() {
+            Some(recorder) => recorder,
+            None => return,
+        };
+
+        if function == "PixelStorei" && args[0].1.as_usize() == UNPACK_ALIGNMENT as usize {
+            recorder.unpack_alignment = args[1].1.as_usize().max(1);
+        }
+
+        let t = call_start.duration_since(recorder.start).as_micros();
+        let mut line = format!("{{\"frame\":{},\"t\":{},\"dur\":{},\"fn\":\"{}\",\"args\":{{", recorder.frame, t, duration, function);
+        for (index, &(name, value)) in args.iter().enumerate() {
+            if index > 0 {
+                line.push(',');
+            }
+            let formatted = match value {
+                TraceValue::Int(value) => value.to_string(),
+                TraceValue::Uint(value) => value.to_string(),
+                TraceValue::Float(value) if value.is_finite() => format!("{:?}", value),
+                TraceValue::Float(value) => format!("\"{}\"", value),
+                TraceValue::Double(value) if value.is_finite() => format!("{:?}", value),
+                TraceValue::Double(value) => format!("\"{}\"", value),
+                TraceValue::Pointer(value) => format!("\"0x{:x}\"", value),
+            };
+            line.push_str(&format!("\"{}\":{}", name, formatted));
+        }
+        line.push('}');
+
+        if let Some((pointer, size)) = payload(function, args, recorder.unpack_alignment) {
+            let bytes = unsafe { slice::from_raw_parts(pointer as *const u8, size) };
+            line.push_str(&format!(",\"payload\":{{\"size\":{}", size));
+            if recorder.payloads != PayloadCapture::SizeOnly {
+                line.push_str(&format!(",\"hash\":\"{:016x}\"", fnv1a(bytes)));
+            }
+            if recorder.payloads == PayloadCapture::Full {
+                line.push_str(&format!(",\"data\":\"{}\"", base64(bytes)));
+            }
+            line.push('}');
+        }
+        line.push('}');
+
+        let _ = writeln!(recorder.writer, "{}", line);
+    }
+
+    /// Finds the client memory read by upload calls, as (pointer, byte count).
+    /// BufferData and BufferSubData always read client memory, whatever buffers are bound.
+    fn payload(function: &str, args: &[(&'static str, TraceValue)], unpack_alignment: usize) -> Option<(usize, usize)> {
+        let value = |index: usize| args[index].1.as_usize();
+        let (pointer, size) = match function {
+            "BufferData" => (value(2), value(1)),
+            "BufferSubData" => (value(3), value(2)),
+            // The pointer is an offset into the bound pixel unpack buffer, which is in GPU memory
+            "TexImage2D" | "TexSubImage2D" if unpack_buffer_bound() => return None,
+            "TexImage2D" => (value(8), image_size(value(3), value(4), value(6), value(7), unpack_alignment)?),
+            "TexSubImage2D" => (value(8), image_size(value(4), value(5), value(6), value(7), unpack_alignment)?),
+            _ => return None,
+        };
+        if pointer == 0 || size == 0 { None } else { Some((pointer, size)) }
+    }
+
+    /// Asks the driver directly, since a traced GetIntegerv would try to record itself while the
+    /// recorder is locked
+    fn unpack_buffer_bound() -> bool {
+        let mut binding: super::types::GLint = 0;
+        unsafe {
+            if !super::storage::GetIntegerv.is_loaded {
+                return false;
+            }
+            let get_integerv = ::std::mem::transmute::<_, extern "system" fn(GLenum_, *mut super::types::GLint)>(super::storage::GetIntegerv.ptr);
+            get_integerv(PIXEL_UNPACK_BUFFER_BINDING, &mut binding);
+        }
+        binding != 0
+    }
+
+    /// Bytes read for a width x height image, with rows padded to the unpack alignment
+    fn image_size(width: usize, height: usize, format: usize, pixel_type: usize, alignment: usize) -> Option<usize> {
+        let components = match format as GLenum_ {
+            RED | RED_INTEGER | DEPTH_COMPONENT | STENCIL_INDEX => 1,
+            RG | RG_INTEGER | DEPTH_STENCIL => 2,
+            RGB | BGR | RGB_INTEGER => 3,
+            RGBA | BGRA | RGBA_INTEGER => 4,
+            _ => return None,
+        };
+        let pixel_size = match pixel_type as GLenum_ {
+            UNSIGNED_BYTE | BYTE => components,
+            UNSIGNED_SHORT | SHORT | HALF_FLOAT => components * 2,
+            UNSIGNED_INT | INT | FLOAT => components * 4,
+            UNSIGNED_INT_24_8 => 4,
+            _ => return None,
+        };
+        let row = (width * pixel_size).div_ceil(alignment) * alignment;
+        Some(row * height)
+    }
+
+    type GLenum_ = super::types::GLenum;
+
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let b0 = chunk[0] as u32;
+            let b1 = *chunk.get(1).unwrap_or(&0) as u32;
+            let b2 = *chunk.get(2).unwrap_or(&0) as u32;
+            let triple = (b0 << 16) | (b1 << 8) | b2;
+            encoded.push(ALPHABET[(triple >> 18) as usize & 63] as char);
+            encoded.push(ALPHABET[(triple >> 12) as usize & 63] as char);
+            encoded.push(if chunk.len() > 1 { ALPHABET[(triple >> 6) as usize & 63] as char } else { '=' });
+            encoded.push(if chunk.len() > 2 { ALPHABET[triple as usize & 63] as char } else { '=' });
+        }
+        encoded
+    }
+}
+
+/// Returned by extension functions when they cannot be called
+#[cfg(any(feature = "khr-debug", feature = "ext-texture-filter-anisotropic", feature = "arb-timer-query"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtensionError {
+    /// The driver does not advertise the extension
+    Unsupported { extension: &'static str },
+    /// The extension is advertised but the driver did not provide this entry point
+    NotLoaded { extension: &'static str, function: &'static str },
+}
+
+#[cfg(any(feature = "khr-debug", feature = "ext-texture-filter-anisotropic", feature = "arb-timer-query"))]
+impl ::std::fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            ExtensionError::Unsupported { extension } => {
+                write!(f, "{} is not supported by the driver", extension)
+            }
+            ExtensionError::NotLoaded { extension, function } => {
+                write!(f, "gl{} from {} was not loaded", function, extension)
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "khr-debug", feature = "ext-texture-filter-anisotropic", feature = "arb-timer-query"))]
+impl ::std::error::Error for ExtensionError {}
+
+/// True if the current context lists the extension (e.g. "GL_KHR_debug")
+#[cfg(any(feature = "khr-debug", feature = "ext-texture-filter-anisotropic", feature = "arb-timer-query"))]
+pub fn has_extension(name: &str) -> bool {
+    unsafe {
+        if !storage::GetIntegerv.is_loaded || !storage::GetStringi.is_loaded {
+            return false;
+        }
+        let mut count: GLint = 0;
+        functions::GetIntegerv(NUM_EXTENSIONS, &mut count);
+        (0..count).any(|index| {
+            let extension = functions::GetStringi(EXTENSIONS, index as GLuint);
+            !extension.is_null() && ::std::ffi::CStr::from_ptr(extension as *const _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+// Defines a checked wrapper that returns an ExtensionError instead of calling a missing function
+#[cfg(any(feature = "khr-debug", feature = "arb-timer-query"))]
+macro_rules! ext_func {
+    ($extension:expr, $fun:ident, $ret:ty, $($name:ident: $typ:ty),*) => {
+        #[allow(non_snake_case)]
+        pub unsafe fn $fun($($name: $typ),*) -> Result<$ret, super::ExtensionError> {
+            if !is_available() {
+                return Err(super::ExtensionError::Unsupported { extension: $extension });
+            }
+            if !storage::$fun.is_loaded {
+                return Err(super::ExtensionError::NotLoaded { extension: $extension, function: stringify!($fun) });
+            }
+            Ok(::std::mem::transmute::<_, extern "system" fn($($typ),*) -> $ret>(storage::$fun.ptr)($($name),*))
+        }
+    }
+}
+
+#[cfg(feature = "khr-debug")]
+pub mod khr_debug {
+    //! GL_KHR_debug: driver messages through a callback, debug groups and object labels
+
+    #![allow(non_upper_case_globals)]
+
+    use std::os::raw::{c_uint, c_void};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::types::*;
+    use super::FnPtr;
+
+    pub const EXTENSION: &str = "GL_KHR_debug";
+
+    pub const DEBUG_OUTPUT: c_uint = 0x92E0;
+    pub const DEBUG_OUTPUT_SYNCHRONOUS: c_uint = 0x8242;
+    pub const CONTEXT_FLAG_DEBUG_BIT: c_uint = 0x00000002;
+    pub const MAX_DEBUG_MESSAGE_LENGTH: c_uint = 0x9143;
+    pub const MAX_DEBUG_LOGGED_MESSAGES: c_uint = 0x9144;
+    pub const MAX_DEBUG_GROUP_STACK_DEPTH: c_uint = 0x826C;
+    pub const MAX_LABEL_LENGTH: c_uint = 0x82E8;
+    pub const DONT_CARE: c_uint = 0x1100;
+
+    /// Where a debug message came from
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum DebugSource {
+        Api = 0x8246,
+        WindowSystem = 0x8247,
+        ShaderCompiler = 0x8248,
+        ThirdParty = 0x8249,
+        Application = 0x824A,
+        Other = 0x824B,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum DebugType {
+        Error = 0x824C,
+        DeprecatedBehavior = 0x824D,
+        UndefinedBehavior = 0x824E,
+        Portability = 0x824F,
+        Performance = 0x8250,
+        Other = 0x8251,
+        Marker = 0x8268,
+        PushGroup = 0x8269,
+        PopGroup = 0x826A,
+    }
+
+    /// Ordered from least to most severe. The order is by rank(), not by the GL values, which
+    /// run the other way for Low, Medium and High.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DebugSeverity {
+        Notification = 0x826B,
+        Low = 0x9148,
+        Medium = 0x9147,
+        High = 0x9146,
+    }
+
+    /// The kind of object an ObjectLabel call names
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ObjectIdentifier {
+        Buffer = 0x82E0,
+        Shader = 0x82E1,
+        Program = 0x82E2,
+        VertexArray = 0x8074,
+        Query = 0x82E3,
+        Sampler = 0x82E6,
+        Texture = 0x1702,
+        Renderbuffer = 0x8D41,
+        Framebuffer = 0x8D40,
+        TransformFeedback = 0x8E22,
+    }
+
+    impl DebugSource {
+        pub fn from_gl(value: GLenum) -> Option<Self> {
+            [Self::Api, Self::WindowSystem, Self::ShaderCompiler, Self::ThirdParty, Self::Application, Self::Other]
+                .iter().copied().find(|source| *source as GLenum == value)
+        }
+    }
+
+    impl DebugType {
+        pub fn from_gl(value: GLenum) -> Option<Self> {
+            [Self::Error, Self::DeprecatedBehavior, Self::UndefinedBehavior, Self::Portability, Self::Performance,
+             Self::Other, Self::Marker, Self::PushGroup, Self::PopGroup]
+                .iter().copied().find(|type_| *type_ as GLenum == value)
+        }
+    }
+
+    impl DebugSeverity {
+        pub fn from_gl(value: GLenum) -> Option<Self> {
+            [Self::Notification, Self::Low, Self::Medium, Self::High]
+                .iter().copied().find(|severity| *severity as GLenum == value)
+        }
+
+        /// 0 for Notification up to 3 for High
+        pub fn rank(self) -> u8 {
+            match self {
+                Self::Notification => 0,
+                Self::Low => 1,
+                Self::Medium => 2,
+                Self::High => 3,
+            }
+        }
+    }
+
+    impl PartialOrd for DebugSeverity {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for DebugSeverity {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.rank().cmp(&other.rank())
+        }
+    }
+
+    static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+    /// True once load() found the extension in the context's extension list
+    pub fn is_available() -> bool {
+        AVAILABLE.load(Ordering::Relaxed)
+    }
+
+    ext_func!(EXTENSION, DebugMessageCallback, (), callback: Option<GLDEBUGPROC>, userParam: *const c_void);
+    ext_func!(EXTENSION, DebugMessageControl, (), source: GLenum, type_: GLenum, severity: GLenum, count: GLsizei, ids: *const GLuint, enabled: GLboolean);
+    ext_func!(EXTENSION, DebugMessageInsert, (), source: GLenum, type_: GLenum, id: GLuint, severity: GLenum, length: GLsizei, buf: *const GLchar);
+    ext_func!(EXTENSION, GetDebugMessageLog, GLuint, count: GLuint, bufSize: GLsizei, sources: *mut GLenum, types: *mut GLenum, ids: *mut GLuint, severities: *mut GLenum, lengths: *mut GLsizei, messageLog: *mut GLchar);
+    ext_func!(EXTENSION, PushDebugGroup, (), source: GLenum, id: GLuint, length: GLsizei, message: *const GLchar);
+    ext_func!(EXTENSION, PopDebugGroup, (),);
+    ext_func!(EXTENSION, ObjectLabel, (), identifier: GLenum, name: GLuint, length: GLsizei, label: *const GLchar);
+    ext_func!(EXTENSION, GetObjectLabel, (), identifier: GLenum, name: GLuint, bufSize: GLsizei, length: *mut GLsizei, label: *mut GLchar);
+
+    mod storage {
+        #![allow(non_snake_case, non_upper_case_globals)]
+
+        use super::super::FnPtr;
+        use std::os::raw::c_void;
+
+        pub static mut DebugMessageCallback: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut DebugMessageControl: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut DebugMessageInsert: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut GetDebugMessageLog: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut PushDebugGroup: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut PopDebugGroup: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut ObjectLabel: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+        pub static mut GetObjectLabel: FnPtr = FnPtr { ptr: FnPtr::not_initialized as *const c_void, is_loaded: false };
+    }
+
+    pub(super) fn load<F>(loadfn: &mut F) where F: FnMut(&'static str) -> *const c_void {
+        unsafe {
+            storage::DebugMessageCallback = FnPtr::new(loadfn("glDebugMessageCallback"));
+            storage::DebugMessageControl = FnPtr::new(loadfn("glDebugMessageControl"));
+            storage::DebugMessageInsert = FnPtr::new(loadfn("glDebugMessageInsert"));
+            storage::GetDebugMessageLog = FnPtr::new(loadfn("glGetDebugMessageLog"));
+            storage::PushDebugGroup = FnPtr::new(loadfn("glPushDebugGroup"));
+            storage::PopDebugGroup = FnPtr::new(loadfn("glPopDebugGroup"));
+            storage::ObjectLabel = FnPtr::new(loadfn("glObjectLabel"));
+            storage::GetObjectLabel = FnPtr::new(loadfn("glGetObjectLabel"));
+        }
+        AVAILABLE.store(super::has_extension(EXTENSION), Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "ext-texture-filter-anisotropic")]
+pub mod ext_texture_filter_anisotropic {
+    //! GL_EXT_texture_filter_anisotropic: sharper textures at grazing angles.
+    //! The extension only adds a texture parameter and a limit, no functions.
+
+    use std::os::raw::c_uint;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::types::*;
+    use super::ExtensionError;
+
+    pub const EXTENSION: &str = "GL_EXT_texture_filter_anisotropic";
+
+    pub const TEXTURE_MAX_ANISOTROPY_EXT: c_uint = 0x84FE;
+    pub const MAX_TEXTURE_MAX_ANISOTROPY_EXT: c_uint = 0x84FF;
+
+    static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+    pub fn is_available() -> bool {
+        AVAILABLE.load(Ordering::Relaxed)
+    }
+
+    /// The largest anisotropy the driver accepts, at least 2.0 when supported
+    pub fn max_anisotropy() -> Result<GLfloat, ExtensionError> {
+        if !is_available() {
+            return Err(ExtensionError::Unsupported { extension: EXTENSION });
+        }
+        let mut value: GLfloat = 1.0;
+        unsafe { super::functions::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut value) };
+        Ok(value)
+    }
+
+    /// Sets the anisotropy of the texture bound to target, clamped to what the driver allows
+    pub unsafe fn set_anisotropy(target: GLenum, anisotropy: GLfloat) -> Result<(), ExtensionError> {
+        let max = max_anisotropy()?;
+        super::functions::TexParameterf(target, TEXTURE_MAX_ANISOTROPY_EXT, anisotropy.max(1.0).min(max));
+        Ok(())
+    }
+
+    pub(super) fn load() {
+        AVAILABLE.store(super::has_extension(EXTENSION), Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "arb-timer-query")]
+pub mod arb_timer_query {
+    //! GL_ARB_timer_query: GPU timestamps and elapsed time queries.
+    //! The entry points are part of core 3.3, so availability only depends on the driver
+    //! providing them.
+
+    use std::os::raw::c_uint;
+
+    use super::types::*;
+    use super::storage;
+
+    pub const EXTENSION: &str = "GL_ARB_timer_query";
+
+    pub const TIME_ELAPSED: c_uint = super::enumerations::TIME_ELAPSED;
+    pub const TIMESTAMP: c_uint = super::enumerations::TIMESTAMP;
+    pub const QUERY_RESULT: c_uint = super::enumerations::QUERY_RESULT;
+    pub const QUERY_RESULT_AVAILABLE: c_uint = super::enumerations::QUERY_RESULT_AVAILABLE;
+
+    /// What a timer query measures
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum TimerTarget {
+        /// Nanoseconds between BeginQuery and EndQuery
+        TimeElapsed = 0x88BF,
+        /// The GPU time in nanoseconds when all previous commands completed (QueryCounter)
+        Timestamp = 0x8E28,
+    }
+
+    pub fn is_available() -> bool {
+        unsafe {
+            storage::QueryCounter.is_loaded
+                && storage::GetQueryObjecti64v.is_loaded
+                && storage::GetQueryObjectui64v.is_loaded
+        }
+    }
+
+    ext_func!(EXTENSION, QueryCounter, (), id: GLuint, target: GLenum);
+    ext_func!(EXTENSION, GetQueryObjecti64v, (), id: GLuint, pname: GLenum, params: *mut GLint64);
+    ext_func!(EXTENSION, GetQueryObjectui64v, (), id: GLuint, pname: GLenum, params: *mut GLuint64);
+}
+
 mod storage {
     #![allow(non_snake_case, non_upper_case_globals)]
 
@@ -1689,353 +2346,390 @@ mod storage {
      store!(WaitSync);
 }
 
-pub fn load<F>(mut loadfn: F) where F: FnMut(&'static str) -> *const c_void {
+/// The outcome of load(): which entry points the driver provided and which it did not.
+/// Calling a missing function panics with "gl: function not initialized".
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+    pub loaded: Vec<&'static str>,
+    pub missing: Vec<&'static str>,
+}
+
+impl LoadReport {
+    /// True when every function was found
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.loaded.iter().any(|loaded| *loaded == name)
+    }
+}
+
+pub fn load<F>(mut loadfn: F) -> LoadReport where F: FnMut(&'static str) -> *const c_void {
+    let mut report = LoadReport::default();
+    let mut record = |name: &'static str| {
+        let ptr = loadfn(name);
+        if ptr.is_null() {
+            report.missing.push(name);
+        } else {
+            report.loaded.push(name);
+        }
+        ptr
+    };
     unsafe {
-         storage::ActiveTexture.set_ptr(loadfn("glActiveTexture"));
-         storage::AttachShader.set_ptr(loadfn("glAttachShader"));
-         storage::BeginConditionalRender.set_ptr(loadfn("glBeginConditionalRender"));
-         storage::BeginQuery.set_ptr(loadfn("glBeginQuery"));
-         storage::BeginTransformFeedback.set_ptr(loadfn("glBeginTransformFeedback"));
-         storage::BindAttribLocation.set_ptr(loadfn("glBindAttribLocation"));
-         storage::BindBuffer.set_ptr(loadfn("glBindBuffer"));
-         storage::BindBufferBase.set_ptr(loadfn("glBindBufferBase"));
-         storage::BindBufferRange.set_ptr(loadfn("glBindBufferRange"));
-         storage::BindFragDataLocation.set_ptr(loadfn("glBindFragDataLocation"));
-         storage::BindFragDataLocationIndexed.set_ptr(loadfn("glBindFragDataLocationIndexed"));
-         storage::BindFramebuffer.set_ptr(loadfn("glBindFramebuffer"));
-         storage::BindRenderbuffer.set_ptr(loadfn("glBindRenderbuffer"));
-         storage::BindSampler.set_ptr(loadfn("glBindSampler"));
-         storage::BindTexture.set_ptr(loadfn("glBindTexture"));
-         storage::BindVertexArray.set_ptr(loadfn("glBindVertexArray"));
-         storage::BlendColor.set_ptr(loadfn("glBlendColor"));
-         storage::BlendEquation.set_ptr(loadfn("glBlendEquation"));
-         storage::BlendEquationSeparate.set_ptr(loadfn("glBlendEquationSeparate"));
-         storage::BlendFunc.set_ptr(loadfn("glBlendFunc"));
-         storage::BlendFuncSeparate.set_ptr(loadfn("glBlendFuncSeparate"));
-         storage::BlitFramebuffer.set_ptr(loadfn("glBlitFramebuffer"));
-         storage::BufferData.set_ptr(loadfn("glBufferData"));
-         storage::BufferSubData.set_ptr(loadfn("glBufferSubData"));
-         storage::CheckFramebufferStatus.set_ptr(loadfn("glCheckFramebufferStatus"));
-         storage::ClampColor.set_ptr(loadfn("glClampColor"));
-         storage::Clear.set_ptr(loadfn("glClear"));
-         storage::ClearBufferfi.set_ptr(loadfn("glClearBufferfi"));
-         storage::ClearBufferfv.set_ptr(loadfn("glClearBufferfv"));
-         storage::ClearBufferiv.set_ptr(loadfn("glClearBufferiv"));
-         storage::ClearBufferuiv.set_ptr(loadfn("glClearBufferuiv"));
-         storage::ClearColor.set_ptr(loadfn("glClearColor"));
-         storage::ClearDepth.set_ptr(loadfn("glClearDepth"));
-         storage::ClearStencil.set_ptr(loadfn("glClearStencil"));
-         storage::ClientWaitSync.set_ptr(loadfn("glClientWaitSync"));
-         storage::ColorMask.set_ptr(loadfn("glColorMask"));
-         storage::ColorMaski.set_ptr(loadfn("glColorMaski"));
-         storage::CompileShader.set_ptr(loadfn("glCompileShader"));
-         storage::CompressedTexImage1D.set_ptr(loadfn("glCompressedTexImage1D"));
-         storage::CompressedTexImage2D.set_ptr(loadfn("glCompressedTexImage2D"));
-         storage::CompressedTexImage3D.set_ptr(loadfn("glCompressedTexImage3D"));
-         storage::CompressedTexSubImage1D.set_ptr(loadfn("glCompressedTexSubImage1D"));
-         storage::CompressedTexSubImage2D.set_ptr(loadfn("glCompressedTexSubImage2D"));
-         storage::CompressedTexSubImage3D.set_ptr(loadfn("glCompressedTexSubImage3D"));
-         storage::CopyBufferSubData.set_ptr(loadfn("glCopyBufferSubData"));
-         storage::CopyTexImage1D.set_ptr(loadfn("glCopyTexImage1D"));
-         storage::CopyTexImage2D.set_ptr(loadfn("glCopyTexImage2D"));
-         storage::CopyTexSubImage1D.set_ptr(loadfn("glCopyTexSubImage1D"));
-         storage::CopyTexSubImage2D.set_ptr(loadfn("glCopyTexSubImage2D"));
-         storage::CopyTexSubImage3D.set_ptr(loadfn("glCopyTexSubImage3D"));
-         storage::CreateProgram.set_ptr(loadfn("glCreateProgram"));
-         storage::CreateShader.set_ptr(loadfn("glCreateShader"));
-         storage::CullFace.set_ptr(loadfn("glCullFace"));
-         storage::DeleteBuffers.set_ptr(loadfn("glDeleteBuffers"));
-         storage::DeleteFramebuffers.set_ptr(loadfn("glDeleteFramebuffers"));
-         storage::DeleteProgram.set_ptr(loadfn("glDeleteProgram"));
-         storage::DeleteQueries.set_ptr(loadfn("glDeleteQueries"));
-         storage::DeleteRenderbuffers.set_ptr(loadfn("glDeleteRenderbuffers"));
-         storage::DeleteSamplers.set_ptr(loadfn("glDeleteSamplers"));
-         storage::DeleteShader.set_ptr(loadfn("glDeleteShader"));
-         storage::DeleteSync.set_ptr(loadfn("glDeleteSync"));
-         storage::DeleteTextures.set_ptr(loadfn("glDeleteTextures"));
-         storage::DeleteVertexArrays.set_ptr(loadfn("glDeleteVertexArrays"));
-         storage::DepthFunc.set_ptr(loadfn("glDepthFunc"));
-         storage::DepthMask.set_ptr(loadfn("glDepthMask"));
-         storage::DepthRange.set_ptr(loadfn("glDepthRange"));
-         storage::DetachShader.set_ptr(loadfn("glDetachShader"));
-         storage::Disable.set_ptr(loadfn("glDisable"));
-         storage::DisableVertexAttribArray.set_ptr(loadfn("glDisableVertexAttribArray"));
-         storage::Disablei.set_ptr(loadfn("glDisablei"));
-         storage::DrawArrays.set_ptr(loadfn("glDrawArrays"));
-         storage::DrawArraysInstanced.set_ptr(loadfn("glDrawArraysInstanced"));
-         storage::DrawBuffer.set_ptr(loadfn("glDrawBuffer"));
-         storage::DrawBuffers.set_ptr(loadfn("glDrawBuffers"));
-         storage::DrawElements.set_ptr(loadfn("glDrawElements"));
-         storage::DrawElementsBaseVertex.set_ptr(loadfn("glDrawElementsBaseVertex"));
-         storage::DrawElementsInstanced.set_ptr(loadfn("glDrawElementsInstanced"));
-         storage::DrawElementsInstancedBaseVertex.set_ptr(loadfn("glDrawElementsInstancedBaseVertex"));
-         storage::DrawRangeElements.set_ptr(loadfn("glDrawRangeElements"));
-         storage::DrawRangeElementsBaseVertex.set_ptr(loadfn("glDrawRangeElementsBaseVertex"));
-         storage::Enable.set_ptr(loadfn("glEnable"));
-         storage::EnableVertexAttribArray.set_ptr(loadfn("glEnableVertexAttribArray"));
-         storage::Enablei.set_ptr(loadfn("glEnablei"));
-         storage::EndConditionalRender.set_ptr(loadfn("glEndConditionalRender"));
-         storage::EndQuery.set_ptr(loadfn("glEndQuery"));
-         storage::EndTransformFeedback.set_ptr(loadfn("glEndTransformFeedback"));
-         storage::FenceSync.set_ptr(loadfn("glFenceSync"));
-         storage::Finish.set_ptr(loadfn("glFinish"));
-         storage::Flush.set_ptr(loadfn("glFlush"));
-         storage::FlushMappedBufferRange.set_ptr(loadfn("glFlushMappedBufferRange"));
-         storage::FramebufferRenderbuffer.set_ptr(loadfn("glFramebufferRenderbuffer"));
-         storage::FramebufferTexture.set_ptr(loadfn("glFramebufferTexture"));
-         storage::FramebufferTexture1D.set_ptr(loadfn("glFramebufferTexture1D"));
-         storage::FramebufferTexture2D.set_ptr(loadfn("glFramebufferTexture2D"));
-         storage::FramebufferTexture3D.set_ptr(loadfn("glFramebufferTexture3D"));
-         storage::FramebufferTextureLayer.set_ptr(loadfn("glFramebufferTextureLayer"));
-         storage::FrontFace.set_ptr(loadfn("glFrontFace"));
-         storage::GenBuffers.set_ptr(loadfn("glGenBuffers"));
-         storage::GenFramebuffers.set_ptr(loadfn("glGenFramebuffers"));
-         storage::GenQueries.set_ptr(loadfn("glGenQueries"));
-         storage::GenRenderbuffers.set_ptr(loadfn("glGenRenderbuffers"));
-         storage::GenSamplers.set_ptr(loadfn("glGenSamplers"));
-         storage::GenTextures.set_ptr(loadfn("glGenTextures"));
-         storage::GenVertexArrays.set_ptr(loadfn("glGenVertexArrays"));
-         storage::GenerateMipmap.set_ptr(loadfn("glGenerateMipmap"));
-         storage::GetActiveAttrib.set_ptr(loadfn("glGetActiveAttrib"));
-         storage::GetActiveUniform.set_ptr(loadfn("glGetActiveUniform"));
-         storage::GetActiveUniformBlockName.set_ptr(loadfn("glGetActiveUniformBlockName"));
-         storage::GetActiveUniformBlockiv.set_ptr(loadfn("glGetActiveUniformBlockiv"));
-         storage::GetActiveUniformName.set_ptr(loadfn("glGetActiveUniformName"));
-         storage::GetActiveUniformsiv.set_ptr(loadfn("glGetActiveUniformsiv"));
-         storage::GetAttachedShaders.set_ptr(loadfn("glGetAttachedShaders"));
-         storage::GetAttribLocation.set_ptr(loadfn("glGetAttribLocation"));
-         storage::GetBooleani_v.set_ptr(loadfn("glGetBooleani_v"));
-         storage::GetBooleanv.set_ptr(loadfn("glGetBooleanv"));
-         storage::GetBufferParameteri64v.set_ptr(loadfn("glGetBufferParameteri64v"));
-         storage::GetBufferParameteriv.set_ptr(loadfn("glGetBufferParameteriv"));
-         storage::GetBufferPointerv.set_ptr(loadfn("glGetBufferPointerv"));
-         storage::GetBufferSubData.set_ptr(loadfn("glGetBufferSubData"));
-         storage::GetCompressedTexImage.set_ptr(loadfn("glGetCompressedTexImage"));
-         storage::GetDoublev.set_ptr(loadfn("glGetDoublev"));
-         storage::GetError.set_ptr(loadfn("glGetError"));
-         storage::GetFloatv.set_ptr(loadfn("glGetFloatv"));
-         storage::GetFragDataIndex.set_ptr(loadfn("glGetFragDataIndex"));
-         storage::GetFragDataLocation.set_ptr(loadfn("glGetFragDataLocation"));
-         storage::GetFramebufferAttachmentParameteriv.set_ptr(loadfn("glGetFramebufferAttachmentParameteriv"));
-         storage::GetInteger64i_v.set_ptr(loadfn("glGetInteger64i_v"));
-         storage::GetInteger64v.set_ptr(loadfn("glGetInteger64v"));
-         storage::GetIntegeri_v.set_ptr(loadfn("glGetIntegeri_v"));
-         storage::GetIntegerv.set_ptr(loadfn("glGetIntegerv"));
-         storage::GetMultisamplefv.set_ptr(loadfn("glGetMultisamplefv"));
-         storage::GetProgramInfoLog.set_ptr(loadfn("glGetProgramInfoLog"));
-         storage::GetProgramiv.set_ptr(loadfn("glGetProgramiv"));
-         storage::GetQueryObjecti64v.set_ptr(loadfn("glGetQueryObjecti64v"));
-         storage::GetQueryObjectiv.set_ptr(loadfn("glGetQueryObjectiv"));
-         storage::GetQueryObjectui64v.set_ptr(loadfn("glGetQueryObjectui64v"));
-         storage::GetQueryObjectuiv.set_ptr(loadfn("glGetQueryObjectuiv"));
-         storage::GetQueryiv.set_ptr(loadfn("glGetQueryiv"));
-         storage::GetRenderbufferParameteriv.set_ptr(loadfn("glGetRenderbufferParameteriv"));
-         storage::GetSamplerParameterIiv.set_ptr(loadfn("glGetSamplerParameterIiv"));
-         storage::GetSamplerParameterIuiv.set_ptr(loadfn("glGetSamplerParameterIuiv"));
-         storage::GetSamplerParameterfv.set_ptr(loadfn("glGetSamplerParameterfv"));
-         storage::GetSamplerParameteriv.set_ptr(loadfn("glGetSamplerParameteriv"));
-         storage::GetShaderInfoLog.set_ptr(loadfn("glGetShaderInfoLog"));
-         storage::GetShaderSource.set_ptr(loadfn("glGetShaderSource"));
-         storage::GetShaderiv.set_ptr(loadfn("glGetShaderiv"));
-         storage::GetString.set_ptr(loadfn("glGetString"));
-         storage::GetStringi.set_ptr(loadfn("glGetStringi"));
-         storage::GetSynciv.set_ptr(loadfn("glGetSynciv"));
-         storage::GetTexImage.set_ptr(loadfn("glGetTexImage"));
-         storage::GetTexLevelParameterfv.set_ptr(loadfn("glGetTexLevelParameterfv"));
-         storage::GetTexLevelParameteriv.set_ptr(loadfn("glGetTexLevelParameteriv"));
-         storage::GetTexParameterIiv.set_ptr(loadfn("glGetTexParameterIiv"));
-         storage::GetTexParameterIuiv.set_ptr(loadfn("glGetTexParameterIuiv"));
-         storage::GetTexParameterfv.set_ptr(loadfn("glGetTexParameterfv"));
-         storage::GetTexParameteriv.set_ptr(loadfn("glGetTexParameteriv"));
-         storage::GetTransformFeedbackVarying.set_ptr(loadfn("glGetTransformFeedbackVarying"));
-         storage::GetUniformBlockIndex.set_ptr(loadfn("glGetUniformBlockIndex"));
-         storage::GetUniformIndices.set_ptr(loadfn("glGetUniformIndices"));
-         storage::GetUniformLocation.set_ptr(loadfn("glGetUniformLocation"));
-         storage::GetUniformfv.set_ptr(loadfn("glGetUniformfv"));
-         storage::GetUniformiv.set_ptr(loadfn("glGetUniformiv"));
-         storage::GetUniformuiv.set_ptr(loadfn("glGetUniformuiv"));
-         storage::GetVertexAttribIiv.set_ptr(loadfn("glGetVertexAttribIiv"));
-         storage::GetVertexAttribIuiv.set_ptr(loadfn("glGetVertexAttribIuiv"));
-         storage::GetVertexAttribPointerv.set_ptr(loadfn("glGetVertexAttribPointerv"));
-         storage::GetVertexAttribdv.set_ptr(loadfn("glGetVertexAttribdv"));
-         storage::GetVertexAttribfv.set_ptr(loadfn("glGetVertexAttribfv"));
-         storage::GetVertexAttribiv.set_ptr(loadfn("glGetVertexAttribiv"));
-         storage::Hint.set_ptr(loadfn("glHint"));
-         storage::IsBuffer.set_ptr(loadfn("glIsBuffer"));
-         storage::IsEnabled.set_ptr(loadfn("glIsEnabled"));
-         storage::IsEnabledi.set_ptr(loadfn("glIsEnabledi"));
-         storage::IsFramebuffer.set_ptr(loadfn("glIsFramebuffer"));
-         storage::IsProgram.set_ptr(loadfn("glIsProgram"));
-         storage::IsQuery.set_ptr(loadfn("glIsQuery"));
-         storage::IsRenderbuffer.set_ptr(loadfn("glIsRenderbuffer"));
-         storage::IsSampler.set_ptr(loadfn("glIsSampler"));
-         storage::IsShader.set_ptr(loadfn("glIsShader"));
-         storage::IsSync.set_ptr(loadfn("glIsSync"));
-         storage::IsTexture.set_ptr(loadfn("glIsTexture"));
-         storage::IsVertexArray.set_ptr(loadfn("glIsVertexArray"));
-         storage::LineWidth.set_ptr(loadfn("glLineWidth"));
-         storage::LinkProgram.set_ptr(loadfn("glLinkProgram"));
-         storage::LogicOp.set_ptr(loadfn("glLogicOp"));
-         storage::MapBuffer.set_ptr(loadfn("glMapBuffer"));
-         storage::MapBufferRange.set_ptr(loadfn("glMapBufferRange"));
-         storage::MultiDrawArrays.set_ptr(loadfn("glMultiDrawArrays"));
-         storage::MultiDrawElements.set_ptr(loadfn("glMultiDrawElements"));
-         storage::MultiDrawElementsBaseVertex.set_ptr(loadfn("glMultiDrawElementsBaseVertex"));
-         storage::PixelStoref.set_ptr(loadfn("glPixelStoref"));
-         storage::PixelStorei.set_ptr(loadfn("glPixelStorei"));
-         storage::PointParameterf.set_ptr(loadfn("glPointParameterf"));
-         storage::PointParameterfv.set_ptr(loadfn("glPointParameterfv"));
-         storage::PointParameteri.set_ptr(loadfn("glPointParameteri"));
-         storage::PointParameteriv.set_ptr(loadfn("glPointParameteriv"));
-         storage::PointSize.set_ptr(loadfn("glPointSize"));
-         storage::PolygonMode.set_ptr(loadfn("glPolygonMode"));
-         storage::PolygonOffset.set_ptr(loadfn("glPolygonOffset"));
-         storage::PrimitiveRestartIndex.set_ptr(loadfn("glPrimitiveRestartIndex"));
-         storage::ProvokingVertex.set_ptr(loadfn("glProvokingVertex"));
-         storage::QueryCounter.set_ptr(loadfn("glQueryCounter"));
-         storage::ReadBuffer.set_ptr(loadfn("glReadBuffer"));
-         storage::ReadPixels.set_ptr(loadfn("glReadPixels"));
-         storage::RenderbufferStorage.set_ptr(loadfn("glRenderbufferStorage"));
-         storage::RenderbufferStorageMultisample.set_ptr(loadfn("glRenderbufferStorageMultisample"));
-         storage::SampleCoverage.set_ptr(loadfn("glSampleCoverage"));
-         storage::SampleMaski.set_ptr(loadfn("glSampleMaski"));
-         storage::SamplerParameterIiv.set_ptr(loadfn("glSamplerParameterIiv"));
-         storage::SamplerParameterIuiv.set_ptr(loadfn("glSamplerParameterIuiv"));
-         storage::SamplerParameterf.set_ptr(loadfn("glSamplerParameterf"));
-         storage::SamplerParameterfv.set_ptr(loadfn("glSamplerParameterfv"));
-         storage::SamplerParameteri.set_ptr(loadfn("glSamplerParameteri"));
-         storage::SamplerParameteriv.set_ptr(loadfn("glSamplerParameteriv"));
-         storage::Scissor.set_ptr(loadfn("glScissor"));
-         storage::ShaderSource.set_ptr(loadfn("glShaderSource"));
-         storage::StencilFunc.set_ptr(loadfn("glStencilFunc"));
-         storage::StencilFuncSeparate.set_ptr(loadfn("glStencilFuncSeparate"));
-         storage::StencilMask.set_ptr(loadfn("glStencilMask"));
-         storage::StencilMaskSeparate.set_ptr(loadfn("glStencilMaskSeparate"));
-         storage::StencilOp.set_ptr(loadfn("glStencilOp"));
-         storage::StencilOpSeparate.set_ptr(loadfn("glStencilOpSeparate"));
-         storage::TexBuffer.set_ptr(loadfn("glTexBuffer"));
-         storage::TexImage1D.set_ptr(loadfn("glTexImage1D"));
-         storage::TexImage2D.set_ptr(loadfn("glTexImage2D"));
-         storage::TexImage2DMultisample.set_ptr(loadfn("glTexImage2DMultisample"));
-         storage::TexImage3D.set_ptr(loadfn("glTexImage3D"));
-         storage::TexImage3DMultisample.set_ptr(loadfn("glTexImage3DMultisample"));
-         storage::TexParameterIiv.set_ptr(loadfn("glTexParameterIiv"));
-         storage::TexParameterIuiv.set_ptr(loadfn("glTexParameterIuiv"));
-         storage::TexParameterf.set_ptr(loadfn("glTexParameterf"));
-         storage::TexParameterfv.set_ptr(loadfn("glTexParameterfv"));
-         storage::TexParameteri.set_ptr(loadfn("glTexParameteri"));
-         storage::TexParameteriv.set_ptr(loadfn("glTexParameteriv"));
-         storage::TexSubImage1D.set_ptr(loadfn("glTexSubImage1D"));
-         storage::TexSubImage2D.set_ptr(loadfn("glTexSubImage2D"));
-         storage::TexSubImage3D.set_ptr(loadfn("glTexSubImage3D"));
-         storage::TransformFeedbackVaryings.set_ptr(loadfn("glTransformFeedbackVaryings"));
-         storage::Uniform1f.set_ptr(loadfn("glUniform1f"));
-         storage::Uniform1fv.set_ptr(loadfn("glUniform1fv"));
-         storage::Uniform1i.set_ptr(loadfn("glUniform1i"));
-         storage::Uniform1iv.set_ptr(loadfn("glUniform1iv"));
-         storage::Uniform1ui.set_ptr(loadfn("glUniform1ui"));
-         storage::Uniform1uiv.set_ptr(loadfn("glUniform1uiv"));
-         storage::Uniform2f.set_ptr(loadfn("glUniform2f"));
-         storage::Uniform2fv.set_ptr(loadfn("glUniform2fv"));
-         storage::Uniform2i.set_ptr(loadfn("glUniform2i"));
-         storage::Uniform2iv.set_ptr(loadfn("glUniform2iv"));
-         storage::Uniform2ui.set_ptr(loadfn("glUniform2ui"));
-         storage::Uniform2uiv.set_ptr(loadfn("glUniform2uiv"));
-         storage::Uniform3f.set_ptr(loadfn("glUniform3f"));
-         storage::Uniform3fv.set_ptr(loadfn("glUniform3fv"));
-         storage::Uniform3i.set_ptr(loadfn("glUniform3i"));
-         storage::Uniform3iv.set_ptr(loadfn("glUniform3iv"));
-         storage::Uniform3ui.set_ptr(loadfn("glUniform3ui"));
-         storage::Uniform3uiv.set_ptr(loadfn("glUniform3uiv"));
-         storage::Uniform4f.set_ptr(loadfn("glUniform4f"));
-         storage::Uniform4fv.set_ptr(loadfn("glUniform4fv"));
-         storage::Uniform4i.set_ptr(loadfn("glUniform4i"));
-         storage::Uniform4iv.set_ptr(loadfn("glUniform4iv"));
-         storage::Uniform4ui.set_ptr(loadfn("glUniform4ui"));
-         storage::Uniform4uiv.set_ptr(loadfn("glUniform4uiv"));
-         storage::UniformBlockBinding.set_ptr(loadfn("glUniformBlockBinding"));
-         storage::UniformMatrix2fv.set_ptr(loadfn("glUniformMatrix2fv"));
-         storage::UniformMatrix2x3fv.set_ptr(loadfn("glUniformMatrix2x3fv"));
-         storage::UniformMatrix2x4fv.set_ptr(loadfn("glUniformMatrix2x4fv"));
-         storage::UniformMatrix3fv.set_ptr(loadfn("glUniformMatrix3fv"));
-         storage::UniformMatrix3x2fv.set_ptr(loadfn("glUniformMatrix3x2fv"));
-         storage::UniformMatrix3x4fv.set_ptr(loadfn("glUniformMatrix3x4fv"));
-         storage::UniformMatrix4fv.set_ptr(loadfn("glUniformMatrix4fv"));
-         storage::UniformMatrix4x2fv.set_ptr(loadfn("glUniformMatrix4x2fv"));
-         storage::UniformMatrix4x3fv.set_ptr(loadfn("glUniformMatrix4x3fv"));
-         storage::UnmapBuffer.set_ptr(loadfn("glUnmapBuffer"));
-         storage::UseProgram.set_ptr(loadfn("glUseProgram"));
-         storage::ValidateProgram.set_ptr(loadfn("glValidateProgram"));
-         storage::VertexAttrib1d.set_ptr(loadfn("glVertexAttrib1d"));
-         storage::VertexAttrib1dv.set_ptr(loadfn("glVertexAttrib1dv"));
-         storage::VertexAttrib1f.set_ptr(loadfn("glVertexAttrib1f"));
-         storage::VertexAttrib1fv.set_ptr(loadfn("glVertexAttrib1fv"));
-         storage::VertexAttrib1s.set_ptr(loadfn("glVertexAttrib1s"));
-         storage::VertexAttrib1sv.set_ptr(loadfn("glVertexAttrib1sv"));
-         storage::VertexAttrib2d.set_ptr(loadfn("glVertexAttrib2d"));
-         storage::VertexAttrib2dv.set_ptr(loadfn("glVertexAttrib2dv"));
-         storage::VertexAttrib2f.set_ptr(loadfn("glVertexAttrib2f"));
-         storage::VertexAttrib2fv.set_ptr(loadfn("glVertexAttrib2fv"));
-         storage::VertexAttrib2s.set_ptr(loadfn("glVertexAttrib2s"));
-         storage::VertexAttrib2sv.set_ptr(loadfn("glVertexAttrib2sv"));
-         storage::VertexAttrib3d.set_ptr(loadfn("glVertexAttrib3d"));
-         storage::VertexAttrib3dv.set_ptr(loadfn("glVertexAttrib3dv"));
-         storage::VertexAttrib3f.set_ptr(loadfn("glVertexAttrib3f"));
-         storage::VertexAttrib3fv.set_ptr(loadfn("glVertexAttrib3fv"));
-         storage::VertexAttrib3s.set_ptr(loadfn("glVertexAttrib3s"));
-         storage::VertexAttrib3sv.set_ptr(loadfn("glVertexAttrib3sv"));
-         storage::VertexAttrib4Nbv.set_ptr(loadfn("glVertexAttrib4Nbv"));
-         storage::VertexAttrib4Niv.set_ptr(loadfn("glVertexAttrib4Niv"));
-         storage::VertexAttrib4Nsv.set_ptr(loadfn("glVertexAttrib4Nsv"));
-         storage::VertexAttrib4Nub.set_ptr(loadfn("glVertexAttrib4Nub"));
-         storage::VertexAttrib4Nubv.set_ptr(loadfn("glVertexAttrib4Nubv"));
-         storage::VertexAttrib4Nuiv.set_ptr(loadfn("glVertexAttrib4Nuiv"));
-         storage::VertexAttrib4Nusv.set_ptr(loadfn("glVertexAttrib4Nusv"));
-         storage::VertexAttrib4bv.set_ptr(loadfn("glVertexAttrib4bv"));
-         storage::VertexAttrib4d.set_ptr(loadfn("glVertexAttrib4d"));
-         storage::VertexAttrib4dv.set_ptr(loadfn("glVertexAttrib4dv"));
-         storage::VertexAttrib4f.set_ptr(loadfn("glVertexAttrib4f"));
-         storage::VertexAttrib4fv.set_ptr(loadfn("glVertexAttrib4fv"));
-         storage::VertexAttrib4iv.set_ptr(loadfn("glVertexAttrib4iv"));
-         storage::VertexAttrib4s.set_ptr(loadfn("glVertexAttrib4s"));
-         storage::VertexAttrib4sv.set_ptr(loadfn("glVertexAttrib4sv"));
-         storage::VertexAttrib4ubv.set_ptr(loadfn("glVertexAttrib4ubv"));
-         storage::VertexAttrib4uiv.set_ptr(loadfn("glVertexAttrib4uiv"));
-         storage::VertexAttrib4usv.set_ptr(loadfn("glVertexAttrib4usv"));
-         storage::VertexAttribDivisor.set_ptr(loadfn("glVertexAttribDivisor"));
-         storage::VertexAttribI1i.set_ptr(loadfn("glVertexAttribI1i"));
-         storage::VertexAttribI1iv.set_ptr(loadfn("glVertexAttribI1iv"));
-         storage::VertexAttribI1ui.set_ptr(loadfn("glVertexAttribI1ui"));
-         storage::VertexAttribI1uiv.set_ptr(loadfn("glVertexAttribI1uiv"));
-         storage::VertexAttribI2i.set_ptr(loadfn("glVertexAttribI2i"));
-         storage::VertexAttribI2iv.set_ptr(loadfn("glVertexAttribI2iv"));
-         storage::VertexAttribI2ui.set_ptr(loadfn("glVertexAttribI2ui"));
-         storage::VertexAttribI2uiv.set_ptr(loadfn("glVertexAttribI2uiv"));
-         storage::VertexAttribI3i.set_ptr(loadfn("glVertexAttribI3i"));
-         storage::VertexAttribI3iv.set_ptr(loadfn("glVertexAttribI3iv"));
-         storage::VertexAttribI3ui.set_ptr(loadfn("glVertexAttribI3ui"));
-         storage::VertexAttribI3uiv.set_ptr(loadfn("glVertexAttribI3uiv"));
-         storage::VertexAttribI4bv.set_ptr(loadfn("glVertexAttribI4bv"));
-         storage::VertexAttribI4i.set_ptr(loadfn("glVertexAttribI4i"));
-         storage::VertexAttribI4iv.set_ptr(loadfn("glVertexAttribI4iv"));
-         storage::VertexAttribI4sv.set_ptr(loadfn("glVertexAttribI4sv"));
-         storage::VertexAttribI4ubv.set_ptr(loadfn("glVertexAttribI4ubv"));
-         storage::VertexAttribI4ui.set_ptr(loadfn("glVertexAttribI4ui"));
-         storage::VertexAttribI4uiv.set_ptr(loadfn("glVertexAttribI4uiv"));
-         storage::VertexAttribI4usv.set_ptr(loadfn("glVertexAttribI4usv"));
-         storage::VertexAttribIPointer.set_ptr(loadfn("glVertexAttribIPointer"));
-         storage::VertexAttribP1ui.set_ptr(loadfn("glVertexAttribP1ui"));
-         storage::VertexAttribP1uiv.set_ptr(loadfn("glVertexAttribP1uiv"));
-         storage::VertexAttribP2ui.set_ptr(loadfn("glVertexAttribP2ui"));
-         storage::VertexAttribP2uiv.set_ptr(loadfn("glVertexAttribP2uiv"));
-         storage::VertexAttribP3ui.set_ptr(loadfn("glVertexAttribP3ui"));
-         storage::VertexAttribP3uiv.set_ptr(loadfn("glVertexAttribP3uiv"));
-         storage::VertexAttribP4ui.set_ptr(loadfn("glVertexAttribP4ui"));
-         storage::VertexAttribP4uiv.set_ptr(loadfn("glVertexAttribP4uiv"));
-         storage::VertexAttribPointer.set_ptr(loadfn("glVertexAttribPointer"));
-         storage::Viewport.set_ptr(loadfn("glViewport"));
-         storage::WaitSync.set_ptr(loadfn("glWaitSync"));
+         storage::ActiveTexture.set_ptr(record("glActiveTexture"));
+         storage::AttachShader.set_ptr(record("glAttachShader"));
+         storage::BeginConditionalRender.set_ptr(record("glBeginConditionalRender"));
+         storage::BeginQuery.set_ptr(record("glBeginQuery"));
+         storage::BeginTransformFeedback.set_ptr(record("glBeginTransformFeedback"));
+         storage::BindAttribLocation.set_ptr(record("glBindAttribLocation"));
+         storage::BindBuffer.set_ptr(record("glBindBuffer"));
+         storage::BindBufferBase.set_ptr(record("glBindBufferBase"));
+         storage::BindBufferRange.set_ptr(record("glBindBufferRange"));
+         storage::BindFragDataLocation.set_ptr(record("glBindFragDataLocation"));
+         storage::BindFragDataLocationIndexed.set_ptr(record("glBindFragDataLocationIndexed"));
+         storage::BindFramebuffer.set_ptr(record("glBindFramebuffer"));
+         storage::BindRenderbuffer.set_ptr(record("glBindRenderbuffer"));
+         storage::BindSampler.set_ptr(record("glBindSampler"));
+         storage::BindTexture.set_ptr(record("glBindTexture"));
+         storage::BindVertexArray.set_ptr(record("glBindVertexArray"));
+         storage::BlendColor.set_ptr(record("glBlendColor"));
+         storage::BlendEquation.set_ptr(record("glBlendEquation"));
+         storage::BlendEquationSeparate.set_ptr(record("glBlendEquationSeparate"));
+         storage::BlendFunc.set_ptr(record("glBlendFunc"));
+         storage::BlendFuncSeparate.set_ptr(record("glBlendFuncSeparate"));
+         storage::BlitFramebuffer.set_ptr(record("glBlitFramebuffer"));
+         storage::BufferData.set_ptr(record("glBufferData"));
+         storage::BufferSubData.set_ptr(record("glBufferSubData"));
+         storage::CheckFramebufferStatus.set_ptr(record("glCheckFramebufferStatus"));
+         storage::ClampColor.set_ptr(record("glClampColor"));
+         storage::Clear.set_ptr(record("glClear"));
+         storage::ClearBufferfi.set_ptr(record("glClearBufferfi"));
+         storage::ClearBufferfv.set_ptr(record("glClearBufferfv"));
+         storage::ClearBufferiv.set_ptr(record("glClearBufferiv"));
+         storage::ClearBufferuiv.set_ptr(record("glClearBufferuiv"));
+         storage::ClearColor.set_ptr(record("glClearColor"));
+         storage::ClearDepth.set_ptr(record("glClearDepth"));
+         storage::ClearStencil.set_ptr(record("glClearStencil"));
+         storage::ClientWaitSync.set_ptr(record("glClientWaitSync"));
+         storage::ColorMask.set_ptr(record("glColorMask"));
+         storage::ColorMaski.set_ptr(record("glColorMaski"));
+         storage::CompileShader.set_ptr(record("glCompileShader"));
+         storage::CompressedTexImage1D.set_ptr(record("glCompressedTexImage1D"));
+         storage::CompressedTexImage2D.set_ptr(record("glCompressedTexImage2D"));
+         storage::CompressedTexImage3D.set_ptr(record("glCompressedTexImage3D"));
+         storage::CompressedTexSubImage1D.set_ptr(record("glCompressedTexSubImage1D"));
+         storage::CompressedTexSubImage2D.set_ptr(record("glCompressedTexSubImage2D"));
+         storage::CompressedTexSubImage3D.set_ptr(record("glCompressedTexSubImage3D"));
+         storage::CopyBufferSubData.set_ptr(record("glCopyBufferSubData"));
+         storage::CopyTexImage1D.set_ptr(record("glCopyTexImage1D"));
+         storage::CopyTexImage2D.set_ptr(record("glCopyTexImage2D"));
+         storage::CopyTexSubImage1D.set_ptr(record("glCopyTexSubImage1D"));
+         storage::CopyTexSubImage2D.set_ptr(record("glCopyTexSubImage2D"));
+         storage::CopyTexSubImage3D.set_ptr(record("glCopyTexSubImage3D"));
+         storage::CreateProgram.set_ptr(record("glCreateProgram"));
+         storage::CreateShader.set_ptr(record("glCreateShader"));
+         storage::CullFace.set_ptr(record("glCullFace"));
+         storage::DeleteBuffers.set_ptr(record("glDeleteBuffers"));
+         storage::DeleteFramebuffers.set_ptr(record("glDeleteFramebuffers"));
+         storage::DeleteProgram.set_ptr(record("glDeleteProgram"));
+         storage::DeleteQueries.set_ptr(record("glDeleteQueries"));
+         storage::DeleteRenderbuffers.set_ptr(record("glDeleteRenderbuffers"));
+         storage::DeleteSamplers.set_ptr(record("glDeleteSamplers"));
+         storage::DeleteShader.set_ptr(record("glDeleteShader"));
+         storage::DeleteSync.set_ptr(record("glDeleteSync"));
+         storage::DeleteTextures.set_ptr(record("glDeleteTextures"));
+         storage::DeleteVertexArrays.set_ptr(record("glDeleteVertexArrays"));
+         storage::DepthFunc.set_ptr(record("glDepthFunc"));
+         storage::DepthMask.set_ptr(record("glDepthMask"));
+         storage::DepthRange.set_ptr(record("glDepthRange"));
+         storage::DetachShader.set_ptr(record("glDetachShader"));
+         storage::Disable.set_ptr(record("glDisable"));
+         storage::DisableVertexAttribArray.set_ptr(record("glDisableVertexAttribArray"));
+         storage::Disablei.set_ptr(record("glDisablei"));
+         storage::DrawArrays.set_ptr(record("glDrawArrays"));
+         storage::DrawArraysInstanced.set_ptr(record("glDrawArraysInstanced"));
+         storage::DrawBuffer.set_ptr(record("glDrawBuffer"));
+         storage::DrawBuffers.set_ptr(record("glDrawBuffers"));
+         storage::DrawElements.set_ptr(record("glDrawElements"));
+         storage::DrawElementsBaseVertex.set_ptr(record("glDrawElementsBaseVertex"));
+         storage::DrawElementsInstanced.set_ptr(record("glDrawElementsInstanced"));
+         storage::DrawElementsInstancedBaseVertex.set_ptr(record("glDrawElementsInstancedBaseVertex"));
+         storage::DrawRangeElements.set_ptr(record("glDrawRangeElements"));
+         storage::DrawRangeElementsBaseVertex.set_ptr(record("glDrawRangeElementsBaseVertex"));
+         storage::Enable.set_ptr(record("glEnable"));
+         storage::EnableVertexAttribArray.set_ptr(record("glEnableVertexAttribArray"));
+         storage::Enablei.set_ptr(record("glEnablei"));
+         storage::EndConditionalRender.set_ptr(record("glEndConditionalRender"));
+         storage::EndQuery.set_ptr(record("glEndQuery"));
+         storage::EndTransformFeedback.set_ptr(record("glEndTransformFeedback"));
+         storage::FenceSync.set_ptr(record("glFenceSync"));
+         storage::Finish.set_ptr(record("glFinish"));
+         storage::Flush.set_ptr(record("glFlush"));
+         storage::FlushMappedBufferRange.set_ptr(record("glFlushMappedBufferRange"));
+         storage::FramebufferRenderbuffer.set_ptr(record("glFramebufferRenderbuffer"));
+         storage::FramebufferTexture.set_ptr(record("glFramebufferTexture"));
+         storage::FramebufferTexture1D.set_ptr(record("glFramebufferTexture1D"));
+         storage::FramebufferTexture2D.set_ptr(record("glFramebufferTexture2D"));
+         storage::FramebufferTexture3D.set_ptr(record("glFramebufferTexture3D"));
+         storage::FramebufferTextureLayer.set_ptr(record("glFramebufferTextureLayer"));
+         storage::FrontFace.set_ptr(record("glFrontFace"));
+         storage::GenBuffers.set_ptr(record("glGenBuffers"));
+         storage::GenFramebuffers.set_ptr(record("glGenFramebuffers"));
+         storage::GenQueries.set_ptr(record("glGenQueries"));
+         storage::GenRenderbuffers.set_ptr(record("glGenRenderbuffers"));
+         storage::GenSamplers.set_ptr(record("glGenSamplers"));
+         storage::GenTextures.set_ptr(record("glGenTextures"));
+         storage::GenVertexArrays.set_ptr(record("glGenVertexArrays"));
+         storage::GenerateMipmap.set_ptr(record("glGenerateMipmap"));
+         storage::GetActiveAttrib.set_ptr(record("glGetActiveAttrib"));
+         storage::GetActiveUniform.set_ptr(record("glGetActiveUniform"));
+         storage::GetActiveUniformBlockName.set_ptr(record("glGetActiveUniformBlockName"));
+         storage::GetActiveUniformBlockiv.set_ptr(record("glGetActiveUniformBlockiv"));
+         storage::GetActiveUniformName.set_ptr(record("glGetActiveUniformName"));
+         storage::GetActiveUniformsiv.set_ptr(record("glGetActiveUniformsiv"));
+         storage::GetAttachedShaders.set_ptr(record("glGetAttachedShaders"));
+         storage::GetAttribLocation.set_ptr(record("glGetAttribLocation"));
+         storage::GetBooleani_v.set_ptr(record("glGetBooleani_v"));
+         storage::GetBooleanv.set_ptr(record("glGetBooleanv"));
+         storage::GetBufferParameteri64v.set_ptr(record("glGetBufferParameteri64v"));
+         storage::GetBufferParameteriv.set_ptr(record("glGetBufferParameteriv"));
+         storage::GetBufferPointerv.set_ptr(record("glGetBufferPointerv"));
+         storage::GetBufferSubData.set_ptr(record("glGetBufferSubData"));
+         storage::GetCompressedTexImage.set_ptr(record("glGetCompressedTexImage"));
+         storage::GetDoublev.set_ptr(record("glGetDoublev"));
+         storage::GetError.set_ptr(record("glGetError"));
+         storage::GetFloatv.set_ptr(record("glGetFloatv"));
+         storage::GetFragDataIndex.set_ptr(record("glGetFragDataIndex"));
+         storage::GetFragDataLocation.set_ptr(record("glGetFragDataLocation"));
+         storage::GetFramebufferAttachmentParameteriv.set_ptr(record("glGetFramebufferAttachmentParameteriv"));
+         storage::GetInteger64i_v.set_ptr(record("glGetInteger64i_v"));
+         storage::GetInteger64v.set_ptr(record("glGetInteger64v"));
+         storage::GetIntegeri_v.set_ptr(record("glGetIntegeri_v"));
+         storage::GetIntegerv.set_ptr(record("glGetIntegerv"));
+         storage::GetMultisamplefv.set_ptr(record("glGetMultisamplefv"));
+         storage::GetProgramInfoLog.set_ptr(record("glGetProgramInfoLog"));
+         storage::GetProgramiv.set_ptr(record("glGetProgramiv"));
+         storage::GetQueryObjecti64v.set_ptr(record("glGetQueryObjecti64v"));
+         storage::GetQueryObjectiv.set_ptr(record("glGetQueryObjectiv"));
+         storage::GetQueryObjectui64v.set_ptr(record("glGetQueryObjectui64v"));
+         storage::GetQueryObjectuiv.set_ptr(record("glGetQueryObjectuiv"));
+         storage::GetQueryiv.set_ptr(record("glGetQueryiv"));
+         storage::GetRenderbufferParameteriv.set_ptr(record("glGetRenderbufferParameteriv"));
+         storage::GetSamplerParameterIiv.set_ptr(record("glGetSamplerParameterIiv"));
+         storage::GetSamplerParameterIuiv.set_ptr(record("glGetSamplerParameterIuiv"));
+         storage::GetSamplerParameterfv.set_ptr(record("glGetSamplerParameterfv"));
+         storage::GetSamplerParameteriv.set_ptr(record("glGetSamplerParameteriv"));
+         storage::GetShaderInfoLog.set_ptr(record("glGetShaderInfoLog"));
+         storage::GetShaderSource.set_ptr(record("glGetShaderSource"));
+         storage::GetShaderiv.set_ptr(record("glGetShaderiv"));
+         storage::GetString.set_ptr(record("glGetString"));
+         storage::GetStringi.set_ptr(record("glGetStringi"));
+         storage::GetSynciv.set_ptr(record("glGetSynciv"));
+         storage::GetTexImage.set_ptr(record("glGetTexImage"));
+         storage::GetTexLevelParameterfv.set_ptr(record("glGetTexLevelParameterfv"));
+         storage::GetTexLevelParameteriv.set_ptr(record("glGetTexLevelParameteriv"));
+         storage::GetTexParameterIiv.set_ptr(record("glGetTexParameterIiv"));
+         storage::GetTexParameterIuiv.set_ptr(record("glGetTexParameterIuiv"));
+         storage::GetTexParameterfv.set_ptr(record("glGetTexParameterfv"));
+         storage::GetTexParameteriv.set_ptr(record("glGetTexParameteriv"));
+         storage::GetTransformFeedbackVarying.set_ptr(record("glGetTransformFeedbackVarying"));
+         storage::GetUniformBlockIndex.set_ptr(record("glGetUniformBlockIndex"));
+         storage::GetUniformIndices.set_ptr(record("glGetUniformIndices"));
+         storage::GetUniformLocation.set_ptr(record("glGetUniformLocation"));
+         storage::GetUniformfv.set_ptr(record("glGetUniformfv"));
+         storage::GetUniformiv.set_ptr(record("glGetUniformiv"));
+         storage::GetUniformuiv.set_ptr(record("glGetUniformuiv"));
+         storage::GetVertexAttribIiv.set_ptr(record("glGetVertexAttribIiv"));
+         storage::GetVertexAttribIuiv.set_ptr(record("glGetVertexAttribIuiv"));
+         storage::GetVertexAttribPointerv.set_ptr(record("glGetVertexAttribPointerv"));
+         storage::GetVertexAttribdv.set_ptr(record("glGetVertexAttribdv"));
+         storage::GetVertexAttribfv.set_ptr(record("glGetVertexAttribfv"));
+         storage::GetVertexAttribiv.set_ptr(record("glGetVertexAttribiv"));
+         storage::Hint.set_ptr(record("glHint"));
+         storage::IsBuffer.set_ptr(record("glIsBuffer"));
+         storage::IsEnabled.set_ptr(record("glIsEnabled"));
+         storage::IsEnabledi.set_ptr(record("glIsEnabledi"));
+         storage::IsFramebuffer.set_ptr(record("glIsFramebuffer"));
+         storage::IsProgram.set_ptr(record("glIsProgram"));
+         storage::IsQuery.set_ptr(record("glIsQuery"));
+         storage::IsRenderbuffer.set_ptr(record("glIsRenderbuffer"));
+         storage::IsSampler.set_ptr(record("glIsSampler"));
+         storage::IsShader.set_ptr(record("glIsShader"));
+         storage::IsSync.set_ptr(record("glIsSync"));
+         storage::IsTexture.set_ptr(record("glIsTexture"));
+         storage::IsVertexArray.set_ptr(record("glIsVertexArray"));
+         storage::LineWidth.set_ptr(record("glLineWidth"));
+         storage::LinkProgram.set_ptr(record("glLinkProgram"));
+         storage::LogicOp.set_ptr(record("glLogicOp"));
+         storage::MapBuffer.set_ptr(record("glMapBuffer"));
+         storage::MapBufferRange.set_ptr(record("glMapBufferRange"));
+         storage::MultiDrawArrays.set_ptr(record("glMultiDrawArrays"));
+         storage::MultiDrawElements.set_ptr(record("glMultiDrawElements"));
+         storage::MultiDrawElementsBaseVertex.set_ptr(record("glMultiDrawElementsBaseVertex"));
+         storage::PixelStoref.set_ptr(record("glPixelStoref"));
+         storage::PixelStorei.set_ptr(record("glPixelStorei"));
+         storage::PointParameterf.set_ptr(record("glPointParameterf"));
+         storage::PointParameterfv.set_ptr(record("glPointParameterfv"));
+         storage::PointParameteri.set_ptr(record("glPointParameteri"));
+         storage::PointParameteriv.set_ptr(record("glPointParameteriv"));
+         storage::PointSize.set_ptr(record("glPointSize"));
+         storage::PolygonMode.set_ptr(record("glPolygonMode"));
+         storage::PolygonOffset.set_ptr(record("glPolygonOffset"));
+         storage::PrimitiveRestartIndex.set_ptr(record("glPrimitiveRestartIndex"));
+         storage::ProvokingVertex.set_ptr(record("glProvokingVertex"));
+         storage::QueryCounter.set_ptr(record("glQueryCounter"));
+         storage::ReadBuffer.set_ptr(record("glReadBuffer"));
+         storage::ReadPixels.set_ptr(record("glReadPixels"));
+         storage::RenderbufferStorage.set_ptr(record("glRenderbufferStorage"));
+         storage::RenderbufferStorageMultisample.set_ptr(record("glRenderbufferStorageMultisample"));
+         storage::SampleCoverage.set_ptr(record("glSampleCoverage"));
+         storage::SampleMaski.set_ptr(record("glSampleMaski"));
+         storage::SamplerParameterIiv.set_ptr(record("glSamplerParameterIiv"));
+         storage::SamplerParameterIuiv.set_ptr(record("glSamplerParameterIuiv"));
+         storage::SamplerParameterf.set_ptr(record("glSamplerParameterf"));
+         storage::SamplerParameterfv.set_ptr(record("glSamplerParameterfv"));
+         storage::SamplerParameteri.set_ptr(record("glSamplerParameteri"));
+         storage::SamplerParameteriv.set_ptr(record("glSamplerParameteriv"));
+         storage::Scissor.set_ptr(record("glScissor"));
+         storage::ShaderSource.set_ptr(record("glShaderSource"));
+         storage::StencilFunc.set_ptr(record("glStencilFunc"));
+         storage::StencilFuncSeparate.set_ptr(record("glStencilFuncSeparate"));
+         storage::StencilMask.set_ptr(record("glStencilMask"));
+         storage::StencilMaskSeparate.set_ptr(record("glStencilMaskSeparate"));
+         storage::StencilOp.set_ptr(record("glStencilOp"));
+         storage::StencilOpSeparate.set_ptr(record("glStencilOpSeparate"));
+         storage::TexBuffer.set_ptr(record("glTexBuffer"));
+         storage::TexImage1D.set_ptr(record("glTexImage1D"));
+         storage::TexImage2D.set_ptr(record("glTexImage2D"));
+         storage::TexImage2DMultisample.set_ptr(record("glTexImage2DMultisample"));
+         storage::TexImage3D.set_ptr(record("glTexImage3D"));
+         storage::TexImage3DMultisample.set_ptr(record("glTexImage3DMultisample"));
+         storage::TexParameterIiv.set_ptr(record("glTexParameterIiv"));
+         storage::TexParameterIuiv.set_ptr(record("glTexParameterIuiv"));
+         storage::TexParameterf.set_ptr(record("glTexParameterf"));
+         storage::TexParameterfv.set_ptr(record("glTexParameterfv"));
+         storage::TexParameteri.set_ptr(record("glTexParameteri"));
+         storage::TexParameteriv.set_ptr(record("glTexParameteriv"));
+         storage::TexSubImage1D.set_ptr(record("glTexSubImage1D"));
+         storage::TexSubImage2D.set_ptr(record("glTexSubImage2D"));
+         storage::TexSubImage3D.set_ptr(record("glTexSubImage3D"));
+         storage::TransformFeedbackVaryings.set_ptr(record("glTransformFeedbackVaryings"));
+         storage::Uniform1f.set_ptr(record("glUniform1f"));
+         storage::Uniform1fv.set_ptr(record("glUniform1fv"));
+         storage::Uniform1i.set_ptr(record("glUniform1i"));
+         storage::Uniform1iv.set_ptr(record("glUniform1iv"));
+         storage::Uniform1ui.set_ptr(record("glUniform1ui"));
+         storage::Uniform1uiv.set_ptr(record("glUniform1uiv"));
+         storage::Uniform2f.set_ptr(record("glUniform2f"));
+         storage::Uniform2fv.set_ptr(record("glUniform2fv"));
+         storage::Uniform2i.set_ptr(record("glUniform2i"));
+         storage::Uniform2iv.set_ptr(record("glUniform2iv"));
+         storage::Uniform2ui.set_ptr(record("glUniform2ui"));
+         storage::Uniform2uiv.set_ptr(record("glUniform2uiv"));
+         storage::Uniform3f.set_ptr(record("glUniform3f"));
+         storage::Uniform3fv.set_ptr(record("glUniform3fv"));
+         storage::Uniform3i.set_ptr(record("glUniform3i"));
+         storage::Uniform3iv.set_ptr(record("glUniform3iv"));
+         storage::Uniform3ui.set_ptr(record("glUniform3ui"));
+         storage::Uniform3uiv.set_ptr(record("glUniform3uiv"));
+         storage::Uniform4f.set_ptr(record("glUniform4f"));
+         storage::Uniform4fv.set_ptr(record("glUniform4fv"));
+         storage::Uniform4i.set_ptr(record("glUniform4i"));
+         storage::Uniform4iv.set_ptr(record("glUniform4iv"));
+         storage::Uniform4ui.set_ptr(record("glUniform4ui"));
+         storage::Uniform4uiv.set_ptr(record("glUniform4uiv"));
+         storage::UniformBlockBinding.set_ptr(record("glUniformBlockBinding"));
+         storage::UniformMatrix2fv.set_ptr(record("glUniformMatrix2fv"));
+         storage::UniformMatrix2x3fv.set_ptr(record("glUniformMatrix2x3fv"));
+         storage::UniformMatrix2x4fv.set_ptr(record("glUniformMatrix2x4fv"));
+         storage::UniformMatrix3fv.set_ptr(record("glUniformMatrix3fv"));
+         storage::UniformMatrix3x2fv.set_ptr(record("glUniformMatrix3x2fv"));
+         storage::UniformMatrix3x4fv.set_ptr(record("glUniformMatrix3x4fv"));
+         storage::UniformMatrix4fv.set_ptr(record("glUniformMatrix4fv"));
+         storage::UniformMatrix4x2fv.set_ptr(record("glUniformMatrix4x2fv"));
+         storage::UniformMatrix4x3fv.set_ptr(record("glUniformMatrix4x3fv"));
+         storage::UnmapBuffer.set_ptr(record("glUnmapBuffer"));
+         storage::UseProgram.set_ptr(record("glUseProgram"));
+         storage::ValidateProgram.set_ptr(record("glValidateProgram"));
+         storage::VertexAttrib1d.set_ptr(record("glVertexAttrib1d"));
+         storage::VertexAttrib1dv.set_ptr(record("glVertexAttrib1dv"));
+         storage::VertexAttrib1f.set_ptr(record("glVertexAttrib1f"));
+         storage::VertexAttrib1fv.set_ptr(record("glVertexAttrib1fv"));
+         storage::VertexAttrib1s.set_ptr(record("glVertexAttrib1s"));
+         storage::VertexAttrib1sv.set_ptr(record("glVertexAttrib1sv"));
+         storage::VertexAttrib2d.set_ptr(record("glVertexAttrib2d"));
+         storage::VertexAttrib2dv.set_ptr(record("glVertexAttrib2dv"));
+         storage::VertexAttrib2f.set_ptr(record("glVertexAttrib2f"));
+         storage::VertexAttrib2fv.set_ptr(record("glVertexAttrib2fv"));
+         storage::VertexAttrib2s.set_ptr(record("glVertexAttrib2s"));
+         storage::VertexAttrib2sv.set_ptr(record("glVertexAttrib2sv"));
+         storage::VertexAttrib3d.set_ptr(record("glVertexAttrib3d"));
+         storage::VertexAttrib3dv.set_ptr(record("glVertexAttrib3dv"));
+         storage::VertexAttrib3f.set_ptr(record("glVertexAttrib3f"));
+         storage::VertexAttrib3fv.set_ptr(record("glVertexAttrib3fv"));
+         storage::VertexAttrib3s.set_ptr(record("glVertexAttrib3s"));
+         storage::VertexAttrib3sv.set_ptr(record("glVertexAttrib3sv"));
+         storage::VertexAttrib4Nbv.set_ptr(record("glVertexAttrib4Nbv"));
+         storage::VertexAttrib4Niv.set_ptr(record("glVertexAttrib4Niv"));
+         storage::VertexAttrib4Nsv.set_ptr(record("glVertexAttrib4Nsv"));
+         storage::VertexAttrib4Nub.set_ptr(record("glVertexAttrib4Nub"));
+         storage::VertexAttrib4Nubv.set_ptr(record("glVertexAttrib4Nubv"));
+         storage::VertexAttrib4Nuiv.set_ptr(record("glVertexAttrib4Nuiv"));
+         storage::VertexAttrib4Nusv.set_ptr(record("glVertexAttrib4Nusv"));
+         storage::VertexAttrib4bv.set_ptr(record("glVertexAttrib4bv"));
+         storage::VertexAttrib4d.set_ptr(record("glVertexAttrib4d"));
+         storage::VertexAttrib4dv.set_ptr(record("glVertexAttrib4dv"));
+         storage::VertexAttrib4f.set_ptr(record("glVertexAttrib4f"));
+         storage::VertexAttrib4fv.set_ptr(record("glVertexAttrib4fv"));
+         storage::VertexAttrib4iv.set_ptr(record("glVertexAttrib4iv"));
+         storage::VertexAttrib4s.set_ptr(record("glVertexAttrib4s"));
+         storage::VertexAttrib4sv.set_ptr(record("glVertexAttrib4sv"));
+         storage::VertexAttrib4ubv.set_ptr(record("glVertexAttrib4ubv"));
+         storage::VertexAttrib4uiv.set_ptr(record("glVertexAttrib4uiv"));
+         storage::VertexAttrib4usv.set_ptr(record("glVertexAttrib4usv"));
+         storage::VertexAttribDivisor.set_ptr(record("glVertexAttribDivisor"));
+         storage::VertexAttribI1i.set_ptr(record("glVertexAttribI1i"));
+         storage::VertexAttribI1iv.set_ptr(record("glVertexAttribI1iv"));
+         storage::VertexAttribI1ui.set_ptr(record("glVertexAttribI1ui"));
+         storage::VertexAttribI1uiv.set_ptr(record("glVertexAttribI1uiv"));
+         storage::VertexAttribI2i.set_ptr(record("glVertexAttribI2i"));
+         storage::VertexAttribI2iv.set_ptr(record("glVertexAttribI2iv"));
+         storage::VertexAttribI2ui.set_ptr(record("glVertexAttribI2ui"));
+         storage::VertexAttribI2uiv.set_ptr(record("glVertexAttribI2uiv"));
+         storage::VertexAttribI3i.set_ptr(record("glVertexAttribI3i"));
+         storage::VertexAttribI3iv.set_ptr(record("glVertexAttribI3iv"));
+         storage::VertexAttribI3ui.set_ptr(record("glVertexAttribI3ui"));
+         storage::VertexAttribI3uiv.set_ptr(record("glVertexAttribI3uiv"));
+         storage::VertexAttribI4bv.set_ptr(record("glVertexAttribI4bv"));
+         storage::VertexAttribI4i.set_ptr(record("glVertexAttribI4i"));
+         storage::VertexAttribI4iv.set_ptr(record("glVertexAttribI4iv"));
+         storage::VertexAttribI4sv.set_ptr(record("glVertexAttribI4sv"));
+         storage::VertexAttribI4ubv.set_ptr(record("glVertexAttribI4ubv"));
+         storage::VertexAttribI4ui.set_ptr(record("glVertexAttribI4ui"));
+         storage::VertexAttribI4uiv.set_ptr(record("glVertexAttribI4uiv"));
+         storage::VertexAttribI4usv.set_ptr(record("glVertexAttribI4usv"));
+         storage::VertexAttribIPointer.set_ptr(record("glVertexAttribIPointer"));
+         storage::VertexAttribP1ui.set_ptr(record("glVertexAttribP1ui"));
+         storage::VertexAttribP1uiv.set_ptr(record("glVertexAttribP1uiv"));
+         storage::VertexAttribP2ui.set_ptr(record("glVertexAttribP2ui"));
+         storage::VertexAttribP2uiv.set_ptr(record("glVertexAttribP2uiv"));
+         storage::VertexAttribP3ui.set_ptr(record("glVertexAttribP3ui"));
+         storage::VertexAttribP3uiv.set_ptr(record("glVertexAttribP3uiv"));
+         storage::VertexAttribP4ui.set_ptr(record("glVertexAttribP4ui"));
+         storage::VertexAttribP4uiv.set_ptr(record("glVertexAttribP4uiv"));
+         storage::VertexAttribPointer.set_ptr(record("glVertexAttribPointer"));
+         storage::Viewport.set_ptr(record("glViewport"));
+         storage::WaitSync.set_ptr(record("glWaitSync"));
 
     }
+
+    // Extensions are not part of the report; use each module's is_available()
+    #[cfg(feature = "khr-debug")]
+    khr_debug::load(&mut loadfn);
+    #[cfg(feature = "ext-texture-filter-anisotropic")]
+    ext_texture_filter_anisotropic::load();
+
+    report
 }
 
@@ -0,0 +1,20 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+
+out vec4 Color;
+
+layout (std140) uniform FrameData
+{
+    mat4 view;
+    mat4 projection;
+    vec3 cameraPosition;
+    vec3 lightPosition;
+    vec3 lightColor;
+};
+
+void main()
+{
+   gl_Position = projection * view * vec4(aPos, 1.0);
+   Color = aColor;
+}
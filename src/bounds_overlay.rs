@@ -0,0 +1,201 @@
+//! Draws the edges of bounding boxes as lines, colored by whether each box passed culling.
+
+use std::path::Path;
+
+use glad_gl::gl;
+
+use crate::{
+    gl_backend::with_backend,
+    gl_objects::{Buffer, BufferTarget, BufferUsage, VertexArray},
+    mesh::Aabb,
+    shader::ShaderProgram,
+    vertex_layout::{ComponentType, VertexLayout},
+};
+
+/// Each box is drawn as 12 edges of 2 vertices
+const VERTICES_PER_BOX: usize = 24;
+
+const VISIBLE_COLOR: [u8; 4] = [64, 255, 64, 160];
+const CULLED_COLOR: [u8; 4] = [255, 64, 64, 160];
+
+/// Positions and colors live in separate buffers, so the colors can be rewritten every frame
+/// without uploading the positions again
+pub struct BoundsOverlay {
+    program: ShaderProgram,
+    vertex_array: VertexArray,
+    colors: Buffer,
+    box_count: usize,
+    /// Kept alive for the vertex array, which sources positions from it
+    _positions: Buffer,
+}
+
+impl BoundsOverlay {
+    pub fn new(boxes: &[Aabb]) -> Self {
+        let program =
+            ShaderProgram::new(Path::new("./src/bounds.vs"), Path::new("./src/bounds.fs"));
+        let layout = VertexLayout::new()
+            .attribute(0, ComponentType::Float, 3)
+            .normalized_attribute(1, ComponentType::UnsignedByte, 4);
+        for problem in layout.validate(program.reflection()) {
+            eprintln!("Warning: {}", problem);
+        }
+
+        let positions: Vec<[f32; 3]> = boxes.iter().flat_map(box_edges).collect();
+        let positions = Buffer::with_data(BufferTarget::Array, &positions, BufferUsage::StaticDraw);
+        let colors = Buffer::with_size(
+            BufferTarget::Array,
+            boxes.len() * VERTICES_PER_BOX * size_of::<[u8; 4]>(),
+            BufferUsage::StreamDraw,
+        );
+        let vertex_array = VertexArray::new();
+        vertex_array.set_planar_layout(&[&positions, &colors], &layout);
+        vertex_array.set_label("bounds overlay");
+        VertexArray::unbind();
+
+        Self {
+            program,
+            vertex_array,
+            colors,
+            box_count: boxes.len(),
+            _positions: positions,
+        }
+    }
+
+    /// The program reading FrameData, for attaching the frame's uniform buffer
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Draws every box, green if visible[i] is true and red otherwise. The lines are blended
+    /// over the scene and do not write depth, so they never hide what is behind them.
+    pub fn draw(&mut self, visible: &[bool]) {
+        assert_eq!(visible.len(), self.box_count);
+        let colors: Vec<[u8; 4]> = visible
+            .iter()
+            .flat_map(|&visible| {
+                let color = if visible { VISIBLE_COLOR } else { CULLED_COLOR };
+                [color; VERTICES_PER_BOX]
+            })
+            .collect();
+        self.colors.set_data(&colors, BufferUsage::StreamDraw);
+
+        self.program.use_program();
+        with_backend(|backend| {
+            backend.enable(gl::BLEND);
+            backend.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            backend.depth_mask(false);
+        });
+        self.vertex_array
+            .draw_arrays(gl::LINES, 0, self.box_count * VERTICES_PER_BOX);
+        with_backend(|backend| {
+            backend.depth_mask(true);
+            backend.disable(gl::BLEND);
+        });
+    }
+}
+
+/// The end points of the 12 edges of a box
+fn box_edges(bounds: &Aabb) -> [[f32; 3]; VERTICES_PER_BOX] {
+    let corner = |index: usize| {
+        [
+            if index & 1 == 0 {
+                bounds.min.x
+            } else {
+                bounds.max.x
+            },
+            if index & 2 == 0 {
+                bounds.min.y
+            } else {
+                bounds.max.y
+            },
+            if index & 4 == 0 {
+                bounds.min.z
+            } else {
+                bounds.max.z
+            },
+        ]
+    };
+    // Corners that differ in exactly one bit share an edge
+    let mut edges = [[0.0; 3]; VERTICES_PER_BOX];
+    let mut next = 0;
+    for from in 0..8 {
+        for bit in [1, 2, 4] {
+            if from & bit == 0 {
+                edges[next] = corner(from);
+                edges[next + 1] = corner(from | bit);
+                next += 2;
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gl_backend::{RecordingBackend, set_backend},
+        vector::Vector3,
+    };
+
+    #[test]
+    fn boxes_are_drawn_as_lines_from_planar_buffers() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(recording.clone()));
+        let boxes = [
+            Aabb {
+                min: Vector3::new(0.0, 0.0, 0.0),
+                max: Vector3::new(1.0, 2.0, 3.0),
+            },
+            Aabb {
+                min: Vector3::new(-1.0, -1.0, -1.0),
+                max: Vector3::new(1.0, 1.0, 1.0),
+            },
+        ];
+        let mut overlay = BoundsOverlay::new(&boxes);
+        recording.clear_log();
+
+        overlay.draw(&[true, false]);
+        let draws = recording.draw_calls();
+        assert_eq!(draws.len(), 1);
+        assert_eq!(draws[0].mode, gl::LINES);
+        assert_eq!(draws[0].count, 48);
+        assert_eq!(draws[0].program, overlay.program().handle());
+        let colors = recording.buffer_contents(overlay.colors.handle()).unwrap();
+        assert_eq!(colors.len(), 48 * 4);
+        assert_eq!(colors[..4], VISIBLE_COLOR);
+        assert_eq!(colors[24 * 4..25 * 4], CULLED_COLOR);
+        assert_eq!(
+            recording.blend_func(),
+            (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)
+        );
+        assert!(!recording.is_enabled(gl::BLEND));
+        assert!(recording.depth_mask());
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+
+    #[test]
+    fn every_edge_joins_corners_one_axis_apart() {
+        let bounds = Aabb {
+            min: Vector3::new(0.0, 0.0, 0.0),
+            max: Vector3::new(1.0, 2.0, 3.0),
+        };
+        let edges = box_edges(&bounds);
+        let mut lengths: Vec<f32> = edges
+            .chunks(2)
+            .map(|edge| {
+                let differences: Vec<f32> = (0..3)
+                    .map(|axis| edge[1][axis] - edge[0][axis])
+                    .filter(|difference| *difference != 0.0)
+                    .collect();
+                assert_eq!(differences.len(), 1);
+                differences[0]
+            })
+            .collect();
+        lengths.sort_by(f32::total_cmp);
+        assert_eq!(
+            lengths,
+            [1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0, 3.0]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{ffi::CStr, fmt};
+
+use glad_gl::gl::{self, GLenum, GLint, GLuint};
+
+/// What the current context supports, queried once after loading
+pub struct Capabilities {
+    pub version: String,
+    pub glsl_version: String,
+    pub vendor: String,
+    pub renderer: String,
+    pub major_version: GLint,
+    pub minor_version: GLint,
+    pub extensions: Vec<String>,
+    /// Texture units usable by all stages together
+    pub max_texture_units: GLint,
+    /// Texture units usable by the fragment shader
+    pub max_fragment_texture_units: GLint,
+    pub max_texture_size: GLint,
+    pub max_vertex_attributes: GLint,
+    pub max_uniform_block_size: GLint,
+    pub max_uniform_buffer_bindings: GLint,
+    pub max_draw_buffers: GLint,
+    pub max_samples: GLint,
+}
+
+fn get_string(name: GLenum) -> String {
+    let string = unsafe { gl::GetString(name) };
+    if string.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(string as *const _) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn get_integer(name: GLenum) -> GLint {
+    let mut value: GLint = 0;
+    unsafe { gl::GetIntegerv(name, &mut value as *mut GLint) };
+    value
+}
+
+impl Capabilities {
+    /// Queries the current context. gl::load must have been called.
+    pub fn query() -> Self {
+        // Core contexts only list extensions one at a time
+        let extension_count = get_integer(gl::NUM_EXTENSIONS);
+        let extensions = (0..extension_count)
+            .filter_map(|index| {
+                let name = unsafe { gl::GetStringi(gl::EXTENSIONS, index as GLuint) };
+                if name.is_null() {
+                    None
+                } else {
+                    Some(
+                        unsafe { CStr::from_ptr(name as *const _) }
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                }
+            })
+            .collect();
+
+        Self {
+            version: get_string(gl::VERSION),
+            glsl_version: get_string(gl::SHADING_LANGUAGE_VERSION),
+            vendor: get_string(gl::VENDOR),
+            renderer: get_string(gl::RENDERER),
+            major_version: get_integer(gl::MAJOR_VERSION),
+            minor_version: get_integer(gl::MINOR_VERSION),
+            extensions,
+            max_texture_units: get_integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_fragment_texture_units: get_integer(gl::MAX_TEXTURE_IMAGE_UNITS),
+            max_texture_size: get_integer(gl::MAX_TEXTURE_SIZE),
+            max_vertex_attributes: get_integer(gl::MAX_VERTEX_ATTRIBS),
+            max_uniform_block_size: get_integer(gl::MAX_UNIFORM_BLOCK_SIZE),
+            max_uniform_buffer_bindings: get_integer(gl::MAX_UNIFORM_BUFFER_BINDINGS),
+            max_draw_buffers: get_integer(gl::MAX_DRAW_BUFFERS),
+            max_samples: get_integer(gl::MAX_SAMPLES),
+        }
+    }
+
+    /// The limits every OpenGL 3.3 core context guarantees, for code that runs without a context
+    #[cfg(test)]
+    pub fn minimum() -> Self {
+        Self {
+            version: "3.3".to_string(),
+            glsl_version: "3.30".to_string(),
+            vendor: String::new(),
+            renderer: String::new(),
+            major_version: 3,
+            minor_version: 3,
+            extensions: Vec::new(),
+            max_texture_units: 48,
+            max_fragment_texture_units: 16,
+            max_texture_size: 1024,
+            max_vertex_attributes: 16,
+            max_uniform_block_size: 16384,
+            max_uniform_buffer_bindings: 36,
+            max_draw_buffers: 8,
+            max_samples: 4,
+        }
+    }
+
+    /// Takes the full name, e.g. "GL_KHR_debug"
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    pub fn supports_version(&self, major: GLint, minor: GLint) -> bool {
+        (self.major_version, self.minor_version) >= (major, minor)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "OpenGL {}", self.version)?;
+        writeln!(f, "GLSL {}", self.glsl_version)?;
+        writeln!(f, "Vendor: {}", self.vendor)?;
+        writeln!(f, "Renderer: {}", self.renderer)?;
+        writeln!(
+            f,
+            "Texture units: {} ({} in fragment shaders), max texture size {}",
+            self.max_texture_units, self.max_fragment_texture_units, self.max_texture_size
+        )?;
+        writeln!(
+            f,
+            "Vertex attributes: {}, draw buffers: {}, samples: {}",
+            self.max_vertex_attributes, self.max_draw_buffers, self.max_samples
+        )?;
+        writeln!(
+            f,
+            "Uniform blocks: {} bytes, {} bindings",
+            self.max_uniform_block_size, self.max_uniform_buffer_bindings
+        )?;
+        write!(f, "Extensions ({}):", self.extensions.len())?;
+        for extension in &self.extensions {
+            write!(f, " {}", extension)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! The view frustum as six planes, for skipping objects the camera cannot see.
+
+use crate::{matrix::Matrix4, mesh::Aabb, vector::Vector3};
+
+/// A plane n . p + d = 0 with n pointing into the frustum
+#[derive(Clone, Copy)]
+struct Plane {
+    normal: Vector3,
+    distance: f32,
+}
+
+pub struct Frustum {
+    planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of projection * view, so they are in world space. A model matrix
+    /// multiplied in as well puts them in that model's space instead.
+    pub fn from_matrix(view_projection: &Matrix4) -> Self {
+        let row = |index: usize| view_projection.data[index];
+        let plane = |sign: f32, index: usize| {
+            let (last, other) = (row(3), row(index));
+            let coefficients: [f32; 4] = std::array::from_fn(|i| last[i] + sign * other[i]);
+            let normal = Vector3::new(coefficients[0], coefficients[1], coefficients[2]);
+            let length = normal.magnitude();
+            Plane {
+                normal: (1.0 / length) * normal,
+                distance: coefficients[3] / length,
+            }
+        };
+        // A point is inside when -w <= x, y, z <= w in clip space
+        Self {
+            planes: [
+                plane(1.0, 0),
+                plane(-1.0, 0),
+                plane(1.0, 1),
+                plane(-1.0, 1),
+                plane(1.0, 2),
+                plane(-1.0, 2),
+            ],
+        }
+    }
+
+    /// False only if the box is entirely outside one of the planes. Boxes near a corner of the
+    /// frustum can pass without being visible, which costs a draw but never drops one.
+    pub fn intersects(&self, bounds: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.normal.x >= 0.0 {
+                    bounds.max.x
+                } else {
+                    bounds.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    bounds.max.y
+                } else {
+                    bounds.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    bounds.max.z
+                } else {
+                    bounds.min.z
+                },
+            );
+            Vector3::dot_product(&plane.normal, &corner) + plane.distance >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, matrix::make_projection_matrix};
+
+    fn cube_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb {
+            min: Vector3::new(x - 1.0, y - 1.0, z - 1.0),
+            max: Vector3::new(x + 1.0, y + 1.0, z + 1.0),
+        }
+    }
+
+    #[test]
+    fn boxes_outside_any_plane_are_culled() {
+        // Looking down -z from the origin
+        let camera = Camera::new();
+        let projection = make_projection_matrix(1.0, 4.0 / 3.0, 0.1, 100.0);
+        let frustum = Frustum::from_matrix(&Matrix4::mult_mat4(&projection, &camera.view_matrix()));
+
+        assert!(frustum.intersects(&cube_at(0.0, 0.0, -10.0)));
+        // Straddling the near plane and the left edge still count
+        assert!(frustum.intersects(&cube_at(0.0, 0.0, 0.0)));
+        assert!(frustum.intersects(&cube_at(-7.0, 0.0, -10.0)));
+
+        assert!(!frustum.intersects(&cube_at(0.0, 0.0, 10.0)));
+        assert!(!frustum.intersects(&cube_at(50.0, 0.0, -10.0)));
+        assert!(!frustum.intersects(&cube_at(0.0, -50.0, -10.0)));
+        assert!(!frustum.intersects(&cube_at(0.0, 0.0, -200.0)));
+    }
+}
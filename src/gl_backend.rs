@@ -0,0 +1,1649 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_void},
+    ptr::null,
+};
+
+use glad_gl::gl::{
+    self, GLbitfield, GLchar, GLenum, GLfloat, GLint, GLsizei, GLuint, khr_debug::ObjectIdentifier,
+};
+
+use crate::{
+    shader::ShaderStage,
+    shader_reflection::ProgramReflection,
+    vertex_layout::{AttributeKind, VertexAttribute},
+};
+
+// Only the in-memory backend for tests checks shaders and state itself
+#[cfg(test)]
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+#[cfg(test)]
+use crate::{
+    glsl::{Diagnostic, Severity, StageInterface, check_program, lint_stage},
+    shader_reflection::base_name,
+};
+
+/// Client memory for a texture upload and how it is laid out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelFormat {
+    pub format: GLenum,
+    pub pixel_type: GLenum,
+}
+
+impl PixelFormat {
+    /// The size of one tightly packed pixel, or None for combinations this crate does not know
+    pub fn bytes_per_pixel(&self) -> Option<usize> {
+        let components = match self.format {
+            gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT | gl::STENCIL_INDEX => 1,
+            gl::RG | gl::RG_INTEGER | gl::DEPTH_STENCIL => 2,
+            gl::RGB | gl::BGR | gl::RGB_INTEGER => 3,
+            gl::RGBA | gl::BGRA | gl::RGBA_INTEGER | gl::BGRA_INTEGER => 4,
+            _ => return None,
+        };
+        Some(match self.pixel_type {
+            gl::UNSIGNED_BYTE | gl::BYTE => components,
+            gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2 * components,
+            gl::UNSIGNED_INT | gl::INT | gl::FLOAT => 4 * components,
+            // Packed types hold a whole pixel
+            gl::UNSIGNED_BYTE_3_3_2 => 1,
+            gl::UNSIGNED_SHORT_5_6_5 | gl::UNSIGNED_SHORT_4_4_4_4 | gl::UNSIGNED_SHORT_5_5_5_1 => 2,
+            gl::UNSIGNED_INT_8_8_8_8
+            | gl::UNSIGNED_INT_8_8_8_8_REV
+            | gl::UNSIGNED_INT_2_10_10_10_REV
+            | gl::UNSIGNED_INT_24_8 => 4,
+            _ => return None,
+        })
+    }
+}
+
+/// A uniform upload. Arrays hold their elements one after another and matrices are stored row
+/// by row, the way Matrix3 and Matrix4 keep them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue<'a> {
+    Int(&'a [i32]),
+    UInt(&'a [u32]),
+    Float(&'a [f32]),
+    Vec2(&'a [f32]),
+    Vec3(&'a [f32]),
+    Vec4(&'a [f32]),
+    Mat3(&'a [f32]),
+    Mat4(&'a [f32]),
+}
+
+/// The GL calls made by the object wrappers, shader programs and the render loop.
+///
+/// The wrappers in gl_objects never call glad-gl directly; they go through the current backend
+/// of the thread (see with_backend). GladBackend forwards to the driver, and in tests
+/// RecordingBackend keeps everything in memory so rendering code can run without a context.
+/// Only context creation, capability queries and debug output talk to the driver directly.
+pub trait GlBackend {
+    fn gen_buffer(&mut self) -> GLuint;
+    fn delete_buffer(&mut self, handle: GLuint);
+    fn bind_buffer(&mut self, target: GLenum, handle: GLuint);
+    fn bind_buffer_base(&mut self, target: GLenum, index: GLuint, handle: GLuint);
+    /// Allocates size bytes for the buffer bound to target, filled from data if given
+    fn buffer_data(&mut self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum);
+    fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]);
+    fn get_buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &mut [u8]);
+
+    fn gen_vertex_array(&mut self) -> GLuint;
+    fn delete_vertex_array(&mut self, handle: GLuint);
+    fn bind_vertex_array(&mut self, handle: GLuint);
+    /// Sources an attribute from the bound array buffer, vertices being stride bytes apart
+    fn vertex_attrib_pointer(&mut self, attribute: &VertexAttribute, stride: usize);
+    fn enable_vertex_attrib_array(&mut self, location: GLuint);
+    /// Draws count indices of index_type, starting offset bytes into the element buffer
+    fn draw_elements(&mut self, mode: GLenum, count: usize, index_type: GLenum, offset: usize);
+    fn draw_arrays(&mut self, mode: GLenum, first: usize, count: usize);
+
+    fn gen_texture(&mut self) -> GLuint;
+    fn delete_texture(&mut self, handle: GLuint);
+    /// Selects the texture unit (0 for TEXTURE0 and so on) that bind_texture affects
+    fn active_texture(&mut self, unit: u32);
+    fn bind_texture(&mut self, target: GLenum, handle: GLuint);
+    fn tex_parameter(&mut self, target: GLenum, name: GLenum, value: GLint);
+    /// Allocates the level of the texture bound to target, filled from pixels if given
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        internal_format: GLenum,
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: Option<&[u8]>,
+    );
+    fn tex_sub_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        origin: (u32, u32),
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: &[u8],
+    );
+    fn generate_mipmap(&mut self, target: GLenum);
+    fn pixel_store(&mut self, name: GLenum, value: GLint);
+
+    fn create_program(&mut self) -> GLuint;
+    fn delete_program(&mut self, handle: GLuint);
+    fn use_program(&mut self, handle: GLuint);
+    fn create_shader(&mut self, stage: ShaderStage) -> GLuint;
+    fn delete_shader(&mut self, handle: GLuint);
+    /// Compiles source into the shader. The error is the compile log.
+    fn compile_shader(&mut self, handle: GLuint, source: &CStr) -> Result<(), String>;
+    fn attach_shader(&mut self, program: GLuint, shader: GLuint);
+    fn detach_shader(&mut self, program: GLuint, shader: GLuint);
+    /// Names the outputs to capture with transform feedback. Takes effect when linking.
+    fn transform_feedback_varyings(&mut self, program: GLuint, varyings: &[CString], mode: GLenum);
+    /// Links the attached shaders. The error is the link log.
+    fn link_program(&mut self, handle: GLuint) -> Result<(), String>;
+    /// The active interface of a linked program
+    fn reflect_program(&mut self, handle: GLuint) -> ProgramReflection;
+    /// -1 if the program has no active uniform of that name
+    fn get_uniform_location(&mut self, program: GLuint, name: &CStr) -> GLint;
+    fn uniform_block_binding(&mut self, program: GLuint, block_index: GLuint, binding: GLuint);
+    /// Sets a uniform of the program in use
+    fn uniform(&mut self, location: GLint, value: UniformValue);
+
+    fn gen_query(&mut self) -> GLuint;
+    fn delete_query(&mut self, handle: GLuint);
+    fn begin_query(&mut self, target: GLenum, handle: GLuint);
+    fn end_query(&mut self, target: GLenum);
+    /// Waits for the result of an ended query
+    fn query_result(&mut self, handle: GLuint) -> GLuint;
+    fn begin_transform_feedback(&mut self, primitive: GLenum);
+    fn end_transform_feedback(&mut self);
+
+    /// Names an object for driver messages and capture tools. Does nothing without KHR_debug.
+    fn object_label(&mut self, identifier: ObjectIdentifier, handle: GLuint, label: &str);
+
+    fn enable(&mut self, capability: GLenum);
+    fn disable(&mut self, capability: GLenum);
+    fn blend_func(&mut self, source: GLenum, destination: GLenum);
+    fn depth_func(&mut self, function: GLenum);
+    fn depth_mask(&mut self, write: bool);
+    fn cull_face(&mut self, face: GLenum);
+    fn viewport(&mut self, x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+    fn clear_color(&mut self, red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat);
+    fn clear(&mut self, mask: GLbitfield);
+}
+
+thread_local! {
+    static BACKEND: RefCell<Box<dyn GlBackend>> = RefCell::new(Box::new(GladBackend));
+    /// Which uniform buffer binding points of the backend's context are taken, so that two
+    /// blocks never share one. Starts empty for every backend.
+    static UNIFORM_BINDINGS: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs f with the current thread's backend. Calls must not nest.
+pub fn with_backend<R>(f: impl FnOnce(&mut dyn GlBackend) -> R) -> R {
+    BACKEND.with(|backend| f(backend.borrow_mut().as_mut()))
+}
+
+/// Replaces the current thread's backend and returns the previous one
+pub fn set_backend(backend: Box<dyn GlBackend>) -> Box<dyn GlBackend> {
+    UNIFORM_BINDINGS.with(|bindings| bindings.borrow_mut().clear());
+    BACKEND.with(|current| std::mem::replace(&mut *current.borrow_mut(), backend))
+}
+
+/// Takes the lowest uniform buffer binding point of the current backend that is free and below
+/// limit, the context's GL_MAX_UNIFORM_BUFFER_BINDINGS
+pub fn allocate_uniform_binding(limit: usize) -> Option<GLuint> {
+    UNIFORM_BINDINGS.with(|bindings| {
+        let mut in_use = bindings.borrow_mut();
+        let binding = in_use.iter().position(|used| !used).unwrap_or(in_use.len());
+        if binding >= limit {
+            return None;
+        }
+        if binding == in_use.len() {
+            in_use.push(true);
+        } else {
+            in_use[binding] = true;
+        }
+        Some(binding as GLuint)
+    })
+}
+
+/// Gives back a binding point taken with allocate_uniform_binding
+pub fn release_uniform_binding(binding: GLuint) {
+    UNIFORM_BINDINGS.with(|bindings| {
+        if let Some(used) = bindings.borrow_mut().get_mut(binding as usize) {
+            *used = false;
+        }
+    });
+}
+
+/// Forwards every call to the driver through glad-gl. The context must be current and loaded.
+pub struct GladBackend;
+
+impl GlBackend for GladBackend {
+    fn gen_buffer(&mut self) -> GLuint {
+        let mut handle: GLuint = 0;
+        unsafe { gl::GenBuffers(1, &mut handle as *mut GLuint) };
+        handle
+    }
+
+    fn delete_buffer(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteBuffers(1, &handle as *const GLuint) }
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, handle: GLuint) {
+        unsafe { gl::BindBuffer(target, handle) }
+    }
+
+    fn bind_buffer_base(&mut self, target: GLenum, index: GLuint, handle: GLuint) {
+        unsafe { gl::BindBufferBase(target, index, handle) }
+    }
+
+    fn buffer_data(&mut self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum) {
+        // The driver would read past the end of a shorter slice
+        assert!(data.is_none_or(|data| data.len() >= size));
+        let pointer = data.map_or(null(), |data| data.as_ptr() as *const c_void);
+        unsafe { gl::BufferData(target, size as isize, pointer, usage) }
+    }
+
+    fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]) {
+        unsafe {
+            gl::BufferSubData(
+                target,
+                offset as isize,
+                data.len() as isize,
+                data.as_ptr() as *const c_void,
+            )
+        }
+    }
+
+    fn get_buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &mut [u8]) {
+        unsafe {
+            gl::GetBufferSubData(
+                target,
+                offset as isize,
+                data.len() as isize,
+                data.as_mut_ptr() as *mut c_void,
+            )
+        }
+    }
+
+    fn gen_vertex_array(&mut self) -> GLuint {
+        let mut handle: GLuint = 0;
+        unsafe { gl::GenVertexArrays(1, &mut handle as *mut GLuint) };
+        handle
+    }
+
+    fn delete_vertex_array(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteVertexArrays(1, &handle as *const GLuint) }
+    }
+
+    fn bind_vertex_array(&mut self, handle: GLuint) {
+        unsafe { gl::BindVertexArray(handle) }
+    }
+
+    fn vertex_attrib_pointer(&mut self, attribute: &VertexAttribute, stride: usize) {
+        unsafe {
+            if attribute.kind == AttributeKind::Integer {
+                gl::VertexAttribIPointer(
+                    attribute.location,
+                    attribute.count,
+                    attribute.component_type.gl_enum(),
+                    stride as GLsizei,
+                    attribute.offset as *const c_void,
+                );
+            } else {
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.count,
+                    attribute.component_type.gl_enum(),
+                    if attribute.kind == AttributeKind::Normalized {
+                        gl::TRUE
+                    } else {
+                        gl::FALSE
+                    },
+                    stride as GLsizei,
+                    attribute.offset as *const c_void,
+                );
+            }
+        }
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: GLuint) {
+        unsafe { gl::EnableVertexAttribArray(location) }
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: usize, index_type: GLenum, offset: usize) {
+        unsafe { gl::DrawElements(mode, count as GLsizei, index_type, offset as *const c_void) }
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: usize, count: usize) {
+        unsafe { gl::DrawArrays(mode, first as GLint, count as GLsizei) }
+    }
+
+    fn gen_texture(&mut self) -> GLuint {
+        let mut handle: GLuint = 0;
+        unsafe { gl::GenTextures(1, &mut handle as *mut GLuint) };
+        handle
+    }
+
+    fn delete_texture(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteTextures(1, &handle as *const GLuint) }
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) }
+    }
+
+    fn bind_texture(&mut self, target: GLenum, handle: GLuint) {
+        unsafe { gl::BindTexture(target, handle) }
+    }
+
+    fn tex_parameter(&mut self, target: GLenum, name: GLenum, value: GLint) {
+        unsafe { gl::TexParameteri(target, name, value) }
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        internal_format: GLenum,
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: Option<&[u8]>,
+    ) {
+        let pointer = pixels.map_or(null(), |pixels| pixels.as_ptr() as *const c_void);
+        unsafe {
+            gl::TexImage2D(
+                target,
+                level,
+                internal_format as GLint,
+                size.0 as GLsizei,
+                size.1 as GLsizei,
+                0,
+                format.format,
+                format.pixel_type,
+                pointer,
+            )
+        }
+    }
+
+    fn tex_sub_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        origin: (u32, u32),
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: &[u8],
+    ) {
+        unsafe {
+            gl::TexSubImage2D(
+                target,
+                level,
+                origin.0 as GLint,
+                origin.1 as GLint,
+                size.0 as GLsizei,
+                size.1 as GLsizei,
+                format.format,
+                format.pixel_type,
+                pixels.as_ptr() as *const c_void,
+            )
+        }
+    }
+
+    fn generate_mipmap(&mut self, target: GLenum) {
+        unsafe { gl::GenerateMipmap(target) }
+    }
+
+    fn pixel_store(&mut self, name: GLenum, value: GLint) {
+        unsafe { gl::PixelStorei(name, value) }
+    }
+
+    fn create_program(&mut self) -> GLuint {
+        unsafe { gl::CreateProgram() }
+    }
+
+    fn delete_program(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteProgram(handle) }
+    }
+
+    fn use_program(&mut self, handle: GLuint) {
+        unsafe { gl::UseProgram(handle) }
+    }
+
+    fn create_shader(&mut self, stage: ShaderStage) -> GLuint {
+        unsafe { gl::CreateShader(stage.gl_enum()) }
+    }
+
+    fn delete_shader(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteShader(handle) }
+    }
+
+    fn compile_shader(&mut self, handle: GLuint, source: &CStr) -> Result<(), String> {
+        unsafe {
+            let source_ptr = source.as_ptr();
+            gl::ShaderSource(handle, 1, &source_ptr as *const *const GLchar, null());
+            gl::CompileShader(handle);
+
+            let mut success: i32 = 0;
+            gl::GetShaderiv(handle, gl::COMPILE_STATUS, &mut success as *mut i32);
+            if success == 0 {
+                return Err(info_log(handle, gl::GetShaderiv, gl::GetShaderInfoLog));
+            }
+        }
+        Ok(())
+    }
+
+    fn attach_shader(&mut self, program: GLuint, shader: GLuint) {
+        unsafe { gl::AttachShader(program, shader) }
+    }
+
+    fn detach_shader(&mut self, program: GLuint, shader: GLuint) {
+        unsafe { gl::DetachShader(program, shader) }
+    }
+
+    fn transform_feedback_varyings(&mut self, program: GLuint, varyings: &[CString], mode: GLenum) {
+        let varying_ptrs: Vec<*const GLchar> = varyings.iter().map(|name| name.as_ptr()).collect();
+        unsafe {
+            gl::TransformFeedbackVaryings(
+                program,
+                varying_ptrs.len() as GLsizei,
+                varying_ptrs.as_ptr(),
+                mode,
+            )
+        }
+    }
+
+    fn link_program(&mut self, handle: GLuint) -> Result<(), String> {
+        unsafe {
+            gl::LinkProgram(handle);
+
+            let mut success: i32 = 0;
+            gl::GetProgramiv(handle, gl::LINK_STATUS, &mut success as *mut i32);
+            if success == 0 {
+                return Err(info_log(handle, gl::GetProgramiv, gl::GetProgramInfoLog));
+            }
+        }
+        Ok(())
+    }
+
+    fn reflect_program(&mut self, handle: GLuint) -> ProgramReflection {
+        ProgramReflection::query(handle)
+    }
+
+    fn get_uniform_location(&mut self, program: GLuint, name: &CStr) -> GLint {
+        unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+    }
+
+    fn uniform_block_binding(&mut self, program: GLuint, block_index: GLuint, binding: GLuint) {
+        unsafe { gl::UniformBlockBinding(program, block_index, binding) }
+    }
+
+    fn uniform(&mut self, location: GLint, value: UniformValue) {
+        unsafe {
+            match value {
+                UniformValue::Int(values) => {
+                    gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr())
+                }
+                UniformValue::UInt(values) => {
+                    gl::Uniform1uiv(location, values.len() as GLsizei, values.as_ptr())
+                }
+                UniformValue::Float(values) => {
+                    gl::Uniform1fv(location, values.len() as GLsizei, values.as_ptr())
+                }
+                UniformValue::Vec2(values) => {
+                    gl::Uniform2fv(location, (values.len() / 2) as GLsizei, values.as_ptr())
+                }
+                UniformValue::Vec3(values) => {
+                    gl::Uniform3fv(location, (values.len() / 3) as GLsizei, values.as_ptr())
+                }
+                UniformValue::Vec4(values) => {
+                    gl::Uniform4fv(location, (values.len() / 4) as GLsizei, values.as_ptr())
+                }
+                UniformValue::Mat3(values) => gl::UniformMatrix3fv(
+                    location,
+                    (values.len() / 9) as GLsizei,
+                    gl::TRUE,
+                    values.as_ptr(),
+                ),
+                UniformValue::Mat4(values) => gl::UniformMatrix4fv(
+                    location,
+                    (values.len() / 16) as GLsizei,
+                    gl::TRUE,
+                    values.as_ptr(),
+                ),
+            }
+        }
+    }
+
+    fn gen_query(&mut self) -> GLuint {
+        let mut handle: GLuint = 0;
+        unsafe { gl::GenQueries(1, &mut handle as *mut GLuint) };
+        handle
+    }
+
+    fn delete_query(&mut self, handle: GLuint) {
+        unsafe { gl::DeleteQueries(1, &handle as *const GLuint) }
+    }
+
+    fn begin_query(&mut self, target: GLenum, handle: GLuint) {
+        unsafe { gl::BeginQuery(target, handle) }
+    }
+
+    fn end_query(&mut self, target: GLenum) {
+        unsafe { gl::EndQuery(target) }
+    }
+
+    fn query_result(&mut self, handle: GLuint) -> GLuint {
+        let mut result: GLuint = 0;
+        unsafe { gl::GetQueryObjectuiv(handle, gl::QUERY_RESULT, &mut result as *mut GLuint) };
+        result
+    }
+
+    fn begin_transform_feedback(&mut self, primitive: GLenum) {
+        unsafe { gl::BeginTransformFeedback(primitive) }
+    }
+
+    fn end_transform_feedback(&mut self) {
+        unsafe { gl::EndTransformFeedback() }
+    }
+
+    fn object_label(&mut self, identifier: ObjectIdentifier, handle: GLuint, label: &str) {
+        if !gl::khr_debug::is_available() {
+            return;
+        }
+        // Unsupported or not, a missing label must never stop rendering
+        let _ = unsafe {
+            gl::khr_debug::ObjectLabel(
+                identifier as GLenum,
+                handle,
+                label.len() as GLsizei,
+                label.as_ptr() as *const _,
+            )
+        };
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        unsafe { gl::Enable(capability) }
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        unsafe { gl::Disable(capability) }
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        unsafe { gl::BlendFunc(source, destination) }
+    }
+
+    fn depth_func(&mut self, function: GLenum) {
+        unsafe { gl::DepthFunc(function) }
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        unsafe { gl::DepthMask(if write { gl::TRUE } else { gl::FALSE }) }
+    }
+
+    fn cull_face(&mut self, face: GLenum) {
+        unsafe { gl::CullFace(face) }
+    }
+
+    fn viewport(&mut self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        unsafe { gl::Viewport(x, y, width, height) }
+    }
+
+    fn clear_color(&mut self, red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {
+        unsafe { gl::ClearColor(red, green, blue, alpha) }
+    }
+
+    fn clear(&mut self, mask: GLbitfield) {
+        unsafe { gl::Clear(mask) }
+    }
+}
+
+/// Reads the info log of a shader or program with the matching pair of getters
+unsafe fn info_log(
+    handle: GLuint,
+    get_parameter: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    unsafe {
+        let mut log_length: GLint = 0;
+        get_parameter(handle, gl::INFO_LOG_LENGTH, &mut log_length as *mut GLint);
+        let mut info_log = vec![0u8; log_length.max(1) as usize];
+        let mut written: GLsizei = 0;
+        get_log(
+            handle,
+            info_log.len() as GLsizei,
+            &mut written as *mut GLsizei,
+            info_log.as_mut_ptr() as *mut GLchar,
+        );
+        String::from_utf8_lossy(&info_log[..written.max(0) as usize]).into_owned()
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    Program,
+    Shader,
+    Query,
+}
+
+/// A draw recorded by RecordingBackend together with the state it used
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawCall {
+    pub mode: GLenum,
+    /// Index count for DrawElements, vertex count for DrawArrays
+    pub count: usize,
+    /// None for DrawArrays
+    pub index_type: Option<GLenum>,
+    pub first: usize,
+    pub vertex_array: GLuint,
+    pub program: GLuint,
+    /// Texture bound to TEXTURE_2D on each unit, ordered by unit
+    pub textures: Vec<(u32, GLuint)>,
+}
+
+#[cfg(test)]
+struct Recording {
+    next_handle: GLuint,
+    /// Hand out the names of deleted objects again instead of fresh ones
+    reuse_names: bool,
+    objects: HashMap<GLuint, ObjectKind>,
+    buffer_contents: HashMap<GLuint, Vec<u8>>,
+    buffer_bindings: HashMap<GLenum, GLuint>,
+    /// Element buffer recorded in each vertex array's state
+    element_buffers: HashMap<GLuint, GLuint>,
+    vertex_array: GLuint,
+    active_unit: u32,
+    textures: HashMap<u32, GLuint>,
+    texture_sizes: HashMap<GLuint, (u32, u32)>,
+    labels: HashMap<GLuint, String>,
+    program: GLuint,
+    /// The stage of each shader, and its interface once it compiled
+    shaders: HashMap<GLuint, (ShaderStage, Option<StageInterface>)>,
+    attached_shaders: HashMap<GLuint, Vec<GLuint>>,
+    feedback_varyings: HashMap<GLuint, Vec<String>>,
+    /// What each successfully linked program reports about its interface
+    linked_programs: HashMap<GLuint, ProgramReflection>,
+    /// The query counting on each target and the primitives written when it began
+    active_queries: HashMap<GLenum, (GLuint, u32)>,
+    query_results: HashMap<GLuint, GLuint>,
+    /// The primitive type captured while transform feedback is active
+    feedback_primitive: Option<GLenum>,
+    primitives_written: u32,
+    enabled: HashSet<GLenum>,
+    blend_func: (GLenum, GLenum),
+    depth_func: GLenum,
+    depth_mask: bool,
+    cull_face: GLenum,
+    viewport: (GLint, GLint, GLsizei, GLsizei),
+    draw_calls: Vec<DrawCall>,
+    /// How often each bind reached the backend, by GL function name
+    binds: HashMap<&'static str, usize>,
+    errors: Vec<String>,
+}
+
+#[cfg(test)]
+impl Default for Recording {
+    /// Starts from GL's initial state
+    fn default() -> Self {
+        Self {
+            next_handle: 0,
+            reuse_names: false,
+            objects: HashMap::new(),
+            buffer_contents: HashMap::new(),
+            buffer_bindings: HashMap::new(),
+            element_buffers: HashMap::new(),
+            vertex_array: 0,
+            active_unit: 0,
+            textures: HashMap::new(),
+            texture_sizes: HashMap::new(),
+            labels: HashMap::new(),
+            program: 0,
+            shaders: HashMap::new(),
+            attached_shaders: HashMap::new(),
+            feedback_varyings: HashMap::new(),
+            linked_programs: HashMap::new(),
+            active_queries: HashMap::new(),
+            query_results: HashMap::new(),
+            feedback_primitive: None,
+            primitives_written: 0,
+            enabled: HashSet::new(),
+            blend_func: (gl::ONE, gl::ZERO),
+            depth_func: gl::LESS,
+            depth_mask: true,
+            cull_face: gl::BACK,
+            viewport: (0, 0, 0, 0),
+            draw_calls: Vec::new(),
+            binds: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Recording {
+    fn create(&mut self, kind: ObjectKind) -> GLuint {
+        if self.reuse_names
+            && let Some(handle) = (1..=self.next_handle).find(|h| !self.objects.contains_key(h))
+        {
+            self.objects.insert(handle, kind);
+            return handle;
+        }
+        // Handles are unique across kinds so that mixing them up is detected
+        self.next_handle += 1;
+        self.objects.insert(self.next_handle, kind);
+        self.next_handle
+    }
+
+    fn delete(&mut self, kind: ObjectKind, handle: GLuint) {
+        // Deleting 0 is allowed and ignored, like in GL
+        if handle == 0 {
+            return;
+        }
+        if self.objects.get(&handle) != Some(&kind) {
+            self.errors
+                .push(format!("deleted {:?} {} which is not alive", kind, handle));
+            return;
+        }
+        self.objects.remove(&handle);
+        self.labels.remove(&handle);
+
+        // Deleting a bound object unbinds it
+        match kind {
+            ObjectKind::Buffer => {
+                self.buffer_contents.remove(&handle);
+                self.buffer_bindings.retain(|_, bound| *bound != handle);
+                self.element_buffers.retain(|_, bound| *bound != handle);
+            }
+            ObjectKind::VertexArray => {
+                self.element_buffers.remove(&handle);
+                if self.vertex_array == handle {
+                    self.vertex_array = 0;
+                }
+            }
+            ObjectKind::Texture => {
+                self.texture_sizes.remove(&handle);
+                self.textures.retain(|_, bound| *bound != handle);
+            }
+            // A program in use stays usable until it is replaced
+            ObjectKind::Program => {
+                self.attached_shaders.remove(&handle);
+                self.feedback_varyings.remove(&handle);
+                if self.program != handle {
+                    self.linked_programs.remove(&handle);
+                }
+            }
+            // Attached shaders live on until their programs are deleted, which only matters to
+            // a driver
+            ObjectKind::Shader => {
+                self.shaders.remove(&handle);
+            }
+            ObjectKind::Query => {
+                self.query_results.remove(&handle);
+            }
+        }
+    }
+
+    fn check_alive(&mut self, kind: ObjectKind, handle: GLuint, action: &str) -> bool {
+        if handle == 0 || self.objects.get(&handle) == Some(&kind) {
+            true
+        } else {
+            self.errors.push(format!(
+                "{} {:?} {} which is not alive",
+                action, kind, handle
+            ));
+            false
+        }
+    }
+
+    fn bound_buffer(&mut self, target: GLenum, action: &str) -> Option<GLuint> {
+        let handle = if target == gl::ELEMENT_ARRAY_BUFFER {
+            self.element_buffers.get(&self.vertex_array).copied()
+        } else {
+            self.buffer_bindings.get(&target).copied()
+        };
+        match handle {
+            Some(handle) if handle != 0 => Some(handle),
+            _ => {
+                self.errors.push(format!(
+                    "{} with no buffer bound to 0x{:04X}",
+                    action, target
+                ));
+                None
+            }
+        }
+    }
+
+    fn bound_texture(&mut self, action: &str) -> Option<GLuint> {
+        match self.textures.get(&self.active_unit) {
+            Some(handle) if *handle != 0 => Some(*handle),
+            _ => {
+                self.errors.push(format!(
+                    "{} with no texture bound to unit {}",
+                    action, self.active_unit
+                ));
+                None
+            }
+        }
+    }
+
+    fn check_draw(&mut self, action: &str) {
+        if self.vertex_array == 0 {
+            self.errors
+                .push(format!("{} with no vertex array bound", action));
+        }
+        if self.program == 0 {
+            self.errors
+                .push(format!("{} with no program in use", action));
+        }
+    }
+
+    /// Counts the primitives a draw adds to an active transform feedback capture
+    fn capture(&mut self, action: &str, mode: GLenum, count: usize) {
+        let Some(primitive) = self.feedback_primitive else {
+            return;
+        };
+        let (captured_as, primitives) = match mode {
+            gl::POINTS => (gl::POINTS, count),
+            gl::LINES => (gl::LINES, count / 2),
+            gl::LINE_STRIP => (gl::LINES, count.saturating_sub(1)),
+            gl::LINE_LOOP => (gl::LINES, if count < 2 { 0 } else { count }),
+            gl::TRIANGLES => (gl::TRIANGLES, count / 3),
+            gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => (gl::TRIANGLES, count.saturating_sub(2)),
+            _ => (mode, 0),
+        };
+        if captured_as != primitive {
+            self.errors.push(format!(
+                "{} with mode 0x{:04X} while capturing primitive 0x{:04X}",
+                action, mode, primitive
+            ));
+            return;
+        }
+        self.primitives_written += primitives as u32;
+    }
+
+    fn draw_call(
+        &self,
+        mode: GLenum,
+        count: usize,
+        index_type: Option<GLenum>,
+        first: usize,
+    ) -> DrawCall {
+        let mut textures: Vec<(u32, GLuint)> = self
+            .textures
+            .iter()
+            .filter(|(_, handle)| **handle != 0)
+            .map(|(unit, handle)| (*unit, *handle))
+            .collect();
+        textures.sort();
+        DrawCall {
+            mode,
+            count,
+            index_type,
+            first,
+            vertex_array: self.vertex_array,
+            program: self.program,
+            textures,
+        }
+    }
+}
+
+/// The errors among diagnostics, one per line like a driver's info log
+#[cfg(test)]
+fn errors_log(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An in-memory backend that tracks objects, bindings and draws instead of rendering.
+///
+/// Clones share the same recording, so a test can install one clone with set_backend and keep
+/// another to inspect. Misuse that a driver would answer with GL_INVALID_OPERATION or undefined
+/// behaviour (binding deleted objects, drawing without a vertex array, out of range writes) is
+/// collected in errors() instead of panicking.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct RecordingBackend {
+    recording: Rc<RefCell<Recording>>,
+}
+
+#[cfg(test)]
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A backend that gives out the names of deleted objects again, as drivers do, for testing
+    /// code that remembers names. Using a deleted object can then go unnoticed.
+    pub fn reusing_names() -> Self {
+        let backend = Self::default();
+        backend.recording.borrow_mut().reuse_names = true;
+        backend
+    }
+
+    pub fn draw_calls(&self) -> Vec<DrawCall> {
+        self.recording.borrow().draw_calls.clone()
+    }
+
+    /// Objects that were created and not deleted yet, sorted by handle
+    pub fn live_objects(&self) -> Vec<(ObjectKind, GLuint)> {
+        let mut objects: Vec<(ObjectKind, GLuint)> = self
+            .recording
+            .borrow()
+            .objects
+            .iter()
+            .map(|(handle, kind)| (*kind, *handle))
+            .collect();
+        objects.sort_by_key(|(_, handle)| *handle);
+        objects
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        self.recording.borrow().errors.clone()
+    }
+
+    /// Contents of a buffer as last uploaded
+    pub fn buffer_contents(&self, handle: GLuint) -> Option<Vec<u8>> {
+        self.recording
+            .borrow()
+            .buffer_contents
+            .get(&handle)
+            .cloned()
+    }
+
+    pub fn label(&self, handle: GLuint) -> Option<String> {
+        self.recording.borrow().labels.get(&handle).cloned()
+    }
+
+    pub fn current_program(&self) -> GLuint {
+        self.recording.borrow().program
+    }
+
+    pub fn current_vertex_array(&self) -> GLuint {
+        self.recording.borrow().vertex_array
+    }
+
+    /// Texture bound to TEXTURE_2D on a unit, or 0
+    pub fn bound_texture(&self, unit: u32) -> GLuint {
+        self.recording
+            .borrow()
+            .textures
+            .get(&unit)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn is_enabled(&self, capability: GLenum) -> bool {
+        self.recording.borrow().enabled.contains(&capability)
+    }
+
+    /// (source, destination) factors
+    pub fn blend_func(&self) -> (GLenum, GLenum) {
+        self.recording.borrow().blend_func
+    }
+
+    pub fn depth_func(&self) -> GLenum {
+        self.recording.borrow().depth_func
+    }
+
+    pub fn depth_mask(&self) -> bool {
+        self.recording.borrow().depth_mask
+    }
+
+    pub fn cull_face(&self) -> GLenum {
+        self.recording.borrow().cull_face
+    }
+
+    pub fn viewport(&self) -> (GLint, GLint, GLsizei, GLsizei) {
+        self.recording.borrow().viewport
+    }
+
+    /// How many times a bind call such as "UseProgram" or "BindTexture" was made
+    pub fn bind_count(&self, name: &str) -> usize {
+        self.recording
+            .borrow()
+            .binds
+            .get(name)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Forgets recorded draws, bind counts and errors, e.g. between frames. Objects and state
+    /// are kept.
+    pub fn clear_log(&self) {
+        let mut recording = self.recording.borrow_mut();
+        recording.draw_calls.clear();
+        recording.binds.clear();
+        recording.errors.clear();
+    }
+}
+
+#[cfg(test)]
+impl GlBackend for RecordingBackend {
+    fn gen_buffer(&mut self) -> GLuint {
+        self.recording.borrow_mut().create(ObjectKind::Buffer)
+    }
+
+    fn delete_buffer(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::Buffer, handle);
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, handle: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        *recording.binds.entry("BindBuffer").or_default() += 1;
+        if !recording.check_alive(ObjectKind::Buffer, handle, "bound") {
+            return;
+        }
+        // The element buffer binding is part of the vertex array's state
+        if target == gl::ELEMENT_ARRAY_BUFFER {
+            let vertex_array = recording.vertex_array;
+            recording.element_buffers.insert(vertex_array, handle);
+        } else {
+            recording.buffer_bindings.insert(target, handle);
+        }
+    }
+
+    fn bind_buffer_base(&mut self, target: GLenum, _index: GLuint, handle: GLuint) {
+        // Also binds the generic binding point, like GL does
+        self.bind_buffer(target, handle);
+    }
+
+    fn buffer_data(&mut self, target: GLenum, size: usize, data: Option<&[u8]>, _usage: GLenum) {
+        let mut recording = self.recording.borrow_mut();
+        let Some(handle) = recording.bound_buffer(target, "BufferData") else {
+            return;
+        };
+        let contents = match data.map(|data| data.get(..size)) {
+            Some(Some(data)) => data.to_vec(),
+            Some(None) => {
+                let length = data.map_or(0, <[u8]>::len);
+                recording.errors.push(format!(
+                    "BufferData of {} bytes into Buffer {} from {} bytes of data",
+                    size, handle, length
+                ));
+                return;
+            }
+            None => vec![0; size],
+        };
+        recording.buffer_contents.insert(handle, contents);
+    }
+
+    fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]) {
+        let mut recording = self.recording.borrow_mut();
+        let Some(handle) = recording.bound_buffer(target, "BufferSubData") else {
+            return;
+        };
+        let contents = recording.buffer_contents.entry(handle).or_default();
+        if offset + data.len() > contents.len() {
+            let size = contents.len();
+            recording.errors.push(format!(
+                "BufferSubData of {} bytes at offset {} overflows buffer {} of {} bytes",
+                data.len(),
+                offset,
+                handle,
+                size
+            ));
+            return;
+        }
+        contents[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn get_buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &mut [u8]) {
+        let mut recording = self.recording.borrow_mut();
+        let Some(handle) = recording.bound_buffer(target, "GetBufferSubData") else {
+            return;
+        };
+        let contents = recording.buffer_contents.entry(handle).or_default();
+        if offset + data.len() > contents.len() {
+            let size = contents.len();
+            recording.errors.push(format!(
+                "GetBufferSubData of {} bytes at offset {} overflows buffer {} of {} bytes",
+                data.len(),
+                offset,
+                handle,
+                size
+            ));
+            return;
+        }
+        data.copy_from_slice(&contents[offset..offset + data.len()]);
+    }
+
+    fn gen_vertex_array(&mut self) -> GLuint {
+        self.recording.borrow_mut().create(ObjectKind::VertexArray)
+    }
+
+    fn delete_vertex_array(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::VertexArray, handle);
+    }
+
+    fn bind_vertex_array(&mut self, handle: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        *recording.binds.entry("BindVertexArray").or_default() += 1;
+        if recording.check_alive(ObjectKind::VertexArray, handle, "bound") {
+            recording.vertex_array = handle;
+        }
+    }
+
+    fn vertex_attrib_pointer(&mut self, attribute: &VertexAttribute, _stride: usize) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.vertex_array == 0 {
+            recording.errors.push(format!(
+                "set attribute {} with no vertex array bound",
+                attribute.location
+            ));
+        }
+        recording.bound_buffer(gl::ARRAY_BUFFER, "VertexAttribPointer");
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.vertex_array == 0 {
+            recording.errors.push(format!(
+                "enabled attribute {} with no vertex array bound",
+                location
+            ));
+        }
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: usize, index_type: GLenum, offset: usize) {
+        let mut recording = self.recording.borrow_mut();
+        recording.check_draw("DrawElements");
+        if let Some(element_buffer) =
+            recording.bound_buffer(gl::ELEMENT_ARRAY_BUFFER, "DrawElements")
+        {
+            let index_size = match index_type {
+                gl::UNSIGNED_BYTE => 1,
+                gl::UNSIGNED_SHORT => 2,
+                _ => 4,
+            };
+            let size = recording
+                .buffer_contents
+                .get(&element_buffer)
+                .map_or(0, |contents| contents.len());
+            if offset + count * index_size > size {
+                recording.errors.push(format!(
+                    "DrawElements reads {} indices past the end of element buffer {}",
+                    count, element_buffer
+                ));
+            }
+        }
+        recording.capture("DrawElements", mode, count);
+        let draw = recording.draw_call(mode, count, Some(index_type), offset);
+        recording.draw_calls.push(draw);
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: usize, count: usize) {
+        let mut recording = self.recording.borrow_mut();
+        recording.check_draw("DrawArrays");
+        recording.capture("DrawArrays", mode, count);
+        let draw = recording.draw_call(mode, count, None, first);
+        recording.draw_calls.push(draw);
+    }
+
+    fn gen_texture(&mut self) -> GLuint {
+        self.recording.borrow_mut().create(ObjectKind::Texture)
+    }
+
+    fn delete_texture(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::Texture, handle);
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        let mut recording = self.recording.borrow_mut();
+        *recording.binds.entry("ActiveTexture").or_default() += 1;
+        recording.active_unit = unit;
+    }
+
+    fn bind_texture(&mut self, _target: GLenum, handle: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        *recording.binds.entry("BindTexture").or_default() += 1;
+        if recording.check_alive(ObjectKind::Texture, handle, "bound") {
+            let unit = recording.active_unit;
+            recording.textures.insert(unit, handle);
+        }
+    }
+
+    fn tex_parameter(&mut self, _target: GLenum, _name: GLenum, _value: GLint) {
+        self.recording.borrow_mut().bound_texture("TexParameteri");
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        _target: GLenum,
+        level: GLint,
+        _internal_format: GLenum,
+        size: (u32, u32),
+        _format: PixelFormat,
+        _pixels: Option<&[u8]>,
+    ) {
+        let mut recording = self.recording.borrow_mut();
+        if let Some(handle) = recording.bound_texture("TexImage2D")
+            && level == 0
+        {
+            recording.texture_sizes.insert(handle, size);
+        }
+    }
+
+    fn tex_sub_image_2d(
+        &mut self,
+        _target: GLenum,
+        _level: GLint,
+        origin: (u32, u32),
+        size: (u32, u32),
+        _format: PixelFormat,
+        _pixels: &[u8],
+    ) {
+        let mut recording = self.recording.borrow_mut();
+        let Some(handle) = recording.bound_texture("TexSubImage2D") else {
+            return;
+        };
+        let (width, height) = recording
+            .texture_sizes
+            .get(&handle)
+            .copied()
+            .unwrap_or((0, 0));
+        if origin.0 + size.0 > width || origin.1 + size.1 > height {
+            recording.errors.push(format!(
+                "TexSubImage2D region {:?}+{:?} is outside texture {} of {}x{}",
+                origin, size, handle, width, height
+            ));
+        }
+    }
+
+    fn generate_mipmap(&mut self, _target: GLenum) {
+        self.recording.borrow_mut().bound_texture("GenerateMipmap");
+    }
+
+    fn pixel_store(&mut self, _name: GLenum, _value: GLint) {}
+
+    fn create_program(&mut self) -> GLuint {
+        self.recording.borrow_mut().create(ObjectKind::Program)
+    }
+
+    fn delete_program(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::Program, handle);
+    }
+
+    fn use_program(&mut self, handle: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        *recording.binds.entry("UseProgram").or_default() += 1;
+        if !recording.check_alive(ObjectKind::Program, handle, "used") {
+            return;
+        }
+        if handle != 0 && !recording.linked_programs.contains_key(&handle) {
+            recording
+                .errors
+                .push(format!("used Program {} which is not linked", handle));
+            return;
+        }
+        // A deleted program goes away once it is no longer in use
+        let previous = recording.program;
+        if previous != 0 && !recording.objects.contains_key(&previous) {
+            recording.linked_programs.remove(&previous);
+        }
+        recording.program = handle;
+    }
+
+    fn create_shader(&mut self, stage: ShaderStage) -> GLuint {
+        let mut recording = self.recording.borrow_mut();
+        let handle = recording.create(ObjectKind::Shader);
+        recording.shaders.insert(handle, (stage, None));
+        handle
+    }
+
+    fn delete_shader(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::Shader, handle);
+    }
+
+    /// Compiles by running the source through the glsl front end, whose errors become the log
+    fn compile_shader(&mut self, handle: GLuint, source: &CStr) -> Result<(), String> {
+        let mut recording = self.recording.borrow_mut();
+        let Some((stage, interface)) = recording.shaders.get_mut(&handle) else {
+            let message = format!("compiled Shader {} which is not alive", handle);
+            recording.errors.push(message.clone());
+            return Err(message);
+        };
+        let label = format!("shader {}", handle);
+        let (stage_interface, diagnostics) = lint_stage(*stage, &label, &source.to_string_lossy());
+        let log = errors_log(&diagnostics);
+        if !log.is_empty() {
+            *interface = None;
+            return Err(log);
+        }
+        *interface = Some(stage_interface);
+        Ok(())
+    }
+
+    fn attach_shader(&mut self, program: GLuint, shader: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.check_alive(ObjectKind::Program, program, "attached to")
+            && recording.check_alive(ObjectKind::Shader, shader, "attached")
+        {
+            recording
+                .attached_shaders
+                .entry(program)
+                .or_default()
+                .push(shader);
+        }
+    }
+
+    fn detach_shader(&mut self, program: GLuint, shader: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        let attached = recording.attached_shaders.entry(program).or_default();
+        match attached.iter().position(|attached| *attached == shader) {
+            Some(position) => {
+                attached.remove(position);
+            }
+            None => recording.errors.push(format!(
+                "detached Shader {} which is not attached to Program {}",
+                shader, program
+            )),
+        }
+    }
+
+    fn transform_feedback_varyings(
+        &mut self,
+        program: GLuint,
+        varyings: &[CString],
+        _mode: GLenum,
+    ) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.check_alive(ObjectKind::Program, program, "set varyings of") {
+            let names = varyings
+                .iter()
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            recording.feedback_varyings.insert(program, names);
+        }
+    }
+
+    /// Links by checking the attached stages against each other with the glsl front end and
+    /// working out the interface from their source
+    fn link_program(&mut self, handle: GLuint) -> Result<(), String> {
+        let mut recording = self.recording.borrow_mut();
+        if handle == 0 || !recording.check_alive(ObjectKind::Program, handle, "linked") {
+            return Err(format!("Program {} is not alive", handle));
+        }
+        let attached = recording
+            .attached_shaders
+            .get(&handle)
+            .cloned()
+            .unwrap_or_default();
+        let mut stages = Vec::with_capacity(attached.len());
+        for shader in attached {
+            match recording.shaders.get(&shader) {
+                Some((_, Some(interface))) => stages.push(interface.clone()),
+                _ => return Err(format!("shader {} is not compiled", shader)),
+            }
+        }
+        if !stages
+            .iter()
+            .any(|stage| stage.stage == ShaderStage::Vertex)
+        {
+            return Err("the program has no vertex shader".to_string());
+        }
+        // Pipeline order, which check_program expects
+        stages.sort_by_key(|stage| match stage.stage {
+            ShaderStage::Vertex => 0,
+            ShaderStage::Geometry => 1,
+            ShaderStage::Fragment => 2,
+        });
+        let log = errors_log(&check_program(&stages));
+        if !log.is_empty() {
+            return Err(log);
+        }
+        let varyings = recording
+            .feedback_varyings
+            .get(&handle)
+            .cloned()
+            .unwrap_or_default();
+        let reflection = ProgramReflection::from_interfaces(&stages, &varyings)?;
+        recording.linked_programs.insert(handle, reflection);
+        Ok(())
+    }
+
+    fn reflect_program(&mut self, handle: GLuint) -> ProgramReflection {
+        let mut recording = self.recording.borrow_mut();
+        match recording.linked_programs.get(&handle) {
+            Some(reflection) => reflection.clone(),
+            None => {
+                recording
+                    .errors
+                    .push(format!("reflected Program {} which is not linked", handle));
+                ProgramReflection::default()
+            }
+        }
+    }
+
+    /// Array elements can be looked up as "name[index]", structs are not supported
+    fn get_uniform_location(&mut self, program: GLuint, name: &CStr) -> GLint {
+        let mut recording = self.recording.borrow_mut();
+        let Some(reflection) = recording.linked_programs.get(&program) else {
+            recording.errors.push(format!(
+                "looked up a uniform of Program {} which is not linked",
+                program
+            ));
+            return -1;
+        };
+        let name = name.to_string_lossy();
+        let (base, element) = match name.strip_suffix(']').and_then(|rest| rest.split_once('[')) {
+            Some((base, element)) => (base, element.parse::<GLint>().ok()),
+            None => (&*name, Some(0)),
+        };
+        reflection
+            .uniforms
+            .iter()
+            .find(|uniform| uniform.location != -1 && base_name(&uniform.name) == base)
+            .zip(element)
+            .filter(|(uniform, element)| *element < uniform.size)
+            .map_or(-1, |(uniform, element)| uniform.location + element)
+    }
+
+    fn uniform_block_binding(&mut self, program: GLuint, block_index: GLuint, binding: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        let block = recording
+            .linked_programs
+            .get_mut(&program)
+            .and_then(|reflection| reflection.uniform_blocks.get_mut(block_index as usize));
+        match block {
+            Some(block) => block.binding = binding as GLint,
+            None => recording.errors.push(format!(
+                "bound block {} of Program {} which has no such block",
+                block_index, program
+            )),
+        }
+    }
+
+    fn uniform(&mut self, location: GLint, _value: UniformValue) {
+        let mut recording = self.recording.borrow_mut();
+        let program = recording.program;
+        let Some(reflection) = recording.linked_programs.get(&program) else {
+            recording
+                .errors
+                .push(format!("set uniform {} with no program in use", location));
+            return;
+        };
+        let known = reflection.uniforms.iter().any(|uniform| {
+            uniform.location != -1
+                && (uniform.location..uniform.location + uniform.size).contains(&location)
+        });
+        if !known {
+            recording.errors.push(format!(
+                "set uniform {} which Program {} does not have",
+                location, program
+            ));
+        }
+    }
+
+    fn gen_query(&mut self) -> GLuint {
+        self.recording.borrow_mut().create(ObjectKind::Query)
+    }
+
+    fn delete_query(&mut self, handle: GLuint) {
+        self.recording
+            .borrow_mut()
+            .delete(ObjectKind::Query, handle);
+    }
+
+    fn begin_query(&mut self, target: GLenum, handle: GLuint) {
+        let mut recording = self.recording.borrow_mut();
+        if handle == 0 || !recording.check_alive(ObjectKind::Query, handle, "began") {
+            return;
+        }
+        if recording.active_queries.contains_key(&target) {
+            recording.errors.push(format!(
+                "began Query {} while another query on 0x{:04X} is active",
+                handle, target
+            ));
+            return;
+        }
+        let start = recording.primitives_written;
+        recording.active_queries.insert(target, (handle, start));
+    }
+
+    /// Only TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN counts anything, other queries result in 0
+    fn end_query(&mut self, target: GLenum) {
+        let mut recording = self.recording.borrow_mut();
+        let Some((handle, start)) = recording.active_queries.remove(&target) else {
+            recording.errors.push(format!(
+                "ended a query on 0x{:04X} that is not active",
+                target
+            ));
+            return;
+        };
+        let result = if target == gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN {
+            recording.primitives_written - start
+        } else {
+            0
+        };
+        recording.query_results.insert(handle, result);
+    }
+
+    fn query_result(&mut self, handle: GLuint) -> GLuint {
+        let mut recording = self.recording.borrow_mut();
+        match recording.query_results.get(&handle) {
+            Some(result) => *result,
+            None => {
+                recording
+                    .errors
+                    .push(format!("read Query {} which has no result", handle));
+                0
+            }
+        }
+    }
+
+    fn begin_transform_feedback(&mut self, primitive: GLenum) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.feedback_primitive.is_some() {
+            recording
+                .errors
+                .push("began transform feedback while it is active".to_string());
+        } else if recording.program == 0 {
+            recording
+                .errors
+                .push("began transform feedback with no program in use".to_string());
+        } else {
+            recording.feedback_primitive = Some(primitive);
+        }
+    }
+
+    fn end_transform_feedback(&mut self) {
+        let mut recording = self.recording.borrow_mut();
+        if recording.feedback_primitive.take().is_none() {
+            recording
+                .errors
+                .push("ended transform feedback that is not active".to_string());
+        }
+    }
+
+    fn object_label(&mut self, identifier: ObjectIdentifier, handle: GLuint, label: &str) {
+        let kind = match identifier {
+            ObjectIdentifier::Buffer => ObjectKind::Buffer,
+            ObjectIdentifier::VertexArray => ObjectKind::VertexArray,
+            ObjectIdentifier::Texture => ObjectKind::Texture,
+            ObjectIdentifier::Program => ObjectKind::Program,
+            ObjectIdentifier::Shader => ObjectKind::Shader,
+            ObjectIdentifier::Query => ObjectKind::Query,
+            _ => return,
+        };
+        let mut recording = self.recording.borrow_mut();
+        if handle != 0 && recording.check_alive(kind, handle, "labelled") {
+            recording.labels.insert(handle, label.to_string());
+        }
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        self.recording.borrow_mut().enabled.insert(capability);
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        self.recording.borrow_mut().enabled.remove(&capability);
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        self.recording.borrow_mut().blend_func = (source, destination);
+    }
+
+    fn depth_func(&mut self, function: GLenum) {
+        self.recording.borrow_mut().depth_func = function;
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        self.recording.borrow_mut().depth_mask = write;
+    }
+
+    fn cull_face(&mut self, face: GLenum) {
+        self.recording.borrow_mut().cull_face = face;
+    }
+
+    fn viewport(&mut self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        self.recording.borrow_mut().viewport = (x, y, width, height);
+    }
+
+    fn clear_color(&mut self, _red: GLfloat, _green: GLfloat, _blue: GLfloat, _alpha: GLfloat) {}
+
+    fn clear(&mut self, _mask: GLbitfield) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_buffer_data_is_an_error() {
+        let mut recording = RecordingBackend::new();
+        let buffer = recording.gen_buffer();
+        recording.bind_buffer(gl::ARRAY_BUFFER, buffer);
+        recording.buffer_data(gl::ARRAY_BUFFER, 8, Some(&[1, 2, 3, 4]), gl::STATIC_DRAW);
+        assert_eq!(
+            recording.errors(),
+            ["BufferData of 8 bytes into Buffer 1 from 4 bytes of data"]
+        );
+        assert_eq!(recording.buffer_contents(buffer), None);
+
+        recording.buffer_data(gl::ARRAY_BUFFER, 2, Some(&[1, 2, 3, 4]), gl::STATIC_DRAW);
+        assert_eq!(recording.buffer_contents(buffer), Some(vec![1, 2]));
+        assert_eq!(recording.errors().len(), 1);
+    }
+}
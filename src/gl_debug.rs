@@ -0,0 +1,163 @@
+use std::{
+    ffi::c_void,
+    ptr::null,
+    slice,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use glad_gl::gl::{
+    self, ExtensionError, GLchar, GLenum, GLint, GLsizei, GLuint,
+    khr_debug::{self, DebugSeverity, DebugSource, DebugType},
+};
+
+use crate::gl_backend::with_backend;
+
+/// Messages below this severity are dropped by the callback
+static MIN_SEVERITY: AtomicU32 = AtomicU32::new(DebugSeverity::Low as u32);
+
+/// True if the context was created with the debug flag, which makes drivers report much more
+pub fn is_debug_context() -> bool {
+    let mut flags: GLint = 0;
+    unsafe { gl::GetIntegerv(gl::CONTEXT_FLAGS, &mut flags as *mut GLint) };
+    flags as GLenum & khr_debug::CONTEXT_FLAG_DEBUG_BIT != 0
+}
+
+/// Routes driver debug messages at or above min_severity to stderr.
+/// Output is synchronous so that a message is printed during the call that caused it.
+pub fn install_debug_callback(min_severity: DebugSeverity) -> Result<(), ExtensionError> {
+    MIN_SEVERITY.store(min_severity as u32, Ordering::Relaxed);
+    unsafe {
+        khr_debug::DebugMessageCallback(Some(debug_callback), null())?;
+
+        // Let the driver skip building messages that would be dropped anyway
+        for severity in [
+            DebugSeverity::Notification,
+            DebugSeverity::Low,
+            DebugSeverity::Medium,
+            DebugSeverity::High,
+        ] {
+            khr_debug::DebugMessageControl(
+                khr_debug::DONT_CARE,
+                khr_debug::DONT_CARE,
+                severity as GLenum,
+                0,
+                null(),
+                if is_reported(severity, min_severity) {
+                    gl::TRUE
+                } else {
+                    gl::FALSE
+                },
+            )?;
+        }
+    }
+    with_backend(|backend| {
+        backend.enable(khr_debug::DEBUG_OUTPUT);
+        backend.enable(khr_debug::DEBUG_OUTPUT_SYNCHRONOUS);
+    });
+    Ok(())
+}
+
+/// True if a message of severity passes a min_severity threshold. Compares by rank, since the
+/// GL values of Low, Medium and High run from most to least severe.
+fn is_reported(severity: DebugSeverity, min_severity: DebugSeverity) -> bool {
+    severity.rank() >= min_severity.rank()
+}
+
+fn severity_name(severity: Option<DebugSeverity>) -> &'static str {
+    match severity {
+        Some(DebugSeverity::High) => "high",
+        Some(DebugSeverity::Medium) => "medium",
+        Some(DebugSeverity::Low) => "low",
+        Some(DebugSeverity::Notification) => "notification",
+        None => "unknown",
+    }
+}
+
+fn source_name(source: Option<DebugSource>) -> &'static str {
+    match source {
+        Some(DebugSource::Api) => "API",
+        Some(DebugSource::WindowSystem) => "window system",
+        Some(DebugSource::ShaderCompiler) => "shader compiler",
+        Some(DebugSource::ThirdParty) => "third party",
+        Some(DebugSource::Application) => "application",
+        Some(DebugSource::Other) | None => "other",
+    }
+}
+
+fn type_name(message_type: Option<DebugType>) -> &'static str {
+    match message_type {
+        Some(DebugType::Error) => "error",
+        Some(DebugType::DeprecatedBehavior) => "deprecated behavior",
+        Some(DebugType::UndefinedBehavior) => "undefined behavior",
+        Some(DebugType::Portability) => "portability",
+        Some(DebugType::Performance) => "performance",
+        Some(DebugType::Marker) => "marker",
+        Some(DebugType::PushGroup) => "push group",
+        Some(DebugType::PopGroup) => "pop group",
+        Some(DebugType::Other) | None => "other",
+    }
+}
+
+/// Called by the driver, possibly from inside any GL call. It must not panic.
+extern "system" fn debug_callback(
+    source: GLenum,
+    message_type: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    // Messages with a severity the extension does not define are always shown
+    let severity = DebugSeverity::from_gl(severity);
+    if let Some(severity) = severity
+        && let Some(min_severity) = DebugSeverity::from_gl(MIN_SEVERITY.load(Ordering::Relaxed))
+        && !is_reported(severity, min_severity)
+    {
+        return;
+    }
+
+    let message = if message.is_null() {
+        String::new()
+    } else if length < 0 {
+        unsafe { std::ffi::CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        let bytes = unsafe { slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    let message = message.trim_end();
+
+    let prefix = match severity {
+        Some(DebugSeverity::High) => "Error",
+        Some(DebugSeverity::Notification) => "Info",
+        _ => "Warning",
+    };
+    eprintln!(
+        "{}: GL {} message from {} (id {}, {} severity): {}",
+        prefix,
+        type_name(DebugType::from_gl(message_type)),
+        source_name(DebugSource::from_gl(source)),
+        id,
+        severity_name(severity),
+        message
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severe_messages_pass_a_low_threshold() {
+        assert!(is_reported(DebugSeverity::High, DebugSeverity::Low));
+        assert!(is_reported(DebugSeverity::Medium, DebugSeverity::Low));
+        assert!(is_reported(DebugSeverity::Low, DebugSeverity::Low));
+        assert!(!is_reported(
+            DebugSeverity::Notification,
+            DebugSeverity::Low
+        ));
+        assert!(!is_reported(DebugSeverity::Medium, DebugSeverity::High));
+    }
+}
@@ -0,0 +1,595 @@
+use std::{path::Path, slice};
+
+use glad_gl::gl::{self, GLenum, GLint, GLuint, khr_debug::ObjectIdentifier};
+use image::{DynamicImage, GenericImageView, ImageError, ImageReader};
+
+use crate::{
+    gl_backend::{PixelFormat, with_backend},
+    vertex_layout::{VertexAttribute, VertexLayout},
+};
+
+/// Plain data that can be copied byte for byte into GL memory and back.
+///
+/// # Safety
+/// Implementors must be Copy, contain no padding bytes and no pointers, and be valid for any bit
+/// pattern (so that data read back from the GPU is a valid value).
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for f32 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Views a slice of plain data as its raw bytes
+pub fn as_bytes<T: Pod>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, size_of_val(data)) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferTarget {
+    Array,
+    ElementArray,
+    Uniform,
+    TransformFeedback,
+}
+
+impl BufferTarget {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            BufferTarget::Array => gl::ARRAY_BUFFER,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            BufferTarget::TransformFeedback => gl::TRANSFORM_FEEDBACK_BUFFER,
+        }
+    }
+}
+
+/// A hint to the driver about how often the data is written and who reads it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferUsage {
+    /// Set once and used many times
+    StaticDraw,
+    /// Rewritten often and used many times
+    DynamicDraw,
+    /// Set once and used a few times
+    StreamDraw,
+    /// Written by the GPU and read back by the CPU
+    DynamicRead,
+}
+
+impl BufferUsage {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            BufferUsage::StaticDraw => gl::STATIC_DRAW,
+            BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+            BufferUsage::StreamDraw => gl::STREAM_DRAW,
+            BufferUsage::DynamicRead => gl::DYNAMIC_READ,
+        }
+    }
+}
+
+/// An owned buffer object, deleted when dropped
+pub struct Buffer {
+    handle: GLuint,
+    target: BufferTarget,
+    size: usize,
+}
+
+impl Buffer {
+    pub fn new(target: BufferTarget) -> Self {
+        Self {
+            handle: with_backend(|backend| backend.gen_buffer()),
+            target,
+            size: 0,
+        }
+    }
+
+    pub fn with_data<T: Pod>(target: BufferTarget, data: &[T], usage: BufferUsage) -> Self {
+        let mut buffer = Self::new(target);
+        buffer.set_data(data, usage);
+        buffer
+    }
+
+    /// Creates a buffer with size bytes of uninitialized storage
+    pub fn with_size(target: BufferTarget, size: usize, usage: BufferUsage) -> Self {
+        let mut buffer = Self::new(target);
+        buffer.bind();
+        with_backend(|backend| backend.buffer_data(target.gl_enum(), size, None, usage.gl_enum()));
+        buffer.size = size;
+        buffer
+    }
+
+    #[cfg(test)]
+    pub fn handle(&self) -> GLuint {
+        self.handle
+    }
+
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    /// Size of the buffer's storage in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn bind(&self) {
+        with_backend(|backend| backend.bind_buffer(self.target.gl_enum(), self.handle))
+    }
+
+    /// Names the buffer in driver messages and capture tools
+    pub fn set_label(&self, label: &str) {
+        with_backend(|backend| backend.object_label(ObjectIdentifier::Buffer, self.handle, label));
+    }
+
+    /// Binds the buffer to an indexed binding point (uniform or transform feedback targets)
+    pub fn bind_base(&self, index: GLuint) {
+        with_backend(|backend| backend.bind_buffer_base(self.target.gl_enum(), index, self.handle))
+    }
+
+    /// Replaces the buffer's storage with a copy of data
+    pub fn set_data<T: Pod>(&mut self, data: &[T], usage: BufferUsage) {
+        let bytes = as_bytes(data);
+        self.bind();
+        with_backend(|backend| {
+            backend.buffer_data(
+                self.target.gl_enum(),
+                bytes.len(),
+                Some(bytes),
+                usage.gl_enum(),
+            )
+        });
+        self.size = bytes.len();
+    }
+
+    /// Overwrites part of the existing storage, starting offset bytes into the buffer
+    pub fn set_sub_data<T: Pod>(&self, offset: usize, data: &[T]) {
+        let bytes = as_bytes(data);
+        assert!(
+            offset + bytes.len() <= self.size,
+            "sub data write of {} bytes at offset {} overflows a buffer of {} bytes",
+            bytes.len(),
+            offset,
+            self.size
+        );
+        self.bind();
+        with_backend(|backend| backend.buffer_sub_data(self.target.gl_enum(), offset, bytes));
+    }
+
+    /// Copies count values starting offset bytes into the buffer back to the CPU
+    pub fn read<T: Pod + Default>(&self, offset: usize, count: usize) -> Vec<T> {
+        let mut data = vec![T::default(); count];
+        let byte_count = size_of_val(data.as_slice());
+        assert!(offset + byte_count <= self.size);
+        self.bind();
+        // Any bit pattern is a valid T, so the values can be filled in as bytes
+        let bytes = unsafe { slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, byte_count) };
+        with_backend(|backend| backend.get_buffer_sub_data(self.target.gl_enum(), offset, bytes));
+        data
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_buffer(self.handle))
+    }
+}
+
+/// Index types accepted by DrawElements
+pub trait IndexType: Pod {
+    const GL_TYPE: GLenum;
+}
+
+impl IndexType for u8 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_BYTE;
+}
+
+impl IndexType for u16 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl IndexType for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+/// An owned vertex array object, deleted when dropped
+pub struct VertexArray {
+    handle: GLuint,
+}
+
+impl VertexArray {
+    pub fn new() -> Self {
+        Self {
+            handle: with_backend(|backend| backend.gen_vertex_array()),
+        }
+    }
+
+    pub fn bind(&self) {
+        with_backend(|backend| backend.bind_vertex_array(self.handle))
+    }
+
+    pub fn set_label(&self, label: &str) {
+        with_backend(|backend| {
+            backend.object_label(ObjectIdentifier::VertexArray, self.handle, label)
+        });
+    }
+
+    pub fn unbind() {
+        with_backend(|backend| backend.bind_vertex_array(0))
+    }
+
+    /// Records the index buffer in the vertex array's state
+    pub fn set_element_buffer(&self, buffer: &Buffer) {
+        assert_eq!(buffer.target(), BufferTarget::ElementArray);
+        self.bind();
+        buffer.bind();
+    }
+
+    /// Sources every attribute of an interleaved layout from buffer
+    pub fn set_layout(&self, buffer: &Buffer, layout: &VertexLayout) {
+        self.bind();
+        buffer.bind();
+        for attribute in layout.attributes() {
+            set_attribute_pointer(attribute, layout.stride());
+        }
+    }
+
+    /// Sources attribute i of layout from buffers[i], with each buffer tightly packed. The
+    /// layout's offsets and stride are ignored.
+    pub fn set_planar_layout(&self, buffers: &[&Buffer], layout: &VertexLayout) {
+        assert_eq!(buffers.len(), layout.attributes().len());
+        self.bind();
+        for (buffer, attribute) in buffers.iter().zip(layout.attributes()) {
+            buffer.bind();
+            let planar = VertexAttribute {
+                offset: 0,
+                ..*attribute
+            };
+            set_attribute_pointer(&planar, attribute.size());
+        }
+    }
+
+    /// Draws count indices of type I from the element buffer
+    pub fn draw_elements<I: IndexType>(&self, mode: GLenum, count: usize) {
+        self.bind();
+        with_backend(|backend| backend.draw_elements(mode, count, I::GL_TYPE, 0))
+    }
+
+    pub fn draw_arrays(&self, mode: GLenum, first: usize, count: usize) {
+        self.bind();
+        with_backend(|backend| backend.draw_arrays(mode, first, count))
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_vertex_array(self.handle))
+    }
+}
+
+fn set_attribute_pointer(attribute: &VertexAttribute, stride: usize) {
+    with_backend(|backend| {
+        backend.vertex_attrib_pointer(attribute, stride);
+        backend.enable_vertex_attrib_array(attribute.location);
+    });
+}
+
+/// Sampling state applied when a texture is created
+#[derive(Clone, Copy)]
+pub struct TextureParameters {
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub min_filter: GLenum,
+    pub mag_filter: GLenum,
+    pub generate_mipmaps: bool,
+}
+
+impl Default for TextureParameters {
+    fn default() -> Self {
+        Self {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            min_filter: gl::LINEAR,
+            mag_filter: gl::LINEAR,
+            generate_mipmaps: true,
+        }
+    }
+}
+
+/// An owned 2D texture, deleted when dropped
+pub struct Texture2D {
+    handle: GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Texture2D {
+    /// Creates a texture with uninitialized storage. format and pixel_type describe the data that
+    /// will later be uploaded with set_sub_image.
+    pub fn new(
+        width: u32,
+        height: u32,
+        internal_format: GLenum,
+        format: GLenum,
+        pixel_type: GLenum,
+        parameters: &TextureParameters,
+    ) -> Self {
+        let texture = Self::create(width, height, parameters);
+        with_backend(|backend| {
+            backend.tex_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                internal_format,
+                (width, height),
+                PixelFormat { format, pixel_type },
+                None,
+            )
+        });
+        texture
+    }
+
+    /// Uploads an image, keeping an alpha channel only if the image has one
+    pub fn from_image(image: &DynamicImage, parameters: &TextureParameters) -> Self {
+        let (width, height) = image.dimensions();
+        let texture = Self::create(width, height, parameters);
+
+        let (format, pixels) = if image.color().has_alpha() {
+            (gl::RGBA, image.to_rgba8().into_raw())
+        } else {
+            (gl::RGB, image.to_rgb8().into_raw())
+        };
+        with_backend(|backend| {
+            // Rows of RGB images are not necessarily 4 byte aligned
+            backend.pixel_store(gl::UNPACK_ALIGNMENT, 1);
+            backend.tex_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                format,
+                (width, height),
+                PixelFormat {
+                    format,
+                    pixel_type: gl::UNSIGNED_BYTE,
+                },
+                Some(&pixels),
+            );
+            backend.pixel_store(gl::UNPACK_ALIGNMENT, 4);
+            if parameters.generate_mipmaps {
+                backend.generate_mipmap(gl::TEXTURE_2D);
+            }
+        });
+        texture
+    }
+
+    /// Decodes an image file and uploads it, labelled with the file name
+    pub fn load(path: &Path, parameters: &TextureParameters) -> Result<Self, ImageError> {
+        let image = ImageReader::open(path)?.decode()?;
+        let texture = Self::from_image(&image, parameters);
+        if let Some(name) = path.file_name() {
+            texture.set_label(&name.to_string_lossy());
+        }
+        Ok(texture)
+    }
+
+    fn create(width: u32, height: u32, parameters: &TextureParameters) -> Self {
+        let handle = with_backend(|backend| {
+            let handle = backend.gen_texture();
+            backend.bind_texture(gl::TEXTURE_2D, handle);
+
+            // set the wrapping parameters
+            backend.tex_parameter(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                parameters.wrap_s as GLint,
+            );
+            backend.tex_parameter(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                parameters.wrap_t as GLint,
+            );
+
+            // Set the filtering parameters (minify and magnify)
+            backend.tex_parameter(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                parameters.min_filter as GLint,
+            );
+            backend.tex_parameter(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAG_FILTER,
+                parameters.mag_filter as GLint,
+            );
+            handle
+        });
+        Self {
+            handle,
+            width,
+            height,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn handle(&self) -> GLuint {
+        self.handle
+    }
+
+    pub fn set_label(&self, label: &str) {
+        with_backend(|backend| backend.object_label(ObjectIdentifier::Texture, self.handle, label));
+    }
+
+    /// Binds the texture to a texture unit (0 for TEXTURE0 and so on)
+    pub fn bind(&self, unit: u32) {
+        with_backend(|backend| {
+            backend.active_texture(unit);
+            backend.bind_texture(gl::TEXTURE_2D, self.handle);
+        });
+    }
+
+    /// Overwrites a region of the base level with tightly packed pixels in the given format.
+    /// The texture is bound on the active texture unit and stays bound there afterwards.
+    pub fn set_sub_image<T: Pod>(
+        &self,
+        region: &TextureRegion,
+        format: GLenum,
+        pixel_type: GLenum,
+        pixels: &[T],
+    ) {
+        assert!(
+            region
+                .x
+                .checked_add(region.width)
+                .is_some_and(|right| right <= self.width)
+                && region
+                    .y
+                    .checked_add(region.height)
+                    .is_some_and(|top| top <= self.height),
+            "region at ({}, {}) of {} x {} texels is outside a {} x {} texture",
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            self.width,
+            self.height
+        );
+        let pixel_format = PixelFormat { format, pixel_type };
+        let bytes = as_bytes(pixels);
+        let Some(pixel_size) = pixel_format.bytes_per_pixel() else {
+            panic!(
+                "unknown pixel format 0x{:04X} with type 0x{:04X}",
+                format, pixel_type
+            );
+        };
+        let needed = (region.width as usize)
+            .checked_mul(region.height as usize)
+            .and_then(|texels| texels.checked_mul(pixel_size));
+        assert!(
+            needed.is_some_and(|needed| bytes.len() >= needed),
+            "{} bytes of pixels are too few for a {} x {} region",
+            bytes.len(),
+            region.width,
+            region.height
+        );
+        with_backend(|backend| {
+            backend.bind_texture(gl::TEXTURE_2D, self.handle);
+            backend.pixel_store(gl::UNPACK_ALIGNMENT, 1);
+            backend.tex_sub_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                (region.x, region.y),
+                (region.width, region.height),
+                pixel_format,
+                bytes,
+            );
+            backend.pixel_store(gl::UNPACK_ALIGNMENT, 4);
+        });
+    }
+}
+
+/// A rectangle of texels, with the origin at the first texel of the uploaded data
+#[derive(Clone, Copy)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_texture(self.handle))
+    }
+}
+
+/// An owned, linked program object, deleted when dropped
+pub struct Program {
+    handle: GLuint,
+}
+
+impl Program {
+    /// Takes ownership of a program handle created with CreateProgram
+    pub fn from_handle(handle: GLuint) -> Self {
+        Self { handle }
+    }
+
+    pub fn handle(&self) -> GLuint {
+        self.handle
+    }
+
+    pub fn bind(&self) {
+        with_backend(|backend| backend.use_program(self.handle))
+    }
+
+    pub fn set_label(&self, label: &str) {
+        with_backend(|backend| backend.object_label(ObjectIdentifier::Program, self.handle, label));
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_program(self.handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_backend::{RecordingBackend, set_backend};
+
+    fn texture(recording: &RecordingBackend) -> Texture2D {
+        set_backend(Box::new(recording.clone()));
+        Texture2D::new(
+            4,
+            4,
+            gl::RGBA8,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            &TextureParameters::default(),
+        )
+    }
+
+    #[test]
+    fn sub_images_are_bound_on_the_active_unit() {
+        let recording = RecordingBackend::new();
+        let texture = texture(&recording);
+        with_backend(|backend| backend.active_texture(3));
+        let region = TextureRegion {
+            x: 1,
+            y: 2,
+            width: 3,
+            height: 2,
+        };
+        texture.set_sub_image(&region, gl::RGBA, gl::UNSIGNED_BYTE, &[0u8; 24]);
+        texture.set_sub_image(&region, gl::RED, gl::FLOAT, &[0.5f32; 6]);
+        assert_eq!(recording.bound_texture(3), texture.handle());
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+
+    #[test]
+    #[should_panic(expected = "23 bytes of pixels are too few for a 3 x 2 region")]
+    fn short_pixel_data_is_rejected() {
+        let texture = texture(&RecordingBackend::new());
+        let region = TextureRegion {
+            x: 0,
+            y: 0,
+            width: 3,
+            height: 2,
+        };
+        texture.set_sub_image(&region, gl::RGBA, gl::UNSIGNED_BYTE, &[0u8; 23]);
+    }
+
+    #[test]
+    #[should_panic(expected = "is outside a 4 x 4 texture")]
+    fn regions_that_wrap_around_are_rejected() {
+        let texture = texture(&RecordingBackend::new());
+        let region = TextureRegion {
+            x: u32::MAX,
+            y: 0,
+            width: 2,
+            height: 1,
+        };
+        texture.set_sub_image(&region, gl::RGBA, gl::UNSIGNED_BYTE, &[0u8; 8]);
+    }
+}
@@ -0,0 +1,491 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ffi::{CStr, CString},
+};
+
+use glad_gl::gl::{
+    self, GLbitfield, GLenum, GLfloat, GLint, GLsizei, GLuint, khr_debug::ObjectIdentifier,
+};
+
+use crate::{
+    gl_backend::{GlBackend, PixelFormat, UniformValue},
+    shader::ShaderStage,
+    shader_reflection::ProgramReflection,
+    vertex_layout::VertexAttribute,
+};
+
+/// Counts of the work sent to the driver since the last take_frame_stats()
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    /// Binds and state changes that reached the driver
+    pub state_changes: u32,
+    /// Binds and state changes that were dropped because nothing would have changed
+    pub redundant_skipped: u32,
+}
+
+thread_local! {
+    static FRAME_STATS: Cell<FrameStats> = Cell::new(FrameStats::default());
+}
+
+fn count(update: impl FnOnce(&mut FrameStats)) {
+    FRAME_STATS.with(|stats| {
+        let mut value = stats.get();
+        update(&mut value);
+        stats.set(value);
+    });
+}
+
+/// Returns the counters for the frame that just ended and starts a new frame
+pub fn take_frame_stats() -> FrameStats {
+    FRAME_STATS.with(|stats| stats.replace(FrameStats::default()))
+}
+
+/// The state the driver was last told about. None means unknown, so the next change always goes
+/// through.
+#[derive(Default)]
+struct ShadowState {
+    program: Option<GLuint>,
+    vertex_array: Option<GLuint>,
+    /// Keyed by target. The element buffer is not tracked because it belongs to the vertex array.
+    buffers: HashMap<GLenum, GLuint>,
+    active_unit: Option<u32>,
+    /// Keyed by (unit, target)
+    textures: HashMap<(u32, GLenum), GLuint>,
+    capabilities: HashMap<GLenum, bool>,
+    blend_func: Option<(GLenum, GLenum)>,
+    depth_func: Option<GLenum>,
+    depth_mask: Option<bool>,
+    cull_face: Option<GLenum>,
+}
+
+/// Returns true if the value differs from the shadowed one and records it
+fn update<T: PartialEq>(shadow: &mut Option<T>, value: T) -> bool {
+    let changed = shadow.as_ref() != Some(&value);
+    count(|stats| {
+        if changed {
+            stats.state_changes += 1;
+        } else {
+            stats.redundant_skipped += 1;
+        }
+    });
+    *shadow = Some(value);
+    changed
+}
+
+fn update_entry<K: std::hash::Hash + Eq, T: PartialEq>(
+    shadow: &mut HashMap<K, T>,
+    key: K,
+    value: T,
+) -> bool {
+    let mut slot = shadow.remove(&key);
+    let changed = update(&mut slot, value);
+    if let Some(value) = slot {
+        shadow.insert(key, value);
+    }
+    changed
+}
+
+/// Wraps another backend and drops binds and state changes that would not change anything.
+///
+/// Every skipped or forwarded change is counted in the thread's FrameStats. The cache only
+/// knows about changes made through it, so code that changes the same state by calling glad-gl
+/// directly must go through the backend instead.
+pub struct CachingBackend {
+    inner: Box<dyn GlBackend>,
+    state: ShadowState,
+}
+
+impl CachingBackend {
+    pub fn new(inner: Box<dyn GlBackend>) -> Self {
+        Self {
+            inner,
+            state: ShadowState::default(),
+        }
+    }
+}
+
+impl GlBackend for CachingBackend {
+    fn gen_buffer(&mut self) -> GLuint {
+        self.inner.gen_buffer()
+    }
+
+    fn delete_buffer(&mut self, handle: GLuint) {
+        // Deleting a bound buffer unbinds it
+        for bound in self.state.buffers.values_mut() {
+            if *bound == handle {
+                *bound = 0;
+            }
+        }
+        self.inner.delete_buffer(handle);
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, handle: GLuint) {
+        if target == gl::ELEMENT_ARRAY_BUFFER
+            || update_entry(&mut self.state.buffers, target, handle)
+        {
+            self.inner.bind_buffer(target, handle);
+        }
+    }
+
+    fn bind_buffer_base(&mut self, target: GLenum, index: GLuint, handle: GLuint) {
+        // Indexed bindings are not tracked, but the call also binds the generic target
+        self.state.buffers.insert(target, handle);
+        self.inner.bind_buffer_base(target, index, handle);
+    }
+
+    fn buffer_data(&mut self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum) {
+        self.inner.buffer_data(target, size, data, usage);
+    }
+
+    fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]) {
+        self.inner.buffer_sub_data(target, offset, data);
+    }
+
+    fn get_buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &mut [u8]) {
+        self.inner.get_buffer_sub_data(target, offset, data);
+    }
+
+    fn gen_vertex_array(&mut self) -> GLuint {
+        self.inner.gen_vertex_array()
+    }
+
+    fn delete_vertex_array(&mut self, handle: GLuint) {
+        if self.state.vertex_array == Some(handle) {
+            self.state.vertex_array = Some(0);
+        }
+        self.inner.delete_vertex_array(handle);
+    }
+
+    fn bind_vertex_array(&mut self, handle: GLuint) {
+        if update(&mut self.state.vertex_array, handle) {
+            self.inner.bind_vertex_array(handle);
+        }
+    }
+
+    fn vertex_attrib_pointer(&mut self, attribute: &VertexAttribute, stride: usize) {
+        self.inner.vertex_attrib_pointer(attribute, stride);
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: GLuint) {
+        self.inner.enable_vertex_attrib_array(location);
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: usize, index_type: GLenum, offset: usize) {
+        self::count(|stats| stats.draw_calls += 1);
+        self.inner.draw_elements(mode, count, index_type, offset);
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: usize, count: usize) {
+        self::count(|stats| stats.draw_calls += 1);
+        self.inner.draw_arrays(mode, first, count);
+    }
+
+    fn gen_texture(&mut self) -> GLuint {
+        self.inner.gen_texture()
+    }
+
+    fn delete_texture(&mut self, handle: GLuint) {
+        for bound in self.state.textures.values_mut() {
+            if *bound == handle {
+                *bound = 0;
+            }
+        }
+        self.inner.delete_texture(handle);
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        if update(&mut self.state.active_unit, unit) {
+            self.inner.active_texture(unit);
+        }
+    }
+
+    fn bind_texture(&mut self, target: GLenum, handle: GLuint) {
+        // Without a known unit the binding cannot be tracked
+        let Some(unit) = self.state.active_unit else {
+            self.inner.bind_texture(target, handle);
+            return;
+        };
+        if update_entry(&mut self.state.textures, (unit, target), handle) {
+            self.inner.bind_texture(target, handle);
+        }
+    }
+
+    fn tex_parameter(&mut self, target: GLenum, name: GLenum, value: GLint) {
+        self.inner.tex_parameter(target, name, value);
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        internal_format: GLenum,
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: Option<&[u8]>,
+    ) {
+        self.inner
+            .tex_image_2d(target, level, internal_format, size, format, pixels);
+    }
+
+    fn tex_sub_image_2d(
+        &mut self,
+        target: GLenum,
+        level: GLint,
+        origin: (u32, u32),
+        size: (u32, u32),
+        format: PixelFormat,
+        pixels: &[u8],
+    ) {
+        self.inner
+            .tex_sub_image_2d(target, level, origin, size, format, pixels);
+    }
+
+    fn generate_mipmap(&mut self, target: GLenum) {
+        self.inner.generate_mipmap(target);
+    }
+
+    fn pixel_store(&mut self, name: GLenum, value: GLint) {
+        self.inner.pixel_store(name, value);
+    }
+
+    fn create_program(&mut self) -> GLuint {
+        self.inner.create_program()
+    }
+
+    fn delete_program(&mut self, handle: GLuint) {
+        // The name may be reused once the program is no longer current, so forget it
+        if self.state.program == Some(handle) {
+            self.state.program = None;
+        }
+        self.inner.delete_program(handle);
+    }
+
+    fn use_program(&mut self, handle: GLuint) {
+        if update(&mut self.state.program, handle) {
+            self.inner.use_program(handle);
+        }
+    }
+
+    fn create_shader(&mut self, stage: ShaderStage) -> GLuint {
+        self.inner.create_shader(stage)
+    }
+
+    fn delete_shader(&mut self, handle: GLuint) {
+        self.inner.delete_shader(handle);
+    }
+
+    fn compile_shader(&mut self, handle: GLuint, source: &CStr) -> Result<(), String> {
+        self.inner.compile_shader(handle, source)
+    }
+
+    fn attach_shader(&mut self, program: GLuint, shader: GLuint) {
+        self.inner.attach_shader(program, shader);
+    }
+
+    fn detach_shader(&mut self, program: GLuint, shader: GLuint) {
+        self.inner.detach_shader(program, shader);
+    }
+
+    fn transform_feedback_varyings(&mut self, program: GLuint, varyings: &[CString], mode: GLenum) {
+        self.inner
+            .transform_feedback_varyings(program, varyings, mode);
+    }
+
+    fn link_program(&mut self, handle: GLuint) -> Result<(), String> {
+        self.inner.link_program(handle)
+    }
+
+    fn reflect_program(&mut self, handle: GLuint) -> ProgramReflection {
+        self.inner.reflect_program(handle)
+    }
+
+    fn get_uniform_location(&mut self, program: GLuint, name: &CStr) -> GLint {
+        self.inner.get_uniform_location(program, name)
+    }
+
+    fn uniform_block_binding(&mut self, program: GLuint, block_index: GLuint, binding: GLuint) {
+        self.inner
+            .uniform_block_binding(program, block_index, binding);
+    }
+
+    fn uniform(&mut self, location: GLint, value: UniformValue) {
+        self.inner.uniform(location, value);
+    }
+
+    fn gen_query(&mut self) -> GLuint {
+        self.inner.gen_query()
+    }
+
+    fn delete_query(&mut self, handle: GLuint) {
+        self.inner.delete_query(handle);
+    }
+
+    fn begin_query(&mut self, target: GLenum, handle: GLuint) {
+        self.inner.begin_query(target, handle);
+    }
+
+    fn end_query(&mut self, target: GLenum) {
+        self.inner.end_query(target);
+    }
+
+    fn query_result(&mut self, handle: GLuint) -> GLuint {
+        self.inner.query_result(handle)
+    }
+
+    fn begin_transform_feedback(&mut self, primitive: GLenum) {
+        self.inner.begin_transform_feedback(primitive);
+    }
+
+    fn end_transform_feedback(&mut self) {
+        self.inner.end_transform_feedback();
+    }
+
+    fn object_label(&mut self, identifier: ObjectIdentifier, handle: GLuint, label: &str) {
+        self.inner.object_label(identifier, handle, label);
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        if update_entry(&mut self.state.capabilities, capability, true) {
+            self.inner.enable(capability);
+        }
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        if update_entry(&mut self.state.capabilities, capability, false) {
+            self.inner.disable(capability);
+        }
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        if update(&mut self.state.blend_func, (source, destination)) {
+            self.inner.blend_func(source, destination);
+        }
+    }
+
+    fn depth_func(&mut self, function: GLenum) {
+        if update(&mut self.state.depth_func, function) {
+            self.inner.depth_func(function);
+        }
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        if update(&mut self.state.depth_mask, write) {
+            self.inner.depth_mask(write);
+        }
+    }
+
+    fn cull_face(&mut self, face: GLenum) {
+        if update(&mut self.state.cull_face, face) {
+            self.inner.cull_face(face);
+        }
+    }
+
+    fn viewport(&mut self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        self.inner.viewport(x, y, width, height);
+    }
+
+    fn clear_color(&mut self, red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {
+        self.inner.clear_color(red, green, blue, alpha);
+    }
+
+    fn clear(&mut self, mask: GLbitfield) {
+        self.inner.clear(mask);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gl_backend::{RecordingBackend, set_backend, with_backend},
+        gl_objects::{Texture2D, TextureParameters},
+        matrix::Matrix4,
+        primitives,
+        shader::ShaderProgram,
+    };
+
+    // Units are switched back and forth for each cube, so ActiveTexture is not repeated
+    const BINDS: [&str; 3] = ["UseProgram", "BindVertexArray", "BindTexture"];
+
+    #[test]
+    fn repeated_binds_are_skipped_and_counted() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(CachingBackend::new(Box::new(recording.clone()))));
+        let program = ShaderProgram::builder()
+            .stage_source(ShaderStage::Vertex, "shader.vs", include_str!("shader.vs"))
+            .stage_source(
+                ShaderStage::Fragment,
+                "shader.fs",
+                include_str!("shader.fs"),
+            )
+            .build()
+            .unwrap();
+        let texture = || {
+            Texture2D::new(
+                2,
+                2,
+                gl::RGBA8,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                &TextureParameters::default(),
+            )
+        };
+        let textures = [texture(), texture()];
+        let cube = primitives::cube(1.0, 1).upload();
+        recording.clear_log();
+        take_frame_stats();
+
+        // Two cubes drawn exactly the same way, as the render loop does
+        let draw = || {
+            program.use_program();
+            for (unit, texture) in textures.iter().enumerate() {
+                texture.bind(unit as u32);
+            }
+            program.set_mat4("model", &Matrix4::identity());
+            cube.draw();
+        };
+        let bind_counts = || BINDS.map(|name| recording.bind_count(name));
+        draw();
+        let first = bind_counts();
+        assert_eq!(first, [1, 1, 2]);
+        draw();
+        assert_eq!(bind_counts(), first);
+        assert_eq!(recording.current_program(), program.handle());
+        let vertex_array = recording.draw_calls()[0].vertex_array;
+        assert_eq!(recording.current_vertex_array(), vertex_array);
+
+        let stats = take_frame_stats();
+        assert_eq!(stats.draw_calls, 2);
+        let forwarded = first.iter().sum::<usize>() + recording.bind_count("ActiveTexture");
+        assert_eq!(stats.state_changes as usize, forwarded);
+        // At least the second cube's program, vertex array and both textures
+        assert!(stats.redundant_skipped >= 4, "{:?}", stats);
+        assert_eq!(recording.draw_calls().len(), 2);
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+
+    #[test]
+    fn names_reused_after_deletion_are_bound_again() {
+        let recording = RecordingBackend::reusing_names();
+        set_backend(Box::new(CachingBackend::new(Box::new(recording.clone()))));
+        with_backend(|backend| {
+            let texture = backend.gen_texture();
+            backend.active_texture(0);
+            backend.bind_texture(gl::TEXTURE_2D, texture);
+            backend.delete_texture(texture);
+            assert_eq!(backend.gen_texture(), texture);
+            backend.bind_texture(gl::TEXTURE_2D, texture);
+
+            let buffer = backend.gen_buffer();
+            backend.bind_buffer(gl::ARRAY_BUFFER, buffer);
+            backend.delete_buffer(buffer);
+            assert_eq!(backend.gen_buffer(), buffer);
+            backend.bind_buffer(gl::ARRAY_BUFFER, buffer);
+        });
+        assert_eq!(recording.bind_count("BindTexture"), 2);
+        assert_eq!(recording.bind_count("BindBuffer"), 2);
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::json::{self, JsonValue};
+
+/// Memory passed to an upload call, as recorded in the trace
+pub struct TracePayload {
+    pub size: u64,
+    pub hash: Option<String>,
+}
+
+/// One GL call read back from a trace written by glad-gl's trace feature
+pub struct TraceCall {
+    pub function: String,
+    /// Microseconds since the trace started
+    pub time: u64,
+    /// Microseconds spent inside the call
+    pub duration: u64,
+    pub args: Vec<(String, JsonValue)>,
+    pub payload: Option<TracePayload>,
+}
+
+pub struct TraceFrame {
+    pub index: u64,
+    pub calls: Vec<TraceCall>,
+}
+
+/// Reads a trace file's contents and groups the calls by frame.
+/// Calls after the last frame marker form a final, unfinished frame.
+pub fn read_trace(text: &str) -> Result<Vec<TraceFrame>, String> {
+    let mut frames: Vec<TraceFrame> = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = json::parse(line)
+            .map_err(|error| format!("line {}: {}", line_index + 1, error.message))?;
+        if record.get("frame_end").is_some() {
+            continue;
+        }
+
+        let field = |name: &str| {
+            record
+                .get(name)
+                .ok_or_else(|| format!("line {}: missing \"{}\"", line_index + 1, name))
+        };
+        let frame = field("frame")?.as_u64().unwrap_or(0);
+        let call = TraceCall {
+            function: field("fn")?.as_str().unwrap_or_default().to_string(),
+            time: field("t")?.as_u64().unwrap_or(0),
+            duration: record.get("dur").and_then(|d| d.as_u64()).unwrap_or(0),
+            args: field("args")?.as_object().unwrap_or_default().to_vec(),
+            payload: record.get("payload").map(|payload| TracePayload {
+                size: payload.get("size").and_then(|s| s.as_u64()).unwrap_or(0),
+                hash: payload
+                    .get("hash")
+                    .and_then(|h| h.as_str())
+                    .map(str::to_string),
+            }),
+        };
+
+        match frames.last_mut() {
+            Some(last) if last.index == frame => last.calls.push(call),
+            _ => frames.push(TraceFrame {
+                index: frame,
+                calls: vec![call],
+            }),
+        }
+    }
+    Ok(frames)
+}
+
+pub fn is_draw_call(function: &str) -> bool {
+    function.starts_with("Draw") || function == "Clear"
+}
+
+/// Calls that change pipeline state rather than data or drawing
+pub fn is_state_change(function: &str) -> bool {
+    function.starts_with("Bind")
+        || function.starts_with("Blend")
+        || function.starts_with("Stencil")
+        || function.starts_with("TexParameter")
+        || matches!(
+            function,
+            "UseProgram"
+                | "Enable"
+                | "Disable"
+                | "ActiveTexture"
+                | "DepthFunc"
+                | "DepthMask"
+                | "CullFace"
+                | "FrontFace"
+                | "Viewport"
+                | "Scissor"
+                | "PolygonMode"
+                | "ColorMask"
+                | "ClearColor"
+                | "ClearDepth"
+                | "PixelStorei"
+                | "LineWidth"
+                | "PointSize"
+        )
+}
+
+pub struct FrameSummary {
+    pub calls: usize,
+    pub draw_calls: usize,
+    pub state_changes: usize,
+    /// State changes that repeat the arguments of the previous call to the same function
+    pub redundant_state_changes: usize,
+    pub uploads: usize,
+    pub upload_bytes: u64,
+    /// Total microseconds spent inside GL calls
+    pub gl_time: u64,
+}
+
+pub fn summarize_frame(frame: &TraceFrame) -> FrameSummary {
+    let mut summary = FrameSummary {
+        calls: frame.calls.len(),
+        draw_calls: 0,
+        state_changes: 0,
+        redundant_state_changes: 0,
+        uploads: 0,
+        upload_bytes: 0,
+        gl_time: 0,
+    };
+
+    // State is keyed by the first argument when it selects what is changed (a bind target or an
+    // Enable cap), so binding two different targets in a row is not counted as redundant.
+    // Texture binds are also keyed by the active texture unit.
+    let mut last_state: HashMap<String, String> = HashMap::new();
+    let mut active_unit = String::new();
+    for call in &frame.calls {
+        summary.gl_time += call.duration;
+        if is_draw_call(&call.function) {
+            summary.draw_calls += 1;
+        }
+        if let Some(payload) = &call.payload {
+            summary.uploads += 1;
+            summary.upload_bytes += payload.size;
+        }
+        if is_state_change(&call.function) {
+            summary.state_changes += 1;
+            let mut key_arg = match call.args.first() {
+                Some((name, value)) if matches!(name.as_str(), "target" | "cap" | "index") => {
+                    value.to_string()
+                }
+                _ => String::new(),
+            };
+            if call.function == "BindTexture" {
+                key_arg.push_str(&format!(" unit {}", active_unit));
+            }
+            let key = format!("{}({})", call.function, key_arg);
+            let arguments = format_arguments(&call.args);
+            if last_state.get(&key) == Some(&arguments) {
+                summary.redundant_state_changes += 1;
+            }
+            last_state.insert(key, arguments);
+            if call.function == "ActiveTexture"
+                && let Some((_, unit)) = call.args.first()
+            {
+                active_unit = unit.to_string();
+            }
+        }
+    }
+    summary
+}
+
+/// Arguments holding GL enums are shown in hex, which is how the headers list them
+fn is_enum_argument(name: &str) -> bool {
+    matches!(
+        name,
+        "target"
+            | "mode"
+            | "type_"
+            | "format"
+            | "internalformat"
+            | "pname"
+            | "cap"
+            | "usage"
+            | "sfactor"
+            | "dfactor"
+            | "func"
+            | "face"
+            | "access"
+            | "texture"
+    )
+}
+
+pub fn format_arguments(args: &[(String, JsonValue)]) -> String {
+    args.iter()
+        .map(|(name, value)| match value.as_u64() {
+            Some(number) if is_enum_argument(name) && number >= 0x100 => {
+                format!("{} = 0x{:04X}", name, number)
+            }
+            _ => match value.as_str() {
+                Some(text) => format!("{} = {}", name, text),
+                None => format!("{} = {}", name, value),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats a call as "+1.234 ms glBindBuffer(target = 0x8892, buffer = 1)", timed from the
+/// start of its frame
+pub fn format_call(call: &TraceCall, frame_start: u64) -> String {
+    let mut line = format!(
+        "{:>+9.3} ms gl{}({})",
+        call.time.saturating_sub(frame_start) as f64 / 1000.0,
+        call.function,
+        format_arguments(&call.args)
+    );
+    if let Some(payload) = &call.payload {
+        line.push_str(&format!("  [{} bytes", payload.size));
+        if let Some(hash) = &payload.hash {
+            line.push_str(&format!(", hash {}", hash));
+        }
+        line.push(']');
+    }
+    line
+}
@@ -0,0 +1,1607 @@
+//! A small GLSL 330 front end that runs without a GL context.
+//! It strips comments, runs the preprocessor, tokenizes and parses the top-level declarations of
+//! a shader so that the in/out/uniform interface of each stage can be checked offline.
+//! Function bodies are not parsed, only scanned for reads and writes of global variables.
+
+use std::{collections::HashMap, fmt};
+
+use crate::shader::ShaderStage;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The file (or other label) the diagnostic refers to
+    pub label: String,
+    /// 1-based line number, 0 when the diagnostic is not tied to a line
+    pub line: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(label: &str, line: usize, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            label: label.to_string(),
+            line,
+            message,
+        }
+    }
+
+    fn warning(label: &str, line: usize, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            label: label.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if self.line > 0 {
+            write!(
+                f,
+                "{}:{}: {}: {}",
+                self.label, self.line, severity, self.message
+            )
+        } else {
+            write!(f, "{}: {}: {}", self.label, severity, self.message)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Identifier,
+    Number,
+    Punct,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+}
+
+impl Token {
+    fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+}
+
+/// A global variable or a member of an interface block
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub type_name: String,
+    /// The array size as written in the source, Some("") for unsized arrays
+    pub array_size: Option<String>,
+    pub location: Option<i64>,
+    /// flat, smooth or noperspective
+    pub interpolation: Option<String>,
+    pub line: usize,
+    /// Whether any function assigns to the variable
+    pub written: bool,
+    /// Whether any function mentions the variable at all
+    pub referenced: bool,
+}
+
+/// An interface block such as `uniform FrameData { ... };` or `out VS_OUT { ... } vs_out;`
+#[derive(Clone, Debug)]
+pub struct InterfaceBlock {
+    pub block_name: String,
+    pub instance_name: Option<String>,
+    pub members: Vec<Variable>,
+    pub line: usize,
+    pub written: bool,
+    pub referenced: bool,
+}
+
+struct Function {
+    name: String,
+    body: Vec<Token>,
+}
+
+/// The interface of a single shader stage
+#[derive(Clone)]
+pub struct StageInterface {
+    pub stage: ShaderStage,
+    pub label: String,
+    pub version: Option<String>,
+    pub inputs: Vec<Variable>,
+    pub outputs: Vec<Variable>,
+    pub uniforms: Vec<Variable>,
+    pub input_blocks: Vec<InterfaceBlock>,
+    pub output_blocks: Vec<InterfaceBlock>,
+    pub uniform_blocks: Vec<InterfaceBlock>,
+    pub functions: Vec<String>,
+}
+
+impl StageInterface {
+    /// Looks up a default-block uniform or a member of an anonymous uniform block
+    pub fn find_uniform(&self, name: &str) -> Option<&Variable> {
+        self.uniforms.iter().find(|u| u.name == name).or_else(|| {
+            self.uniform_blocks
+                .iter()
+                .filter(|block| block.instance_name.is_none())
+                .flat_map(|block| block.members.iter())
+                .find(|member| member.name == name)
+        })
+    }
+}
+
+/// Guesses the stage of a shader from its file extension (.vs/.vert, .gs/.geom, .fs/.frag)
+pub fn stage_from_extension(extension: &str) -> Option<ShaderStage> {
+    match extension {
+        "vs" | "vert" => Some(ShaderStage::Vertex),
+        "gs" | "geom" => Some(ShaderStage::Geometry),
+        "fs" | "frag" => Some(ShaderStage::Fragment),
+        _ => None,
+    }
+}
+
+/// Parses a stage and reports problems that can be found without looking at other stages
+pub fn lint_stage(
+    stage: ShaderStage,
+    label: &str,
+    source: &str,
+) -> (StageInterface, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+
+    let stripped = strip_comments(source);
+    let preprocessed = preprocess(&stripped, label, &mut diagnostics);
+    let mut parser = Parser {
+        tokens: preprocessed.tokens,
+        position: 0,
+        label,
+        diagnostics: &mut diagnostics,
+    };
+    let mut interface = StageInterface {
+        stage,
+        label: label.to_string(),
+        version: preprocessed.version.clone(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        uniforms: Vec::new(),
+        input_blocks: Vec::new(),
+        output_blocks: Vec::new(),
+        uniform_blocks: Vec::new(),
+        functions: Vec::new(),
+    };
+    let functions = parser.parse(&mut interface);
+    interface.functions = functions.iter().map(|f| f.name.clone()).collect();
+
+    match &preprocessed.version {
+        None => diagnostics.push(Diagnostic::warning(
+            label,
+            0,
+            "missing #version directive, the driver will assume GLSL 110".to_string(),
+        )),
+        Some(version) if !version.starts_with("330") => diagnostics.push(Diagnostic::warning(
+            label,
+            preprocessed.version_line,
+            format!("expected #version 330 core but found #version {}", version),
+        )),
+        _ => {}
+    }
+
+    if !interface.functions.iter().any(|name| name == "main") {
+        diagnostics.push(Diagnostic::error(label, 0, "no main function".to_string()));
+    }
+
+    // Find out which globals the functions read and write
+    for function in &functions {
+        let variables = interface
+            .inputs
+            .iter_mut()
+            .chain(interface.outputs.iter_mut())
+            .chain(interface.uniforms.iter_mut());
+        for variable in variables {
+            let (referenced, written) = scan_usage(&function.body, &variable.name);
+            variable.referenced |= referenced;
+            variable.written |= written;
+        }
+        let blocks = interface
+            .input_blocks
+            .iter_mut()
+            .chain(interface.output_blocks.iter_mut())
+            .chain(interface.uniform_blocks.iter_mut());
+        for block in blocks {
+            match block.instance_name.clone() {
+                Some(instance_name) => {
+                    let (referenced, written) = scan_usage(&function.body, &instance_name);
+                    block.referenced |= referenced;
+                    block.written |= written;
+                }
+                None => {
+                    for member in &mut block.members {
+                        let (referenced, written) = scan_usage(&function.body, &member.name);
+                        member.referenced |= referenced;
+                        member.written |= written;
+                        block.referenced |= referenced;
+                        block.written |= written;
+                    }
+                }
+            }
+        }
+    }
+
+    for output in &interface.outputs {
+        if !output.written {
+            diagnostics.push(Diagnostic::warning(
+                label,
+                output.line,
+                format!("output `{}` is declared but never written", output.name),
+            ));
+        }
+    }
+    for input in interface.inputs.iter().filter(|input| !input.referenced) {
+        diagnostics.push(Diagnostic::warning(
+            label,
+            input.line,
+            format!("input `{}` is declared but never read", input.name),
+        ));
+    }
+    for uniform in interface
+        .uniforms
+        .iter()
+        .filter(|uniform| !uniform.referenced)
+    {
+        diagnostics.push(Diagnostic::warning(
+            label,
+            uniform.line,
+            format!(
+                "uniform `{}` is never used and will be optimized away",
+                uniform.name
+            ),
+        ));
+    }
+    if stage == ShaderStage::Vertex
+        && !functions
+            .iter()
+            .any(|function| scan_usage(&function.body, "gl_Position").1)
+    {
+        diagnostics.push(Diagnostic::warning(
+            label,
+            0,
+            "gl_Position is never written".to_string(),
+        ));
+    }
+
+    (interface, diagnostics)
+}
+
+/// Checks that the outputs of one stage feed the inputs of the next.
+/// Geometry shader inputs are arrays of the previous stage's outputs.
+pub fn check_interface(producer: &StageInterface, consumer: &StageInterface) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let consumer_takes_arrays = consumer.stage == ShaderStage::Geometry;
+
+    for input in &consumer.inputs {
+        let output = match input.location {
+            Some(location) => producer
+                .outputs
+                .iter()
+                .find(|output| output.location == Some(location)),
+            None => producer
+                .outputs
+                .iter()
+                .find(|output| output.name == input.name),
+        };
+        let output = match output {
+            Some(output) => output,
+            None => {
+                diagnostics.push(Diagnostic::error(
+                    &consumer.label,
+                    input.line,
+                    format!(
+                        "input `{} {}` has no matching output in {}",
+                        input.type_name, input.name, producer.label
+                    ),
+                ));
+                continue;
+            }
+        };
+
+        let input_array = if consumer_takes_arrays {
+            None
+        } else {
+            input.array_size.clone()
+        };
+        if output.type_name != input.type_name || output.array_size != input_array {
+            diagnostics.push(Diagnostic::error(
+                &consumer.label,
+                input.line,
+                format!(
+                    "input `{}` is declared as {} but {} writes {}",
+                    input.name,
+                    describe_type(&input.type_name, &input_array),
+                    producer.label,
+                    describe_type(&output.type_name, &output.array_size)
+                ),
+            ));
+        }
+        if output.interpolation.as_deref().unwrap_or("smooth")
+            != input.interpolation.as_deref().unwrap_or("smooth")
+        {
+            diagnostics.push(Diagnostic::error(
+                &consumer.label,
+                input.line,
+                format!(
+                    "interpolation qualifiers of `{}` differ between {} and {}",
+                    input.name, producer.label, consumer.label
+                ),
+            ));
+        }
+        if !output.written {
+            let message = format!(
+                "input `{}` is fed by an output that {} declares but never writes",
+                input.name, producer.label
+            );
+            if input.referenced {
+                diagnostics.push(Diagnostic::error(&consumer.label, input.line, message));
+            } else {
+                diagnostics.push(Diagnostic::warning(&consumer.label, input.line, message));
+            }
+        }
+    }
+
+    for output in &producer.outputs {
+        let consumed = consumer.inputs.iter().any(|input| match output.location {
+            Some(location) => input.location == Some(location) || input.name == output.name,
+            None => input.name == output.name,
+        });
+        if !consumed {
+            diagnostics.push(Diagnostic::warning(
+                &producer.label,
+                output.line,
+                format!(
+                    "output `{}` is never read by {}",
+                    output.name, consumer.label
+                ),
+            ));
+        }
+    }
+
+    for input_block in &consumer.input_blocks {
+        let output_block = producer
+            .output_blocks
+            .iter()
+            .find(|block| block.block_name == input_block.block_name);
+        match output_block {
+            None => diagnostics.push(Diagnostic::error(
+                &consumer.label,
+                input_block.line,
+                format!(
+                    "input block `{}` has no matching output block in {}",
+                    input_block.block_name, producer.label
+                ),
+            )),
+            Some(output_block) => {
+                let same_members = output_block.members.len() == input_block.members.len()
+                    && output_block
+                        .members
+                        .iter()
+                        .zip(input_block.members.iter())
+                        .all(|(a, b)| {
+                            a.name == b.name
+                                && a.type_name == b.type_name
+                                && a.array_size == b.array_size
+                        });
+                if !same_members {
+                    diagnostics.push(Diagnostic::error(
+                        &consumer.label,
+                        input_block.line,
+                        format!(
+                            "members of block `{}` differ from the output block in {}",
+                            input_block.block_name, producer.label
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Runs the interface checks over the stages of a program, which must be given in pipeline order
+pub fn check_program(stages: &[StageInterface]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for pair in stages.windows(2) {
+        diagnostics.extend(check_interface(&pair[0], &pair[1]));
+    }
+
+    // Mixing versions links, but each stage then follows the rules of its own version
+    if let Some(first) = stages.first() {
+        for stage in &stages[1..] {
+            if let (Some(version), Some(first_version)) = (&stage.version, &first.version)
+                && version != first_version
+            {
+                diagnostics.push(Diagnostic::warning(
+                    &stage.label,
+                    0,
+                    format!(
+                        "#version {} differs from #version {} in {}",
+                        version, first_version, first.label
+                    ),
+                ));
+            }
+        }
+    }
+
+    // A uniform shared between stages must have the same type everywhere
+    let mut seen: HashMap<&str, (&Variable, &str)> = HashMap::new();
+    for stage in stages {
+        for uniform in &stage.uniforms {
+            match seen.get(uniform.name.as_str()) {
+                Some((other, other_label))
+                    if other.type_name != uniform.type_name
+                        || other.array_size != uniform.array_size =>
+                {
+                    diagnostics.push(Diagnostic::error(
+                        &stage.label,
+                        uniform.line,
+                        format!(
+                            "uniform `{}` is declared as {} here but as {} in {}",
+                            uniform.name,
+                            describe_type(&uniform.type_name, &uniform.array_size),
+                            describe_type(&other.type_name, &other.array_size),
+                            other_label
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(&uniform.name, (uniform, &stage.label));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Checks that every uniform name passed to a set_* call exists in at least one stage.
+/// Names may index into arrays or structs, e.g. "lights[2].color" is checked as "lights".
+pub fn check_uniform_names(stages: &[StageInterface], uses: &[UniformUse]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for uniform_use in uses {
+        let base = uniform_use
+            .name
+            .split(['[', '.'])
+            .next()
+            .unwrap_or_default();
+        if !stages
+            .iter()
+            .any(|stage| stage.find_uniform(base).is_some())
+        {
+            diagnostics.push(Diagnostic::error(
+                &uniform_use.label,
+                uniform_use.line,
+                format!(
+                    "uniform `{}` is set here but is not declared by any stage of the program",
+                    uniform_use.name
+                ),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// A uniform name passed to one of ShaderProgram's set_* methods
+pub struct UniformUse {
+    pub name: String,
+    pub label: String,
+    pub line: usize,
+}
+
+/// Finds `.set_*("name"` calls with a string literal name in Rust source
+pub fn find_uniform_setter_calls(label: &str, rust_source: &str) -> Vec<UniformUse> {
+    let mut uses = Vec::new();
+    for (line_index, line) in rust_source.lines().enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find(".set_") {
+            rest = &rest[start + ".set_".len()..];
+            let method_length = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let after_method = rest[method_length..].trim_start();
+            let Some(arguments) = after_method.strip_prefix('(') else {
+                continue;
+            };
+            let Some(literal) = arguments.trim_start().strip_prefix('"') else {
+                continue;
+            };
+            if let Some(end) = literal.find('"') {
+                uses.push(UniformUse {
+                    name: literal[..end].to_string(),
+                    label: label.to_string(),
+                    line: line_index + 1,
+                });
+            }
+        }
+    }
+    uses
+}
+
+fn describe_type(type_name: &str, array_size: &Option<String>) -> String {
+    match array_size {
+        Some(size) => format!("{}[{}]", type_name, size),
+        None => type_name.to_string(),
+    }
+}
+
+/// Returns whether a function body mentions name, and whether it assigns to it.
+/// An assignment is the name, optionally followed by swizzles, member accesses and subscripts,
+/// followed by an assignment operator, or the name next to ++/--.
+fn scan_usage(body: &[Token], name: &str) -> (bool, bool) {
+    let mut referenced = false;
+    let mut written = false;
+
+    for (index, token) in body.iter().enumerate() {
+        if token.kind != TokenKind::Identifier || token.text != name {
+            continue;
+        }
+        // Skip member accesses like other.name
+        if index > 0 && body[index - 1].is(".") {
+            continue;
+        }
+        referenced = true;
+
+        if index > 0 && (body[index - 1].is("++") || body[index - 1].is("--")) {
+            written = true;
+            continue;
+        }
+
+        let mut cursor = index + 1;
+        loop {
+            match body.get(cursor) {
+                Some(next) if next.is(".") => cursor += 2,
+                Some(next) if next.is("[") => {
+                    let mut depth = 0;
+                    while let Some(token) = body.get(cursor) {
+                        if token.is("[") {
+                            depth += 1;
+                        } else if token.is("]") {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        cursor += 1;
+                    }
+                    cursor += 1;
+                }
+                _ => break,
+            }
+        }
+        if let Some(next) = body.get(cursor)
+            && matches!(
+                next.text.as_str(),
+                "=" | "+="
+                    | "-="
+                    | "*="
+                    | "/="
+                    | "%="
+                    | "<<="
+                    | ">>="
+                    | "&="
+                    | "^="
+                    | "|="
+                    | "++"
+                    | "--"
+            )
+        {
+            written = true;
+        }
+    }
+
+    (referenced, written)
+}
+
+/// Replaces comments with whitespace, keeping newlines so that line numbers stay correct
+fn strip_comments(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            while let Some(&next) = chars.peek() {
+                if next == '\n' {
+                    break;
+                }
+                chars.next();
+            }
+            result.push(' ');
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut previous = '\0';
+            for next in chars.by_ref() {
+                if next == '\n' {
+                    result.push('\n');
+                }
+                if previous == '*' && next == '/' {
+                    break;
+                }
+                previous = next;
+            }
+            result.push(' ');
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn tokenize(text: &str, line: usize) -> Vec<Token> {
+    const THREE_CHAR: [&str; 2] = ["<<=", ">>="];
+    const TWO_CHAR: [&str; 19] = [
+        "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=", "++", "--", "==", "!=", "<=", ">=", "&&",
+        "||", "^^", "<<", ">>",
+    ];
+
+    let mut tokens = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Identifier,
+                text: text[start..i].to_string(),
+                line,
+            });
+        } else if c.is_ascii_digit()
+            || (c == '.' && i + 1 < bytes.len() && bytes[i + 1].is_ascii_digit())
+        {
+            let start = i;
+            while i < bytes.len() {
+                let b = bytes[i];
+                let exponent_sign = (b == b'+' || b == b'-')
+                    && (bytes[i - 1] == b'e' || bytes[i - 1] == b'E')
+                    && !text[start..i].starts_with("0x");
+                if b.is_ascii_alphanumeric() || b == b'.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Number,
+                text: text[start..i].to_string(),
+                line,
+            });
+        } else {
+            let rest = &text[i..];
+            let length = if THREE_CHAR.iter().any(|op| rest.starts_with(op)) {
+                3
+            } else if TWO_CHAR.iter().any(|op| rest.starts_with(op)) {
+                2
+            } else {
+                c.len_utf8()
+            };
+            tokens.push(Token {
+                kind: TokenKind::Punct,
+                text: rest[..length].to_string(),
+                line,
+            });
+            i += length;
+        }
+    }
+    tokens
+}
+
+struct Preprocessed {
+    tokens: Vec<Token>,
+    version: Option<String>,
+    version_line: usize,
+}
+
+/// The state of one #if/#ifdef group
+struct Conditional {
+    /// Whether the enclosing group is active
+    parent_active: bool,
+    /// Whether the current branch is active
+    active: bool,
+    /// Whether some branch of the group has already been taken
+    taken: bool,
+}
+
+/// Runs the preprocessor over comment-free source and tokenizes the active lines.
+/// Only object-like macros are expanded; function-like macros are reported as unsupported.
+fn preprocess(source: &str, label: &str, diagnostics: &mut Vec<Diagnostic>) -> Preprocessed {
+    let mut tokens = Vec::new();
+    let mut macros: HashMap<String, Vec<Token>> = HashMap::new();
+    let mut conditionals: Vec<Conditional> = Vec::new();
+    let mut version = None;
+    let mut version_line = 0;
+
+    // Join lines ending in a backslash, remembering the line each joined line started on
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let (start, mut text) = pending.take().unwrap_or((index + 1, String::new()));
+        match line.strip_suffix('\\') {
+            Some(continued) => {
+                text.push_str(continued);
+                pending = Some((start, text));
+            }
+            None => {
+                text.push_str(line);
+                lines.push((start, text));
+            }
+        }
+    }
+    if let Some(last) = pending {
+        lines.push(last);
+    }
+
+    for (line_number, line) in lines {
+        let active = conditionals.last().is_none_or(|c| c.active);
+        let trimmed = line.trim_start();
+
+        let Some(directive_line) = trimmed.strip_prefix('#') else {
+            if active {
+                let line_tokens = tokenize(&line, line_number);
+                tokens.extend(expand_macros(line_tokens, &macros, 0));
+            }
+            continue;
+        };
+
+        let directive_line = directive_line.trim_start();
+        let directive_length = directive_line
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(directive_line.len());
+        let directive = &directive_line[..directive_length];
+        let rest = directive_line[directive_length..].trim();
+
+        match directive {
+            "ifdef" | "ifndef" | "if" => {
+                let condition = if !active {
+                    false
+                } else if directive == "if" {
+                    evaluate_condition(rest, &macros, label, line_number, diagnostics)
+                } else {
+                    macros.contains_key(rest) == (directive == "ifdef")
+                };
+                conditionals.push(Conditional {
+                    parent_active: active,
+                    active: active && condition,
+                    taken: condition,
+                });
+            }
+            "elif" | "else" => {
+                let Some(conditional) = conditionals.last_mut() else {
+                    diagnostics.push(Diagnostic::error(
+                        label,
+                        line_number,
+                        format!("#{} without #if", directive),
+                    ));
+                    continue;
+                };
+                let condition = if conditional.taken || !conditional.parent_active {
+                    false
+                } else if directive == "elif" {
+                    evaluate_condition(rest, &macros, label, line_number, diagnostics)
+                } else {
+                    true
+                };
+                conditional.active = conditional.parent_active && condition;
+                conditional.taken |= condition;
+            }
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        label,
+                        line_number,
+                        "#endif without #if".to_string(),
+                    ));
+                }
+            }
+            _ if !active => {}
+            "version" => {
+                if version.is_some() {
+                    diagnostics.push(Diagnostic::error(
+                        label,
+                        line_number,
+                        "#version may only appear once".to_string(),
+                    ));
+                } else if !tokens.is_empty() {
+                    diagnostics.push(Diagnostic::error(
+                        label,
+                        line_number,
+                        "#version must come before anything else".to_string(),
+                    ));
+                }
+                version = Some(rest.to_string());
+                version_line = line_number;
+            }
+            "define" => {
+                let mut define_tokens = tokenize(rest, line_number).into_iter();
+                match define_tokens.next() {
+                    Some(name) if name.kind == TokenKind::Identifier => {
+                        let function_like = rest[name.text.len()..].starts_with('(');
+                        if function_like {
+                            diagnostics.push(Diagnostic::warning(
+                                label,
+                                line_number,
+                                format!(
+                                    "function-like macro `{}` is not expanded by the checker",
+                                    name.text
+                                ),
+                            ));
+                        }
+                        macros.insert(name.text, define_tokens.collect());
+                    }
+                    _ => diagnostics.push(Diagnostic::error(
+                        label,
+                        line_number,
+                        "#define without a macro name".to_string(),
+                    )),
+                }
+            }
+            "undef" => {
+                macros.remove(rest);
+            }
+            "error" => diagnostics.push(Diagnostic::error(
+                label,
+                line_number,
+                format!("#error {}", rest),
+            )),
+            "extension" | "pragma" | "line" | "" => {}
+            _ => diagnostics.push(Diagnostic::error(
+                label,
+                line_number,
+                format!("unknown preprocessor directive #{}", directive),
+            )),
+        }
+    }
+
+    if !conditionals.is_empty() {
+        diagnostics.push(Diagnostic::error(
+            label,
+            0,
+            "unterminated #if block".to_string(),
+        ));
+    }
+
+    Preprocessed {
+        tokens,
+        version,
+        version_line,
+    }
+}
+
+fn expand_macros(
+    tokens: Vec<Token>,
+    macros: &HashMap<String, Vec<Token>>,
+    depth: usize,
+) -> Vec<Token> {
+    // Guards against self-referencing macros
+    if depth > 16 {
+        return tokens;
+    }
+
+    let mut result = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        match macros.get(&token.text) {
+            Some(replacement) if token.kind == TokenKind::Identifier => {
+                let replacement = replacement
+                    .iter()
+                    .map(|t| Token {
+                        line: token.line,
+                        ..t.clone()
+                    })
+                    .collect();
+                result.extend(expand_macros(replacement, macros, depth + 1));
+            }
+            _ => result.push(token),
+        }
+    }
+    result
+}
+
+/// Evaluates the integer expression of an #if or #elif
+fn evaluate_condition(
+    expression: &str,
+    macros: &HashMap<String, Vec<Token>>,
+    label: &str,
+    line: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> bool {
+    // Resolve defined(X) before macro expansion, as the preprocessor does
+    let raw = tokenize(expression, line);
+    let mut resolved = Vec::with_capacity(raw.len());
+    let mut index = 0;
+    while index < raw.len() {
+        if raw[index].is("defined") {
+            let (name, consumed) = if raw.get(index + 1).is_some_and(|t| t.is("(")) {
+                (raw.get(index + 2), 4)
+            } else {
+                (raw.get(index + 1), 2)
+            };
+            let defined = name.is_some_and(|name| macros.contains_key(&name.text));
+            resolved.push(Token {
+                kind: TokenKind::Number,
+                text: if defined { "1" } else { "0" }.to_string(),
+                line,
+            });
+            index += consumed;
+        } else {
+            resolved.push(raw[index].clone());
+            index += 1;
+        }
+    }
+
+    let expanded = expand_macros(resolved, macros, 0);
+    let mut evaluator = ConditionEvaluator {
+        tokens: &expanded,
+        position: 0,
+    };
+    match evaluator.parse_or() {
+        Some(value) if evaluator.position == expanded.len() => value != 0,
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                label,
+                line,
+                format!("could not evaluate preprocessor condition `{}`", expression),
+            ));
+            false
+        }
+    }
+}
+
+/// Recursive descent evaluator for preprocessor conditions. Unknown identifiers evaluate to 0.
+struct ConditionEvaluator<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl ConditionEvaluator<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|t| t.text.as_str())
+    }
+
+    fn parse_or(&mut self) -> Option<i64> {
+        let mut value = self.parse_and()?;
+        while self.peek() == Some("||") {
+            self.position += 1;
+            let rhs = self.parse_and()?;
+            value = ((value != 0) || (rhs != 0)) as i64;
+        }
+        Some(value)
+    }
+
+    fn parse_and(&mut self) -> Option<i64> {
+        let mut value = self.parse_comparison()?;
+        while self.peek() == Some("&&") {
+            self.position += 1;
+            let rhs = self.parse_comparison()?;
+            value = ((value != 0) && (rhs != 0)) as i64;
+        }
+        Some(value)
+    }
+
+    fn parse_comparison(&mut self) -> Option<i64> {
+        let mut value = self.parse_additive()?;
+        while let Some(op) = self.peek() {
+            let op = op.to_string();
+            if !matches!(op.as_str(), "==" | "!=" | "<" | ">" | "<=" | ">=") {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.parse_additive()?;
+            value = match op.as_str() {
+                "==" => value == rhs,
+                "!=" => value != rhs,
+                "<" => value < rhs,
+                ">" => value > rhs,
+                "<=" => value <= rhs,
+                _ => value >= rhs,
+            } as i64;
+        }
+        Some(value)
+    }
+
+    fn parse_additive(&mut self) -> Option<i64> {
+        let mut value = self.parse_multiplicative()?;
+        while let Some(op) = self.peek() {
+            let op = op.to_string();
+            if op != "+" && op != "-" {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.parse_multiplicative()?;
+            value = if op == "+" { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    fn parse_multiplicative(&mut self) -> Option<i64> {
+        let mut value = self.parse_unary()?;
+        while let Some(op) = self.peek() {
+            let op = op.to_string();
+            if !matches!(op.as_str(), "*" | "/" | "%") {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.parse_unary()?;
+            value = match op.as_str() {
+                "*" => value * rhs,
+                _ if rhs == 0 => return None,
+                "/" => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    fn parse_unary(&mut self) -> Option<i64> {
+        match self.peek()? {
+            "!" => {
+                self.position += 1;
+                Some((self.parse_unary()? == 0) as i64)
+            }
+            "-" => {
+                self.position += 1;
+                Some(-self.parse_unary()?)
+            }
+            "(" => {
+                self.position += 1;
+                let value = self.parse_or()?;
+                if self.peek() != Some(")") {
+                    return None;
+                }
+                self.position += 1;
+                Some(value)
+            }
+            _ => {
+                let token = &self.tokens[self.position];
+                self.position += 1;
+                match token.kind {
+                    TokenKind::Number => {
+                        token.text.trim_end_matches(['u', 'U']).parse::<i64>().ok()
+                    }
+                    TokenKind::Identifier => Some(0),
+                    TokenKind::Punct => None,
+                }
+            }
+        }
+    }
+}
+
+const STORAGE_QUALIFIERS: [&str; 6] = ["in", "out", "inout", "uniform", "const", "attribute"];
+const OTHER_QUALIFIERS: [&str; 10] = [
+    "flat",
+    "smooth",
+    "noperspective",
+    "centroid",
+    "invariant",
+    "highp",
+    "mediump",
+    "lowp",
+    "varying",
+    "precise",
+];
+
+#[derive(Default)]
+struct Qualifiers {
+    storage: Option<String>,
+    interpolation: Option<String>,
+    location: Option<i64>,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    label: &'a str,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek().is_some_and(|t| t.is(text))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn current_line(&self) -> usize {
+        self.peek()
+            .or(self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(0)
+    }
+
+    fn error(&mut self, message: String) {
+        let line = self.current_line();
+        self.diagnostics
+            .push(Diagnostic::error(self.label, line, message));
+    }
+
+    fn expect(&mut self, text: &str) -> bool {
+        if self.peek_is(text) {
+            self.position += 1;
+            true
+        } else {
+            let found = self
+                .peek()
+                .map(|t| format!("`{}`", t.text))
+                .unwrap_or("end of file".to_string());
+            self.error(format!("expected `{}` but found {}", text, found));
+            false
+        }
+    }
+
+    fn expect_identifier(&mut self) -> Option<Token> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Identifier => self.next(),
+            _ => {
+                let found = self
+                    .peek()
+                    .map(|t| format!("`{}`", t.text))
+                    .unwrap_or("end of file".to_string());
+                self.error(format!("expected an identifier but found {}", found));
+                None
+            }
+        }
+    }
+
+    /// Skips to just past the next semicolon at bracket depth 0, used to recover from errors
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token.text.as_str() {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => depth -= 1,
+                ";" if depth <= 0 => return,
+                _ => {}
+            }
+            if depth < 0 {
+                return;
+            }
+        }
+    }
+
+    /// Collects the tokens between a matching pair of brackets, consuming both brackets
+    fn take_group(&mut self, open: &str, close: &str) -> Vec<Token> {
+        let mut group = Vec::new();
+        if !self.expect(open) {
+            return group;
+        }
+        let mut depth = 1;
+        while let Some(token) = self.next() {
+            if token.is(open) {
+                depth += 1;
+            } else if token.is(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return group;
+                }
+            }
+            group.push(token);
+        }
+        self.error(format!("unterminated `{}`", open));
+        group
+    }
+
+    fn parse(&mut self, interface: &mut StageInterface) -> Vec<Function> {
+        let mut functions = Vec::new();
+        while self.peek().is_some() {
+            if self.peek_is(";") {
+                self.position += 1;
+                continue;
+            }
+            if self.peek_is("precision") {
+                self.skip_statement();
+                continue;
+            }
+            let start = self.position;
+            if let Some(function) = self.parse_declaration(interface) {
+                functions.push(function);
+            }
+            // Make sure a bad declaration can never stall the parser
+            if self.position == start {
+                self.position += 1;
+            }
+        }
+        functions
+    }
+
+    fn parse_qualifiers(&mut self) -> Qualifiers {
+        let mut qualifiers = Qualifiers::default();
+        loop {
+            let Some(token) = self.peek().cloned() else {
+                return qualifiers;
+            };
+            if token.is("layout") {
+                self.position += 1;
+                let layout = self.take_group("(", ")");
+                for pair in layout.split(|t| t.is(",")) {
+                    if let [key, equals, value] = pair
+                        && key.is("location")
+                        && equals.is("=")
+                    {
+                        qualifiers.location = value.text.parse().ok();
+                    }
+                }
+            } else if STORAGE_QUALIFIERS.contains(&token.text.as_str()) {
+                if token.is("attribute") {
+                    self.error(
+                        "`attribute` is not available in GLSL 330 core, use `in`".to_string(),
+                    );
+                }
+                self.position += 1;
+                qualifiers.storage = Some(token.text);
+            } else if OTHER_QUALIFIERS.contains(&token.text.as_str()) {
+                if token.is("varying") {
+                    self.error(
+                        "`varying` is not available in GLSL 330 core, use `in`/`out`".to_string(),
+                    );
+                }
+                self.position += 1;
+                if matches!(token.text.as_str(), "flat" | "smooth" | "noperspective") {
+                    qualifiers.interpolation = Some(token.text);
+                }
+            } else {
+                return qualifiers;
+            }
+        }
+    }
+
+    /// Parses an optional `[size]` suffix
+    fn parse_array_suffix(&mut self) -> Option<String> {
+        if !self.peek_is("[") {
+            return None;
+        }
+        let size = self.take_group("[", "]");
+        Some(
+            size.iter()
+                .map(|t| t.text.as_str())
+                .collect::<Vec<&str>>()
+                .join(""),
+        )
+    }
+
+    fn parse_declaration(&mut self, interface: &mut StageInterface) -> Option<Function> {
+        let qualifiers = self.parse_qualifiers();
+
+        if self.peek_is("struct") {
+            self.position += 1;
+            self.expect_identifier();
+            self.take_group("{", "}");
+            self.skip_statement();
+            return None;
+        }
+
+        // Declarations such as `layout (triangles) in;` have no type or name
+        if self.peek_is(";") && qualifiers.storage.is_some() {
+            self.position += 1;
+            return None;
+        }
+
+        let type_token = self.expect_identifier()?;
+
+        // Interface block: qualifier BlockName { members } [instance[size]];
+        if self.peek_is("{") {
+            let members = self.parse_block_members();
+            let instance_name = match self.peek() {
+                Some(token) if token.kind == TokenKind::Identifier => self.next().map(|t| t.text),
+                _ => None,
+            };
+            self.parse_array_suffix();
+            self.expect(";");
+
+            let block = InterfaceBlock {
+                block_name: type_token.text,
+                instance_name,
+                members,
+                line: type_token.line,
+                written: false,
+                referenced: false,
+            };
+            match qualifiers.storage.as_deref() {
+                Some("uniform") => interface.uniform_blocks.push(block),
+                Some("in") => interface.input_blocks.push(block),
+                Some("out") => interface.output_blocks.push(block),
+                _ => self.error(format!(
+                    "block `{}` needs an in, out or uniform qualifier",
+                    block.block_name
+                )),
+            }
+            return None;
+        }
+
+        let name_token = self.expect_identifier()?;
+
+        if self.peek_is("(") {
+            // Function definition or prototype
+            self.take_group("(", ")");
+            if self.peek_is("{") {
+                let body = self.take_group("{", "}");
+                return Some(Function {
+                    name: name_token.text,
+                    body,
+                });
+            }
+            self.expect(";");
+            return None;
+        }
+
+        // Variable declarators: name[size] = init, name2 ...;
+        let mut name_token = name_token;
+        loop {
+            let array_size = self.parse_array_suffix();
+            let variable = Variable {
+                name: name_token.text.clone(),
+                type_name: type_token.text.clone(),
+                array_size,
+                location: qualifiers.location,
+                interpolation: qualifiers.interpolation.clone(),
+                line: name_token.line,
+                written: false,
+                referenced: false,
+            };
+            match qualifiers.storage.as_deref() {
+                Some("in") | Some("attribute") => interface.inputs.push(variable),
+                Some("out") => interface.outputs.push(variable),
+                Some("uniform") => interface.uniforms.push(variable),
+                _ => {}
+            }
+
+            // Skip initializers
+            if self.peek_is("=") {
+                let mut depth = 0;
+                while let Some(token) = self.peek() {
+                    match token.text.as_str() {
+                        "(" | "[" | "{" => depth += 1,
+                        ")" | "]" | "}" => depth -= 1,
+                        "," | ";" if depth == 0 => break,
+                        _ => {}
+                    }
+                    self.position += 1;
+                }
+            }
+
+            if self.peek_is(",") {
+                self.position += 1;
+                name_token = self.expect_identifier()?;
+                continue;
+            }
+            if !self.expect(";") {
+                self.skip_statement();
+            }
+            return None;
+        }
+    }
+
+    fn parse_block_members(&mut self) -> Vec<Variable> {
+        let body = self.take_group("{", "}");
+        let mut members = Vec::new();
+        for declaration in body.split(|t| t.is(";")) {
+            // Drop layout(...) and other qualifiers in front of the member type
+            let mut tokens = declaration.iter().peekable();
+            let mut rest = Vec::new();
+            while let Some(token) = tokens.next() {
+                if token.is("layout") {
+                    for inner in tokens.by_ref() {
+                        if inner.is(")") {
+                            break;
+                        }
+                    }
+                } else if !OTHER_QUALIFIERS.contains(&token.text.as_str()) {
+                    rest.push(token);
+                }
+            }
+            let Some((type_token, declarators)) = rest.split_first() else {
+                continue;
+            };
+            for declarator in declarators.split(|t| t.is(",")) {
+                let Some(name) = declarator.first() else {
+                    continue;
+                };
+                let array_size = if declarator.len() > 1 && declarator[1].is("[") {
+                    Some(
+                        declarator[2..declarator.len() - 1]
+                            .iter()
+                            .map(|t| t.text.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(""),
+                    )
+                } else {
+                    None
+                };
+                members.push(Variable {
+                    name: name.text.clone(),
+                    type_name: type_token.text.clone(),
+                    array_size,
+                    location: None,
+                    interpolation: None,
+                    line: name.line,
+                    written: false,
+                    referenced: false,
+                });
+            }
+        }
+        members
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .collect()
+    }
+
+    /// Lints both stages and checks them as a program, returning the errors
+    fn check_pair(vertex: &str, fragment: &str) -> (Vec<StageInterface>, Vec<Diagnostic>) {
+        let (vertex, mut diagnostics) = lint_stage(ShaderStage::Vertex, "vertex", vertex);
+        let (fragment, fragment_diagnostics) =
+            lint_stage(ShaderStage::Fragment, "fragment", fragment);
+        diagnostics.extend(fragment_diagnostics);
+        let stages = vec![vertex, fragment];
+        diagnostics.extend(check_program(&stages));
+        (stages, errors(diagnostics))
+    }
+
+    fn assert_one_error(errors: &[Diagnostic], label: &str, line: usize, message: &str) {
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].label, label);
+        assert_eq!(errors[0].line, line);
+        assert!(errors[0].message.contains(message), "{}", errors[0]);
+    }
+
+    #[test]
+    fn the_demo_program_has_no_errors() {
+        let (stages, mut errors) = check_pair(include_str!("shader.vs"), include_str!("shader.fs"));
+        let uses = find_uniform_setter_calls("main.rs", include_str!("main.rs"));
+        assert!(!uses.is_empty());
+        errors.extend(check_uniform_names(&stages, &uses));
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    const VERTEX: &str = "#version 330 core
+layout (location = 0) in vec3 position;
+out vec3 color;
+void main()
+{
+    gl_Position = vec4(position, 1.0);
+    color = position;
+}
+";
+
+    #[test]
+    fn mismatched_types_are_errors() {
+        let fragment = "#version 330 core
+in vec4 color;
+out vec4 fragment_color;
+void main()
+{
+    fragment_color = color;
+}
+";
+        let (_, errors) = check_pair(VERTEX, fragment);
+        assert_one_error(
+            &errors,
+            "fragment",
+            2,
+            "declared as vec4 but vertex writes vec3",
+        );
+    }
+
+    #[test]
+    fn inputs_without_an_output_are_errors() {
+        let fragment = "#version 330 core
+in vec3 color;
+in vec2 uv;
+out vec4 fragment_color;
+void main()
+{
+    fragment_color = vec4(color, uv.x);
+}
+";
+        let (_, errors) = check_pair(VERTEX, fragment);
+        assert_one_error(&errors, "fragment", 3, "`vec2 uv` has no matching output");
+    }
+
+    #[test]
+    fn mismatched_interpolation_is_an_error() {
+        let fragment = "#version 330 core
+flat in vec3 color;
+out vec4 fragment_color;
+void main()
+{
+    fragment_color = vec4(color, 1.0);
+}
+";
+        let (_, errors) = check_pair(VERTEX, fragment);
+        assert_one_error(
+            &errors,
+            "fragment",
+            2,
+            "interpolation qualifiers of `color`",
+        );
+    }
+
+    #[test]
+    fn unknown_uniform_names_are_errors() {
+        let fragment = "#version 330 core
+in vec3 color;
+out vec4 fragment_color;
+uniform float brightness;
+uniform vec3 lights[4];
+void main()
+{
+    fragment_color = vec4(brightness * color + lights[0], 1.0);
+}
+";
+        let rust = r#"
+program.set_float("brightness", 2.0);
+program.set_vec3("lights[3]", &light);
+program.set_float ( "brightnes", 2.0);
+"#;
+        let (stages, errors) = check_pair(VERTEX, fragment);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let uses = find_uniform_setter_calls("scene.rs", rust);
+        assert_eq!(uses.len(), 3);
+        assert_one_error(
+            &check_uniform_names(&stages, &uses),
+            "scene.rs",
+            4,
+            "uniform `brightnes` is set here",
+        );
+    }
+
+    #[test]
+    fn stages_with_different_versions_are_warned_about() {
+        let fragment = "#version 400 core
+in vec3 color;
+out vec4 fragment_color;
+void main()
+{
+    fragment_color = vec4(color, 1.0);
+}
+";
+        let (vertex, _) = lint_stage(ShaderStage::Vertex, "vertex", VERTEX);
+        let (fragment, _) = lint_stage(ShaderStage::Fragment, "fragment", fragment);
+        let diagnostics = check_program(&[vertex, fragment]);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].label, "fragment");
+        assert_eq!(
+            diagnostics[0].message,
+            "#version 400 core differs from #version 330 core in vertex"
+        );
+    }
+}
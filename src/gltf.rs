@@ -0,0 +1,999 @@
+//! glTF 2.0 models, either as .gltf JSON with external or data: URI buffers, or as binary .glb.
+//! Triangle primitives become meshes, the default scene's nodes keep their local transforms and
+//! metallic-roughness materials refer to images decoded with the image crate. Everything happens
+//! on the CPU. Sparse accessors, skins, morph targets, animations and cameras are not read.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, ImageError, ImageReader};
+
+use crate::{
+    json::{self, JsonError, JsonValue},
+    matrix::Matrix4,
+    mesh::{Mesh, MeshVertex},
+    vector::{Vector2, Vector3, Vector4},
+};
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Json(JsonError),
+    Image {
+        image: usize,
+        error: ImageError,
+    },
+    /// The file is readable but breaks the glTF rules or uses something not supported
+    Invalid(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            GltfError::Json(error) => write!(f, "Invalid glTF JSON at {}", error),
+            GltfError::Image { image, error } => {
+                write!(f, "Failed to decode glTF image {}: {}", image, error)
+            }
+            GltfError::Invalid(message) => write!(f, "Invalid glTF: {}", message),
+        }
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, GltfError> {
+    Err(GltfError::Invalid(message))
+}
+
+/// A texture used by a material
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GltfTexture {
+    /// Index into GltfModel::images
+    pub image: usize,
+    /// Which TEXCOORD_n set the texture uses. Only set 0 is loaded into meshes.
+    pub texcoord: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fragments with alpha below alpha_cutoff are discarded
+    Mask,
+    Blend,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<GltfTexture>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Roughness in the green channel and metalness in the blue one
+    pub metallic_roughness_texture: Option<GltfTexture>,
+    pub normal_texture: Option<GltfTexture>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<GltfTexture>,
+    pub occlusion_strength: f32,
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<GltfTexture>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+pub struct GltfPrimitive {
+    pub mesh: Mesh,
+    /// Index into GltfModel::materials
+    pub material: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+pub struct GltfNode {
+    pub name: String,
+    /// Relative to the parent node
+    pub transform: Matrix4,
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<DynamicImage>,
+    pub nodes: Vec<GltfNode>,
+    /// The root nodes of the default scene
+    pub scene: Vec<usize>,
+}
+
+impl GltfModel {
+    /// Every mesh placed by the scene, with the world transform of the node that places it
+    pub fn mesh_instances(&self) -> Vec<(usize, Matrix4)> {
+        let mut instances = Vec::new();
+        let mut pending: Vec<(usize, Matrix4)> = self
+            .scene
+            .iter()
+            .map(|root| (*root, Matrix4::identity()))
+            .collect();
+        while let Some((index, parent)) = pending.pop() {
+            let node = &self.nodes[index];
+            let world = Matrix4::mult_mat4(&parent, &node.transform);
+            if let Some(mesh) = node.mesh {
+                instances.push((mesh, world));
+            }
+            pending.extend(node.children.iter().map(|child| (*child, world)));
+        }
+        instances
+    }
+}
+
+/// Reads a .gltf or .glb file. External buffers and images are resolved relative to it.
+pub fn load_gltf(path: &Path) -> Result<GltfModel, GltfError> {
+    let bytes = fs::read(path).map_err(|error| GltfError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    if bytes.starts_with(b"glTF") {
+        parse_glb(&bytes, Some(directory))
+    } else {
+        let text = String::from_utf8(bytes)
+            .map_err(|_| GltfError::Invalid("the JSON is not UTF-8".to_string()))?;
+        parse_gltf(&text, None, Some(directory))
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Parses a binary .glb container: a 12 byte header, a JSON chunk and an optional BIN chunk
+pub fn parse_glb(bytes: &[u8], directory: Option<&Path>) -> Result<GltfModel, GltfError> {
+    if bytes.len() < 12 || !bytes.starts_with(b"glTF") {
+        return invalid("not a binary glTF file".to_string());
+    }
+    let version = read_u32(bytes, 4);
+    if version != 2 {
+        return invalid(format!("binary glTF version {} is not supported", version));
+    }
+    let length = (read_u32(bytes, 8) as usize).min(bytes.len());
+
+    let mut json_text = None;
+    let mut binary = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset) as usize;
+        let chunk_type = read_u32(bytes, offset + 4);
+        let start = offset + 8;
+        let Some(chunk) = bytes.get(start..start + chunk_length) else {
+            return invalid(format!("chunk at byte {} runs past the end", offset));
+        };
+        match chunk_type {
+            // "JSON"
+            0x4E4F534A if json_text.is_none() => {
+                json_text = Some(
+                    std::str::from_utf8(chunk)
+                        .map_err(|_| GltfError::Invalid("the JSON is not UTF-8".to_string()))?,
+                );
+            }
+            // "BIN\0"
+            0x004E4942 if binary.is_none() => binary = Some(chunk),
+            // Unknown chunks must be skipped
+            _ => {}
+        }
+        offset = start + chunk_length.next_multiple_of(4);
+    }
+
+    let Some(json_text) = json_text else {
+        return invalid("binary glTF without a JSON chunk".to_string());
+    };
+    parse_gltf(json_text, binary, directory)
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for character in text.bytes() {
+        let value = match character {
+            b'A'..=b'Z' => character - b'A',
+            b'a'..=b'z' => character - b'a' + 26,
+            b'0'..=b'9' => character - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+    while position < bytes.len() {
+        if bytes[position] == b'%'
+            && let Some(value) = uri
+                .get(position + 1..position + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(value);
+            position += 3;
+        } else {
+            decoded.push(bytes[position]);
+            position += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads the bytes behind a buffer or image URI, which is either a data: URI or a path
+fn read_uri(uri: &str, directory: Option<&Path>) -> Result<Vec<u8>, GltfError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let Some((_, encoded)) = data.split_once(";base64,") else {
+            return invalid("data URIs must be base64 encoded".to_string());
+        };
+        return decode_base64(encoded)
+            .ok_or_else(|| GltfError::Invalid("data URI is not valid base64".to_string()));
+    }
+    let Some(directory) = directory else {
+        return invalid(format!("external file {} cannot be resolved", uri));
+    };
+    let path = directory.join(decode_percent(uri));
+    fs::read(&path).map_err(|error| GltfError::Io { path, error })
+}
+
+/// The parsed JSON with the helpers to look things up in it
+struct Document<'a> {
+    root: &'a JsonValue,
+    buffers: Vec<Vec<u8>>,
+}
+
+fn array<'a>(value: &'a JsonValue, key: &str) -> &'a [JsonValue] {
+    value
+        .get(key)
+        .and_then(|values| values.as_array())
+        .unwrap_or_default()
+}
+
+fn index(value: &JsonValue, key: &str) -> Option<usize> {
+    value
+        .get(key)
+        .and_then(|index| index.as_u64())
+        .map(|index| index as usize)
+}
+
+fn number(value: &JsonValue, key: &str, default: f32) -> f32 {
+    value
+        .get(key)
+        .and_then(|number| number.as_f64())
+        .map_or(default, |number| number as f32)
+}
+
+fn numbers<const N: usize>(value: &JsonValue, key: &str, default: [f32; N]) -> [f32; N] {
+    let Some(values) = value.get(key).and_then(|values| values.as_array()) else {
+        return default;
+    };
+    let mut result = default;
+    for (slot, value) in result.iter_mut().zip(values) {
+        *slot = value.as_f64().map_or(*slot, |number| number as f32);
+    }
+    result
+}
+
+fn name(value: &JsonValue) -> String {
+    value
+        .get("name")
+        .and_then(|name| name.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+impl Document<'_> {
+    fn element(&self, key: &str, position: usize) -> Result<&JsonValue, GltfError> {
+        array(self.root, key)
+            .get(position)
+            .ok_or_else(|| GltfError::Invalid(format!("{} {} does not exist", key, position)))
+    }
+
+    /// The bytes of a buffer view and its stride, 0 if the elements are tightly packed
+    fn buffer_view(&self, position: usize) -> Result<(&[u8], usize), GltfError> {
+        let view = self.element("bufferViews", position)?;
+        let Some(buffer) = index(view, "buffer").and_then(|buffer| self.buffers.get(buffer)) else {
+            return invalid(format!("bufferView {} has no valid buffer", position));
+        };
+        let offset = index(view, "byteOffset").unwrap_or(0);
+        let length = index(view, "byteLength").unwrap_or(0);
+        let Some(bytes) = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+        else {
+            return invalid(format!("bufferView {} runs past its buffer", position));
+        };
+        Ok((bytes, index(view, "byteStride").unwrap_or(0)))
+    }
+
+    /// Reads an accessor as numbers, count elements of the returned component count each.
+    /// Normalized integers are mapped to [0, 1] or [-1, 1].
+    fn accessor(&self, position: usize) -> Result<(Vec<f64>, usize), GltfError> {
+        let accessor = self.element("accessors", position)?;
+        if accessor.get("sparse").is_some() {
+            return invalid(format!("accessor {} is sparse", position));
+        }
+        let components = match accessor.get("type").and_then(|kind| kind.as_str()) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return invalid(format!("accessor {} has an unknown type", position)),
+        };
+        let component_type = index(accessor, "componentType").unwrap_or(0);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => {
+                return invalid(format!(
+                    "accessor {} has unknown component type {}",
+                    position, component_type
+                ));
+            }
+        };
+        let normalized = accessor
+            .get("normalized")
+            .and_then(|normalized| normalized.as_bool())
+            .unwrap_or(false);
+        let count = index(accessor, "count").unwrap_or(0);
+        let too_large = || GltfError::Invalid(format!("accessor {} is too large", position));
+        let value_count = count.checked_mul(components).ok_or_else(too_large)?;
+
+        // Accessors without a buffer view are all zeros. The count is all that limits their
+        // size, so the allocation may fail.
+        let Some(view) = index(accessor, "bufferView") else {
+            let mut values = Vec::new();
+            values
+                .try_reserve_exact(value_count)
+                .map_err(|_| too_large())?;
+            values.resize(value_count, 0.0);
+            return Ok((values, components));
+        };
+        let (bytes, stride) = self.buffer_view(view)?;
+        let element_size = components * component_size;
+        let stride = if stride == 0 { element_size } else { stride };
+        let offset = index(accessor, "byteOffset").unwrap_or(0);
+        if count > 0 {
+            let end = (count - 1)
+                .checked_mul(stride)
+                .and_then(|end| end.checked_add(offset))
+                .and_then(|end| end.checked_add(element_size));
+            if end.is_none_or(|end| end > bytes.len()) {
+                return invalid(format!("accessor {} runs past its bufferView", position));
+            }
+        }
+
+        let mut values = Vec::with_capacity(value_count);
+        for element in 0..count {
+            for component in 0..components {
+                let at = offset + element * stride + component * component_size;
+                let raw = &bytes[at..at + component_size];
+                let value = match component_type {
+                    5120 => raw[0] as i8 as f64,
+                    5121 => raw[0] as f64,
+                    5122 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    5123 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    5125 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                    _ => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                };
+                values.push(match (normalized, component_type) {
+                    (true, 5120) => (value / 127.0).max(-1.0),
+                    (true, 5121) => value / 255.0,
+                    (true, 5122) => (value / 32767.0).max(-1.0),
+                    (true, 5123) => value / 65535.0,
+                    _ => value,
+                });
+            }
+        }
+        Ok((values, components))
+    }
+
+    /// Reads an accessor that must have the given component count
+    fn attribute(&self, position: usize, components: usize) -> Result<Vec<f64>, GltfError> {
+        let (values, actual) = self.accessor(position)?;
+        if actual != components {
+            return invalid(format!(
+                "accessor {} has {} components where {} are needed",
+                position, actual, components
+            ));
+        }
+        Ok(values)
+    }
+
+    fn primitive(&self, primitive: &JsonValue) -> Result<Option<GltfPrimitive>, GltfError> {
+        let attributes = primitive.get("attributes");
+        let attribute = |name: &str| attributes.and_then(|attributes| index(attributes, name));
+        let Some(position_accessor) = attribute("POSITION") else {
+            return invalid("primitive without POSITION".to_string());
+        };
+
+        let positions = self.attribute(position_accessor, 3)?;
+        let count = positions.len() / 3;
+        let optional = |name: &str, components: usize| -> Result<Option<Vec<f64>>, GltfError> {
+            let Some(accessor) = attribute(name) else {
+                return Ok(None);
+            };
+            let values = self.attribute(accessor, components)?;
+            if values.len() != count * components {
+                return invalid(format!("{} has a different count than POSITION", name));
+            }
+            Ok(Some(values))
+        };
+        let normals = optional("NORMAL", 3)?;
+        let uvs = optional("TEXCOORD_0", 2)?;
+        let tangents = optional("TANGENT", 4)?;
+
+        let vertices = (0..count)
+            .map(|vertex| {
+                let read = |values: &Option<Vec<f64>>, components: usize, component: usize| {
+                    values
+                        .as_ref()
+                        .map_or(0.0, |values| values[vertex * components + component] as f32)
+                };
+                MeshVertex {
+                    position: Vector3::new(
+                        positions[vertex * 3] as f32,
+                        positions[vertex * 3 + 1] as f32,
+                        positions[vertex * 3 + 2] as f32,
+                    ),
+                    normal: Vector3::new(
+                        read(&normals, 3, 0),
+                        read(&normals, 3, 1),
+                        read(&normals, 3, 2),
+                    ),
+                    uv: Vector2::new(read(&uvs, 2, 0), read(&uvs, 2, 1)),
+                    tangent: Vector4::new(
+                        read(&tangents, 4, 0),
+                        read(&tangents, 4, 1),
+                        read(&tangents, 4, 2),
+                        read(&tangents, 4, 3),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let indices: Vec<u32> = match index(primitive, "indices") {
+            Some(accessor) => {
+                let values = self.attribute(accessor, 1)?;
+                if values.iter().any(|value| *value as usize >= count) {
+                    return invalid(format!("accessor {} has an index out of range", accessor));
+                }
+                values.iter().map(|value| *value as u32).collect()
+            }
+            None => (0..count as u32).collect(),
+        };
+
+        // Strips and fans are turned into lists; points and lines have no triangles
+        let triangles = match index(primitive, "mode").unwrap_or(4) {
+            4 => indices[..indices.len() - indices.len() % 3].to_vec(),
+            5 => (2..indices.len())
+                .flat_map(|corner| {
+                    if corner % 2 == 0 {
+                        [indices[corner - 2], indices[corner - 1], indices[corner]]
+                    } else {
+                        [indices[corner - 1], indices[corner - 2], indices[corner]]
+                    }
+                })
+                .collect(),
+            6 => (2..indices.len())
+                .flat_map(|corner| [indices[0], indices[corner - 1], indices[corner]])
+                .collect(),
+            mode => {
+                eprintln!("Warning: skipping glTF primitive with mode {}", mode);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(GltfPrimitive {
+            mesh: Mesh::new(vertices, triangles),
+            material: index(primitive, "material"),
+        }))
+    }
+
+    fn texture(&self, info: Option<&JsonValue>) -> Result<Option<GltfTexture>, GltfError> {
+        let Some(info) = info else {
+            return Ok(None);
+        };
+        let Some(texture) = index(info, "index") else {
+            return invalid("textureInfo without an index".to_string());
+        };
+        let Some(image) = index(self.element("textures", texture)?, "source") else {
+            // Images only reachable through extensions are not supported
+            return Ok(None);
+        };
+        Ok(Some(GltfTexture {
+            image,
+            texcoord: index(info, "texCoord").unwrap_or(0) as u32,
+        }))
+    }
+
+    fn material(&self, material: &JsonValue) -> Result<GltfMaterial, GltfError> {
+        let empty = JsonValue::Object(Vec::new());
+        let pbr = material.get("pbrMetallicRoughness").unwrap_or(&empty);
+        let normal = material.get("normalTexture");
+        let occlusion = material.get("occlusionTexture");
+        Ok(GltfMaterial {
+            name: name(material),
+            base_color: numbers(pbr, "baseColorFactor", [1.0; 4]),
+            base_color_texture: self.texture(pbr.get("baseColorTexture"))?,
+            metallic: number(pbr, "metallicFactor", 1.0),
+            roughness: number(pbr, "roughnessFactor", 1.0),
+            metallic_roughness_texture: self.texture(pbr.get("metallicRoughnessTexture"))?,
+            normal_texture: self.texture(normal)?,
+            normal_scale: normal.map_or(1.0, |normal| number(normal, "scale", 1.0)),
+            occlusion_texture: self.texture(occlusion)?,
+            occlusion_strength: occlusion
+                .map_or(1.0, |occlusion| number(occlusion, "strength", 1.0)),
+            emissive: numbers(material, "emissiveFactor", [0.0; 3]),
+            emissive_texture: self.texture(material.get("emissiveTexture"))?,
+            alpha_mode: match material.get("alphaMode").and_then(|mode| mode.as_str()) {
+                Some("MASK") => AlphaMode::Mask,
+                Some("BLEND") => AlphaMode::Blend,
+                _ => AlphaMode::Opaque,
+            },
+            alpha_cutoff: number(material, "alphaCutoff", 0.5),
+            double_sided: material
+                .get("doubleSided")
+                .and_then(|double_sided| double_sided.as_bool())
+                .unwrap_or(false),
+        })
+    }
+
+    fn image(
+        &self,
+        position: usize,
+        image: &JsonValue,
+        directory: Option<&Path>,
+    ) -> Result<DynamicImage, GltfError> {
+        let decode_error = |error| GltfError::Image {
+            image: position,
+            error,
+        };
+        if let Some(view) = index(image, "bufferView") {
+            let (bytes, _) = self.buffer_view(view)?;
+            return image::load_from_memory(bytes).map_err(decode_error);
+        }
+        let Some(uri) = image.get("uri").and_then(|uri| uri.as_str()) else {
+            return invalid(format!("image {} has neither uri nor bufferView", position));
+        };
+        if uri.starts_with("data:") {
+            return image::load_from_memory(&read_uri(uri, directory)?).map_err(decode_error);
+        }
+        let Some(directory) = directory else {
+            return invalid(format!("external image {} cannot be resolved", uri));
+        };
+        let path = directory.join(decode_percent(uri));
+        ImageReader::open(&path)
+            .map_err(|error| GltfError::Io { path, error })?
+            .decode()
+            .map_err(decode_error)
+    }
+
+    fn node(&self, node: &JsonValue) -> GltfNode {
+        let transform = if node.get("matrix").is_some() {
+            // Stored column by column
+            let values = numbers(node, "matrix", [0.0; 16]);
+            let mut transform = Matrix4::zero();
+            for (position, value) in values.iter().enumerate() {
+                transform.data[position % 4][position / 4] = *value;
+            }
+            transform
+        } else {
+            let [x, y, z] = numbers(node, "translation", [0.0; 3]);
+            let [qx, qy, qz, qw] = numbers(node, "rotation", [0.0, 0.0, 0.0, 1.0]);
+            let [sx, sy, sz] = numbers(node, "scale", [1.0; 3]);
+            Matrix4::mult_mat4(
+                &Matrix4::mult_mat4(
+                    &Matrix4::translate(x, y, z),
+                    &Matrix4::rotate_by_quaternion(qx, qy, qz, qw),
+                ),
+                &Matrix4::scale(sx, sy, sz),
+            )
+        };
+        GltfNode {
+            name: name(node),
+            transform,
+            mesh: index(node, "mesh"),
+            children: array(node, "children")
+                .iter()
+                .filter_map(|child| child.as_u64().map(|child| child as usize))
+                .collect(),
+        }
+    }
+}
+
+/// Parses glTF JSON. binary is the BIN chunk of a .glb, used by a buffer without a URI.
+/// Without a directory, only data: URIs and the BIN chunk can be read.
+pub fn parse_gltf(
+    text: &str,
+    binary: Option<&[u8]>,
+    directory: Option<&Path>,
+) -> Result<GltfModel, GltfError> {
+    let root = json::parse(text).map_err(GltfError::Json)?;
+    let version = root
+        .get("asset")
+        .and_then(|asset| asset.get("version"))
+        .and_then(|version| version.as_str())
+        .unwrap_or_default();
+    if !version.starts_with("2.") {
+        return invalid(format!("asset version \"{}\" is not 2.x", version));
+    }
+
+    let mut buffers = Vec::new();
+    for (position, buffer) in array(&root, "buffers").iter().enumerate() {
+        let mut bytes = match buffer.get("uri").and_then(|uri| uri.as_str()) {
+            Some(uri) => read_uri(uri, directory)?,
+            None => match (position, binary) {
+                (0, Some(binary)) => binary.to_vec(),
+                _ => return invalid(format!("buffer {} has no data", position)),
+            },
+        };
+        let length = index(buffer, "byteLength").unwrap_or(0);
+        if bytes.len() < length {
+            return invalid(format!(
+                "buffer {} has {} bytes but byteLength is {}",
+                position,
+                bytes.len(),
+                length
+            ));
+        }
+        // The BIN chunk may be padded
+        bytes.truncate(length);
+        buffers.push(bytes);
+    }
+    let document = Document {
+        root: &root,
+        buffers,
+    };
+
+    let mut meshes = Vec::new();
+    for mesh in array(&root, "meshes") {
+        let mut primitives = Vec::new();
+        for primitive in array(mesh, "primitives") {
+            if let Some(primitive) = document.primitive(primitive)? {
+                primitives.push(primitive);
+            }
+        }
+        meshes.push(GltfMesh {
+            name: name(mesh),
+            primitives,
+        });
+    }
+
+    let materials = array(&root, "materials")
+        .iter()
+        .map(|material| document.material(material))
+        .collect::<Result<Vec<_>, _>>()?;
+    let images = array(&root, "images")
+        .iter()
+        .enumerate()
+        .map(|(position, image)| document.image(position, image, directory))
+        .collect::<Result<Vec<_>, _>>()?;
+    let nodes: Vec<GltfNode> = array(&root, "nodes")
+        .iter()
+        .map(|node| document.node(node))
+        .collect();
+
+    // Check references now so users of the model can index without checking
+    for mesh in &meshes {
+        for primitive in &mesh.primitives {
+            if primitive
+                .material
+                .is_some_and(|material| material >= materials.len())
+            {
+                return invalid(format!("mesh \"{}\" uses a missing material", mesh.name));
+            }
+        }
+    }
+    for material in &materials {
+        let textures = [
+            material.base_color_texture,
+            material.metallic_roughness_texture,
+            material.normal_texture,
+            material.occlusion_texture,
+            material.emissive_texture,
+        ];
+        if textures
+            .iter()
+            .flatten()
+            .any(|texture| texture.image >= images.len())
+        {
+            return invalid(format!(
+                "material \"{}\" uses a missing image",
+                material.name
+            ));
+        }
+    }
+    for node in &nodes {
+        if node.mesh.is_some_and(|mesh| mesh >= meshes.len())
+            || node.children.iter().any(|child| *child >= nodes.len())
+        {
+            return invalid(format!(
+                "node \"{}\" refers to a missing mesh or node",
+                node.name
+            ));
+        }
+    }
+
+    // Without a default scene, show the first one, or every node that is nobody's child
+    let default_scene = index(&root, "scene").or((!array(&root, "scenes").is_empty()).then_some(0));
+    let scene: Vec<usize> = match default_scene {
+        Some(scene) => array(document.element("scenes", scene)?, "nodes")
+            .iter()
+            .filter_map(|node| node.as_u64().map(|node| node as usize))
+            .collect(),
+        None => (0..nodes.len())
+            .filter(|node| !nodes.iter().any(|parent| parent.children.contains(node)))
+            .collect(),
+    };
+    if scene.iter().any(|node| *node >= nodes.len()) {
+        return invalid("the scene refers to a missing node".to_string());
+    }
+    // Nodes must form trees: one parent at most and no node its own ancestor, otherwise
+    // mesh_instances would never finish
+    let mut parents: Vec<Option<usize>> = vec![None; nodes.len()];
+    for (parent, node) in nodes.iter().enumerate() {
+        for child in &node.children {
+            if parents[*child].replace(parent).is_some() {
+                return invalid(format!("node {} has more than one parent", child));
+            }
+        }
+    }
+    for start in 0..nodes.len() {
+        let mut ancestor = parents[start];
+        for _ in 0..nodes.len() {
+            let Some(node) = ancestor else {
+                break;
+            };
+            if node == start {
+                return invalid(format!("node {} is its own ancestor", start));
+            }
+            ancestor = parents[node];
+        }
+    }
+
+    Ok(GltfModel {
+        meshes,
+        materials,
+        images,
+        nodes,
+        scene,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triangle mesh drawn through indices 2, 1, 0, the same triangle without indices, and
+    /// a scene placing them with translation, rotation and scale, a matrix and a child node
+    const FIXTURE: &str = r#"{
+        "asset": {"version": "2.0"},
+        "buffers": [BUFFER],
+        "bufferViews": VIEWS,
+        "accessors": ACCESSORS,
+        "meshes": [
+            {"name": "indexed", "primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]},
+            {"name": "listed", "primitives": [{"attributes": {"POSITION": 0}}]}
+        ],
+        "nodes": [
+            {"name": "root", "mesh": 0, "children": [1],
+             "translation": [1, 2, 3], "scale": [2, 2, 2]},
+            {"name": "child", "mesh": 1,
+             "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 5, 1]},
+            {"name": "turned", "mesh": 0, "rotation": [0, 0.70710677, 0, 0.70710677]}
+        ],
+        "scene": 0,
+        "scenes": [{"nodes": [0, 2]}]
+    }"#;
+
+    const VIEWS: &str = r#"[
+        {"buffer": 0, "byteLength": 36},
+        {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+    ]"#;
+
+    const ACCESSORS: &str = r#"[
+        {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+        {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+    ]"#;
+
+    /// Positions (1, 0, 0), (0, 1, 0) and (0, 0, 1), then u16 indices padded to 4 bytes
+    fn binary() -> Vec<u8> {
+        let positions = [1.0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let mut bytes: Vec<u8> = positions.iter().flat_map(|v| v.to_le_bytes()).collect();
+        for index in [2u16, 1, 0, 0] {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+                bits | (*byte as u32) << (16 - 8 * i)
+            });
+            for sextet in 0..4 {
+                text.push(if sextet <= chunk.len() {
+                    ALPHABET[(bits >> (18 - 6 * sextet) & 63) as usize] as char
+                } else {
+                    '='
+                });
+            }
+        }
+        text
+    }
+
+    fn fixture(buffer: &str, views: &str, accessors: &str) -> String {
+        FIXTURE
+            .replace("BUFFER", buffer)
+            .replace("VIEWS", views)
+            .replace("ACCESSORS", accessors)
+    }
+
+    fn data_uri_gltf(views: &str, accessors: &str) -> String {
+        let buffer = format!(
+            r#"{{"byteLength": 44, "uri": "data:application/octet-stream;base64,{}"}}"#,
+            encode_base64(&binary())
+        );
+        fixture(&buffer, views, accessors)
+    }
+
+    fn glb() -> Vec<u8> {
+        let mut json = fixture(r#"{"byteLength": 44}"#, VIEWS, ACCESSORS).into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let binary = binary();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"glTF");
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"JSON");
+        bytes.extend_from_slice(&json);
+        bytes.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"BIN\0");
+        bytes.extend_from_slice(&binary);
+        bytes
+    }
+
+    fn transform_point(matrix: &Matrix4, point: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = point;
+        let result = Matrix4::mult_vector(matrix, &Vector4::new(x, y, z, 1.0));
+        [result.x, result.y, result.z]
+    }
+
+    fn check_fixture(model: &GltfModel) {
+        assert_eq!(model.meshes.len(), 2);
+        let indexed = &model.meshes[0].primitives[0].mesh;
+        let listed = &model.meshes[1].primitives[0].mesh;
+        assert_eq!(indexed.indices.to_u32(), [2, 1, 0]);
+        assert_eq!(listed.indices.to_u32(), [0, 1, 2]);
+        for mesh in [indexed, listed] {
+            assert_eq!(mesh.vertices.len(), 3);
+            let position = mesh.vertices[1].position;
+            assert_eq!([position.x, position.y, position.z], [0.0, 1.0, 0.0]);
+        }
+
+        // Where each instance puts the vertex at (1, 0, 0)
+        let mut placed: Vec<(usize, [f32; 3])> = model
+            .mesh_instances()
+            .iter()
+            .map(|(mesh, world)| (*mesh, transform_point(world, [1.0, 0.0, 0.0])))
+            .collect();
+        placed.sort_by(|a, b| a.0.cmp(&b.0).then(a.1[2].total_cmp(&b.1[2])));
+        let expected = [
+            // Rotated a quarter turn around y
+            (0, [0.0, 0.0, -1.0]),
+            // Scaled by 2, then moved by (1, 2, 3)
+            (0, [3.0, 2.0, 3.0]),
+            // Moved by (0, 0, 5) inside the root
+            (1, [3.0, 2.0, 13.0]),
+        ];
+        assert_eq!(placed.len(), expected.len());
+        for ((mesh, point), (expected_mesh, expected_point)) in placed.iter().zip(expected) {
+            assert_eq!(*mesh, expected_mesh);
+            for (value, expected) in point.iter().zip(expected_point) {
+                assert!((value - expected).abs() < 1e-5, "{:?}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn gltf_with_a_data_uri_buffer() {
+        let text = data_uri_gltf(VIEWS, ACCESSORS);
+        check_fixture(&parse_gltf(&text, None, None).unwrap());
+    }
+
+    #[test]
+    fn glb_with_a_bin_chunk() {
+        check_fixture(&parse_glb(&glb(), None).unwrap());
+
+        // load_gltf tells the formats apart by content
+        let directory = std::env::temp_dir().join(format!("gltf_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("triangle.glb"), glb()).unwrap();
+        fs::write(
+            directory.join("triangle.gltf"),
+            data_uri_gltf(VIEWS, ACCESSORS),
+        )
+        .unwrap();
+        check_fixture(&load_gltf(&directory.join("triangle.glb")).unwrap());
+        check_fixture(&load_gltf(&directory.join("triangle.gltf")).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn oversized_accessors_are_invalid() {
+        let cases = [
+            // 2^53 zero-filled elements
+            (
+                VIEWS,
+                r#"[{"componentType": 5126, "count": 9007199254740992, "type": "VEC3"}]"#,
+            ),
+            // count * 16 overflows
+            (
+                VIEWS,
+                r#"[{"componentType": 5126, "count": 1152921504606846976, "type": "MAT4"}]"#,
+            ),
+            // 2^53 elements in a 36 byte view
+            (
+                VIEWS,
+                r#"[{"bufferView": 0, "componentType": 5126, "count": 9007199254740992, "type": "VEC3"}]"#,
+            ),
+            // The stride times the count overflows
+            (
+                r#"[{"buffer": 0, "byteLength": 36, "byteStride": 4611686018427387904}]"#,
+                r#"[{"bufferView": 0, "componentType": 5126, "count": 5, "type": "VEC3"}]"#,
+            ),
+            // The view's offset plus its length overflows
+            (
+                r#"[{"buffer": 0, "byteOffset": 18446744073709549568, "byteLength": 4096}]"#,
+                r#"[{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}]"#,
+            ),
+        ];
+        for (views, accessors) in cases {
+            assert!(
+                matches!(
+                    parse_gltf(&data_uri_gltf(views, accessors), None, None),
+                    Err(GltfError::Invalid(_))
+                ),
+                "{}",
+                accessors
+            );
+        }
+    }
+}
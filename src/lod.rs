@@ -0,0 +1,158 @@
+//! Levels of detail: a mesh with simplified versions of itself, and the choice between them
+//! from how large the mesh appears on screen.
+
+use crate::{
+    mesh::{GpuMesh, Mesh},
+    simplify::simplify,
+    vector::Vector3,
+};
+
+/// How far a level may stray from the full mesh on screen, in pixels
+pub const PIXEL_TOLERANCE: f32 = 1.0;
+
+pub struct LodLevel {
+    pub mesh: Mesh,
+    /// How far this level strays from the full mesh, in the mesh's units
+    pub error: f32,
+}
+
+/// A mesh and its simplified versions, from the full mesh at level 0 to the coarsest
+pub struct LodChain {
+    pub levels: Vec<LodLevel>,
+    /// Bounding sphere of the full mesh
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+impl LodChain {
+    /// Simplifies the mesh to ratio times the triangles of the level before, up to max_levels
+    /// levels in total. Stops early once a level is no longer noticeably smaller, for example
+    /// because everything left is a border or seam.
+    pub fn build(mesh: Mesh, max_levels: usize, ratio: f32) -> Self {
+        let (center, radius) = match mesh.bounds() {
+            Some(bounds) => (bounds.center(), 0.5 * bounds.size().magnitude()),
+            None => (Vector3::default(), 0.0),
+        };
+        let mut levels = vec![LodLevel { mesh, error: 0.0 }];
+        while levels.len() < max_levels {
+            let previous = levels[levels.len() - 1].mesh.triangle_count();
+            let target = (previous as f32 * ratio) as usize;
+            if target == 0 {
+                break;
+            }
+            // Always simplifying the full mesh keeps errors measured against it
+            let (mesh, error) = simplify(&levels[0].mesh, target, f32::MAX);
+            if mesh.triangle_count() as f32 > 0.9 * previous as f32 {
+                break;
+            }
+            levels.push(LodLevel { mesh, error });
+        }
+        Self {
+            levels,
+            center,
+            radius,
+        }
+    }
+
+    /// Uploads every level
+    pub fn upload(&self) -> GpuLodChain {
+        GpuLodChain {
+            levels: self
+                .levels
+                .iter()
+                .map(|level| level.mesh.upload())
+                .collect(),
+            errors: self.levels.iter().map(|level| level.error).collect(),
+            center: self.center,
+            radius: self.radius,
+        }
+    }
+}
+
+/// The levels of a chain in GPU memory, with what is needed to choose between them
+pub struct GpuLodChain {
+    pub levels: Vec<GpuMesh>,
+    pub errors: Vec<f32>,
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+impl GpuLodChain {
+    pub fn select(&self, projected_size: f32) -> usize {
+        select_level(&self.errors, self.radius, projected_size)
+    }
+}
+
+/// The coarsest level whose error stays within PIXEL_TOLERANCE when a bounding sphere of the
+/// given radius covers projected_size pixels. Errors are in the same units as the radius.
+pub fn select_level(errors: &[f32], radius: f32, projected_size: f32) -> usize {
+    if radius <= 0.0 {
+        return 0;
+    }
+    let pixels_per_unit = projected_size / (2.0 * radius);
+    errors
+        .iter()
+        .rposition(|error| error * pixels_per_unit <= PIXEL_TOLERANCE)
+        .unwrap_or(0)
+}
+
+/// The height in pixels a sphere of the given radius covers at a distance from the camera,
+/// for a projection made by make_projection_matrix with vertical field of view fov (in
+/// radians) and a viewport viewport_height pixels high. Infinite when the camera is inside it.
+pub fn projected_size(radius: f32, distance: f32, fov: f32, viewport_height: f32) -> f32 {
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    // The projection scales y by 1 / tan(fov / 2) and the viewport maps [-1, 1] to the height
+    let scale = viewport_height / (2.0 * (fov / 2.0).tan());
+    2.0 * radius * scale / (distance * distance - radius * radius).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives;
+
+    #[test]
+    fn smaller_projections_select_coarser_levels() {
+        let errors = [0.0, 0.001, 0.01, 0.1];
+        let sizes = [10000.0, 1000.0, 100.0, 10.0, 1.0];
+        let levels: Vec<usize> = sizes
+            .iter()
+            .map(|size| select_level(&errors, 1.0, *size))
+            .collect();
+        assert_eq!(levels, [0, 1, 2, 3, 3]);
+        assert!(levels.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        assert_eq!(select_level(&errors, 0.0, 1.0), 0);
+        assert_eq!(select_level(&errors, -1.0, 1.0), 0);
+        // The camera inside the sphere needs the full mesh
+        let inside = projected_size(1.0, 0.5, 1.0, 600.0);
+        assert_eq!(select_level(&errors, 1.0, inside), 0);
+    }
+
+    #[test]
+    fn chains_get_coarser_level_by_level() {
+        let sphere = primitives::uv_sphere(1.0, 32, 16);
+        let full = sphere.triangle_count();
+        let chain = LodChain::build(sphere, 4, 0.5);
+        assert_eq!(chain.levels.len(), 4);
+        assert_eq!(chain.levels[0].mesh.triangle_count(), full);
+        for pair in chain.levels.windows(2) {
+            // Seams and poles may stop a level short of half, but never short of 90%
+            let (coarse, fine) = (pair[1].mesh.triangle_count(), pair[0].mesh.triangle_count());
+            assert!(coarse as f32 <= 0.9 * fine as f32);
+            assert!(pair[1].error >= pair[0].error);
+        }
+        assert!((chain.radius - 3.0f32.sqrt()).abs() < 1e-3);
+
+        let near = projected_size(chain.radius, 3.0, 1.0, 600.0);
+        let far = projected_size(chain.radius, 3000.0, 1.0, 600.0);
+        assert!(near > far);
+        let errors: Vec<f32> = chain.levels.iter().map(|level| level.error).collect();
+        assert!(
+            select_level(&errors, chain.radius, near) <= select_level(&errors, chain.radius, far)
+        );
+        assert_eq!(select_level(&errors, chain.radius, far), 3);
+    }
+}
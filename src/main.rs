@@ -1,28 +1,112 @@
+mod bounds_overlay;
 mod camera;
+mod capabilities;
+mod frustum;
+mod gl_backend;
+mod gl_debug;
+mod gl_objects;
+mod gl_state;
+mod gl_trace;
+mod glsl;
+mod gltf;
+mod json;
+mod lod;
 mod math;
 mod matrix;
+mod mesh;
+mod mesh_cache;
+mod normals;
+mod normals_overlay;
+mod obj;
+mod optimize;
+mod ply;
+mod primitives;
 mod shader;
+mod shader_reflection;
+mod simplify;
+mod stl;
+mod terrain;
+mod transform_feedback;
+mod uniform_buffer;
 mod vector;
+mod vertex_layout;
 
-use std::{ffi::c_void, path::Path, time::Instant};
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    process::exit,
+    time::Instant,
+};
 
-use glad_gl::gl::{self, GLsizei, GLuint};
+use glad_gl::gl::{self, khr_debug::DebugSeverity};
 use glfw::{self, Context, Key, OpenGlProfileHint, WindowEvent, WindowHint, WindowMode};
-use image::ImageReader;
+use image::DynamicImage;
 
 use crate::{
+    bounds_overlay::BoundsOverlay,
     camera::Camera,
+    capabilities::Capabilities,
+    frustum::Frustum,
+    gl_backend::{GladBackend, set_backend, with_backend},
+    gl_debug::{install_debug_callback, is_debug_context},
+    gl_objects::{Texture2D, TextureParameters, TextureRegion},
+    gl_state::{CachingBackend, take_frame_stats},
+    gl_trace::{format_call, read_trace, summarize_frame},
+    glsl::{Severity, check_program, check_uniform_names, find_uniform_setter_calls, lint_stage},
+    gltf::load_gltf,
+    lod::{GpuLodChain, LodChain, projected_size},
     math::angle_to_rad,
     matrix::{Matrix4, make_projection_matrix},
-    shader::ShaderProgram,
-    vector::Vector3,
+    mesh::{Aabb, GpuMesh, Mesh},
+    mesh_cache::{MESH_CACHE_EXTENSION, load_mesh_cache, save_mesh_cache},
+    normals::{
+        NormalWeighting, compute_smooth_normals, compute_tangents, flat_shaded, split_creases,
+    },
+    normals_overlay::NormalsOverlay,
+    obj::load_obj,
+    optimize::optimize,
+    ply::{PlyFormat, PlyModel, load_ply, save_ply},
+    shader::{ShaderError, ShaderProgram},
+    stl::{StlFormat, load_stl, save_stl},
+    terrain::{Heightmap, fractal_noise},
+    transform_feedback::{
+        FeedbackPrimitive, TransformFeedbackBuffer, TransformFeedbackMode, capture,
+    },
+    uniform_buffer::{FrameData, UniformBuffer},
+    vector::{Vector3, Vector4},
 };
 
+/// Height of the terrain where the heightmap is white
+const TERRAIN_HEIGHT: f32 = 6.0;
+/// Where the terrain's height 0 is in the world
+const TERRAIN_BASE: f32 = -4.0;
+
 fn main() {
+    // Offline tools that run without creating a window
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(command) = args.get(1) {
+        match command.as_str() {
+            "lint-shaders" => exit(lint_shaders(&args[2..])),
+            "trace-dump" => exit(trace_dump(&args[2..])),
+            "export-mesh" => exit(export_mesh(&args[2..])),
+            "bake" => exit(bake(&args[2..])),
+            _ => {
+                eprintln!("Unknown command {}", command);
+                eprintln!("Usage: learn_opengl [lint-shaders [shader files...] [rust files...]]");
+                eprintln!("       learn_opengl [trace-dump <trace file> [frame]]");
+                eprintln!(
+                    "       learn_opengl [export-mesh <model or primitive> <output> [ascii]]"
+                );
+                eprintln!("       learn_opengl [bake <model> [output]]");
+                exit(2);
+            }
+        }
+    }
+
     // Initialize GLFW and window
     let width = 800;
     let height = 600;
-    let (mut glfw_data, mut window, events_receiver) = {
+    let (mut glfw_data, mut window, events_receiver, capabilities) = {
         let mut glfw_data = glfw::init_no_callbacks().unwrap();
 
         // Ask for version 3.3 and the core profile
@@ -30,321 +114,1224 @@ fn main() {
         glfw_data.window_hint(WindowHint::ContextVersionMinor(3));
         glfw_data.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
 
+        // Debug builds ask for a debug context so the driver explains what went wrong
+        glfw_data.window_hint(WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
+
         // Create a window object
         let (mut window, events_receiver) = glfw_data
             .create_window(width, height, "LearnOpenGL", WindowMode::Windowed)
             .unwrap();
 
         window.make_current();
+        window.set_key_polling(true);
 
         // Load opengl object pointers
-        gl::load(|e| glfw_data.get_proc_address_raw(e).unwrap() as *const std::os::raw::c_void);
+        let load_report = gl::load(|e| {
+            glfw_data
+                .get_proc_address_raw(e)
+                .map_or(std::ptr::null(), |f| f as *const std::os::raw::c_void)
+        });
+        println!(
+            "Loaded {} of {} GL functions",
+            load_report.loaded.len(),
+            load_report.loaded.len() + load_report.missing.len()
+        );
+        for name in &load_report.missing {
+            eprintln!("Warning: the driver does not provide {}", name);
+        }
+        let capabilities = Capabilities::query();
+        println!("{}", capabilities);
+        if !capabilities.supports_version(3, 3) {
+            eprintln!(
+                "Warning: the driver created an OpenGL {}.{} context, the demo needs 3.3",
+                capabilities.major_version, capabilities.minor_version
+            );
+        }
 
-        // Set up viewport
-        unsafe {
-            gl::Viewport(0, 0, width as i32, height as i32);
-            gl::Enable(gl::DEPTH_TEST);
+        // Skip binds and state changes that would not change anything
+        set_backend(Box::new(CachingBackend::new(Box::new(GladBackend))));
+
+        // Record GL calls when built with gl-trace and LEARN_OPENGL_TRACE names the output file
+        #[cfg(feature = "gl-trace")]
+        if let Ok(trace_path) = std::env::var("LEARN_OPENGL_TRACE") {
+            let payloads = match std::env::var("LEARN_OPENGL_TRACE_PAYLOADS").as_deref() {
+                Ok("full") => gl::trace::PayloadCapture::Full,
+                Ok("size") => gl::trace::PayloadCapture::SizeOnly,
+                _ => gl::trace::PayloadCapture::Hash,
+            };
+            if let Err(error) = gl::trace::start(Path::new(&trace_path), payloads) {
+                eprintln!(
+                    "Warning: failed to start GL trace {}: {}",
+                    trace_path, error
+                );
+            }
+        }
+
+        // Print driver messages, skipping the informational ones. Debug output is core from 4.3
+        // and needs KHR_debug before that.
+        if is_debug_context()
+            && (capabilities.supports_version(4, 3) || capabilities.has_extension("GL_KHR_debug"))
+            && let Err(error) = install_debug_callback(DebugSeverity::Low)
+        {
+            eprintln!("Warning: GL debug output is unavailable: {}", error);
         }
 
-        (glfw_data, window, events_receiver)
+        (glfw_data, window, events_receiver, capabilities)
     };
 
     // TODO: register resize callback
-    let shader_program =
-        ShaderProgram::new(Path::new("./src/shader.vs"), Path::new("./src/shader.fs"));
-
-    // Vertex input
-    let triangle_one: [f32; 180] = [
-        -0.5, -0.5, -0.5, 0.0, 0.0, 0.5, -0.5, -0.5, 1.0, 0.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5, 0.5,
-        -0.5, 1.0, 1.0, -0.5, 0.5, -0.5, 0.0, 1.0, -0.5, -0.5, -0.5, 0.0, 0.0, -0.5, -0.5, 0.5,
-        0.0, 0.0, 0.5, -0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 1.0, -0.5,
-        0.5, 0.5, 0.0, 1.0, -0.5, -0.5, 0.5, 0.0, 0.0, -0.5, 0.5, 0.5, 1.0, 0.0, -0.5, 0.5, -0.5,
-        1.0, 1.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, -0.5, 0.5, 0.0,
-        0.0, -0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5,
-        -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, 0.5, 0.0, 0.0, 0.5, 0.5, 0.5,
-        1.0, 0.0, -0.5, -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, -0.5, 1.0, 1.0, 0.5, -0.5, 0.5, 1.0, 0.0,
-        0.5, -0.5, 0.5, 1.0, 0.0, -0.5, -0.5, 0.5, 0.0, 0.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, 0.5,
-        -0.5, 0.0, 1.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 0.0,
-        -0.5, 0.5, 0.5, 0.0, 0.0, -0.5, 0.5, -0.5, 0.0, 1.0,
-    ];
-    let indices: [i32; 36] = [
-        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
-        25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35,
-    ];
-    let vaos = {
-        let mut vbos: [GLuint; 2] = [0, 0];
-        let mut vaos: [GLuint; 2] = [0, 0];
-        let mut ebos: [GLuint; 2] = [0, 0];
-        unsafe {
-            // Generate a vertex array object
-            gl::GenVertexArrays(2, vaos.as_mut_ptr() as *mut GLuint);
-
-            // Generate a vertex buffer object
-            gl::GenBuffers(2, vbos.as_mut_ptr() as *mut GLuint);
-
-            // Bind vertex array object first
-            gl::BindVertexArray(vaos[0]);
-
-            // Set vbo type to ARRAY_BUFFER for vertices
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbos[0]);
-
-            // Copy the data into the buffer
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (size_of::<f32>() * triangle_one.len()) as isize,
-                triangle_one.as_ptr() as *const c_void,
-                gl::STATIC_DRAW, // Data is set once and used many times
-            );
+    let mut scene = Scene::new(
+        &capabilities,
+        width,
+        height,
+        std::env::var("LEARN_OPENGL_MODEL").ok(),
+        std::env::var("LEARN_OPENGL_TERRAIN").ok(),
+        std::env::var("LEARN_OPENGL_UNIFORMS").ok(),
+    );
 
-            gl::GenBuffers(2, ebos.as_mut_ptr() as *mut GLuint);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebos[0]);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (size_of::<i32>() * indices.len()) as isize,
-                indices.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
+    // Debug builds make sure the GPU runs the vertex shader the way the CPU math expects
+    if cfg!(debug_assertions) {
+        match scene.check_vertex_shader() {
+            Ok(problems) => {
+                for problem in problems {
+                    eprintln!("Warning: {}", problem);
+                }
+            }
+            Err(error) => eprintln!("Warning: cannot check the vertex shader: {}", error),
+        }
+    }
 
-            // position attribute
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                (5 * size_of::<f32>()) as GLsizei,
-                0 as *const c_void,
-            );
-            gl::EnableVertexAttribArray(0);
-
-            // texture attribute
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                (5 * size_of::<f32>()) as GLsizei,
-                (3 * size_of::<f32>()) as *const c_void,
-            );
-            gl::EnableVertexAttribArray(1);
+    let time_start = Instant::now();
+    let mut last_title_update = time_start;
+    while !window.should_close() {
+        for (_, event) in glfw::flush_messages(&events_receiver) {
+            match event {
+                WindowEvent::Key(Key::Escape, _, glfw::Action::Press, _) => {
+                    window.set_should_close(true);
+                }
+                // Up and down fade between the two textures
+                WindowEvent::Key(Key::Up, _, glfw::Action::Press | glfw::Action::Repeat, _) => {
+                    scene.set_mix_value(scene.mix_value + 0.1);
+                }
+                WindowEvent::Key(Key::Down, _, glfw::Action::Press | glfw::Action::Repeat, _) => {
+                    scene.set_mix_value(scene.mix_value - 0.1);
+                }
+                // N shows the vertex normals of the cubes or model
+                WindowEvent::Key(Key::N, _, glfw::Action::Press, _) => {
+                    scene.show_normals = !scene.show_normals;
+                }
+                // B shows the terrain chunks' bounds, green if drawn and red if culled
+                WindowEvent::Key(Key::B, _, glfw::Action::Press, _) => {
+                    scene.show_bounds = !scene.show_bounds;
+                }
+                _ => {}
+            }
         }
-        vaos
-    };
 
-    let (texture_one_id, texture_two_id) = {
-        // Load the texture from memory
-        let texture_data = ImageReader::open("./data/container.jpg")
-            .unwrap()
-            .decode()
-            .unwrap();
+        let millis_since = Instant::now().duration_since(time_start).as_millis() as f32;
+        scene.draw(millis_since);
 
-        // Generate an opengl texture
-        let texture_one_id = unsafe {
-            let mut texture_id: GLuint = 0;
-            gl::GenTextures(1, &mut texture_id as *mut GLuint);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-
-            // set the wrapping parameters
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-            // Set the filtering parameters (minify and magnify)
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                texture_data.width() as i32,
-                texture_data.height() as i32,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                texture_data.as_bytes().as_ptr() as *const c_void,
-            );
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+        // Show how much state the cache saved, updated once a second to keep the title readable
+        let stats = take_frame_stats();
+        if last_title_update.elapsed().as_secs() >= 1 {
+            window.set_title(&format!(
+                "LearnOpenGL - {} draws, {} state changes, {} redundant skipped",
+                stats.draw_calls, stats.state_changes, stats.redundant_skipped
+            ));
+            last_title_update = Instant::now();
+        }
+
+        window.swap_buffers();
+        #[cfg(feature = "gl-trace")]
+        gl::trace::end_frame();
+        glfw_data.poll_events();
+    }
 
-            texture_id
+    #[cfg(feature = "gl-trace")]
+    if let Err(error) = gl::trace::stop() {
+        eprintln!("Warning: failed to finish GL trace: {}", error);
+    }
+}
+
+/// Everything drawn each frame, created once the context is current
+struct Scene {
+    shader_program: ShaderProgram,
+    /// None if its shaders failed to build
+    normals_overlay: Option<NormalsOverlay>,
+    show_normals: bool,
+    gpu_chains: Vec<GpuLodChain>,
+    terrain: Option<Heightmap>,
+    terrain_chunks: Vec<(GpuMesh, Aabb)>,
+    /// Outlines of the terrain chunks, None without terrain
+    bounds_overlay: Option<BoundsOverlay>,
+    show_bounds: bool,
+    texture_one: Texture2D,
+    texture_two: Texture2D,
+    /// How much of texture_two shows through texture_one, from 0 to 1
+    mix_value: f32,
+    frame_data_buffer: UniformBuffer<FrameData>,
+    cube_positions: [Vector3; 10],
+    camera: Camera,
+    light_position: Vector3,
+    light_color: Vector3,
+    fov: f32,
+    height: u32,
+    projection: Matrix4,
+}
+
+impl Scene {
+    /// Loads the shaders, textures and meshes. model is a model or mesh cache path drawn
+    /// instead of the cube, terrain a heightmap image path or "noise", and uniforms overrides
+    /// uniforms of the shader as "name=value;name=value".
+    fn new(
+        capabilities: &Capabilities,
+        width: u32,
+        height: u32,
+        model: Option<String>,
+        terrain: Option<String>,
+        uniforms: Option<String>,
+    ) -> Self {
+        // Set up viewport
+        with_backend(|backend| {
+            backend.viewport(0, 0, width as i32, height as i32);
+            backend.enable(gl::DEPTH_TEST);
+            // Primitives and terrain chunks are closed and wound counter-clockwise from outside
+            backend.enable(gl::CULL_FACE);
+            backend.cull_face(gl::BACK);
+        });
+
+        let shader_program =
+            ShaderProgram::new(Path::new("./src/shader.vs"), Path::new("./src/shader.fs"));
+        print!("Shader interface:\n{}", shader_program.reflection());
+        let normals_overlay = match NormalsOverlay::new() {
+            Ok(overlay) => Some(overlay),
+            Err(error) => {
+                eprintln!("Warning: normals cannot be shown: {}", error);
+                None
+            }
         };
 
-        // Load the texture from memory
-        let texture_data = ImageReader::open("./data/awesomeface.png")
-            .unwrap()
-            .decode()
-            .unwrap();
+        // Vertex input, either the model or baked mesh cache or a cube.
+        // A cache is uploaded straight from the file; anything else gets its LODs built here.
+        let (label, gpu_chains, model_texture) = match model {
+            Some(model_path) if is_mesh_cache(Path::new(&model_path)) => {
+                match load_mesh_cache(Path::new(&model_path)) {
+                    Ok(cache) => {
+                        if let Some(bounds) = cache
+                            .submeshes
+                            .iter()
+                            .filter_map(|submesh| submesh.bounds)
+                            .reduce(|a, b| a.union(&b))
+                        {
+                            println!(
+                                "{}: {} meshes within {}",
+                                model_path,
+                                cache.submeshes.len(),
+                                bounds
+                            );
+                        }
+                        (model_path, cache.upload(), None)
+                    }
+                    Err(error) => {
+                        eprintln!("Warning: {}", error);
+                        ("cube".to_string(), cube_lod_chain(), None)
+                    }
+                }
+            }
+            Some(model_path) => match load_model(Path::new(&model_path)) {
+                Ok(model) => {
+                    let chains = build_lod_chains(&model_path, model.meshes);
+                    let gpu_chains = chains.iter().map(|(_, chain)| chain.upload()).collect();
+                    (model_path, gpu_chains, model.texture)
+                }
+                Err(error) => {
+                    eprintln!("Warning: {}", error);
+                    ("cube".to_string(), cube_lod_chain(), None)
+                }
+            },
+            None => ("cube".to_string(), cube_lod_chain(), None),
+        };
+
+        // Data is set once and used many times
+        for (index, chain) in gpu_chains.iter().enumerate() {
+            for (level, gpu_mesh) in chain.levels.iter().enumerate() {
+                gpu_mesh.set_label(&format!("{} {} LOD {}", label, index, level));
+            }
+        }
+        if let Some(gpu_mesh) = gpu_chains.first().and_then(|chain| chain.levels.first()) {
+            for problem in gpu_mesh.layout().validate(shader_program.reflection()) {
+                eprintln!("Warning: {}", problem);
+            }
+        }
 
-        // Generate an opengl texture
-        let texture_two_id = unsafe {
-            let mut texture_id: GLuint = 0;
-            gl::GenTextures(1, &mut texture_id as *mut GLuint);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-
-            // set the wrapping parameters
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-            // Set the filtering parameters (minify and magnify)
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as i32,
-                texture_data.width() as i32,
-                texture_data.height() as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                texture_data.as_bytes().as_ptr() as *const c_void,
+        // Ground below the cubes, from the heightmap image named by terrain or from
+        // noise when it is "noise". Each chunk is drawn only if its box is in view.
+        let terrain = match terrain.as_deref() {
+            Some("noise") => Some(Heightmap::from_fn(129, 129, 0.5, |x, z| {
+                TERRAIN_HEIGHT * fractal_noise(0.05 * x, 0.05 * z, 1, 5)
+            })),
+            Some(path) => match Heightmap::load(Path::new(path), 0.25, TERRAIN_HEIGHT) {
+                Ok(heightmap) => Some(heightmap),
+                Err(error) => {
+                    eprintln!("Warning: {}", error);
+                    None
+                }
+            },
+            None => None,
+        };
+        let terrain_chunks: Vec<(GpuMesh, Aabb)> = terrain
+            .iter()
+            .flat_map(|heightmap| heightmap.build_chunks(32, 1.0))
+            .map(|chunk| {
+                let gpu_mesh = chunk.mesh.upload();
+                gpu_mesh.set_label(&format!("terrain chunk {} {}", chunk.column, chunk.row));
+                let offset = Vector3::new(0.0, TERRAIN_BASE, 0.0);
+                let bounds = Aabb {
+                    min: chunk.bounds.min + offset,
+                    max: chunk.bounds.max + offset,
+                };
+                (gpu_mesh, bounds)
+            })
+            .collect();
+        if let Some(heightmap) = &terrain {
+            let size = heightmap.size();
+            println!(
+                "Terrain: {} x {} samples {} apart, {} x {} wide in {} chunks",
+                heightmap.columns(),
+                heightmap.rows(),
+                heightmap.spacing(),
+                size.x,
+                size.y,
+                terrain_chunks.len()
             );
-            gl::GenerateMipmap(gl::TEXTURE_2D);
-            texture_id
+        }
+        let bounds_overlay = (!terrain_chunks.is_empty()).then(|| {
+            let boxes: Vec<Aabb> = terrain_chunks.iter().map(|(_, bounds)| *bounds).collect();
+            BoundsOverlay::new(&boxes)
+        });
+
+        // Load the textures from disk, using the model's own texture if it has one
+        let texture_one = match model_texture {
+            Some(image) => Texture2D::from_image(&image, &TextureParameters::default()),
+            None => load_texture(Path::new("./data/container.jpg")),
         };
+        let texture_two = load_texture(Path::new("./data/awesomeface.png"));
 
-        (texture_one_id, texture_two_id)
-    };
+        // Set texture uniforms
+        {
+            shader_program.use_program();
+            shader_program.set_int("texture1", 0);
+            shader_program.set_int("texture2", 1);
+        }
+        let mix_value = 0.2;
+        shader_program.set_float("mixValue", mix_value);
+        for assignment in uniforms.iter().flat_map(|uniforms| uniforms.split(';')) {
+            let result = match assignment.split_once('=') {
+                Some((name, value)) => shader_program.set_uniform_from_str(name.trim(), value),
+                None => Err(format!("expected name=value, got \"{}\"", assignment)),
+            };
+            if let Err(error) = result {
+                eprintln!("Warning: cannot set uniform: {}", error);
+            }
+        }
 
-    // Set texture uniforms
-    {
-        shader_program.use_program();
-        shader_program.set_int("texture1\0", 0);
-        shader_program.set_int("texture2\0", 1);
-    }
+        // Projection transform
+        let fov = angle_to_rad(45.0);
+        let projection = make_projection_matrix(fov, (width as f32) / (height as f32), 0.1, 100.0);
 
-    // Projection transform
-    let projection = make_projection_matrix(
-        angle_to_rad(45.0),
-        (width as f32) / (height as f32),
-        0.1,
-        100.0,
-    );
+        let cube_positions = [
+            Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vector3 {
+                x: 2.0,
+                y: 5.0,
+                z: -15.0,
+            },
+            Vector3 {
+                x: -1.5,
+                y: -2.2,
+                z: -2.5,
+            },
+            Vector3 {
+                x: -3.8,
+                y: -2.0,
+                z: -12.3,
+            },
+            Vector3 {
+                x: 2.4,
+                y: -0.4,
+                z: -3.5,
+            },
+            Vector3 {
+                x: -1.7,
+                y: 3.0,
+                z: -7.5,
+            },
+            Vector3 {
+                x: 1.3,
+                y: -2.0,
+                z: -2.5,
+            },
+            Vector3 {
+                x: 1.5,
+                y: 2.0,
+                z: 2.5,
+            },
+            Vector3 {
+                x: 1.5,
+                y: 0.2,
+                z: -1.5,
+            },
+            Vector3 {
+                x: -1.3,
+                y: 1.0,
+                z: -1.5,
+            },
+        ];
 
-    let cube_positions = [
-        Vector3 {
+        let mut camera = Camera::new();
+        camera.position.z = 3.0;
+
+        let light_position = Vector3 {
             x: 0.0,
-            y: 0.0,
+            y: 10.0,
             z: 0.0,
-        },
-        Vector3 {
-            x: 2.0,
-            y: 5.0,
-            z: -15.0,
-        },
-        Vector3 {
-            x: -1.5,
-            y: -2.2,
-            z: -2.5,
-        },
-        Vector3 {
-            x: -3.8,
-            y: -2.0,
-            z: -12.3,
-        },
-        Vector3 {
-            x: 2.4,
-            y: -0.4,
-            z: -3.5,
-        },
-        Vector3 {
-            x: -1.7,
-            y: 3.0,
-            z: -7.5,
-        },
-        Vector3 {
-            x: 1.3,
-            y: -2.0,
-            z: -2.5,
-        },
-        Vector3 {
-            x: 1.5,
-            y: 2.0,
-            z: 2.5,
-        },
-        Vector3 {
-            x: 1.5,
-            y: 0.2,
-            z: -1.5,
-        },
-        Vector3 {
-            x: -1.3,
+        };
+        let light_color = Vector3 {
+            x: 1.0,
             y: 1.0,
-            z: -1.5,
-        },
-    ];
-
-    let mut camera = Camera::new();
-    camera.position.z = 3.0;
+            z: 1.0,
+        };
 
-    let time_start = Instant::now();
-    while !window.should_close() {
-        for (_, event) in glfw::flush_messages(&events_receiver) {
-            match event {
-                WindowEvent::Key(Key::Escape, _, glfw::Action::Press, _) => {
-                    window.set_should_close(true);
-                }
-                _ => {}
+        // View and projection are shared by every draw, so they live in a uniform buffer that is
+        // written once per frame
+        let frame_data = FrameData {
+            view: camera.view_matrix(),
+            projection,
+            camera_position: camera.position,
+            light_position,
+            light_color,
+        };
+        let frame_data_buffer = match UniformBuffer::new(&frame_data, capabilities) {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                eprintln!("{}", error);
+                exit(1);
             }
+        };
+        frame_data_buffer.attach(&shader_program);
+        if let Some(overlay) = &normals_overlay {
+            frame_data_buffer.attach(overlay.program());
+        }
+        if let Some(overlay) = &bounds_overlay {
+            frame_data_buffer.attach(overlay.program());
         }
 
-        let millis_since = Instant::now().duration_since(time_start).as_millis() as f32;
+        Self {
+            shader_program,
+            normals_overlay,
+            show_normals: false,
+            gpu_chains,
+            terrain,
+            terrain_chunks,
+            bounds_overlay,
+            show_bounds: false,
+            texture_one,
+            texture_two,
+            mix_value,
+            frame_data_buffer,
+            cube_positions,
+            camera,
+            light_position,
+            light_color,
+            fov,
+            height,
+            projection,
+        }
+    }
+
+    /// Changes how much of the second texture is mixed in, clamped to 0..=1
+    fn set_mix_value(&mut self, mix_value: f32) {
+        self.mix_value = mix_value.clamp(0.0, 1.0);
+        self.shader_program.use_program();
+        self.shader_program.set_float("mixValue", self.mix_value);
+    }
+
+    /// Captures what shader.vs writes for a triangle with transform feedback and compares it
+    /// with the same transform done on the CPU. Returns a description of each difference.
+    fn check_vertex_shader(&self) -> Result<Vec<String>, ShaderError> {
+        let program = ShaderProgram::builder()
+            .vertex_file(Path::new("./src/shader.vs"))
+            .transform_feedback_varyings(
+                &["gl_Position", "TexCoord"],
+                TransformFeedbackMode::Separate,
+            )
+            .build()?;
+        let frame_data = FrameData {
+            view: self.camera.view_matrix(),
+            projection: self.projection,
+            camera_position: self.camera.position,
+            light_position: self.light_position,
+            light_color: self.light_color,
+        };
+        self.frame_data_buffer.update(&frame_data);
+        self.frame_data_buffer.attach(&program);
+        let model = Matrix4::translate(1.0, 2.0, -3.0);
+        program.use_program();
+        program.set_mat4("model", &model);
+
+        let triangle = primitives::fullscreen_triangle();
+        let gpu_mesh = triangle.upload();
+        let count = gpu_mesh.index_count();
+        let positions = TransformFeedbackBuffer::new(count * size_of::<[f32; 4]>());
+        let tex_coords = TransformFeedbackBuffer::new(count * size_of::<[f32; 2]>());
+        let primitive = FeedbackPrimitive::for_draw_mode(gl::TRIANGLES).unwrap();
+        capture(&[&positions, &tex_coords], primitive, true, || {
+            gpu_mesh.draw()
+        });
+        let positions = positions.read::<[f32; 4]>(count);
+        let tex_coords = tex_coords.read::<[f32; 2]>(count);
 
+        let transform = Matrix4::mult_mat4(
+            &Matrix4::mult_mat4(&frame_data.projection, &frame_data.view),
+            &model,
+        );
+        let close = |a: f32, b: f32| (a - b).abs() <= 1e-3 * a.abs().max(b.abs()).max(1.0);
+        let mut problems = Vec::new();
+        for (index, (position, tex_coord)) in positions.iter().zip(&tex_coords).enumerate() {
+            let vertex = &triangle.vertices[triangle.indices.get(index) as usize];
+            let expected =
+                Matrix4::mult_vector(&transform, &Vector4::from_vector3(&vertex.position));
+            let expected = [expected.x, expected.y, expected.z, expected.w];
+            if !position.iter().zip(&expected).all(|(a, b)| close(*a, *b)) {
+                problems.push(format!(
+                    "shader.vs put vertex {} at {:?} but the CPU expects {:?}",
+                    index, position, expected
+                ));
+            }
+            let expected = [vertex.uv.x, vertex.uv.y];
+            if !tex_coord.iter().zip(&expected).all(|(a, b)| close(*a, *b)) {
+                problems.push(format!(
+                    "shader.vs gave vertex {} texture coordinates {:?} instead of {:?}",
+                    index, tex_coord, expected
+                ));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Moves the camera and draws the frame millis_since milliseconds after the start
+    fn draw(&mut self, millis_since: f32) {
         // Update Camera
         let view = {
-            camera.target = Vector3 {
+            self.camera.target = Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
             };
             let orbit_radius = 20.0;
             let period = 10_000.0;
-            camera.position = Vector3 {
+            self.camera.position = Vector3 {
                 x: orbit_radius * (6.18 * millis_since / period).cos(),
                 y: 0.0,
                 z: orbit_radius * (6.18 * millis_since / period).sin(),
             };
 
-            camera.view_matrix()
+            // Stay above the ground
+            if let Some(heightmap) = &self.terrain {
+                let ground =
+                    TERRAIN_BASE + heightmap.height(self.camera.position.x, self.camera.position.z);
+                self.camera.position.y = self.camera.position.y.max(ground + 1.5);
+            }
+
+            self.camera.view_matrix()
         };
+        self.frame_data_buffer.update(&FrameData {
+            view,
+            projection: self.projection,
+            camera_position: self.camera.position,
+            light_position: self.light_position,
+            light_color: self.light_color,
+        });
 
-        unsafe {
+        with_backend(|backend| {
             // clear the color buffer
-            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-            // bind textures on corresponding texture units
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, texture_one_id);
-            gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, texture_two_id);
-
-            // Render
-            for (cube_index, cube_position) in cube_positions.iter().enumerate() {
-                let transform = {
-                    // World transforms
-                    let transform =
-                        Matrix4::translate(cube_position.x, cube_position.y, cube_position.z);
-
-                    // Model transforms
-                    let rotation = 20.0 * (cube_index as f32) + millis_since;
-                    let period = 2000.0; // in ms
-                    let transform = Matrix4::mult_mat4(
-                        &transform,
-                        &Matrix4::rotate_around_x(6.18 * rotation / period),
-                    );
-
-                    transform
-                };
-                shader_program.use_program();
-                shader_program.set_mat4("model\0", &transform);
-                shader_program.set_mat4("view\0", &view);
-                shader_program.set_mat4("projection\0", &projection);
-                gl::BindVertexArray(vaos[0]);
-                gl::DrawElements(gl::TRIANGLES, 36, gl::UNSIGNED_INT, 0 as *const c_void);
+            backend.clear_color(0.2, 0.3, 0.3, 1.0);
+            backend.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        });
+
+        // bind textures on corresponding texture units
+        self.texture_one.bind(0);
+        self.texture_two.bind(1);
+
+        // Render
+        for (cube_index, cube_position) in self.cube_positions.iter().enumerate() {
+            let transform = {
+                // World transforms
+                let transform =
+                    Matrix4::translate(cube_position.x, cube_position.y, cube_position.z);
+
+                // Model transforms
+                let rotation = 20.0 * (cube_index as f32) + millis_since;
+                let period = 2000.0; // in ms
+                let transform = Matrix4::mult_mat4(
+                    &transform,
+                    &Matrix4::rotate_around_x(6.18 * rotation / period),
+                );
+
+                transform
+            };
+            self.shader_program.use_program();
+            self.shader_program.set_mat4("model", &transform);
+            for chain in &self.gpu_chains {
+                // The model transform only rotates, so the bounding sphere keeps its radius
+                let center = Vector3::from_vector4(&Matrix4::mult_vector(
+                    &transform,
+                    &Vector4::from_vector3(&chain.center),
+                ));
+                let distance = (center - self.camera.position).magnitude();
+                let size = projected_size(chain.radius, distance, self.fov, self.height as f32);
+                chain.levels[chain.select(size)].draw();
+            }
+            if self.show_normals
+                && let Some(overlay) = &self.normals_overlay
+            {
+                overlay.draw(
+                    &transform,
+                    self.gpu_chains.iter().map(|chain| &chain.levels[0]),
+                );
             }
         }
 
-        window.swap_buffers();
-        glfw_data.poll_events();
+        if !self.terrain_chunks.is_empty() {
+            let frustum = Frustum::from_matrix(&Matrix4::mult_mat4(&self.projection, &view));
+            self.shader_program.use_program();
+            self.shader_program
+                .set_mat4("model", &Matrix4::translate(0.0, TERRAIN_BASE, 0.0));
+            let mut visible = Vec::with_capacity(self.terrain_chunks.len());
+            for (gpu_mesh, bounds) in &self.terrain_chunks {
+                let is_visible = frustum.intersects(bounds);
+                if is_visible {
+                    gpu_mesh.draw();
+                }
+                visible.push(is_visible);
+            }
+            if self.show_bounds
+                && let Some(overlay) = &mut self.bounds_overlay
+            {
+                overlay.draw(&visible);
+            }
+        }
+    }
+}
+
+/// Loads a texture, or a checkerboard if the file cannot be decoded so the scene still draws
+fn load_texture(path: &Path) -> Texture2D {
+    Texture2D::load(path, &TextureParameters::default()).unwrap_or_else(|error| {
+        eprintln!("Warning: failed to load {}: {}", path.display(), error);
+        let parameters = TextureParameters {
+            min_filter: gl::NEAREST,
+            mag_filter: gl::NEAREST,
+            generate_mipmaps: false,
+            ..TextureParameters::default()
+        };
+        let size = 8;
+        let checkers: Vec<[u8; 3]> = (0..size * size)
+            .map(|texel| {
+                if (texel % size + texel / size) % 2 == 0 {
+                    [255, 0, 255]
+                } else {
+                    [0, 0, 0]
+                }
+            })
+            .collect();
+        let texture = Texture2D::new(
+            size,
+            size,
+            gl::RGB8,
+            gl::RGB,
+            gl::UNSIGNED_BYTE,
+            &parameters,
+        );
+        let region = TextureRegion {
+            x: 0,
+            y: 0,
+            width: size,
+            height: size,
+        };
+        texture.set_sub_image(&region, gl::RGB, gl::UNSIGNED_BYTE, &checkers);
+        texture
+    })
+}
+
+fn is_mesh_cache(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(MESH_CACHE_EXTENSION))
+}
+
+fn cube_lod_chain() -> Vec<GpuLodChain> {
+    vec![LodChain::build(primitives::cube(1.0, 1), 1, 0.5).upload()]
+}
+
+/// Builds simplified versions of each named mesh for when the model is far away, orders every
+/// level for the vertex cache and prints what that did
+fn build_lod_chains(label: &str, meshes: Vec<(String, Mesh)>) -> Vec<(String, LodChain)> {
+    let mut chains: Vec<(String, LodChain)> = meshes
+        .into_iter()
+        .map(|(name, mesh)| (name, LodChain::build(mesh, 4, 0.5)))
+        .collect();
+    if let Some(bounds) = chains
+        .iter()
+        .filter_map(|(_, chain)| chain.levels[0].mesh.bounds())
+        .reduce(|a, b| a.union(&b))
+    {
+        println!("{}: {} meshes within {}", label, chains.len(), bounds);
+    }
+    for (name, chain) in &mut chains {
+        for (level, lod) in chain.levels.iter_mut().enumerate() {
+            let report = optimize(&mut lod.mesh, Some(1.05));
+            println!(
+                "{} {} LOD {}: {} triangles, error {:.4}, {}",
+                label,
+                name,
+                level,
+                lod.mesh.triangle_count(),
+                lod.error,
+                report
+            );
+        }
+    }
+    chains
+}
+
+/// The meshes of a model file, each with a name, and the texture to draw them with
+struct Model {
+    meshes: Vec<(String, Mesh)>,
+    /// The diffuse or base color texture of the first mesh's material, if it has one
+    texture: Option<DynamicImage>,
+}
+
+/// Gives a mesh without normals the shading LEARN_OPENGL_SHADING asks for: "flat", "area" for
+/// area-weighted smoothing, "crease:<degrees>" to keep edges sharper than the angle, or
+/// angle-weighted smoothing otherwise
+fn generate_normals(mesh: &mut Mesh, shading: &str) {
+    if shading == "flat" {
+        *mesh = flat_shaded(mesh);
+    } else if shading == "area" {
+        compute_smooth_normals(mesh, NormalWeighting::Area);
+    } else if let Some(degrees) = shading.strip_prefix("crease:") {
+        match degrees.parse::<f32>() {
+            Ok(degrees) => {
+                *mesh = split_creases(mesh, degrees.to_radians(), NormalWeighting::Angle)
+            }
+            Err(_) => {
+                eprintln!("Warning: crease angle {} is not a number", degrees);
+                compute_smooth_normals(mesh, NormalWeighting::Angle);
+            }
+        }
+    } else {
+        compute_smooth_normals(mesh, NormalWeighting::Angle);
+    }
+}
+
+/// Loads the meshes of a model file, picking the format from the extension. Meshes that come
+/// without normals get them from generate_normals, and tangents are computed for those without
+/// them.
+fn load_model(path: &Path) -> Result<Model, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let mut texture = None;
+    let mut meshes: Vec<(String, Mesh)> = match extension.as_deref() {
+        Some("obj") => {
+            let model = load_obj(path).map_err(|error| error.to_string())?;
+            let diffuse_map = model
+                .meshes
+                .first()
+                .and_then(|mesh| model.material(mesh))
+                .and_then(|material| material.diffuse_map.as_ref());
+            if let Some(diffuse_map) = diffuse_map {
+                match image::open(diffuse_map) {
+                    Ok(image) => texture = Some(image),
+                    Err(error) => eprintln!(
+                        "Warning: failed to load {}: {}",
+                        diffuse_map.display(),
+                        error
+                    ),
+                }
+            }
+            // Named after the object and group, e.g. "car/wheel"
+            model
+                .meshes
+                .into_iter()
+                .map(|mesh| {
+                    let name = [mesh.object, mesh.group]
+                        .into_iter()
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<String>>()
+                        .join("/");
+                    (name, mesh.mesh)
+                })
+                .collect()
+        }
+        Some("gltf" | "glb") => {
+            // Bake each node's transform into its meshes so the scene is drawn as authored
+            let model = load_gltf(path).map_err(|error| error.to_string())?;
+            let mut meshes = Vec::new();
+            let mut materials = Vec::new();
+            for (mesh, transform) in model.mesh_instances() {
+                let gltf_mesh = &model.meshes[mesh];
+                for (index, primitive) in gltf_mesh.primitives.iter().enumerate() {
+                    let mut mesh = primitive.mesh.clone();
+                    mesh.transform(&transform);
+                    // Unnamed meshes are numbered below like other formats
+                    let name = match gltf_mesh.primitives.len() {
+                        _ if gltf_mesh.name.is_empty() => String::new(),
+                        1 => gltf_mesh.name.clone(),
+                        _ => format!("{} {}", gltf_mesh.name, index),
+                    };
+                    meshes.push((name, mesh));
+                    materials.push(primitive.material);
+                }
+            }
+            texture = materials
+                .first()
+                .copied()
+                .flatten()
+                .and_then(|material| model.materials.get(material)?.base_color_texture)
+                .and_then(|texture| model.images.get(texture.image).cloned());
+            meshes
+        }
+        // The most detailed level of each submesh, for exporting or baking again
+        Some(extension) if extension == MESH_CACHE_EXTENSION => {
+            let cache = load_mesh_cache(path).map_err(|error| error.to_string())?;
+            cache
+                .submeshes
+                .iter()
+                .filter_map(|submesh| {
+                    let level = submesh.levels.first()?;
+                    Some((submesh.name.clone(), cache.level_mesh(level)))
+                })
+                .collect()
+        }
+        // Scans and CAD parts come in unusual units, so their size is worth printing
+        Some("stl") => {
+            let model = load_stl(path).map_err(|error| error.to_string())?;
+            if let Some(bounds) = model.bounds {
+                println!("{}: {} within {}", path.display(), model.name, bounds);
+            }
+            vec![(model.name.trim().to_string(), model.mesh)]
+        }
+        Some("ply") => {
+            let model = load_ply(path).map_err(|error| error.to_string())?;
+            if let Some(bounds) = model.bounds {
+                println!("{}: within {}", path.display(), bounds);
+            }
+            vec![(String::new(), model.mesh)]
+        }
+        _ => {
+            return Err(format!(
+                "{} is not a supported model format",
+                path.display()
+            ));
+        }
+    };
+
+    // Groups and objects without faces would only add empty draws
+    let mesh_count = meshes.len();
+    meshes.retain(|(_, mesh)| !mesh.indices.is_empty());
+    if meshes.len() < mesh_count {
+        println!(
+            "{}: skipped {} meshes without triangles",
+            path.display(),
+            mesh_count - meshes.len()
+        );
+    }
+    if meshes.is_empty() {
+        return Err(format!("{} has no triangles", path.display()));
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let shading = std::env::var("LEARN_OPENGL_SHADING").unwrap_or_default();
+    for (index, (name, mesh)) in meshes.iter_mut().enumerate() {
+        if name.is_empty() {
+            *name = format!("{} {}", stem, index);
+        }
+        let is_zero = |v: &Vector3| v.x == 0.0 && v.y == 0.0 && v.z == 0.0;
+        if mesh.vertices.iter().any(|vertex| is_zero(&vertex.normal)) {
+            generate_normals(mesh, &shading);
+        }
+        if mesh.vertices.iter().any(|vertex| vertex.tangent.w == 0.0) {
+            compute_tangents(mesh);
+        }
+    }
+    Ok(Model { meshes, texture })
+}
+
+/// Writes a model file or one of the generated primitives as STL or PLY, picking the format
+/// from the output extension. Files are binary unless "ascii" is given.
+/// Returns the process exit code.
+fn export_mesh(args: &[String]) -> i32 {
+    let (Some(input), Some(output)) = (args.first(), args.get(1)) else {
+        eprintln!("Usage: learn_opengl export-mesh <model or primitive> <output> [ascii]");
+        eprintln!("Primitives: cube, plane, sphere, icosphere, cylinder, cone, torus, capsule");
+        return 2;
+    };
+    let ascii = args.get(2).map(String::as_str) == Some("ascii");
+
+    let mesh = match input.as_str() {
+        "cube" => primitives::cube(1.0, 1),
+        "plane" => primitives::plane(1.0, 1.0, 1, 1),
+        "sphere" => primitives::uv_sphere(0.5, 32, 16),
+        "icosphere" => primitives::icosphere(0.5, 3),
+        "cylinder" => primitives::cylinder(0.5, 1.0, 32, 1, 1),
+        "cone" => primitives::cone(0.5, 1.0, 32, 1, 1),
+        "torus" => primitives::torus(0.5, 0.2, 32, 16),
+        "capsule" => primitives::capsule(0.25, 0.5, 32, 8),
+        _ => match load_model(Path::new(input)) {
+            Ok(model) => {
+                let mut merged = Mesh::from_triangle_list(Vec::new());
+                for (_, mesh) in &model.meshes {
+                    merged.append(mesh);
+                }
+                merged
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                return 2;
+            }
+        },
+    };
+
+    let output_path = Path::new(output);
+    let extension = output_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let result = match extension.as_deref() {
+        Some("stl") => {
+            let format = if ascii {
+                StlFormat::Ascii
+            } else {
+                StlFormat::Binary
+            };
+            save_stl(output_path, &mesh, input, format)
+        }
+        Some("ply") => {
+            let format = if ascii {
+                PlyFormat::Ascii
+            } else {
+                PlyFormat::BinaryLittleEndian
+            };
+            save_ply(output_path, &PlyModel::from_mesh(mesh), format)
+        }
+        _ => {
+            eprintln!("Cannot export to {}, use .stl or .ply", output);
+            return 2;
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("Failed to write {}: {}", output, error);
+            1
+        }
+    }
+}
+
+/// Converts a model into a mesh cache with its LODs already built, so it loads without parsing.
+/// The output defaults to the model's path with the cache extension.
+/// Returns the process exit code.
+fn bake(args: &[String]) -> i32 {
+    let Some(input) = args.first() else {
+        eprintln!("Usage: learn_opengl bake <model> [output]");
+        return 2;
+    };
+    let input_path = Path::new(input);
+    let output_path = match args.get(1) {
+        Some(output) => PathBuf::from(output),
+        None => input_path.with_extension(MESH_CACHE_EXTENSION),
+    };
+
+    let model = match load_model(input_path) {
+        Ok(model) => model,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    let chains = build_lod_chains(input, model.meshes);
+    let submeshes: Vec<(String, &LodChain)> = chains
+        .iter()
+        .map(|(name, chain)| (name.clone(), chain))
+        .collect();
+    match save_mesh_cache(&output_path, &submeshes) {
+        Ok(()) => {
+            println!("Baked {} into {}", input, output_path.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("Failed to write {}: {}", output_path.display(), error);
+            1
+        }
+    }
+}
+
+/// Checks shader stages against each other and against the uniform names set from Rust.
+/// Stages are given in pipeline order and identified by extension; .rs files are scanned for
+/// set_* calls. Without arguments the demo's own shaders and main.rs are checked.
+/// Returns the process exit code.
+fn lint_shaders(paths: &[String]) -> i32 {
+    let default_paths = [
+        "./src/shader.vs".to_string(),
+        "./src/shader.fs".to_string(),
+        "./src/main.rs".to_string(),
+    ];
+    let paths = if paths.is_empty() {
+        &default_paths[..]
+    } else {
+        paths
+    };
+
+    let mut stages = Vec::new();
+    let mut uniform_uses = Vec::new();
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        let source = match read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("Failed to read {}: {}", path, error);
+                return 2;
+            }
+        };
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        if extension == "rs" {
+            uniform_uses.extend(find_uniform_setter_calls(path, &source));
+            continue;
+        }
+        let Some(stage) = glsl::stage_from_extension(extension) else {
+            eprintln!(
+                "Cannot tell the shader stage of {} from its extension",
+                path
+            );
+            return 2;
+        };
+        let (interface, stage_diagnostics) = lint_stage(stage, path, &source);
+        stages.push(interface);
+        diagnostics.extend(stage_diagnostics);
+    }
+    diagnostics.extend(check_program(&stages));
+    diagnostics.extend(check_uniform_names(&stages, &uniform_uses));
+
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    eprintln!(
+        "{} error(s), {} warning(s)",
+        errors,
+        diagnostics.len() - errors
+    );
+
+    if errors > 0 { 1 } else { 0 }
+}
+
+/// Prints a summary line per frame of a trace recorded with the gl-trace feature. When a frame
+/// number is given, that frame's calls are listed as well.
+/// Returns the process exit code.
+fn trace_dump(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: learn_opengl trace-dump <trace file> [frame]");
+        return 2;
+    };
+    let selected_frame = match args.get(1).map(|frame| frame.parse::<u64>()) {
+        None => None,
+        Some(Ok(frame)) => Some(frame),
+        Some(Err(_)) => {
+            eprintln!("Frame must be a number, got {}", args[1]);
+            return 2;
+        }
+    };
+
+    let text = match read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", path, error);
+            return 2;
+        }
+    };
+    let frames = match read_trace(&text) {
+        Ok(frames) => frames,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            return 1;
+        }
+    };
+
+    for frame in &frames {
+        let summary = summarize_frame(frame);
+        println!(
+            "frame {}: {} calls, {} draws, {} state changes ({} redundant), {} uploads ({} bytes), {:.3} ms in GL",
+            frame.index,
+            summary.calls,
+            summary.draw_calls,
+            summary.state_changes,
+            summary.redundant_state_changes,
+            summary.uploads,
+            summary.upload_bytes,
+            summary.gl_time as f64 / 1000.0
+        );
+    }
+
+    if let Some(selected_frame) = selected_frame {
+        let Some(frame) = frames.iter().find(|frame| frame.index == selected_frame) else {
+            eprintln!("{} has no frame {}", path, selected_frame);
+            return 1;
+        };
+        println!();
+        let frame_start = frame.calls.first().map_or(0, |call| call.time);
+        for call in &frame.calls {
+            println!("{}", format_call(call, frame_start));
+        }
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_backend::RecordingBackend;
+
+    #[test]
+    fn a_frame_draws_every_cube() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(CachingBackend::new(Box::new(recording.clone()))));
+        let mut scene = Scene::new(&Capabilities::minimum(), 800, 600, None, None, None);
+        assert_eq!(recording.viewport(), (0, 0, 800, 600));
+        assert!(recording.is_enabled(gl::DEPTH_TEST));
+        assert!(recording.is_enabled(gl::CULL_FACE));
+        assert_eq!(recording.cull_face(), gl::BACK);
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+
+        recording.clear_log();
+        scene.draw(0.0);
+        let draws = recording.draw_calls();
+        assert_eq!(draws.len(), 10);
+        let program = scene.shader_program.handle();
+        let textures = vec![
+            (0, scene.texture_one.handle()),
+            (1, scene.texture_two.handle()),
+        ];
+        for draw in &draws {
+            assert_eq!(draw.mode, gl::TRIANGLES);
+            assert!(draw.index_type.is_some());
+            assert_eq!(draw.program, program);
+            assert_eq!(draw.textures, textures);
+            assert_eq!(
+                recording.label(draw.vertex_array).as_deref(),
+                Some("cube 0 LOD 0")
+            );
+        }
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+
+    #[test]
+    fn obj_models_keep_their_names_and_diffuse_texture() {
+        let directory =
+            std::env::temp_dir().join(format!("load_model_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("pair.obj"),
+            "mtllib pair.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\no pair\ng left\nusemtl paint\n\
+             f 1 2 3\ng right\nf 1 3 2\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("pair.mtl"),
+            "newmtl paint\nmap_Kd paint.png\n",
+        )
+        .unwrap();
+        image::RgbImage::new(2, 4)
+            .save(directory.join("paint.png"))
+            .unwrap();
+
+        let model = load_model(&directory.join("pair.obj")).unwrap();
+        let names: Vec<&str> = model.meshes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["pair/left", "pair/right"]);
+        let texture = model.texture.unwrap();
+        assert_eq!((texture.width(), texture.height()), (2, 4));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn generated_normals_follow_the_shading() {
+        let without_normals = || {
+            let mut mesh = primitives::cube(1.0, 1);
+            for vertex in &mut mesh.vertices {
+                vertex.normal = Vector3::default();
+            }
+            mesh
+        };
+        let on_an_axis = |mesh: &Mesh| {
+            mesh.vertices.iter().all(|vertex| {
+                let n = vertex.normal;
+                (n.x.abs() + n.y.abs() + n.z.abs() - 1.0).abs() < 1e-5
+            })
+        };
+
+        let mut smooth = without_normals();
+        generate_normals(&mut smooth, "");
+        assert!(!on_an_axis(&smooth));
+        let mut area = without_normals();
+        generate_normals(&mut area, "area");
+        assert!(!on_an_axis(&area));
+
+        // Cube edges are 90 degrees, so a smaller crease angle keeps every face flat
+        for shading in ["flat", "crease:30"] {
+            let mut mesh = without_normals();
+            generate_normals(&mut mesh, shading);
+            assert!(on_an_axis(&mesh), "{}", shading);
+            assert_eq!(mesh.vertices.len(), 24, "{}", shading);
+        }
+        let mut creased = without_normals();
+        generate_normals(&mut creased, "crease:120");
+        assert!(!on_an_axis(&creased));
+    }
+
+    #[test]
+    fn normals_are_drawn_through_the_geometry_shader() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(CachingBackend::new(Box::new(recording.clone()))));
+        let mut scene = Scene::new(&Capabilities::minimum(), 800, 600, None, None, None);
+        let normals_program = scene.normals_overlay.as_ref().unwrap().program().handle();
+
+        scene.show_normals = true;
+        recording.clear_log();
+        scene.draw(0.0);
+        let draws = recording.draw_calls();
+        assert_eq!(draws.len(), 20);
+        assert_eq!(
+            draws
+                .iter()
+                .filter(|draw| draw.program == normals_program)
+                .count(),
+            10
+        );
+        assert_eq!(recording.depth_func(), gl::LESS);
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
     }
 }
@@ -1,5 +1,11 @@
 use crate::vector::Vector4;
 
+#[derive(Clone, Copy)]
+pub struct Matrix3 {
+    pub data: [[f32; 3]; 3],
+}
+
+#[derive(Clone, Copy)]
 pub struct Matrix4 {
     pub data: [[f32; 4]; 4],
 }
@@ -91,6 +97,33 @@ impl Matrix4 {
         result
     }
 
+    /// Rotation by a unit quaternion x i + y j + z k + w
+    pub fn rotate_by_quaternion(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self {
+            data: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - z * w),
+                    2.0 * (x * z + y * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y + z * w),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - x * w),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z - y * w),
+                    2.0 * (y * z + x * w),
+                    1.0 - 2.0 * (x * x + y * y),
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
     fn dot(a: &Matrix4, b: &Matrix4, i: usize, j: usize) -> f32 {
         a.data[i][0] * b.data[0][j]
             + a.data[i][1] * b.data[1][j]
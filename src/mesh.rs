@@ -0,0 +1,509 @@
+use std::{collections::HashMap, fmt};
+
+use glad_gl::gl::{self, GLenum};
+
+use crate::{
+    gl_objects::{Buffer, BufferTarget, BufferUsage, Pod, VertexArray, as_bytes},
+    matrix::Matrix4,
+    vector::{Vector2, Vector3, Vector4, calc_cross_product},
+    vertex_layout::{Vertex, VertexLayout, impl_vertex},
+};
+
+/// The vertex format shared by every mesh: position at location 0, texture coordinates at 1,
+/// normal at 2 and tangent at 3. The tangent's w is the handedness of the bitangent (+1 or -1).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct MeshVertex {
+    pub position: Vector3,
+    pub normal: Vector3,
+    pub uv: Vector2,
+    pub tangent: Vector4,
+}
+
+unsafe impl Pod for MeshVertex {}
+
+impl_vertex!(MeshVertex {
+    position: 0, Float, 3;
+    uv: 1, Float, 2;
+    normal: 2, Float, 3;
+    tangent: 3, Float, 4;
+});
+
+impl MeshVertex {
+    pub fn new(position: Vector3, normal: Vector3, uv: Vector2) -> Self {
+        Self {
+            position,
+            normal,
+            uv,
+            tangent: Vector4::default(),
+        }
+    }
+
+    /// Every component as a flat array, for comparisons
+    fn components(&self) -> [f32; 12] {
+        [
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.normal.x,
+            self.normal.y,
+            self.normal.z,
+            self.uv.x,
+            self.uv.y,
+            self.tangent.x,
+            self.tangent.y,
+            self.tangent.z,
+            self.tangent.w,
+        ]
+    }
+
+    /// True if no component differs by more than epsilon
+    pub fn approx_eq(&self, other: &MeshVertex, epsilon: f32) -> bool {
+        self.components()
+            .iter()
+            .zip(other.components().iter())
+            .all(|(a, b)| (a - b).abs() <= epsilon)
+    }
+}
+
+/// Triangle list indices, stored as u16 whenever every vertex can be addressed with one
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks the narrowest index type that can address vertex_count vertices
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, position: usize) -> u32 {
+        match self {
+            Indices::U16(indices) => indices[position] as u32,
+            Indices::U32(indices) => indices[position],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.len()).map(|position| self.get(position))
+    }
+
+    pub fn to_u32(&self) -> Vec<u32> {
+        self.iter().collect()
+    }
+
+    /// UNSIGNED_SHORT or UNSIGNED_INT
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            Indices::U16(_) => gl::UNSIGNED_SHORT,
+            Indices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// An axis-aligned bounding box
+#[derive(Clone, Copy)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    /// The smallest box holding every point, or None if there are none
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vector3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        let mut bounds = Aabb {
+            min: first,
+            max: first,
+        };
+        for point in points {
+            bounds.extend(point);
+        }
+        Some(bounds)
+    }
+
+    pub fn extend(&mut self, point: &Vector3) {
+        self.min = Vector3::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Vector3::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut bounds = *self;
+        bounds.extend(&other.min);
+        bounds.extend(&other.max);
+        bounds
+    }
+
+    pub fn center(&self) -> Vector3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn size(&self) -> Vector3 {
+        self.max - self.min
+    }
+}
+
+impl fmt::Display for Aabb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3})",
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z
+        )
+    }
+}
+
+/// An indexed triangle list on the CPU
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Indices,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<MeshVertex>, indices: Vec<u32>) -> Self {
+        assert!(
+            indices.len().is_multiple_of(3),
+            "indices must form whole triangles"
+        );
+        assert!(
+            indices
+                .iter()
+                .all(|index| (*index as usize) < vertices.len()),
+            "index out of range"
+        );
+        let indices = Indices::new(indices, vertices.len());
+        Self { vertices, indices }
+    }
+
+    /// Makes a mesh from a triangle list where every three vertices form a triangle
+    pub fn from_triangle_list(vertices: Vec<MeshVertex>) -> Self {
+        let indices = (0..vertices.len() as u32).collect();
+        Self::new(vertices, indices)
+    }
+
+    /// None for a mesh without vertices
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|vertex| &vertex.position))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// The vertex indices of each triangle
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        (0..self.triangle_count()).map(|triangle| {
+            [
+                self.indices.get(triangle * 3),
+                self.indices.get(triangle * 3 + 1),
+                self.indices.get(triangle * 3 + 2),
+            ]
+        })
+    }
+
+    /// Merges vertices whose attributes all differ by at most epsilon and rewrites the indices
+    /// to share them. The first vertex of each group is kept. Triangles that collapse because
+    /// two of their corners were merged are removed.
+    pub fn weld(&self, epsilon: f32) -> Mesh {
+        // Candidates are found through a grid over positions with cells epsilon wide, so a
+        // match is always in the same or a neighbouring cell
+        let cell_size = if epsilon > 0.0 { epsilon } else { 1e-5 };
+        let cell_of = |position: &Vector3| {
+            (
+                (position.x / cell_size).floor() as i64,
+                (position.y / cell_size).floor() as i64,
+                (position.z / cell_size).floor() as i64,
+            )
+        };
+
+        let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+        let mut vertices: Vec<MeshVertex> = Vec::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        for vertex in &self.vertices {
+            let (x, y, z) = cell_of(&vertex.position);
+            let mut found = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let Some(candidates) = grid.get(&(x + dx, y + dy, z + dz)) else {
+                            continue;
+                        };
+                        if let Some(index) = candidates
+                            .iter()
+                            .find(|index| vertices[**index as usize].approx_eq(vertex, epsilon))
+                        {
+                            found = Some(*index);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            let index = found.unwrap_or_else(|| {
+                let index = vertices.len() as u32;
+                vertices.push(*vertex);
+                grid.entry((x, y, z)).or_default().push(index);
+                index
+            });
+            remap.push(index);
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for [a, b, c] in self.triangles() {
+            let triangle = [remap[a as usize], remap[b as usize], remap[c as usize]];
+            if triangle[0] != triangle[1]
+                && triangle[1] != triangle[2]
+                && triangle[0] != triangle[2]
+            {
+                indices.extend_from_slice(&triangle);
+            }
+        }
+        Mesh::new(vertices, indices)
+    }
+
+    /// Adds the vertices and triangles of another mesh
+    pub fn append(&mut self, other: &Mesh) {
+        let base = self.vertices.len() as u32;
+        let mut indices = self.indices.to_u32();
+        indices.extend(other.indices.iter().map(|index| base + index));
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices = Indices::new(indices, self.vertices.len());
+    }
+
+    /// Moves the mesh by a transform. Normals and tangents are turned with it and the winding
+    /// is reversed if the transform mirrors the mesh.
+    pub fn transform(&mut self, transform: &Matrix4) {
+        let m = &transform.data;
+        let column = |index: usize| Vector3::new(m[0][index], m[1][index], m[2][index]);
+        // Normals are transformed by the inverse transpose, which is the cofactor matrix up to
+        // a scale that normalizing removes
+        let cofactors = [
+            calc_cross_product(&column(1), &column(2)),
+            calc_cross_product(&column(2), &column(0)),
+            calc_cross_product(&column(0), &column(1)),
+        ];
+        let determinant = Vector3::dot_product(&column(0), &cofactors[0]);
+        let sign = if determinant < 0.0 { -1.0 } else { 1.0 };
+        let direction = |v: &Vector3| v.x * column(0) + v.y * column(1) + v.z * column(2);
+        let normalized = |v: Vector3| {
+            if v.magnitude() > 0.0 {
+                Vector3::calc_normalized_vector(&v)
+            } else {
+                v
+            }
+        };
+
+        for vertex in &mut self.vertices {
+            let position =
+                Matrix4::mult_vector(transform, &Vector4::from_vector3(&vertex.position));
+            vertex.position = Vector3::from_vector4(&position);
+            let normal = &vertex.normal;
+            vertex.normal = normalized(
+                sign * (normal.x * cofactors[0]
+                    + normal.y * cofactors[1]
+                    + normal.z * cofactors[2]),
+            );
+            let tangent = normalized(direction(&Vector3::from_vector4(&vertex.tangent)));
+            vertex.tangent = Vector4::new(tangent.x, tangent.y, tangent.z, sign * vertex.tangent.w);
+        }
+
+        if determinant < 0.0 {
+            let mut indices = self.indices.to_u32();
+            for triangle in indices.chunks_mut(3) {
+                triangle.swap(1, 2);
+            }
+            self.indices = Indices::new(indices, self.vertices.len());
+        }
+    }
+
+    /// Uploads the vertices and indices into new buffers and a vertex array
+    pub fn upload(&self) -> GpuMesh {
+        let indices = match &self.indices {
+            Indices::U16(indices) => as_bytes(indices),
+            Indices::U32(indices) => as_bytes(indices),
+        };
+        GpuMesh::from_raw(
+            as_bytes(&self.vertices),
+            indices,
+            self.indices.gl_type(),
+            self.indices.len(),
+        )
+    }
+}
+
+/// A mesh in GPU memory, ready to draw
+pub struct GpuMesh {
+    vertex_array: VertexArray,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_type: GLenum,
+    index_count: usize,
+}
+
+impl GpuMesh {
+    /// Uploads vertices already in MeshVertex layout and index_count indices of index_type
+    /// (UNSIGNED_SHORT or UNSIGNED_INT) straight from bytes, such as those of a mesh cache
+    pub fn from_raw(
+        vertices: &[u8],
+        indices: &[u8],
+        index_type: GLenum,
+        index_count: usize,
+    ) -> GpuMesh {
+        let index_size = if index_type == gl::UNSIGNED_SHORT {
+            2
+        } else {
+            4
+        };
+        assert_eq!(
+            vertices.len() % size_of::<MeshVertex>(),
+            0,
+            "vertex bytes must hold whole vertices"
+        );
+        assert_eq!(
+            indices.len(),
+            index_count * index_size,
+            "index bytes must match the index count"
+        );
+        let vertex_buffer =
+            Buffer::with_data(BufferTarget::Array, vertices, BufferUsage::StaticDraw);
+        let index_buffer =
+            Buffer::with_data(BufferTarget::ElementArray, indices, BufferUsage::StaticDraw);
+
+        let vertex_array = VertexArray::new();
+        vertex_array.set_element_buffer(&index_buffer);
+        vertex_array.set_layout(&vertex_buffer, &MeshVertex::layout());
+        // Otherwise the next index buffer created would replace this one in the vertex array
+        VertexArray::unbind();
+
+        GpuMesh {
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            index_type,
+            index_count,
+        }
+    }
+
+    pub fn layout(&self) -> VertexLayout {
+        MeshVertex::layout()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    /// Names the vertex array and buffers as "label", "label vertices" and "label indices"
+    pub fn set_label(&self, label: &str) {
+        self.vertex_array.set_label(label);
+        self.vertex_buffer.set_label(&format!("{} vertices", label));
+        self.index_buffer.set_label(&format!("{} indices", label));
+    }
+
+    /// Draws every triangle. The program must already be in use.
+    pub fn draw(&self) {
+        if self.index_type == gl::UNSIGNED_SHORT {
+            self.vertex_array
+                .draw_elements::<u16>(gl::TRIANGLES, self.index_count);
+        } else {
+            self.vertex_array
+                .draw_elements::<u32>(gl::TRIANGLES, self.index_count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cube the demo used to draw: 36 corners of position and texture coordinates, one
+    /// per triangle corner
+    const CUBE: [f32; 180] = [
+        -0.5, -0.5, -0.5, 0.0, 0.0, 0.5, -0.5, -0.5, 1.0, 0.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5, 0.5,
+        -0.5, 1.0, 1.0, -0.5, 0.5, -0.5, 0.0, 1.0, -0.5, -0.5, -0.5, 0.0, 0.0, -0.5, -0.5, 0.5,
+        0.0, 0.0, 0.5, -0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 1.0, -0.5,
+        0.5, 0.5, 0.0, 1.0, -0.5, -0.5, 0.5, 0.0, 0.0, -0.5, 0.5, 0.5, 1.0, 0.0, -0.5, 0.5, -0.5,
+        1.0, 1.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, -0.5, 0.5, 0.0,
+        0.0, -0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5,
+        -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, 0.5, 0.0, 0.0, 0.5, 0.5, 0.5,
+        1.0, 0.0, -0.5, -0.5, -0.5, 0.0, 1.0, 0.5, -0.5, -0.5, 1.0, 1.0, 0.5, -0.5, 0.5, 1.0, 0.0,
+        0.5, -0.5, 0.5, 1.0, 0.0, -0.5, -0.5, 0.5, 0.0, 0.0, -0.5, -0.5, -0.5, 0.0, 1.0, -0.5, 0.5,
+        -0.5, 0.0, 1.0, 0.5, 0.5, -0.5, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 0.0,
+        -0.5, 0.5, 0.5, 0.0, 0.0, -0.5, 0.5, -0.5, 0.0, 1.0,
+    ];
+
+    fn vertex(x: f32, y: f32, z: f32, u: f32, v: f32) -> MeshVertex {
+        MeshVertex::new(
+            Vector3::new(x, y, z),
+            Vector3::default(),
+            Vector2::new(u, v),
+        )
+    }
+
+    #[test]
+    fn weld_shrinks_the_cube_to_at_most_24_vertices() {
+        let cube = Mesh::from_triangle_list(
+            CUBE.chunks(5)
+                .map(|c| vertex(c[0], c[1], c[2], c[3], c[4]))
+                .collect(),
+        );
+        assert_eq!(cube.vertices.len(), 36);
+        let welded = cube.weld(1e-5);
+        assert!(welded.vertices.len() <= 24, "{}", welded.vertices.len());
+        assert_eq!(welded.triangle_count(), 12);
+        // Every corner still has its original attributes
+        for (original, index) in cube.indices.iter().zip(welded.indices.iter()) {
+            assert!(
+                welded.vertices[index as usize].approx_eq(&cube.vertices[original as usize], 0.0)
+            );
+        }
+    }
+
+    #[test]
+    fn weld_keeps_uv_seams_apart() {
+        // Two triangles sharing an edge in space, with different texture coordinates on it
+        let mesh = Mesh::from_triangle_list(vec![
+            vertex(0.0, 0.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 0.0, 0.0, 1.0, 0.0),
+            vertex(1.0, 1.0, 0.0, 1.0, 1.0),
+            vertex(0.0, 0.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 1.0, 0.0, 0.0, 1.0),
+            vertex(0.0, 1.0, 0.0, 0.0, 1.0),
+        ]);
+        let welded = mesh.weld(1e-5);
+        // Only the first corner is shared; (1, 1, 0) stays split by its u
+        assert_eq!(welded.vertices.len(), 5);
+        assert_eq!(welded.triangle_count(), 2);
+
+        // Nearly equal corners within epsilon do merge
+        let mut close = mesh.clone();
+        close.vertices[4].uv.x = 1.0 + 1e-7;
+        assert_eq!(close.weld(1e-5).vertices.len(), 4);
+    }
+}
@@ -0,0 +1,727 @@
+//! A compact binary cache of baked meshes, so a model loads with one read and its bytes go
+//! straight into GPU buffers without parsing or simplifying anything at startup.
+//!
+//! Everything is little-endian. A 32 byte header is followed by the payload:
+//!
+//! ```text
+//! header:  magic "LOGLMESH", version u32, reserved u32 (0),
+//!          payload length u64, FNV-1a 64 checksum of the payload u64
+//! payload: vertex layout: stride u32, attribute count u32,
+//!              then per attribute location, component type (GL enum), kind, count, offset (u32)
+//!          submesh count u32, then per submesh:
+//!              name length u32 and UTF-8 bytes, has bounds u32, bounds min and max (6 f32),
+//!              bounding sphere center and radius (4 f32), level count u32, then per level:
+//!                  error f32, vertex offset u64, vertex count u32,
+//!                  index offset u64, index count u32, index size u32 (2 or 4)
+//!          vertex data length u64, index data length u64,
+//!          vertex data and index data, each starting on a 4 byte boundary of the file
+//! ```
+//!
+//! Vertex data is MeshVertex records exactly as they sit in memory and offsets are in bytes
+//! from the start of each block, so a level's vertices and indices are plain slices of the file.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use glad_gl::gl::{self, GLenum, GLint};
+
+use crate::{
+    lod::{GpuLodChain, LodChain},
+    mesh::{Aabb, GpuMesh, Indices, Mesh, MeshVertex},
+    vector::{Vector2, Vector3, Vector4},
+    vertex_layout::{AttributeKind, ComponentType, Vertex, VertexLayout},
+};
+
+pub const MESH_CACHE_MAGIC: &[u8; 8] = b"LOGLMESH";
+/// Bumped whenever the layout of the file changes
+pub const MESH_CACHE_VERSION: u32 = 1;
+pub const MESH_CACHE_EXTENSION: &str = "meshcache";
+
+const HEADER_SIZE: usize = 32;
+
+#[derive(Debug)]
+pub enum MeshCacheError {
+    Io { path: PathBuf, error: io::Error },
+    Invalid(String),
+    Version { found: u32, expected: u32 },
+    Checksum,
+}
+
+impl fmt::Display for MeshCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshCacheError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            MeshCacheError::Invalid(message) => write!(f, "Invalid mesh cache: {}", message),
+            MeshCacheError::Version { found, expected } => write!(
+                f,
+                "Mesh cache has version {} but version {} is expected, bake it again",
+                found, expected
+            ),
+            MeshCacheError::Checksum => {
+                write!(f, "Mesh cache checksum does not match, the file is corrupt")
+            }
+        }
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, MeshCacheError> {
+    Err(MeshCacheError::Invalid(message))
+}
+
+/// 64-bit FNV-1a
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn attribute_kind_code(kind: AttributeKind) -> u32 {
+    match kind {
+        AttributeKind::Float => 0,
+        AttributeKind::Normalized => 1,
+        AttributeKind::Integer => 2,
+    }
+}
+
+fn attribute_kind_from_code(code: u32) -> Option<AttributeKind> {
+    match code {
+        0 => Some(AttributeKind::Float),
+        1 => Some(AttributeKind::Normalized),
+        2 => Some(AttributeKind::Integer),
+        _ => None,
+    }
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_f32s(bytes: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn encode_layout(bytes: &mut Vec<u8>, layout: &VertexLayout) {
+    push_u32(bytes, layout.stride() as u32);
+    push_u32(bytes, layout.attributes().len() as u32);
+    for attribute in layout.attributes() {
+        push_u32(bytes, attribute.location);
+        push_u32(bytes, attribute.component_type.gl_enum());
+        push_u32(bytes, attribute_kind_code(attribute.kind));
+        push_u32(bytes, attribute.count as u32);
+        push_u32(bytes, attribute.offset as u32);
+    }
+}
+
+fn align(bytes: &mut Vec<u8>, start: usize) {
+    while !(start + bytes.len()).is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+/// Writes each named chain as a submesh with all of its levels
+pub fn write_mesh_cache(
+    writer: &mut impl Write,
+    submeshes: &[(String, &LodChain)],
+) -> io::Result<()> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut payload = Vec::new();
+    encode_layout(&mut payload, &MeshVertex::layout());
+
+    push_u32(&mut payload, submeshes.len() as u32);
+    for (name, chain) in submeshes {
+        push_u32(&mut payload, name.len() as u32);
+        payload.extend_from_slice(name.as_bytes());
+        match chain.levels.first().and_then(|level| level.mesh.bounds()) {
+            Some(bounds) => {
+                push_u32(&mut payload, 1);
+                push_f32s(
+                    &mut payload,
+                    &[
+                        bounds.min.x,
+                        bounds.min.y,
+                        bounds.min.z,
+                        bounds.max.x,
+                        bounds.max.y,
+                        bounds.max.z,
+                    ],
+                );
+            }
+            None => {
+                push_u32(&mut payload, 0);
+                push_f32s(&mut payload, &[0.0; 6]);
+            }
+        }
+        push_f32s(
+            &mut payload,
+            &[chain.center.x, chain.center.y, chain.center.z, chain.radius],
+        );
+
+        push_u32(&mut payload, chain.levels.len() as u32);
+        for level in &chain.levels {
+            let mesh = &level.mesh;
+            push_f32s(&mut payload, &[level.error]);
+            push_u64(&mut payload, vertices.len() as u64);
+            push_u32(&mut payload, mesh.vertices.len() as u32);
+            for vertex in &mesh.vertices {
+                let (position, normal, uv, tangent) =
+                    (vertex.position, vertex.normal, vertex.uv, vertex.tangent);
+                push_f32s(
+                    &mut vertices,
+                    &[
+                        position.x, position.y, position.z, normal.x, normal.y, normal.z, uv.x,
+                        uv.y, tangent.x, tangent.y, tangent.z, tangent.w,
+                    ],
+                );
+            }
+
+            // Every block starts 4 byte aligned, so u32 indices stay aligned after u16 ones
+            align(&mut indices, 0);
+            push_u64(&mut payload, indices.len() as u64);
+            push_u32(&mut payload, mesh.indices.len() as u32);
+            match &mesh.indices {
+                Indices::U16(values) => {
+                    push_u32(&mut payload, 2);
+                    for value in values {
+                        indices.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                Indices::U32(values) => {
+                    push_u32(&mut payload, 4);
+                    for value in values {
+                        indices.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    push_u64(&mut payload, vertices.len() as u64);
+    push_u64(&mut payload, indices.len() as u64);
+    align(&mut payload, HEADER_SIZE);
+    payload.extend_from_slice(&vertices);
+    align(&mut payload, HEADER_SIZE);
+    payload.extend_from_slice(&indices);
+
+    writer.write_all(MESH_CACHE_MAGIC)?;
+    writer.write_all(&MESH_CACHE_VERSION.to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+    writer.write_all(&checksum(&payload).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+pub fn save_mesh_cache(path: &Path, submeshes: &[(String, &LodChain)]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_mesh_cache(&mut writer, submeshes)?;
+    writer.flush()
+}
+
+/// One level of a submesh: where its vertices and indices are in the file
+#[derive(Clone, Debug)]
+pub struct CachedLevel {
+    pub error: f32,
+    pub vertices: Range<usize>,
+    pub indices: Range<usize>,
+    /// UNSIGNED_SHORT or UNSIGNED_INT
+    pub index_type: GLenum,
+    pub index_count: usize,
+}
+
+impl CachedLevel {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / size_of::<MeshVertex>()
+    }
+}
+
+#[derive(Clone)]
+pub struct CachedSubmesh {
+    pub name: String,
+    pub bounds: Option<Aabb>,
+    pub center: Vector3,
+    pub radius: f32,
+    pub levels: Vec<CachedLevel>,
+}
+
+/// A loaded cache: the whole file and the directory of what is in it
+pub struct MeshCache {
+    bytes: Vec<u8>,
+    pub submeshes: Vec<CachedSubmesh>,
+}
+
+impl MeshCache {
+    /// The vertices of a level exactly as the GPU wants them
+    pub fn vertex_bytes(&self, level: &CachedLevel) -> &[u8] {
+        &self.bytes[level.vertices.clone()]
+    }
+
+    /// The indices of a level exactly as the GPU wants them
+    pub fn index_bytes(&self, level: &CachedLevel) -> &[u8] {
+        &self.bytes[level.indices.clone()]
+    }
+
+    /// Decodes a level back into a mesh on the CPU
+    pub fn level_mesh(&self, level: &CachedLevel) -> Mesh {
+        let floats: Vec<f32> = self
+            .vertex_bytes(level)
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        let vertices = floats
+            .chunks_exact(12)
+            .map(|f| MeshVertex {
+                position: Vector3::new(f[0], f[1], f[2]),
+                normal: Vector3::new(f[3], f[4], f[5]),
+                uv: Vector2::new(f[6], f[7]),
+                tangent: Vector4::new(f[8], f[9], f[10], f[11]),
+            })
+            .collect();
+        let bytes = self.index_bytes(level);
+        let indices = if level.index_type == gl::UNSIGNED_SHORT {
+            Indices::U16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                    .collect(),
+            )
+        } else {
+            Indices::U32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            )
+        };
+        Mesh { vertices, indices }
+    }
+
+    /// Uploads every level of every submesh straight from the file's bytes
+    pub fn upload(&self) -> Vec<GpuLodChain> {
+        self.submeshes
+            .iter()
+            .map(|submesh| GpuLodChain {
+                levels: submesh
+                    .levels
+                    .iter()
+                    .map(|level| {
+                        GpuMesh::from_raw(
+                            self.vertex_bytes(level),
+                            self.index_bytes(level),
+                            level.index_type,
+                            level.index_count,
+                        )
+                    })
+                    .collect(),
+                errors: submesh.levels.iter().map(|level| level.error).collect(),
+                center: submesh.center,
+                radius: submesh.radius,
+            })
+            .collect()
+    }
+}
+
+pub fn load_mesh_cache(path: &Path) -> Result<MeshCache, MeshCacheError> {
+    let bytes = fs::read(path).map_err(|error| MeshCacheError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    parse_mesh_cache(bytes)
+}
+
+/// Reads little-endian values from the payload, failing at its end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize, what: &str) -> Result<&'a [u8], MeshCacheError> {
+        match self
+            .bytes
+            .get(self.position..self.position.saturating_add(length))
+        {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            }
+            None => invalid(format!("file ends inside {}", what)),
+        }
+    }
+
+    fn u32(&mut self, what: &str) -> Result<u32, MeshCacheError> {
+        let bytes = self.take(4, what)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self, what: &str) -> Result<u64, MeshCacheError> {
+        let bytes = self.take(8, what)?;
+        let mut value = [0; 8];
+        value.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    fn f32(&mut self, what: &str) -> Result<f32, MeshCacheError> {
+        Ok(f32::from_bits(self.u32(what)?))
+    }
+
+    fn vector3(&mut self, what: &str) -> Result<Vector3, MeshCacheError> {
+        Ok(Vector3::new(
+            self.f32(what)?,
+            self.f32(what)?,
+            self.f32(what)?,
+        ))
+    }
+
+    fn skip_to_alignment(&mut self) {
+        while !(HEADER_SIZE + self.position).is_multiple_of(4) {
+            self.position += 1;
+        }
+    }
+}
+
+fn decode_layout(reader: &mut Reader) -> Result<VertexLayout, MeshCacheError> {
+    let mut layout = VertexLayout::with_stride(reader.u32("vertex layout")? as usize);
+    let attribute_count = reader.u32("vertex layout")?;
+    for _ in 0..attribute_count {
+        let location = reader.u32("vertex attribute")?;
+        let component_type = reader.u32("vertex attribute")?;
+        let Some(component_type) = ComponentType::from_gl_enum(component_type) else {
+            return invalid(format!(
+                "attribute {} has unknown component type {:#x}",
+                location, component_type
+            ));
+        };
+        let Some(kind) = attribute_kind_from_code(reader.u32("vertex attribute")?) else {
+            return invalid(format!("attribute {} has an unknown kind", location));
+        };
+        let count = reader.u32("vertex attribute")?;
+        let offset = reader.u32("vertex attribute")?;
+        if !(1..=4).contains(&count) || layout.attribute_at_location(location).is_some() {
+            return invalid(format!("attribute {} is malformed", location));
+        }
+        layout = layout.attribute_at(
+            location,
+            component_type,
+            count as GLint,
+            kind,
+            offset as usize,
+        );
+    }
+    Ok(layout)
+}
+
+/// Describes the first way in which a cached layout differs from the expected one
+fn layout_difference(cached: &VertexLayout, expected: &VertexLayout) -> Option<String> {
+    for attribute in expected.attributes() {
+        match cached.attribute_at_location(attribute.location) {
+            None => {
+                return Some(format!(
+                    "the cache has no vertex attribute at location {}",
+                    attribute.location
+                ));
+            }
+            Some(cached) if cached != attribute => {
+                return Some(format!(
+                    "vertex attribute {} is {:?} x{} ({:?}) at offset {} in the cache \
+                     but {:?} x{} ({:?}) at offset {} in MeshVertex",
+                    attribute.location,
+                    cached.component_type,
+                    cached.count,
+                    cached.kind,
+                    cached.offset,
+                    attribute.component_type,
+                    attribute.count,
+                    attribute.kind,
+                    attribute.offset
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+    if cached != expected {
+        return Some(format!(
+            "the cached vertex layout ({} attributes, stride {}) differs from MeshVertex \
+             ({} attributes, stride {})",
+            cached.attributes().len(),
+            cached.stride(),
+            expected.attributes().len(),
+            expected.stride()
+        ));
+    }
+    None
+}
+
+/// Checks the header, checksum and vertex layout and reads the directory of a whole file.
+/// The vertex and index data stay in the returned cache untouched.
+pub fn parse_mesh_cache(bytes: Vec<u8>) -> Result<MeshCache, MeshCacheError> {
+    if cfg!(target_endian = "big") {
+        return invalid("mesh caches can only be loaded on little-endian machines".to_string());
+    }
+    if bytes.len() < HEADER_SIZE {
+        return invalid(format!("{} bytes is too short for the header", bytes.len()));
+    }
+    if &bytes[0..8] != MESH_CACHE_MAGIC {
+        return invalid("missing LOGLMESH magic".to_string());
+    }
+    let mut header = Reader {
+        bytes: &bytes[8..HEADER_SIZE],
+        position: 0,
+    };
+    let version = header.u32("header")?;
+    if version != MESH_CACHE_VERSION {
+        return Err(MeshCacheError::Version {
+            found: version,
+            expected: MESH_CACHE_VERSION,
+        });
+    }
+    header.u32("header")?;
+    let length = header.u64("header")?;
+    let expected_checksum = header.u64("header")?;
+    let payload = &bytes[HEADER_SIZE..];
+    if payload.len() as u64 != length {
+        return invalid(format!(
+            "header promises {} bytes of payload but there are {}",
+            length,
+            payload.len()
+        ));
+    }
+    if checksum(payload) != expected_checksum {
+        return Err(MeshCacheError::Checksum);
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    let layout = decode_layout(&mut reader)?;
+    if let Some(problem) = layout_difference(&layout, &MeshVertex::layout()) {
+        return invalid(format!("{}, bake it again", problem));
+    }
+
+    let submesh_count = reader.u32("submesh count")?;
+    let mut submeshes = Vec::new();
+    for _ in 0..submesh_count {
+        let name_length = reader.u32("submesh name")? as usize;
+        let name = String::from_utf8(reader.take(name_length, "submesh name")?.to_vec())
+            .map_err(|_| MeshCacheError::Invalid("submesh name is not UTF-8".to_string()))?;
+        let has_bounds = reader.u32("bounds")? != 0;
+        let min = reader.vector3("bounds")?;
+        let max = reader.vector3("bounds")?;
+        let center = reader.vector3("bounding sphere")?;
+        let radius = reader.f32("bounding sphere")?;
+
+        let level_count = reader.u32("level count")?;
+        let mut levels = Vec::new();
+        for _ in 0..level_count {
+            let error = reader.f32("level")?;
+            let vertex_offset = reader.u64("level")? as usize;
+            let vertex_count = reader.u32("level")? as usize;
+            let index_offset = reader.u64("level")? as usize;
+            let index_count = reader.u32("level")? as usize;
+            let index_type = match reader.u32("level")? {
+                2 => gl::UNSIGNED_SHORT,
+                4 => gl::UNSIGNED_INT,
+                size => return invalid(format!("{} byte indices in {}", size, name)),
+            };
+            if !index_count.is_multiple_of(3) {
+                return invalid(format!("{} has a partial triangle", name));
+            }
+            let index_size = if index_type == gl::UNSIGNED_SHORT {
+                2
+            } else {
+                4
+            };
+            levels.push(CachedLevel {
+                error,
+                vertices: vertex_offset
+                    ..vertex_offset.saturating_add(vertex_count * size_of::<MeshVertex>()),
+                indices: index_offset..index_offset.saturating_add(index_count * index_size),
+                index_type,
+                index_count,
+            });
+        }
+        submeshes.push(CachedSubmesh {
+            name,
+            bounds: has_bounds.then_some(Aabb { min, max }),
+            center,
+            radius,
+            levels,
+        });
+    }
+
+    let vertex_length = reader.u64("data lengths")? as usize;
+    let index_length = reader.u64("data lengths")? as usize;
+    reader.skip_to_alignment();
+    let vertex_start = HEADER_SIZE + reader.position;
+    reader.take(vertex_length, "vertex data")?;
+    reader.skip_to_alignment();
+    let index_start = HEADER_SIZE + reader.position;
+    reader.take(index_length, "index data")?;
+
+    // Make the ranges absolute, and check them so a level can be handed to the GPU unchecked
+    for submesh in &mut submeshes {
+        for level in &mut submesh.levels {
+            if level.vertices.end > vertex_length || level.indices.end > index_length {
+                return invalid(format!("a level of {} lies outside the data", submesh.name));
+            }
+            level.vertices = vertex_start + level.vertices.start..vertex_start + level.vertices.end;
+            level.indices = index_start + level.indices.start..index_start + level.indices.end;
+        }
+    }
+    let cache = MeshCache { bytes, submeshes };
+    for submesh in &cache.submeshes {
+        for level in &submesh.levels {
+            let vertex_count = level.vertex_count();
+            let indices = cache.index_bytes(level);
+            let in_range = if level.index_type == gl::UNSIGNED_SHORT {
+                indices
+                    .chunks_exact(2)
+                    .all(|b| (u16::from_le_bytes([b[0], b[1]]) as usize) < vertex_count)
+            } else {
+                indices
+                    .chunks_exact(4)
+                    .all(|b| (u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize) < vertex_count)
+            };
+            if !in_range {
+                return invalid(format!("{} has an index out of range", submesh.name));
+            }
+        }
+    }
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives;
+
+    fn baked_cube() -> (LodChain, Vec<u8>) {
+        let chain = LodChain::build(primitives::cube(1.0, 4), 3, 0.5);
+        let mut bytes = Vec::new();
+        write_mesh_cache(&mut bytes, &[("cube".to_string(), &chain)]).unwrap();
+        (chain, bytes)
+    }
+
+    fn store_checksum(bytes: &mut [u8]) {
+        let sum = checksum(&bytes[HEADER_SIZE..]);
+        bytes[24..32].copy_from_slice(&sum.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        let (chain, bytes) = baked_cube();
+        assert!(chain.levels.len() > 1);
+        let cache = parse_mesh_cache(bytes).unwrap();
+        assert_eq!(cache.submeshes.len(), 1);
+        let submesh = &cache.submeshes[0];
+        assert_eq!(submesh.name, "cube");
+        assert_eq!(submesh.radius, chain.radius);
+        let (stored, original) = (
+            submesh.bounds.unwrap(),
+            chain.levels[0].mesh.bounds().unwrap(),
+        );
+        assert_eq!(
+            [stored.min.x, stored.min.y, stored.min.z],
+            [original.min.x, original.min.y, original.min.z]
+        );
+        assert_eq!(
+            [stored.max.x, stored.max.y, stored.max.z],
+            [original.max.x, original.max.y, original.max.z]
+        );
+
+        assert_eq!(submesh.levels.len(), chain.levels.len());
+        for (level, original) in submesh.levels.iter().zip(&chain.levels) {
+            assert_eq!(level.error, original.error);
+            let mesh = cache.level_mesh(level);
+            assert_eq!(mesh.indices, original.mesh.indices);
+            assert_eq!(mesh.vertices.len(), original.mesh.vertices.len());
+            for (vertex, expected) in mesh.vertices.iter().zip(&original.mesh.vertices) {
+                assert!(vertex.approx_eq(expected, 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_payload_fails_the_checksum() {
+        let (_, bytes) = baked_cube();
+        for position in [HEADER_SIZE, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupt = bytes.clone();
+            corrupt[position] ^= 0x01;
+            assert!(matches!(
+                parse_mesh_cache(corrupt),
+                Err(MeshCacheError::Checksum)
+            ));
+        }
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let (_, mut bytes) = baked_cube();
+        bytes[8..12].copy_from_slice(&(MESH_CACHE_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            parse_mesh_cache(bytes),
+            Err(MeshCacheError::Version { found, expected })
+                if found == MESH_CACHE_VERSION + 1 && expected == MESH_CACHE_VERSION
+        ));
+    }
+
+    #[test]
+    fn truncated_files_are_invalid() {
+        let (_, bytes) = baked_cube();
+        for length in [0, 8, HEADER_SIZE - 1, HEADER_SIZE, bytes.len() - 1] {
+            assert!(matches!(
+                parse_mesh_cache(bytes[..length].to_vec()),
+                Err(MeshCacheError::Invalid(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn other_vertex_layouts_are_described() {
+        let (_, mut bytes) = baked_cube();
+        // The component type of the first attribute follows the stride, count and location
+        let position = HEADER_SIZE + 12;
+        bytes[position..position + 4].copy_from_slice(&gl::SHORT.to_le_bytes());
+        store_checksum(&mut bytes);
+        match parse_mesh_cache(bytes) {
+            Err(MeshCacheError::Invalid(message)) => {
+                assert!(
+                    message.starts_with("vertex attribute 0 is Short x3"),
+                    "{}",
+                    message
+                )
+            }
+            _ => panic!("a different layout was accepted"),
+        }
+    }
+
+    #[test]
+    fn out_of_range_indices_are_invalid() {
+        let (_, bytes) = baked_cube();
+        let cache = parse_mesh_cache(bytes.clone()).unwrap();
+        let level = &cache.submeshes[0].levels[0];
+        assert_eq!(level.index_type, gl::UNSIGNED_SHORT);
+        let position = level.indices.start;
+
+        let mut bytes = bytes;
+        bytes[position..position + 2].copy_from_slice(&(level.vertex_count() as u16).to_le_bytes());
+        store_checksum(&mut bytes);
+        assert!(matches!(
+            parse_mesh_cache(bytes),
+            Err(MeshCacheError::Invalid(message)) if message.contains("out of range")
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+#version 330 core
+layout (triangles) in;
+layout (line_strip, max_vertices = 6) out;
+
+in vec3 worldNormal[];
+
+uniform float normalLength;
+
+layout (std140) uniform FrameData
+{
+    mat4 view;
+    mat4 projection;
+    vec3 cameraPosition;
+    vec3 lightPosition;
+    vec3 lightColor;
+};
+
+void main()
+{
+   // One line per vertex, from the vertex along its normal
+   for (int i = 0; i < 3; i++)
+   {
+      vec4 start = gl_in[i].gl_Position;
+      gl_Position = projection * view * start;
+      EmitVertex();
+      gl_Position = projection * view * (start + vec4(normalLength * worldNormal[i], 0.0));
+      EmitVertex();
+      EndPrimitive();
+   }
+}
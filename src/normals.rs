@@ -0,0 +1,357 @@
+//! Normals and tangents computed from a mesh's triangles, for models that come without them.
+//! Smooth normals average the faces around each position, flat normals give every face its own
+//! vertices and a crease angle decides per corner which neighbouring faces are smoothed over.
+//! Tangents follow MikkTSpace, so normal maps baked by other tools are decoded the same way.
+
+use std::collections::HashMap;
+
+use crate::{
+    mesh::{Mesh, MeshVertex},
+    vector::{Vector3, Vector4, calc_cross_product},
+};
+
+/// How much each face counts towards the normal of a vertex it touches
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalWeighting {
+    /// Larger faces count more. Cheap, but long thin triangles can tilt the normal.
+    Area,
+    /// Faces count by the angle they make at the vertex, so the result does not depend on how
+    /// the faces around it are split into triangles
+    Angle,
+}
+
+/// Makes the tangent perpendicular to the normal and picks the handedness so that
+/// cross(normal, tangent) * w points along the bitangent
+pub fn orthogonalize(normal: &Vector3, tangent: &Vector3, bitangent: &Vector3) -> Option<Vector4> {
+    let tangent = *tangent - Vector3::dot_product(normal, tangent) * *normal;
+    if tangent.magnitude() < 1e-6 {
+        return None;
+    }
+    let tangent = Vector3::calc_normalized_vector(&tangent);
+    let handedness = if Vector3::dot_product(&calc_cross_product(normal, &tangent), bitangent) < 0.0
+    {
+        -1.0
+    } else {
+        1.0
+    };
+    Some(Vector4::new(tangent.x, tangent.y, tangent.z, handedness))
+}
+
+/// Any unit vector perpendicular to the normal, for points where the texture has no direction
+pub fn fallback_tangent(normal: &Vector3) -> Vector4 {
+    let axis = if normal.x.abs() < 0.9 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+    orthogonalize(normal, &axis, &calc_cross_product(normal, &axis))
+        .unwrap_or(Vector4::new(1.0, 0.0, 0.0, 1.0))
+}
+
+fn normalized_or_zero(v: Vector3) -> Vector3 {
+    if v.magnitude() > 0.0 {
+        Vector3::calc_normalized_vector(&v)
+    } else {
+        v
+    }
+}
+
+/// Positions are compared bit for bit, so corners split for texture seams still count as one
+pub fn position_key(position: &Vector3) -> [u32; 3] {
+    // Adding 0.0 turns -0.0 into 0.0
+    [
+        (position.x + 0.0).to_bits(),
+        (position.y + 0.0).to_bits(),
+        (position.z + 0.0).to_bits(),
+    ]
+}
+
+/// The angle between two edges leaving a corner, in radians
+fn angle_between(a: &Vector3, b: &Vector3) -> f32 {
+    let lengths = a.magnitude() * b.magnitude();
+    if lengths <= 0.0 {
+        return 0.0;
+    }
+    (Vector3::dot_product(a, b) / lengths)
+        .clamp(-1.0, 1.0)
+        .acos()
+}
+
+/// The unit normal of every triangle and how much it counts at each of its corners.
+/// Triangles without area have a zero normal and weight.
+fn face_normals(mesh: &Mesh, weighting: NormalWeighting) -> Vec<(Vector3, [f32; 3])> {
+    mesh.triangles()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| mesh.vertices[index as usize].position);
+            let face = calc_cross_product(&(b - a), &(c - a));
+            let weights = match weighting {
+                // Twice the area, which does not matter once the sum is normalized
+                NormalWeighting::Area => [face.magnitude(); 3],
+                NormalWeighting::Angle => [
+                    angle_between(&(b - a), &(c - a)),
+                    angle_between(&(c - b), &(a - b)),
+                    angle_between(&(a - c), &(b - c)),
+                ],
+            };
+            (normalized_or_zero(face), weights)
+        })
+        .collect()
+}
+
+/// Replaces every normal with the weighted average of the faces around its position. Vertices
+/// that share a position get the same normal, so the surface is smooth across texture seams.
+pub fn compute_smooth_normals(mesh: &mut Mesh, weighting: NormalWeighting) {
+    let faces = face_normals(mesh, weighting);
+    let mut sums: HashMap<[u32; 3], Vector3> = HashMap::new();
+    for (triangle, (normal, weights)) in mesh.triangles().zip(&faces) {
+        for (index, weight) in triangle.iter().zip(weights) {
+            let key = position_key(&mesh.vertices[*index as usize].position);
+            let sum = sums.entry(key).or_default();
+            *sum = *sum + *weight * *normal;
+        }
+    }
+    for vertex in &mut mesh.vertices {
+        if let Some(sum) = sums.get(&position_key(&vertex.position)) {
+            vertex.normal = normalized_or_zero(*sum);
+        }
+    }
+}
+
+/// Gives every triangle its own vertices with the face normal. Corners that end up identical,
+/// such as those inside a flat face made of several triangles, are shared again.
+pub fn flat_shaded(mesh: &Mesh) -> Mesh {
+    let faces = face_normals(mesh, NormalWeighting::Area);
+    let mut vertices = Vec::with_capacity(mesh.indices.len());
+    for (triangle, (normal, _)) in mesh.triangles().zip(&faces) {
+        for index in triangle {
+            let mut vertex = mesh.vertices[index as usize];
+            vertex.normal = *normal;
+            vertices.push(vertex);
+        }
+    }
+    Mesh::from_triangle_list(vertices).weld(0.0)
+}
+
+/// Smooths each corner only over the faces around its position whose normals are within
+/// crease_angle radians of its own face, so hard edges stay sharp. Vertices are split where the
+/// faces that share them end up with different normals. An angle of pi smooths everything and
+/// an angle of zero gives flat shading.
+pub fn split_creases(mesh: &Mesh, crease_angle: f32, weighting: NormalWeighting) -> Mesh {
+    let faces = face_normals(mesh, weighting);
+    let triangles: Vec<[u32; 3]> = mesh.triangles().collect();
+    let mut corners_at: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+    for (face, triangle) in triangles.iter().enumerate() {
+        for (corner, index) in triangle.iter().enumerate() {
+            let key = position_key(&mesh.vertices[*index as usize].position);
+            corners_at.entry(key).or_default().push((face, corner));
+        }
+    }
+
+    let threshold = crease_angle.cos();
+    let mut vertices: Vec<MeshVertex> = Vec::new();
+    // Output vertex for each (input vertex, normal) pair already made
+    let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for (face, triangle) in triangles.iter().enumerate() {
+        let face_normal = &faces[face].0;
+        for index in triangle {
+            let mut vertex = mesh.vertices[*index as usize];
+            let mut sum = Vector3::default();
+            if face_normal.magnitude() > 0.0 {
+                for (other, corner) in &corners_at[&position_key(&vertex.position)] {
+                    let (other_normal, weights) = &faces[*other];
+                    if *other == face
+                        || Vector3::dot_product(face_normal, other_normal) >= threshold
+                    {
+                        sum = sum + weights[*corner] * *other_normal;
+                    }
+                }
+            }
+            // Triangles without area keep the normal the vertex had
+            if sum.magnitude() > 0.0 {
+                vertex.normal = Vector3::calc_normalized_vector(&sum);
+            }
+
+            let key = (*index, position_key(&vertex.normal));
+            let output = *split.entry(key).or_insert_with(|| {
+                vertices.push(vertex);
+                vertices.len() as u32 - 1
+            });
+            indices.push(output);
+        }
+    }
+    Mesh::new(vertices, indices)
+}
+
+/// The direction of increasing u on a triangle and whether the texture keeps its
+/// orientation there. None if the texture coordinates have no area.
+fn texture_direction(mesh: &Mesh, triangle: [u32; 3]) -> Option<(Vector3, bool)> {
+    let [a, b, c] = triangle.map(|index| &mesh.vertices[index as usize]);
+    let (edge_1, edge_2) = (b.position - a.position, c.position - a.position);
+    let (delta_1, delta_2) = (b.uv - a.uv, c.uv - a.uv);
+    let signed_area = delta_1.x * delta_2.y - delta_1.y * delta_2.x;
+    if signed_area.abs() < 1e-12 {
+        return None;
+    }
+    let tangent = (1.0 / signed_area) * (delta_2.y * edge_1 - delta_1.y * edge_2);
+    if tangent.magnitude() <= 0.0 {
+        return None;
+    }
+    Some((tangent, signed_area > 0.0))
+}
+
+/// Computes tangents the way MikkTSpace does: each face's u direction is made perpendicular to
+/// the vertex normal and averaged weighted by the corner angle, and the handedness in w is +1
+/// where the texture keeps its orientation and -1 where it is mirrored. A vertex shared by
+/// mirrored and unmirrored faces is split in two. Normals must be set first.
+pub fn compute_tangents(mesh: &mut Mesh) {
+    let triangles: Vec<[u32; 3]> = mesh.triangles().collect();
+    let directions: Vec<_> = triangles
+        .iter()
+        .map(|triangle| texture_direction(mesh, *triangle))
+        .collect();
+
+    // Tangent sums per (vertex, handedness), in the order the groups are first seen
+    let mut groups: HashMap<(u32, bool), usize> = HashMap::new();
+    let mut sums: Vec<(u32, bool, Vector3)> = Vec::new();
+    for (triangle, direction) in triangles.iter().zip(&directions) {
+        let Some((tangent, orientation)) = direction else {
+            continue;
+        };
+        let positions = triangle.map(|index| mesh.vertices[index as usize].position);
+        for corner in 0..3 {
+            let index = triangle[corner];
+            let normal = &mesh.vertices[index as usize].normal;
+            let project =
+                |v: Vector3| normalized_or_zero(v - Vector3::dot_product(normal, &v) * *normal);
+            let projected = project(*tangent);
+            let weight = angle_between(
+                &project(positions[(corner + 1) % 3] - positions[corner]),
+                &project(positions[(corner + 2) % 3] - positions[corner]),
+            );
+            let group = *groups.entry((index, *orientation)).or_insert_with(|| {
+                sums.push((index, *orientation, Vector3::default()));
+                sums.len() - 1
+            });
+            sums[group].2 = sums[group].2 + weight * projected;
+        }
+    }
+
+    // The first group of a vertex keeps it, later ones get a copy
+    let mut group_vertex = Vec::with_capacity(sums.len());
+    let mut claimed = vec![false; mesh.vertices.len()];
+    for (index, orientation, sum) in &sums {
+        let mut vertex = mesh.vertices[*index as usize];
+        let tangent = if sum.magnitude() > 0.0 {
+            Vector3::calc_normalized_vector(sum)
+        } else {
+            Vector3::from_vector4(&fallback_tangent(&vertex.normal))
+        };
+        let handedness = if *orientation { 1.0 } else { -1.0 };
+        vertex.tangent = Vector4::new(tangent.x, tangent.y, tangent.z, handedness);
+        if claimed[*index as usize] {
+            group_vertex.push(mesh.vertices.len() as u32);
+            mesh.vertices.push(vertex);
+        } else {
+            claimed[*index as usize] = true;
+            group_vertex.push(*index);
+            mesh.vertices[*index as usize] = vertex;
+        }
+    }
+    // Vertices only used by triangles without texture area
+    for (index, claimed) in claimed.iter().enumerate() {
+        if !claimed {
+            let vertex = &mut mesh.vertices[index];
+            vertex.tangent = fallback_tangent(&vertex.normal);
+        }
+    }
+
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for (triangle, direction) in triangles.iter().zip(&directions) {
+        for index in triangle {
+            let group = match direction {
+                Some((_, orientation)) => groups.get(&(*index, *orientation)),
+                // Follow whichever group the vertex already has
+                None => groups
+                    .get(&(*index, true))
+                    .or_else(|| groups.get(&(*index, false))),
+            };
+            indices.push(group.map_or(*index, |group| group_vertex[*group]));
+        }
+    }
+    *mesh = Mesh::new(std::mem::take(&mut mesh.vertices), indices);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{cube, uv_sphere};
+
+    #[test]
+    fn smooth_sphere_normals_point_away_from_the_centre() {
+        for weighting in [NormalWeighting::Area, NormalWeighting::Angle] {
+            let mut sphere = uv_sphere(2.0, 32, 16);
+            for vertex in &mut sphere.vertices {
+                vertex.normal = Vector3::default();
+            }
+            compute_smooth_normals(&mut sphere, weighting);
+            for vertex in &sphere.vertices {
+                let expected = Vector3::calc_normalized_vector(&vertex.position);
+                assert!(
+                    Vector3::dot_product(&vertex.normal, &expected) > 0.999,
+                    "{:?} normal at ({}, {}, {})",
+                    weighting,
+                    vertex.position.x,
+                    vertex.position.y,
+                    vertex.position.z
+                );
+            }
+        }
+    }
+
+    /// Every normal is one of the six axes, the one the vertex's face lies across
+    fn assert_axis_normals(mesh: &Mesh) {
+        for vertex in &mesh.vertices {
+            let normal = vertex.normal;
+            let components = [normal.x.abs(), normal.y.abs(), normal.z.abs()];
+            assert_eq!(
+                components
+                    .iter()
+                    .filter(|c| (**c - 1.0).abs() < 1e-6)
+                    .count(),
+                1
+            );
+            assert_eq!(components.iter().filter(|c| **c < 1e-6).count(), 2);
+            // The cube has size 1, so each face is half a unit from the centre
+            assert!((Vector3::dot_product(&normal, &vertex.position) - 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn cube_edges_stay_hard() {
+        let crease_angle = 30f32.to_radians();
+        for subdivisions in [1, 3] {
+            let cube = cube(1.0, subdivisions);
+            assert_axis_normals(&flat_shaded(&cube));
+            for weighting in [NormalWeighting::Area, NormalWeighting::Angle] {
+                let split = split_creases(&cube, crease_angle, weighting);
+                assert_eq!(split.triangle_count(), cube.triangle_count());
+                assert_axis_normals(&split);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_are_unit_length_and_perpendicular_to_normals() {
+        let mut meshes = [uv_sphere(1.0, 16, 8), cube(1.0, 2)];
+        for mesh in &mut meshes {
+            compute_tangents(mesh);
+            for vertex in &mesh.vertices {
+                let tangent = Vector3::new(vertex.tangent.x, vertex.tangent.y, vertex.tangent.z);
+                assert!((tangent.magnitude() - 1.0).abs() < 1e-4);
+                assert!(Vector3::dot_product(&tangent, &vertex.normal).abs() < 1e-4);
+                assert!(vertex.tangent.w == 1.0 || vertex.tangent.w == -1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec3 aNormal;
+
+out vec3 worldNormal;
+
+uniform mat4 model;
+
+void main()
+{
+   // The geometry shader projects, so the position stays in world space here
+   gl_Position = model * vec4(aPos, 1.0);
+   worldNormal = normalize(mat3(model) * aNormal);
+}
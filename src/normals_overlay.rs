@@ -0,0 +1,58 @@
+//! Draws vertex normals as short lines, generated from each triangle by a geometry shader.
+
+use std::path::Path;
+
+use glad_gl::gl;
+
+use crate::{
+    gl_backend::with_backend,
+    matrix::Matrix4,
+    mesh::GpuMesh,
+    shader::{ShaderError, ShaderProgram, ShaderStage},
+};
+
+/// How far each line reaches from its vertex, in world units
+const NORMAL_LENGTH: f32 = 0.2;
+
+const FRAGMENT: &str = "#version 330 core
+out vec4 FragColor;
+
+void main()
+{
+   FragColor = vec4(1.0, 1.0, 0.0, 1.0);
+}
+";
+
+pub struct NormalsOverlay {
+    program: ShaderProgram,
+}
+
+impl NormalsOverlay {
+    pub fn new() -> Result<Self, ShaderError> {
+        let program = ShaderProgram::builder()
+            .vertex_file(Path::new("./src/normals.vs"))
+            .geometry_file(Path::new("./src/normals.gs"))
+            .stage_source(ShaderStage::Fragment, "normals.fs", FRAGMENT)
+            .build()?;
+        program.use_program();
+        program.set_float("normalLength", NORMAL_LENGTH);
+        Ok(Self { program })
+    }
+
+    /// The program reading FrameData, for attaching the frame's uniform buffer
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Draws the normals of the meshes placed by model. Leaves the overlay's program in use.
+    pub fn draw<'a>(&self, model: &Matrix4, meshes: impl IntoIterator<Item = &'a GpuMesh>) {
+        self.program.use_program();
+        self.program.set_mat4("model", model);
+        // Each line starts on the surface, at the depth the mesh already wrote there
+        with_backend(|backend| backend.depth_func(gl::LEQUAL));
+        for mesh in meshes {
+            mesh.draw();
+        }
+        with_backend(|backend| backend.depth_func(gl::LESS));
+    }
+}
@@ -0,0 +1,699 @@
+//! Wavefront OBJ models and their MTL material libraries.
+//! Faces may use any of the v, v/vt, v//vn and v/vt/vn forms with positive or negative indices.
+//! Polygons are split into triangles by ear clipping, so concave faces come out right too.
+//! Lines, points, smoothing groups and free-form geometry are ignored.
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mesh::{Mesh, MeshVertex},
+    vector::{Vector2, Vector3, calc_cross_product},
+};
+
+#[derive(Debug)]
+pub struct ObjParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ObjParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: io::Error },
+    Parse { path: PathBuf, error: ObjParseError },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            ObjError::Parse { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+/// A material from an MTL file. Texture paths are resolved relative to the MTL file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// Ka
+    pub ambient: [f32; 3],
+    /// Kd
+    pub diffuse: [f32; 3],
+    /// Ks
+    pub specular: [f32; 3],
+    /// Ns, the specular exponent
+    pub shininess: f32,
+    /// d, or 1 - Tr
+    pub opacity: f32,
+    pub ambient_map: Option<PathBuf>,
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    pub shininess_map: Option<PathBuf>,
+    pub opacity_map: Option<PathBuf>,
+    /// map_Bump, bump or norm
+    pub normal_map: Option<PathBuf>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: [0.0; 3],
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            opacity: 1.0,
+            ambient_map: None,
+            diffuse_map: None,
+            specular_map: None,
+            shininess_map: None,
+            opacity_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+/// The faces of one object or group that share a material
+pub struct ObjMesh {
+    /// Set by o, empty if the file has none
+    pub object: String,
+    /// Set by g, empty if the file has none
+    pub group: String,
+    /// Set by usemtl
+    pub material: Option<String>,
+    /// Normals are zero if the file does not have them
+    pub mesh: Mesh,
+}
+
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<Material>,
+    /// The file names given to mtllib, as written in the OBJ file
+    pub material_libraries: Vec<String>,
+}
+
+impl ObjModel {
+    pub fn material(&self, mesh: &ObjMesh) -> Option<&Material> {
+        let name = mesh.material.as_deref()?;
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+/// Reads an OBJ file and the MTL files it names. Material libraries that cannot be read are
+/// reported as warnings, since models are often shared without them.
+pub fn load_obj(path: &Path) -> Result<ObjModel, ObjError> {
+    let text = fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let mut model = parse_obj(&text).map_err(|error| ObjError::Parse {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    for library in &model.material_libraries {
+        match load_mtl(&directory.join(library)) {
+            Ok(materials) => model.materials.extend(materials),
+            Err(error) => eprintln!("Warning: {}", error),
+        }
+    }
+    Ok(model)
+}
+
+pub fn load_mtl(path: &Path) -> Result<Vec<Material>, ObjError> {
+    let text = fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    parse_mtl(&text, path.parent().unwrap_or(Path::new(""))).map_err(|error| ObjError::Parse {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+fn parse_float(token: Option<&str>, line: usize, what: &str) -> Result<f32, ObjParseError> {
+    let token = token.ok_or_else(|| ObjParseError {
+        line,
+        message: format!("missing {}", what),
+    })?;
+    token.parse().map_err(|_| ObjParseError {
+        line,
+        message: format!("invalid {} \"{}\"", what, token),
+    })
+}
+
+/// Turns a 1-based or negative OBJ index into a 0-based one
+fn resolve_index(
+    token: &str,
+    count: usize,
+    line: usize,
+    what: &str,
+) -> Result<usize, ObjParseError> {
+    let error = |message: String| ObjParseError { line, message };
+    let index: i64 = token
+        .parse()
+        .map_err(|_| error(format!("invalid {} index \"{}\"", what, token)))?;
+    let resolved = if index > 0 {
+        index - 1
+    } else if index < 0 {
+        count as i64 + index
+    } else {
+        return Err(error(format!("{} index 0 is not allowed", what)));
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(error(format!(
+            "{} index {} is out of range ({} defined)",
+            what, index, count
+        )));
+    }
+    Ok(resolved as usize)
+}
+
+/// The mesh being built from consecutive faces that share object, group and material
+struct MeshBuilder {
+    object: String,
+    group: String,
+    material: Option<String>,
+    vertices: Vec<MeshVertex>,
+    indices: Vec<u32>,
+    /// Vertex for each (position, texture coordinate, normal) combination already used
+    corners: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl MeshBuilder {
+    fn new(object: &str, group: &str, material: Option<String>) -> Self {
+        Self {
+            object: object.to_string(),
+            group: group.to_string(),
+            material,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            corners: HashMap::new(),
+        }
+    }
+
+    fn finish(self, meshes: &mut Vec<ObjMesh>) {
+        if self.indices.is_empty() {
+            return;
+        }
+        meshes.push(ObjMesh {
+            object: self.object,
+            group: self.group,
+            material: self.material,
+            mesh: Mesh::new(self.vertices, self.indices),
+        });
+    }
+}
+
+/// Parses the contents of an OBJ file. Material libraries are listed but not read.
+pub fn parse_obj(text: &str) -> Result<ObjModel, ObjParseError> {
+    let mut positions: Vec<Vector3> = Vec::new();
+    let mut uvs: Vec<Vector2> = Vec::new();
+    let mut normals: Vec<Vector3> = Vec::new();
+    let mut meshes = Vec::new();
+    let mut material_libraries = Vec::new();
+    let mut current = MeshBuilder::new("", "", None);
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let rest = line[keyword.len()..].trim();
+
+        match keyword {
+            "v" => {
+                let x = parse_float(tokens.next(), line_number, "x coordinate")?;
+                let y = parse_float(tokens.next(), line_number, "y coordinate")?;
+                let z = parse_float(tokens.next(), line_number, "z coordinate")?;
+                positions.push(Vector3::new(x, y, z));
+            }
+            "vt" => {
+                let u = parse_float(tokens.next(), line_number, "u coordinate")?;
+                let v = match tokens.next() {
+                    Some(token) => parse_float(Some(token), line_number, "v coordinate")?,
+                    None => 0.0,
+                };
+                uvs.push(Vector2::new(u, v));
+            }
+            "vn" => {
+                let x = parse_float(tokens.next(), line_number, "normal x")?;
+                let y = parse_float(tokens.next(), line_number, "normal y")?;
+                let z = parse_float(tokens.next(), line_number, "normal z")?;
+                normals.push(Vector3::new(x, y, z));
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for token in tokens {
+                    let mut parts = token.split('/');
+                    let position = resolve_index(
+                        parts.next().unwrap_or_default(),
+                        positions.len(),
+                        line_number,
+                        "vertex",
+                    )?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(part) => Some(resolve_index(
+                            part,
+                            uvs.len(),
+                            line_number,
+                            "texture coordinate",
+                        )?),
+                    };
+                    let normal = match parts.next() {
+                        Some("") | None => None,
+                        Some(part) => {
+                            Some(resolve_index(part, normals.len(), line_number, "normal")?)
+                        }
+                    };
+                    if parts.next().is_some() {
+                        return Err(ObjParseError {
+                            line: line_number,
+                            message: format!("invalid face vertex \"{}\"", token),
+                        });
+                    }
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(ObjParseError {
+                        line: line_number,
+                        message: format!("face has {} vertices, at least 3 needed", corners.len()),
+                    });
+                }
+
+                let polygon: Vec<Vector3> =
+                    corners.iter().map(|corner| positions[corner.0]).collect();
+                let corner_indices: Vec<u32> = corners
+                    .iter()
+                    .map(|&corner| {
+                        *current.corners.entry(corner).or_insert_with(|| {
+                            current.vertices.push(MeshVertex::new(
+                                positions[corner.0],
+                                corner.2.map(|index| normals[index]).unwrap_or_default(),
+                                corner.1.map(|index| uvs[index]).unwrap_or_default(),
+                            ));
+                            current.vertices.len() as u32 - 1
+                        })
+                    })
+                    .collect();
+                for triangle in triangulate(&polygon) {
+                    current
+                        .indices
+                        .extend(triangle.iter().map(|corner| corner_indices[*corner]));
+                }
+            }
+            "o" | "g" | "usemtl" => {
+                let mut object = current.object.clone();
+                let mut group = current.group.clone();
+                let mut material = current.material.clone();
+                match keyword {
+                    "o" => object = rest.to_string(),
+                    "g" => group = rest.to_string(),
+                    _ => material = Some(rest.to_string()),
+                }
+                let finished =
+                    std::mem::replace(&mut current, MeshBuilder::new(&object, &group, material));
+                finished.finish(&mut meshes);
+            }
+            "mtllib" => {
+                if rest.is_empty() {
+                    return Err(ObjParseError {
+                        line: line_number,
+                        message: "mtllib without a file name".to_string(),
+                    });
+                }
+                material_libraries.push(rest.to_string());
+            }
+            _ => {}
+        }
+    }
+    current.finish(&mut meshes);
+
+    Ok(ObjModel {
+        meshes,
+        materials: Vec::new(),
+        material_libraries,
+    })
+}
+
+/// True if p is inside or on the edge of the 2D triangle (a, b, c), which must be
+/// counter-clockwise
+fn in_triangle(p: (f32, f32), a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> bool {
+    let side = |from: (f32, f32), to: (f32, f32)| {
+        (to.0 - from.0) * (p.1 - from.1) - (to.1 - from.1) * (p.0 - from.0)
+    };
+    side(a, b) >= 0.0 && side(b, c) >= 0.0 && side(c, a) >= 0.0
+}
+
+/// Splits a planar polygon into triangles with the same winding, returned as indices into the
+/// polygon. Ear clipping is used so concave polygons work; if the polygon is too degenerate for
+/// that, what is left is split as a fan.
+pub fn triangulate(polygon: &[Vector3]) -> Vec<[usize; 3]> {
+    if polygon.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Project onto the plane the polygon faces the most, using Newell's normal
+    let mut normal = Vector3::default();
+    for (index, current) in polygon.iter().enumerate() {
+        let next = &polygon[(index + 1) % polygon.len()];
+        normal = normal + calc_cross_product(current, next);
+    }
+    let points: Vec<(f32, f32)> = polygon
+        .iter()
+        .map(|p| {
+            if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+                (p.y * normal.x.signum(), p.z)
+            } else if normal.y.abs() >= normal.z.abs() {
+                (p.z * normal.y.signum(), p.x)
+            } else {
+                (p.x * normal.z.signum(), p.y)
+            }
+        })
+        .collect();
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&position| {
+            let previous = remaining[(position + count - 1) % count];
+            let current = remaining[position];
+            let next = remaining[(position + 1) % count];
+            let (a, b, c) = (points[previous], points[current], points[next]);
+            let convex = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) > 0.0;
+            convex
+                && remaining.iter().all(|&other| {
+                    other == previous
+                        || other == current
+                        || other == next
+                        || !in_triangle(points[other], a, b, c)
+                })
+        });
+        let Some(position) = ear else {
+            break;
+        };
+        triangles.push([
+            remaining[(position + count - 1) % count],
+            remaining[position],
+            remaining[(position + 1) % count],
+        ]);
+        remaining.remove(position);
+    }
+    for position in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[position], remaining[position + 1]]);
+    }
+    triangles
+}
+
+/// The file name of a map_ statement, skipping options such as -s 1 1 1 or -clamp on
+fn texture_path(rest: &str, directory: &Path, line: usize) -> Result<PathBuf, ObjParseError> {
+    let tokens: Vec<&str> = rest.split_whitespace().collect();
+    let mut position = 0;
+    while position < tokens.len() && tokens[position].starts_with('-') {
+        position += 1;
+        while position < tokens.len()
+            && (tokens[position].parse::<f32>().is_ok()
+                || matches!(tokens[position], "on" | "off")
+                || (tokens[position - 1] == "-imfchan" && tokens[position].len() == 1))
+        {
+            position += 1;
+        }
+    }
+    if position == tokens.len() {
+        return Err(ObjParseError {
+            line,
+            message: "texture statement without a file name".to_string(),
+        });
+    }
+    Ok(directory.join(tokens[position..].join(" ")))
+}
+
+fn parse_color(rest: &str, line: usize) -> Result<[f32; 3], ObjParseError> {
+    if rest.starts_with("spectral") || rest.starts_with("xyz") {
+        return Err(ObjParseError {
+            line,
+            message: "only rgb colors are supported".to_string(),
+        });
+    }
+    let mut tokens = rest.split_whitespace();
+    let red = parse_float(tokens.next(), line, "red")?;
+    // A single value is a gray
+    let green = match tokens.next() {
+        Some(token) => parse_float(Some(token), line, "green")?,
+        None => return Ok([red; 3]),
+    };
+    let blue = parse_float(tokens.next(), line, "blue")?;
+    Ok([red, green, blue])
+}
+
+/// Parses the contents of an MTL file. Texture paths are joined to directory.
+pub fn parse_mtl(text: &str, directory: &Path) -> Result<Vec<Material>, ObjParseError> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some(keyword) = line.split_whitespace().next() else {
+            continue;
+        };
+        let rest = line[keyword.len()..].trim();
+
+        if keyword == "newmtl" {
+            if rest.is_empty() {
+                return Err(ObjParseError {
+                    line: line_number,
+                    message: "newmtl without a name".to_string(),
+                });
+            }
+            materials.push(Material::new(rest));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(ObjParseError {
+                line: line_number,
+                message: format!("{} before the first newmtl", keyword),
+            });
+        };
+
+        match keyword {
+            "Ka" => material.ambient = parse_color(rest, line_number)?,
+            "Kd" => material.diffuse = parse_color(rest, line_number)?,
+            "Ks" => material.specular = parse_color(rest, line_number)?,
+            "Ns" => material.shininess = parse_float(Some(rest), line_number, "shininess")?,
+            "d" => material.opacity = parse_float(Some(rest), line_number, "opacity")?,
+            "Tr" => material.opacity = 1.0 - parse_float(Some(rest), line_number, "transparency")?,
+            "map_Ka" => material.ambient_map = Some(texture_path(rest, directory, line_number)?),
+            "map_Kd" => material.diffuse_map = Some(texture_path(rest, directory, line_number)?),
+            "map_Ks" => material.specular_map = Some(texture_path(rest, directory, line_number)?),
+            "map_Ns" => material.shininess_map = Some(texture_path(rest, directory, line_number)?),
+            "map_d" => material.opacity_map = Some(texture_path(rest, directory, line_number)?),
+            "map_Bump" | "map_bump" | "bump" | "norm" => {
+                material.normal_map = Some(texture_path(rest, directory, line_number)?)
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                          vn 0 0 1\n";
+
+    fn only_mesh(text: &str) -> Mesh {
+        let mut model = parse_obj(text).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+        model.meshes.remove(0).mesh
+    }
+
+    /// Twice the signed area of each triangle seen from +z
+    fn signed_areas(mesh: &Mesh) -> Vec<f32> {
+        mesh.triangles()
+            .map(|[a, b, c]| {
+                let (a, b, c) = (
+                    mesh.vertices[a as usize].position,
+                    mesh.vertices[b as usize].position,
+                    mesh.vertices[c as usize].position,
+                );
+                calc_cross_product(&(b - a), &(c - a)).z
+            })
+            .collect()
+    }
+
+    #[test]
+    fn face_index_forms() {
+        for (face, has_uv, has_normal) in [
+            ("f 1 2 3 4", false, false),
+            ("f 1/1 2/2 3/3 4/4", true, false),
+            ("f 1//1 2//1 3//1 4//1", false, true),
+            ("f 1/1/1 2/2/1 3/3/1 4/4/1", true, true),
+        ] {
+            let mesh = only_mesh(&format!("{}{}\n", SQUARE, face));
+            assert_eq!(mesh.vertices.len(), 4, "{}", face);
+            assert_eq!(mesh.triangle_count(), 2, "{}", face);
+            let expected = MeshVertex::new(
+                Vector3::new(1.0, 1.0, 0.0),
+                if has_normal {
+                    Vector3::new(0.0, 0.0, 1.0)
+                } else {
+                    Vector3::default()
+                },
+                if has_uv {
+                    Vector2::new(1.0, 1.0)
+                } else {
+                    Vector2::default()
+                },
+            );
+            assert!(mesh.vertices[2].approx_eq(&expected, 0.0), "{}", face);
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_end() {
+        let relative = only_mesh(&format!(
+            "{}f -4/-4/-1 -3/-3/-1 -2/-2/-1 -1/-1/-1\n",
+            SQUARE
+        ));
+        let absolute = only_mesh(&format!("{}f 1/1/1 2/2/1 3/3/1 4/4/1\n", SQUARE));
+        assert_eq!(relative.indices, absolute.indices);
+        for (a, b) in relative.vertices.iter().zip(&absolute.vertices) {
+            assert!(a.approx_eq(b, 0.0));
+        }
+    }
+
+    #[test]
+    fn quads_and_concave_polygons_are_triangulated() {
+        let quad = only_mesh(&format!("{}f 1 2 3 4\n", SQUARE));
+        assert_eq!(signed_areas(&quad).iter().sum::<f32>(), 2.0);
+
+        // An L shape whose reflex corner at (1, 1) would break a fan from the first vertex
+        let concave =
+            only_mesh("v 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nv 0 0 0\nf 1 2 3 4 5 6\n");
+        let areas = signed_areas(&concave);
+        assert_eq!(areas.len(), 4);
+        assert!(areas.iter().all(|area| *area > 0.0), "{:?}", areas);
+        assert_eq!(areas.iter().sum::<f32>(), 6.0);
+    }
+
+    #[test]
+    fn objects_groups_and_materials_split_meshes() {
+        let model = parse_obj(&format!(
+            "mtllib house.mtl\n{}o house\ng walls\nusemtl brick\nf 1 2 3\nf 1 3 4\n\
+             usemtl glass\nf 1 2 3\ng roof\nf 2 3 4\no shed\nf 1 2 4\n",
+            SQUARE
+        ))
+        .unwrap();
+        assert_eq!(model.material_libraries, vec!["house.mtl".to_string()]);
+        let summary: Vec<(&str, &str, Option<&str>, usize)> = model
+            .meshes
+            .iter()
+            .map(|mesh| {
+                (
+                    mesh.object.as_str(),
+                    mesh.group.as_str(),
+                    mesh.material.as_deref(),
+                    mesh.mesh.triangle_count(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("house", "walls", Some("brick"), 2),
+                ("house", "walls", Some("glass"), 1),
+                ("house", "roof", Some("glass"), 1),
+                ("shed", "roof", Some("glass"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn material_maps_are_relative_to_the_directory() {
+        let materials = parse_mtl(
+            "newmtl brick\nKd 0.5 0.25 0.125\nKs 0.5\nNs 32\nTr 0.25\n\
+             map_Kd -s 2 2 1 -clamp on textures/brick.png\nbump -bm 0.5 brick normal.png\n\
+             newmtl glass\nd 0.5\n",
+            Path::new("models/house"),
+        )
+        .unwrap();
+        assert_eq!(materials.len(), 2);
+        let brick = &materials[0];
+        assert_eq!(brick.diffuse, [0.5, 0.25, 0.125]);
+        assert_eq!(brick.specular, [0.5; 3]);
+        assert_eq!(brick.shininess, 32.0);
+        assert_eq!(brick.opacity, 0.75);
+        assert_eq!(
+            brick.diffuse_map.as_deref(),
+            Some(Path::new("models/house/textures/brick.png"))
+        );
+        assert_eq!(
+            brick.normal_map.as_deref(),
+            Some(Path::new("models/house/brick normal.png"))
+        );
+        assert_eq!(materials[1].opacity, 0.5);
+
+        // load_obj finds the library next to the OBJ file
+        let directory = std::env::temp_dir().join(format!("obj_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("square.obj"),
+            format!("mtllib square.mtl\n{}usemtl paint\nf 1 2 3 4\n", SQUARE),
+        )
+        .unwrap();
+        fs::write(
+            directory.join("square.mtl"),
+            "newmtl paint\nmap_Kd paint.png\n",
+        )
+        .unwrap();
+        let model = load_obj(&directory.join("square.obj")).unwrap();
+        let material = model.material(&model.meshes[0]).unwrap();
+        assert_eq!(material.diffuse_map, Some(directory.join("paint.png")));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        for (text, line, fragment) in [
+            ("v 0 0 0\nv 1 0 x\n", 2, "invalid z coordinate \"x\""),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 0 1 2\n",
+                5,
+                "vertex index 0",
+            ),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n", 4, "out of range"),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n", 4, "out of range"),
+            (
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n",
+                4,
+                "texture coordinate index 1",
+            ),
+        ] {
+            let error = parse_obj(text).err().unwrap();
+            assert_eq!(error.line, line, "{}", text);
+            assert!(error.message.contains(fragment), "{}", error);
+        }
+        let error = parse_mtl("newmtl a\n\nKd 1 x 0\n", Path::new(""))
+            .err()
+            .unwrap();
+        assert_eq!(error.line, 3);
+    }
+}
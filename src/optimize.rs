@@ -0,0 +1,421 @@
+//! Reordering of triangles and vertices so the GPU does less work for the same mesh.
+//! Triangles are ordered for the post-transform vertex cache with Tom Forsyth's linear-speed
+//! algorithm, optionally regrouped so outward facing clusters are drawn first to reduce
+//! overdraw, and vertices are then stored in the order the triangles first use them.
+//! Cache behaviour is measured with a FIFO cache simulation, which is how most GPUs behave.
+
+use std::fmt;
+
+use crate::{
+    mesh::{Indices, Mesh},
+    vector::{Vector3, calc_cross_product},
+};
+
+/// The cache size assumed by the optimizer and used for the reported figures
+pub const CACHE_SIZE: usize = 32;
+
+/// Vertex cache figures of a mesh before and after optimize
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizationReport {
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+    pub atvr_before: f32,
+    pub atvr_after: f32,
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ACMR {:.3} -> {:.3}, ATVR {:.3} -> {:.3} (FIFO cache of {})",
+            self.acmr_before, self.acmr_after, self.atvr_before, self.atvr_after, CACHE_SIZE
+        )
+    }
+}
+
+/// Counts the vertices a FIFO cache of cache_size entries misses for each triangle
+fn cache_misses(indices: &[u32], vertex_count: usize, cache_size: usize) -> Vec<u32> {
+    // A vertex is in the cache if it was added fewer than cache_size insertions ago
+    let mut inserted_at: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut insertions = 0;
+    indices
+        .chunks(3)
+        .map(|triangle| {
+            let mut misses = 0;
+            for index in triangle {
+                let cached =
+                    inserted_at[*index as usize].is_some_and(|time| insertions - time < cache_size);
+                if !cached {
+                    inserted_at[*index as usize] = Some(insertions);
+                    insertions += 1;
+                    misses += 1;
+                }
+            }
+            misses
+        })
+        .collect()
+}
+
+/// Average cache miss ratio: vertices transformed per triangle. 0.5 is the best a regular grid
+/// can reach and 3 means the cache never helps.
+pub fn acmr(mesh: &Mesh, cache_size: usize) -> f32 {
+    if mesh.triangle_count() == 0 {
+        return 0.0;
+    }
+    let misses: u32 = cache_misses(&mesh.indices.to_u32(), mesh.vertices.len(), cache_size)
+        .iter()
+        .sum();
+    misses as f32 / mesh.triangle_count() as f32
+}
+
+/// Average transform to vertex ratio: vertices transformed per vertex. 1 is the best possible.
+pub fn atvr(mesh: &Mesh, cache_size: usize) -> f32 {
+    let used = {
+        let mut used = vec![false; mesh.vertices.len()];
+        for index in mesh.indices.iter() {
+            used[index as usize] = true;
+        }
+        used.iter().filter(|used| **used).count()
+    };
+    if used == 0 {
+        return 0.0;
+    }
+    let misses: u32 = cache_misses(&mesh.indices.to_u32(), mesh.vertices.len(), cache_size)
+        .iter()
+        .sum();
+    misses as f32 / used as f32
+}
+
+/// Forsyth's score for a vertex at a cache position with the given number of triangles left
+fn vertex_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        // The last triangle's vertices are penalised a little so it is not simply repeated
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32)
+            .max(0.0)
+            .powf(1.5),
+    };
+    // Vertices with few triangles left are finished first so they leave the working set
+    cache_score + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+/// Reorders triangles for the post-transform vertex cache. Each step draws the triangle whose
+/// vertices score highest, favouring vertices that are in the cache and have few triangles left.
+pub fn optimize_vertex_cache(mesh: &mut Mesh) {
+    let indices = mesh.indices.to_u32();
+    let triangle_count = indices.len() / 3;
+    let vertex_count = mesh.vertices.len();
+
+    // Triangles of each vertex, as offsets into one shared list
+    let mut starts = vec![0; vertex_count + 1];
+    for index in &indices {
+        starts[*index as usize + 1] += 1;
+    }
+    for vertex in 0..vertex_count {
+        starts[vertex + 1] += starts[vertex];
+    }
+    let mut remaining: Vec<usize> = (0..vertex_count)
+        .map(|vertex| starts[vertex + 1] - starts[vertex])
+        .collect();
+    let mut adjacency = vec![0; indices.len()];
+    let mut filled = starts.clone();
+    for (position, index) in indices.iter().enumerate() {
+        adjacency[filled[*index as usize]] = position / 3;
+        filled[*index as usize] += 1;
+    }
+
+    let mut vertex_scores: Vec<f32> = remaining
+        .iter()
+        .map(|remaining| vertex_score(None, *remaining))
+        .collect();
+    let mut triangle_scores: Vec<f32> = indices
+        .chunks(3)
+        .map(|triangle| {
+            triangle
+                .iter()
+                .map(|index| vertex_scores[*index as usize])
+                .sum()
+        })
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(indices.len());
+    // Where to look for a new start once the cache holds no useful triangle
+    let mut next_unemitted = 0;
+    let mut best = None;
+
+    for _ in 0..triangle_count {
+        let triangle = match best {
+            Some(triangle) => triangle,
+            None => {
+                while emitted[next_unemitted] {
+                    next_unemitted += 1;
+                }
+                next_unemitted
+            }
+        };
+        emitted[triangle] = true;
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        output.extend_from_slice(corners);
+
+        // Move the triangle's vertices to the front of the cache and retire the triangle
+        for corner in corners.iter().rev() {
+            cache.retain(|cached| cached != corner);
+            cache.insert(0, *corner);
+            let vertex = *corner as usize;
+            let list = &mut adjacency[starts[vertex]..starts[vertex] + remaining[vertex]];
+            if let Some(slot) = list.iter().position(|other| *other == triangle) {
+                let last = list.len() - 1;
+                list.swap(slot, last);
+                remaining[vertex] -= 1;
+            }
+        }
+
+        // Rescore the cached vertices, including the ones just pushed out, and their triangles
+        best = None;
+        let mut best_score = f32::MIN;
+        for (position, vertex) in cache.iter().enumerate() {
+            let vertex = *vertex as usize;
+            let cache_position = (position < CACHE_SIZE).then_some(position);
+            let score = vertex_score(cache_position, remaining[vertex]);
+            let change = score - vertex_scores[vertex];
+            vertex_scores[vertex] = score;
+            for other in &adjacency[starts[vertex]..starts[vertex] + remaining[vertex]] {
+                triangle_scores[*other] += change;
+            }
+        }
+        for vertex in &cache {
+            let vertex = *vertex as usize;
+            for other in &adjacency[starts[vertex]..starts[vertex] + remaining[vertex]] {
+                if triangle_scores[*other] > best_score {
+                    best_score = triangle_scores[*other];
+                    best = Some(*other);
+                }
+            }
+        }
+        cache.truncate(CACHE_SIZE);
+    }
+
+    mesh.indices = Indices::new(output, vertex_count);
+}
+
+/// Regroups cache-ordered triangles into clusters and draws the clusters facing away from the
+/// mesh's centre first, so they tend to hide what is drawn after them. Clusters end wherever
+/// the cache order starts afresh anyway, and as soon as a cluster drawn on its own has an ACMR
+/// within threshold times the mesh's, so 1.05 trades at most about 5% of cache efficiency.
+/// Run optimize_vertex_cache first.
+pub fn optimize_overdraw(mesh: &mut Mesh, threshold: f32) {
+    let indices = mesh.indices.to_u32();
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+    let misses = cache_misses(&indices, mesh.vertices.len(), CACHE_SIZE);
+    let total_misses: u32 = misses.iter().sum();
+    let limit = threshold * total_misses as f32 / triangle_count as f32;
+
+    // Clusters may be drawn in any order, so their misses are counted with a cache that starts
+    // empty at each cluster
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    let mut cluster_misses = 0;
+    let mut inserted_at: Vec<Option<usize>> = vec![None; mesh.vertices.len()];
+    let mut insertions = 0;
+    let mut cluster_start = 0;
+    for (triangle, triangle_misses) in misses.iter().enumerate() {
+        let size = triangle - start;
+        let hard = *triangle_misses == 3;
+        let soft = cluster_misses as f32 <= limit * size as f32;
+        if size > 0 && (hard || soft) {
+            clusters.push((start, triangle));
+            start = triangle;
+            cluster_misses = 0;
+            cluster_start = insertions;
+        }
+        for index in &indices[triangle * 3..triangle * 3 + 3] {
+            let cached = inserted_at[*index as usize]
+                .is_some_and(|time| time >= cluster_start && insertions - time < CACHE_SIZE);
+            if !cached {
+                inserted_at[*index as usize] = Some(insertions);
+                insertions += 1;
+                cluster_misses += 1;
+            }
+        }
+    }
+    clusters.push((start, triangle_count));
+
+    // Area-weighted centroids and normals
+    let position = |index: u32| mesh.vertices[index as usize].position;
+    let triangle_centroid_and_normal = |triangle: usize| {
+        let [a, b, c] = [0, 1, 2].map(|corner| position(indices[triangle * 3 + corner]));
+        let face = calc_cross_product(&(b - a), &(c - a));
+        let area = face.magnitude();
+        ((1.0 / 3.0) * (a + b + c), face, area)
+    };
+    let mut mesh_centroid = Vector3::default();
+    let mut mesh_area = 0.0;
+    for triangle in 0..triangle_count {
+        let (centroid, _, area) = triangle_centroid_and_normal(triangle);
+        mesh_centroid = mesh_centroid + area * centroid;
+        mesh_area += area;
+    }
+    if mesh_area > 0.0 {
+        mesh_centroid = (1.0 / mesh_area) * mesh_centroid;
+    }
+
+    let mut sorted: Vec<(f32, usize, usize)> = clusters
+        .iter()
+        .map(|(start, end)| {
+            let mut centroid = Vector3::default();
+            let mut normal = Vector3::default();
+            let mut area = 0.0;
+            for triangle in *start..*end {
+                let (triangle_centroid, face, triangle_area) =
+                    triangle_centroid_and_normal(triangle);
+                centroid = centroid + triangle_area * triangle_centroid;
+                normal = normal + face;
+                area += triangle_area;
+            }
+            if area <= 0.0 || normal.magnitude() <= 0.0 {
+                return (f32::MIN, *start, *end);
+            }
+            let centroid = (1.0 / area) * centroid;
+            let normal = Vector3::calc_normalized_vector(&normal);
+            (
+                Vector3::dot_product(&(centroid - mesh_centroid), &normal),
+                *start,
+                *end,
+            )
+        })
+        .collect();
+    // Stable, so clusters facing the same way keep their cache order
+    sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut output = Vec::with_capacity(indices.len());
+    for (_, start, end) in sorted {
+        output.extend_from_slice(&indices[start * 3..end * 3]);
+    }
+    mesh.indices = Indices::new(output, mesh.vertices.len());
+}
+
+/// Stores vertices in the order the triangles first use them, so vertex fetches move through
+/// memory in one direction. Vertices no triangle uses are dropped.
+pub fn optimize_vertex_fetch(mesh: &mut Mesh) {
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.vertices.len()];
+    let mut vertices = Vec::with_capacity(mesh.vertices.len());
+    let indices: Vec<u32> = mesh
+        .indices
+        .iter()
+        .map(|index| {
+            *remap[index as usize].get_or_insert_with(|| {
+                vertices.push(mesh.vertices[index as usize]);
+                vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+    *mesh = Mesh::new(vertices, indices);
+}
+
+/// Runs the vertex cache pass, the overdraw pass if a threshold is given, and the vertex fetch
+/// pass, and reports how the cache figures changed
+pub fn optimize(mesh: &mut Mesh, overdraw_threshold: Option<f32>) -> OptimizationReport {
+    let acmr_before = acmr(mesh, CACHE_SIZE);
+    let atvr_before = atvr(mesh, CACHE_SIZE);
+    optimize_vertex_cache(mesh);
+    if let Some(threshold) = overdraw_threshold {
+        optimize_overdraw(mesh, threshold);
+    }
+    optimize_vertex_fetch(mesh);
+    OptimizationReport {
+        acmr_before,
+        acmr_after: acmr(mesh, CACHE_SIZE),
+        atvr_before,
+        atvr_after: atvr(mesh, CACHE_SIZE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{plane, uv_sphere};
+
+    /// A grid whose triangles are in a fixed pseudo-random order, so the cache barely helps
+    fn shuffled_grid() -> Mesh {
+        let mut grid = plane(1.0, 1.0, 24, 24);
+        let mut triangles: Vec<[u32; 3]> = grid.triangles().collect();
+        let mut state: u32 = 12345;
+        for i in (1..triangles.len()).rev() {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            triangles.swap(i, (state >> 8) as usize % (i + 1));
+        }
+        let count = grid.vertices.len();
+        grid.indices = Indices::new(triangles.concat(), count);
+        grid
+    }
+
+    /// The mesh's triangles by vertex position, each rotated to start at its smallest corner
+    /// so the winding is kept, and sorted
+    fn triangle_set(mesh: &Mesh) -> Vec<[[u32; 3]; 3]> {
+        let mut triangles: Vec<_> = mesh
+            .triangles()
+            .map(|triangle| {
+                let mut corners = triangle.map(|index| {
+                    let position = mesh.vertices[index as usize].position;
+                    [position.x, position.y, position.z].map(f32::to_bits)
+                });
+                let first = (0..3).min_by_key(|corner| corners[*corner]).unwrap();
+                corners.rotate_left(first);
+                corners
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn vertex_cache_order_lowers_acmr_and_keeps_triangles() {
+        let mut grid = shuffled_grid();
+        let before = triangle_set(&grid);
+        let acmr_before = acmr(&grid, CACHE_SIZE);
+        optimize_vertex_cache(&mut grid);
+        let acmr_after = acmr(&grid, CACHE_SIZE);
+        assert!(
+            acmr_after < 0.75 * acmr_before,
+            "ACMR {} -> {}",
+            acmr_before,
+            acmr_after
+        );
+        assert_eq!(triangle_set(&grid), before);
+    }
+
+    #[test]
+    fn vertex_fetch_order_follows_first_use() {
+        let mut grid = shuffled_grid();
+        let before = triangle_set(&grid);
+        optimize_vertex_fetch(&mut grid);
+        assert_eq!(triangle_set(&grid), before);
+        let mut next = 0;
+        for index in grid.indices.iter() {
+            assert!(index <= next, "vertex {} used before {}", next, index);
+            if index == next {
+                next += 1;
+            }
+        }
+        assert_eq!(next as usize, grid.vertices.len());
+    }
+
+    #[test]
+    fn overdraw_order_keeps_every_triangle() {
+        for threshold in [1.0, 1.05, 3.0] {
+            let mut sphere = uv_sphere(1.0, 24, 12);
+            optimize_vertex_cache(&mut sphere);
+            let before = triangle_set(&sphere);
+            optimize_overdraw(&mut sphere, threshold);
+            assert_eq!(triangle_set(&sphere), before);
+        }
+    }
+}
@@ -0,0 +1,838 @@
+//! PLY files, common for scanned geometry, in ASCII and binary little-endian form.
+//! Vertex positions, normals, texture coordinates and colors map onto the mesh; every other
+//! vertex property is kept by name so nothing a scanner wrote is lost. Faces are read from the
+//! vertex_indices (or vertex_index) list of the face element and split into triangles. Other
+//! elements are skipped.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mesh::{Aabb, Mesh, MeshVertex},
+    obj::triangulate,
+    vector::{Vector2, Vector3},
+};
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io { path: PathBuf, error: io::Error },
+    Invalid(String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            PlyError::Invalid(message) => write!(f, "Invalid PLY: {}", message),
+        }
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, PlyError> {
+    Err(PlyError::Invalid(message))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => PlyType::Int8,
+            "uchar" | "uint8" => PlyType::Uint8,
+            "short" | "int16" => PlyType::Int16,
+            "ushort" | "uint16" => PlyType::Uint16,
+            "int" | "int32" => PlyType::Int32,
+            "uint" | "uint32" => PlyType::Uint32,
+            "float" | "float32" => PlyType::Float32,
+            "double" | "float64" => PlyType::Float64,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlyType::Int8 => "char",
+            PlyType::Uint8 => "uchar",
+            PlyType::Int16 => "short",
+            PlyType::Uint16 => "ushort",
+            PlyType::Int32 => "int",
+            PlyType::Uint32 => "uint",
+            PlyType::Float32 => "float",
+            PlyType::Float64 => "double",
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::Uint8 => 1,
+            PlyType::Int16 | PlyType::Uint16 => 2,
+            PlyType::Int32 | PlyType::Uint32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+
+    /// The value that stands for full intensity in a color channel of this type
+    fn color_scale(&self) -> f64 {
+        match self {
+            PlyType::Int8 => 127.0,
+            PlyType::Uint8 => 255.0,
+            PlyType::Int16 => 32767.0,
+            PlyType::Uint16 => 65535.0,
+            PlyType::Int32 => 2147483647.0,
+            PlyType::Uint32 => 4294967295.0,
+            PlyType::Float32 | PlyType::Float64 => 1.0,
+        }
+    }
+
+    fn read(&self, bytes: &[u8]) -> f64 {
+        match self {
+            PlyType::Int8 => bytes[0] as i8 as f64,
+            PlyType::Uint8 => bytes[0] as f64,
+            PlyType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::Uint16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            PlyType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Uint32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            PlyType::Float64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write, value: f64, format: PlyFormat) -> io::Result<()> {
+        if format == PlyFormat::Ascii {
+            return match self {
+                PlyType::Float32 => write!(writer, "{}", value as f32),
+                PlyType::Float64 => write!(writer, "{}", value),
+                _ => write!(writer, "{}", value.round() as i64),
+            };
+        }
+        match self {
+            PlyType::Int8 => writer.write_all(&(value.round() as i8).to_le_bytes()),
+            PlyType::Uint8 => writer.write_all(&(value.round() as u8).to_le_bytes()),
+            PlyType::Int16 => writer.write_all(&(value.round() as i16).to_le_bytes()),
+            PlyType::Uint16 => writer.write_all(&(value.round() as u16).to_le_bytes()),
+            PlyType::Int32 => writer.write_all(&(value.round() as i32).to_le_bytes()),
+            PlyType::Uint32 => writer.write_all(&(value.round() as u32).to_le_bytes()),
+            PlyType::Float32 => writer.write_all(&(value as f32).to_le_bytes()),
+            PlyType::Float64 => writer.write_all(&value.to_le_bytes()),
+        }
+    }
+}
+
+/// A vertex property that has no place in MeshVertex, with one value per vertex
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub data_type: PlyType,
+    pub values: Vec<f64>,
+}
+
+pub struct PlyModel {
+    pub mesh: Mesh,
+    pub bounds: Option<Aabb>,
+    /// RGBA in [0, 1] per vertex, if the file has colors. Alpha is 1 when only RGB is given.
+    pub colors: Option<Vec<[f32; 4]>>,
+    pub properties: Vec<PlyProperty>,
+    pub comments: Vec<String>,
+}
+
+impl PlyModel {
+    pub fn from_mesh(mesh: Mesh) -> Self {
+        let bounds = mesh.bounds();
+        Self {
+            mesh,
+            bounds,
+            colors: None,
+            properties: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(PlyType),
+    List { count: PlyType, item: PlyType },
+}
+
+struct PropertyDefinition {
+    name: String,
+    kind: PropertyKind,
+}
+
+struct ElementDefinition {
+    name: String,
+    count: usize,
+    properties: Vec<PropertyDefinition>,
+}
+
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl Value {
+    fn scalar(&self) -> f64 {
+        match self {
+            Value::Scalar(value) => *value,
+            Value::List(values) => values.first().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Where element values come from: lines of text or little-endian bytes
+enum Body<'a> {
+    Ascii {
+        lines: std::iter::Enumerate<std::str::Lines<'a>>,
+        /// Line number of the first body line
+        first_line: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        position: usize,
+    },
+}
+
+impl Body<'_> {
+    fn read_element(&mut self, element: &ElementDefinition) -> Result<Vec<Value>, PlyError> {
+        match self {
+            Body::Ascii { lines, first_line } => {
+                let (line_index, line) = loop {
+                    match lines.next() {
+                        Some((_, line)) if line.trim().is_empty() => continue,
+                        Some(line) => break line,
+                        None => return invalid(format!("file ends inside {}", element.name)),
+                    }
+                };
+                let line_number = *first_line + line_index;
+                let mut tokens = line.split_whitespace();
+                let mut next = |what: &str| -> Result<f64, PlyError> {
+                    let token = tokens.next().ok_or_else(|| {
+                        PlyError::Invalid(format!("line {}: missing {}", line_number, what))
+                    })?;
+                    token.parse().map_err(|_| {
+                        PlyError::Invalid(format!(
+                            "line {}: invalid {} \"{}\"",
+                            line_number, what, token
+                        ))
+                    })
+                };
+                let mut values = Vec::with_capacity(element.properties.len());
+                for property in &element.properties {
+                    values.push(match property.kind {
+                        PropertyKind::Scalar(_) => Value::Scalar(next(&property.name)?),
+                        PropertyKind::List { .. } => {
+                            let count = next(&property.name)? as usize;
+                            let items =
+                                (0..count)
+                                    .map(|_| next(&property.name))
+                                    .collect::<Result<Vec<_>, _>>()?;
+                            Value::List(items)
+                        }
+                    });
+                }
+                if tokens.next().is_some() {
+                    return invalid(format!(
+                        "line {}: more values than {} has properties",
+                        line_number, element.name
+                    ));
+                }
+                Ok(values)
+            }
+            Body::Binary { bytes, position } => {
+                let mut read = |data_type: PlyType| -> Result<f64, PlyError> {
+                    let Some(raw) = bytes.get(*position..*position + data_type.size()) else {
+                        return invalid(format!("file ends inside {}", element.name));
+                    };
+                    *position += data_type.size();
+                    Ok(data_type.read(raw))
+                };
+                let mut values = Vec::with_capacity(element.properties.len());
+                for property in &element.properties {
+                    values.push(match property.kind {
+                        PropertyKind::Scalar(data_type) => Value::Scalar(read(data_type)?),
+                        PropertyKind::List { count, item } => {
+                            let count = read(count)? as usize;
+                            let items = (0..count)
+                                .map(|_| read(item))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            Value::List(items)
+                        }
+                    });
+                }
+                Ok(values)
+            }
+        }
+    }
+}
+
+pub fn load_ply(path: &Path) -> Result<PlyModel, PlyError> {
+    let bytes = fs::read(path).map_err(|error| PlyError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    parse_ply(&bytes)
+}
+
+pub fn parse_ply(bytes: &[u8]) -> Result<PlyModel, PlyError> {
+    // The header is text up to and including the end_header line
+    let Some(header_end) = bytes
+        .windows(10)
+        .position(|window| window == b"end_header")
+        .and_then(|start| {
+            let newline = bytes[start..].iter().position(|byte| *byte == b'\n')?;
+            Some(start + newline + 1)
+        })
+    else {
+        return invalid("no end_header line".to_string());
+    };
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .map_err(|_| PlyError::Invalid("header is not text".to_string()))?;
+
+    let mut format = None;
+    let mut elements: Vec<ElementDefinition> = Vec::new();
+    let mut comments = Vec::new();
+    let mut header_lines = 0;
+    for (line_index, line) in header.lines().enumerate() {
+        header_lines = line_index + 1;
+        let error =
+            |message: &str| PlyError::Invalid(format!("line {}: {}", line_index + 1, message));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["ply"] if line_index == 0 => {}
+            _ if line_index == 0 => return Err(error("not a PLY file")),
+            ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", other, ..] => {
+                return Err(error(&format!("format {} is not supported", other)));
+            }
+            ["comment", ..] | ["obj_info", ..] => {
+                comments.push(line.trim()[tokens[0].len()..].trim().to_string());
+            }
+            ["element", name, count] => elements.push(ElementDefinition {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(&format!("invalid count \"{}\"", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let (Some(count), Some(item)) = (PlyType::parse(count), PlyType::parse(item))
+                else {
+                    return Err(error("unknown list type"));
+                };
+                let Some(element) = elements.last_mut() else {
+                    return Err(error("property before the first element"));
+                };
+                element.properties.push(PropertyDefinition {
+                    name: name.to_string(),
+                    kind: PropertyKind::List { count, item },
+                });
+            }
+            ["property", data_type, name] => {
+                let Some(data_type) = PlyType::parse(data_type) else {
+                    return Err(error(&format!("unknown type \"{}\"", data_type)));
+                };
+                let Some(element) = elements.last_mut() else {
+                    return Err(error("property before the first element"));
+                };
+                element.properties.push(PropertyDefinition {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(data_type),
+                });
+            }
+            ["end_header"] | [] => {}
+            _ => {
+                return Err(error(&format!(
+                    "unexpected header line \"{}\"",
+                    line.trim()
+                )));
+            }
+        }
+    }
+    let Some(format) = format else {
+        return invalid("header has no format line".to_string());
+    };
+
+    let mut body = match format {
+        PlyFormat::Ascii => Body::Ascii {
+            lines: std::str::from_utf8(&bytes[header_end..])
+                .map_err(|_| PlyError::Invalid("ASCII body is not text".to_string()))?
+                .lines()
+                .enumerate(),
+            first_line: header_lines + 1,
+        },
+        PlyFormat::BinaryLittleEndian => Body::Binary {
+            bytes: &bytes[header_end..],
+            position: 0,
+        },
+    };
+
+    let mut vertices: Vec<MeshVertex> = Vec::new();
+    let mut colors: Option<Vec<[f32; 4]>> = None;
+    let mut properties: Vec<PlyProperty> = Vec::new();
+    // Corners of every face, split into triangles once all vertices are known
+    let mut faces: Vec<Vec<u32>> = Vec::new();
+    for element in &elements {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|property| names.contains(&property.name.as_str()))
+        };
+        match element.name.as_str() {
+            "vertex" => {
+                let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                let uv = [
+                    find(&["s", "u", "texture_u", "texture_s"]),
+                    find(&["t", "v", "texture_v", "texture_t"]),
+                ];
+                let color = [
+                    find(&["red", "diffuse_red", "r"]),
+                    find(&["green", "diffuse_green", "g"]),
+                    find(&["blue", "diffuse_blue", "b"]),
+                    find(&["alpha", "diffuse_alpha", "a"]),
+                ];
+                if position.iter().any(Option::is_none) {
+                    return invalid("vertex element without x, y and z".to_string());
+                }
+                let has_color = color[..3].iter().all(Option::is_some);
+                let used: Vec<usize> = position
+                    .iter()
+                    .chain(&normal)
+                    .chain(&uv)
+                    .chain(if has_color { &color[..] } else { &[] })
+                    .flatten()
+                    .copied()
+                    .collect();
+                let extra: Vec<usize> = (0..element.properties.len())
+                    .filter(|property| {
+                        !used.contains(property)
+                            && matches!(element.properties[*property].kind, PropertyKind::Scalar(_))
+                    })
+                    .collect();
+                for property in &extra {
+                    let PropertyKind::Scalar(data_type) = element.properties[*property].kind else {
+                        continue;
+                    };
+                    properties.push(PlyProperty {
+                        name: element.properties[*property].name.clone(),
+                        data_type,
+                        // The count comes from the header, so nothing is reserved up front
+                        values: Vec::new(),
+                    });
+                }
+                if has_color {
+                    colors = Some(Vec::new());
+                }
+
+                for _ in 0..element.count {
+                    let values = body.read_element(element)?;
+                    let get =
+                        |slot: Option<usize>| slot.map_or(0.0, |slot| values[slot].scalar() as f32);
+                    vertices.push(MeshVertex::new(
+                        Vector3::new(get(position[0]), get(position[1]), get(position[2])),
+                        Vector3::new(get(normal[0]), get(normal[1]), get(normal[2])),
+                        Vector2::new(get(uv[0]), get(uv[1])),
+                    ));
+                    if let Some(colors) = &mut colors {
+                        let channel = |slot: Option<usize>| {
+                            slot.map_or(1.0, |slot| {
+                                let scale = match element.properties[slot].kind {
+                                    PropertyKind::Scalar(data_type) => data_type.color_scale(),
+                                    PropertyKind::List { .. } => 1.0,
+                                };
+                                (values[slot].scalar() / scale) as f32
+                            })
+                        };
+                        colors.push([
+                            channel(color[0]),
+                            channel(color[1]),
+                            channel(color[2]),
+                            channel(color[3]),
+                        ]);
+                    }
+                    for (property, slot) in properties.iter_mut().zip(&extra) {
+                        property.values.push(values[*slot].scalar());
+                    }
+                }
+            }
+            "face" => {
+                let Some(list) = find(&["vertex_indices", "vertex_index"]) else {
+                    return invalid("face element without vertex_indices".to_string());
+                };
+                for face in 0..element.count {
+                    let values = body.read_element(element)?;
+                    let Value::List(corners) = &values[list] else {
+                        return invalid("vertex_indices is not a list".to_string());
+                    };
+                    if corners.len() < 3 {
+                        return invalid(format!("face {} has fewer than 3 vertices", face));
+                    }
+                    if corners.iter().any(|corner| *corner < 0.0) {
+                        return invalid(format!("face {} has a negative index", face));
+                    }
+                    faces.push(corners.iter().map(|corner| *corner as u32).collect());
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_element(element)?;
+                }
+            }
+        }
+    }
+
+    if let Some(index) = faces
+        .iter()
+        .flatten()
+        .find(|index| **index as usize >= vertices.len())
+    {
+        return invalid(format!(
+            "face index {} is out of range ({} vertices)",
+            index,
+            vertices.len()
+        ));
+    }
+    // Vertices may come after faces, which is why polygons are only split here
+    let mut indices: Vec<u32> = Vec::new();
+    for corners in &faces {
+        if corners.len() == 3 {
+            indices.extend_from_slice(corners);
+            continue;
+        }
+        let polygon: Vec<Vector3> = corners
+            .iter()
+            .map(|corner| vertices[*corner as usize].position)
+            .collect();
+        for triangle in triangulate(&polygon) {
+            indices.extend(triangle.iter().map(|corner| corners[*corner]));
+        }
+    }
+    let mesh = Mesh::new(vertices, indices);
+    Ok(PlyModel {
+        bounds: mesh.bounds(),
+        mesh,
+        colors,
+        properties,
+        comments,
+    })
+}
+
+fn write_separator(writer: &mut impl Write, format: PlyFormat) -> io::Result<()> {
+    if format == PlyFormat::Ascii {
+        write!(writer, " ")?;
+    }
+    Ok(())
+}
+
+/// Writes positions, normals, texture coordinates, colors if present and the extra properties
+pub fn write_ply(writer: &mut impl Write, model: &PlyModel, format: PlyFormat) -> io::Result<()> {
+    let mesh = &model.mesh;
+    writeln!(writer, "ply")?;
+    writeln!(
+        writer,
+        "format {} 1.0",
+        match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        }
+    )?;
+    for comment in &model.comments {
+        writeln!(writer, "comment {}", comment)?;
+    }
+    writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+    for name in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(writer, "property float {}", name)?;
+    }
+    if model.colors.is_some() {
+        for name in ["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property uchar {}", name)?;
+        }
+    }
+    for property in &model.properties {
+        writeln!(
+            writer,
+            "property {} {}",
+            property.data_type.name(),
+            property.name
+        )?;
+    }
+    writeln!(writer, "element face {}", mesh.triangle_count())?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for (index, vertex) in mesh.vertices.iter().enumerate() {
+        let mut values: Vec<(PlyType, f64)> = [
+            vertex.position.x,
+            vertex.position.y,
+            vertex.position.z,
+            vertex.normal.x,
+            vertex.normal.y,
+            vertex.normal.z,
+            vertex.uv.x,
+            vertex.uv.y,
+        ]
+        .iter()
+        .map(|value| (PlyType::Float32, *value as f64))
+        .collect();
+        if let Some(colors) = &model.colors {
+            let color = colors.get(index).copied().unwrap_or([1.0; 4]);
+            values.extend(
+                color
+                    .iter()
+                    .map(|channel| (PlyType::Uint8, (channel.clamp(0.0, 1.0) * 255.0) as f64)),
+            );
+        }
+        for property in &model.properties {
+            values.push((
+                property.data_type,
+                property.values.get(index).copied().unwrap_or(0.0),
+            ));
+        }
+        for (position, (data_type, value)) in values.iter().enumerate() {
+            if position > 0 {
+                write_separator(writer, format)?;
+            }
+            data_type.write(writer, *value, format)?;
+        }
+        if format == PlyFormat::Ascii {
+            writeln!(writer)?;
+        }
+    }
+    for triangle in mesh.triangles() {
+        PlyType::Uint8.write(writer, 3.0, format)?;
+        for index in triangle {
+            write_separator(writer, format)?;
+            PlyType::Uint32.write(writer, index as f64, format)?;
+        }
+        if format == PlyFormat::Ascii {
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn save_ply(path: &Path, model: &PlyModel, format: PlyFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_ply(&mut writer, model, format)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitives, vector::calc_cross_product};
+
+    fn error(bytes: &[u8]) -> String {
+        match parse_ply(bytes) {
+            Err(PlyError::Invalid(message)) => message,
+            Err(other) => panic!("unexpected error {}", other),
+            Ok(_) => panic!("parsed invalid PLY"),
+        }
+    }
+
+    #[test]
+    fn round_trips_keep_vertices_faces_colors_and_properties() {
+        let mesh = primitives::uv_sphere(1.5, 12, 8);
+        let vertex_count = mesh.vertices.len();
+        let mut model = PlyModel::from_mesh(mesh);
+        model.colors = Some(
+            (0..vertex_count)
+                .map(|index| [(index % 256) as f32 / 255.0, 0.0, 1.0, 1.0])
+                .collect(),
+        );
+        model.properties.push(PlyProperty {
+            name: "confidence".to_string(),
+            data_type: PlyType::Float32,
+            values: (0..vertex_count).map(|index| index as f64 * 0.25).collect(),
+        });
+        model.comments.push("made by a test".to_string());
+
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian] {
+            let mut bytes = Vec::new();
+            write_ply(&mut bytes, &model, format).unwrap();
+            let loaded = parse_ply(&bytes).unwrap();
+
+            assert_eq!(loaded.mesh.triangle_count(), model.mesh.triangle_count());
+            assert_eq!(loaded.mesh.indices.to_u32(), model.mesh.indices.to_u32());
+            // Tangents are not stored
+            for (actual, expected) in loaded.mesh.vertices.iter().zip(&model.mesh.vertices) {
+                let expected = MeshVertex::new(expected.position, expected.normal, expected.uv);
+                assert!(actual.approx_eq(&expected, 0.0), "{:?}", format);
+            }
+            let (bounds, expected) = (loaded.bounds.unwrap(), model.bounds.unwrap());
+            assert_eq!(
+                (bounds.min.y, bounds.max.x),
+                (expected.min.y, expected.max.x),
+                "{:?}",
+                format
+            );
+
+            let colors = loaded.colors.unwrap();
+            for (actual, expected) in colors.iter().zip(model.colors.as_ref().unwrap()) {
+                for channel in 0..4 {
+                    assert!((actual[channel] - expected[channel]).abs() < 1e-6);
+                }
+            }
+            assert_eq!(loaded.properties, model.properties, "{:?}", format);
+            assert_eq!(loaded.comments, ["made by a test"]);
+        }
+    }
+
+    #[test]
+    fn colors_are_scaled_by_their_type_and_unknown_properties_kept() {
+        let bytes = b"ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+property ushort intensity
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 51 0.5 7
+1 0 0 0 255 102 0.25 65535
+0 1 0 0 0 0 1 0
+3 0 1 2
+";
+        let model = parse_ply(bytes).unwrap();
+        let colors = model.colors.unwrap();
+        assert_eq!(colors[0], [1.0, 0.0, 0.2, 1.0]);
+        assert_eq!(colors[1], [0.0, 1.0, 0.4, 1.0]);
+        let names: Vec<(&str, PlyType)> = model
+            .properties
+            .iter()
+            .map(|property| (property.name.as_str(), property.data_type))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("confidence", PlyType::Float32),
+                ("intensity", PlyType::Uint16)
+            ]
+        );
+        assert_eq!(model.properties[0].values, [0.5, 0.25, 1.0]);
+        assert_eq!(model.properties[1].values, [7.0, 65535.0, 0.0]);
+
+        let bytes = b"ply
+format ascii 1.0
+element vertex 1
+property float x
+property float y
+property float z
+property float r
+property float g
+property float b
+property float alpha
+end_header
+0 0 0 0.5 0.25 1 0.75
+";
+        let model = parse_ply(bytes).unwrap();
+        assert_eq!(model.colors.unwrap(), [[0.5, 0.25, 1.0, 0.75]]);
+        assert!(model.properties.is_empty());
+    }
+
+    #[test]
+    fn polygons_before_vertices_are_split_by_their_positions() {
+        // Concave at the third corner, so only the diagonal from the first corner is inside
+        let bytes = b"ply
+format ascii 1.0
+element face 1
+property list uchar int vertex_indices
+element vertex 4
+property float x
+property float y
+property float z
+end_header
+4 0 1 2 3
+0 0 0
+2 0 0
+1 0.5 0
+1 2 0
+";
+        let model = parse_ply(bytes).unwrap();
+        let mesh = &model.mesh;
+        assert_eq!(mesh.triangle_count(), 2);
+        for [a, b, c] in mesh.triangles() {
+            let position = |index: u32| mesh.vertices[index as usize].position;
+            let (a, b, c) = (position(a), position(b), position(c));
+            assert!(calc_cross_product(&(b - a), &(c - a)).z > 0.0);
+        }
+    }
+
+    #[test]
+    fn corrupt_files_are_invalid() {
+        let header = "ply\nformat binary_little_endian 1.0\nelement vertex 2\nproperty float x\n\
+                      property float y\nproperty float z\nend_header\n";
+        let mut bytes = header.as_bytes().to_vec();
+        for component in [0.0f32, 0.0, 0.0, 1.0, 1.0] {
+            bytes.extend_from_slice(&component.to_le_bytes());
+        }
+        assert_eq!(error(&bytes), "file ends inside vertex");
+
+        let out_of_range = b"ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+3 0 1 3
+";
+        assert_eq!(
+            error(out_of_range),
+            "face index 3 is out of range (3 vertices)"
+        );
+
+        let no_end = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n0\n";
+        assert_eq!(error(no_end), "no end_header line");
+
+        // A count this large must run out of body rather than memory
+        let huge = b"ply
+format ascii 1.0
+element vertex 18446744073709551615
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+end_header
+0 0 0 255 255 255 1
+";
+        assert_eq!(error(huge), "file ends inside vertex");
+    }
+}
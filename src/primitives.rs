@@ -0,0 +1,508 @@
+//! Generators for common shapes. Every shape is centred on the origin, has unit-length normals
+//! pointing outwards and is wound counter-clockwise when seen from outside, so it works with
+//! back-face culling. Texture coordinates run from 0 to 1 unless noted otherwise.
+
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::{
+    mesh::{Mesh, MeshVertex},
+    normals::{fallback_tangent, orthogonalize},
+    vector::{Vector2, Vector3, Vector4, calc_cross_product},
+};
+
+/// Parameter step used to estimate tangents from neighbouring points of a surface
+const STEP: f32 = 1e-3;
+
+/// Adds the triangle so that it is counter-clockwise when seen from the side its normals point
+/// to. Triangles without area, such as those touching a pole, are skipped.
+fn push_triangle(vertices: &[MeshVertex], indices: &mut Vec<u32>, [a, b, c]: [u32; 3]) {
+    let (first, second, third) = (
+        &vertices[a as usize],
+        &vertices[b as usize],
+        &vertices[c as usize],
+    );
+    let face = calc_cross_product(
+        &(second.position - first.position),
+        &(third.position - first.position),
+    );
+    if face.magnitude() <= 1e-12 {
+        return;
+    }
+    let normal = first.normal + second.normal + third.normal;
+    if Vector3::dot_product(&face, &normal) >= 0.0 {
+        indices.extend_from_slice(&[a, b, c]);
+    } else {
+        indices.extend_from_slice(&[a, c, b]);
+    }
+}
+
+/// Estimates the direction in which the texture's u increases at (u, v) of a surface
+fn surface_tangent(
+    surface: &impl Fn(f32, f32) -> (Vector3, Vector3, Vector2),
+    u: f32,
+    v: f32,
+    normal: &Vector3,
+) -> Vector4 {
+    // Poles and apexes do not move along u, so fall back to a point just beside them
+    let nudged = if v < 0.5 {
+        v + 10.0 * STEP
+    } else {
+        v - 10.0 * STEP
+    };
+    for v in [v, nudged] {
+        let (before_u, _, uv_before_u) = surface(u - STEP, v);
+        let (after_u, _, uv_after_u) = surface(u + STEP, v);
+        let (before_v, _, uv_before_v) = surface(u, v - STEP);
+        let (after_v, _, uv_after_v) = surface(u, v + STEP);
+        let (edge_u, edge_v) = (after_u - before_u, after_v - before_v);
+        let (delta_u, delta_v) = (uv_after_u - uv_before_u, uv_after_v - uv_before_v);
+
+        let determinant = delta_u.x * delta_v.y - delta_v.x * delta_u.y;
+        if determinant.abs() < 1e-12 {
+            continue;
+        }
+        let tangent = (1.0 / determinant) * (delta_v.y * edge_u - delta_u.y * edge_v);
+        let bitangent = (1.0 / determinant) * (delta_u.x * edge_v - delta_v.x * edge_u);
+        if let Some(tangent) = orthogonalize(normal, &tangent, &bitangent) {
+            return tangent;
+        }
+    }
+    fallback_tangent(normal)
+}
+
+/// Samples a surface on a grid of columns x rows quads. The surface maps (u, v) in [0, 1] to a
+/// position, a unit normal and texture coordinates, and must be defined slightly outside that
+/// range so tangents can be estimated at the edges.
+fn parametric_surface(
+    columns: u32,
+    rows: u32,
+    surface: impl Fn(f32, f32) -> (Vector3, Vector3, Vector2),
+) -> Mesh {
+    let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+    for row in 0..=rows {
+        let v = row as f32 / rows as f32;
+        for column in 0..=columns {
+            let u = column as f32 / columns as f32;
+            let (position, normal, uv) = surface(u, v);
+            let tangent = surface_tangent(&surface, u, v, &normal);
+            vertices.push(MeshVertex {
+                position,
+                normal,
+                uv,
+                tangent,
+            });
+        }
+    }
+
+    let stride = columns + 1;
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let corner = row * stride + column;
+            let (right, up) = (corner + 1, corner + stride);
+            push_triangle(&vertices, &mut indices, [corner, right, up + 1]);
+            push_triangle(&vertices, &mut indices, [corner, up + 1, up]);
+        }
+    }
+    Mesh::new(vertices, indices)
+}
+
+/// Sine and cosine of a polar angle of pi * v, exact at the poles so that pole triangles collapse
+fn polar(v: f32) -> (f32, f32) {
+    if v == 0.0 {
+        (0.0, 1.0)
+    } else if v == 1.0 {
+        (0.0, -1.0)
+    } else {
+        (PI * v).sin_cos()
+    }
+}
+
+/// Sine and cosine of a full turn times t, exact at both ends so that the vertices on either
+/// side of a texture seam have the same position
+fn turn(t: f32) -> (f32, f32) {
+    if t == 0.0 || t == 1.0 {
+        (0.0, 1.0)
+    } else {
+        (2.0 * PI * t).sin_cos()
+    }
+}
+
+/// A point on a surface of revolution around the y axis. The angle is 2 * pi * u and the
+/// profile gives the distance from the axis, the height and the normal's (radial, y) direction.
+fn revolve(u: f32, radius: f32, y: f32, normal: (f32, f32)) -> (Vector3, Vector3) {
+    let (sin, cos) = turn(u);
+    (
+        Vector3::new(radius * cos, y, -radius * sin),
+        Vector3::new(normal.0 * cos, normal.1, -normal.0 * sin),
+    )
+}
+
+/// A flat disc at height y facing up or down, with texture coordinates projected from above
+/// (or below) so the texture is not mirrored
+fn disc(radius: f32, y: f32, facing_up: bool, segments: u32, rings: u32) -> Mesh {
+    let sign = if facing_up { 1.0 } else { -1.0 };
+    parametric_surface(segments, rings, |u, v| {
+        let (position, _) = revolve(u, radius * v, y, (0.0, 0.0));
+        let uv = Vector2::new(
+            0.5 + 0.5 * position.x / radius,
+            0.5 - sign * 0.5 * position.z / radius,
+        );
+        (position, Vector3::new(0.0, sign, 0.0), uv)
+    })
+}
+
+/// An axis-aligned cube with size long edges. Each face is split into subdivisions x subdivisions
+/// quads and has the whole texture.
+pub fn cube(size: f32, subdivisions: u32) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+    let half = 0.5 * size;
+    // Normal, then the directions of u and v across the face as seen from outside
+    let faces = [
+        ((1.0, 0.0, 0.0), (0.0, 0.0, -1.0), (0.0, 1.0, 0.0)),
+        ((-1.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 0.0)),
+        ((0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, -1.0)),
+        ((0.0, -1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)),
+        ((0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)),
+        ((0.0, 0.0, -1.0), (-1.0, 0.0, 0.0), (0.0, 1.0, 0.0)),
+    ];
+
+    let mut mesh = Mesh::new(Vec::new(), Vec::new());
+    for (normal, across, up) in faces {
+        let normal = Vector3::new(normal.0, normal.1, normal.2);
+        let across = Vector3::new(across.0, across.1, across.2);
+        let up = Vector3::new(up.0, up.1, up.2);
+        mesh.append(&parametric_surface(subdivisions, subdivisions, |u, v| {
+            let position = half * normal + ((u - 0.5) * size) * across + ((v - 0.5) * size) * up;
+            (position, normal, Vector2::new(u, v))
+        }));
+    }
+    mesh
+}
+
+/// A flat grid in the xz plane facing +y, width along x and depth along z. The texture's v
+/// increases towards -z.
+pub fn plane(width: f32, depth: f32, columns: u32, rows: u32) -> Mesh {
+    parametric_surface(columns.max(1), rows.max(1), |u, v| {
+        (
+            Vector3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector2::new(u, v),
+        )
+    })
+}
+
+/// A sphere made of segments slices around the y axis and rings stacks from pole to pole. The
+/// texture wraps around once with v running from the bottom pole to the top one.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    parametric_surface(segments.max(3), rings.max(2), |u, v| {
+        let (sin, cos) = polar(v);
+        let (position, normal) = revolve(u, radius * sin, -radius * cos, (sin, -cos));
+        (position, normal, Vector2::new(u, v))
+    })
+}
+
+/// A sphere made by splitting each face of an icosahedron into four, subdivisions times. It has
+/// 20 * 4^subdivisions triangles of nearly equal size. Texture coordinates use the same mapping
+/// as uv_sphere, with vertices duplicated along the seam.
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut positions: Vec<Vector3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|(x, y, z)| Vector3::calc_normalized_vector(&Vector3::new(*x, *y, *z)))
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Edges are shared by two triangles, so each midpoint is made once
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let middle = positions[a as usize] + positions[b as usize];
+                positions.push(Vector3::calc_normalized_vector(&middle));
+                positions.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    // Texture coordinates depend on the triangle at the seam and the poles, so every corner gets
+    // its own vertex and identical ones are welded afterwards
+    let mut vertices = Vec::with_capacity(triangles.len() * 3);
+    for triangle in &triangles {
+        let corners = triangle.map(|index| positions[index as usize]);
+        let mut uvs = corners.map(|normal| {
+            let angle = (-normal.z).atan2(normal.x);
+            let u = if angle < 0.0 {
+                angle / (2.0 * PI) + 1.0
+            } else {
+                angle / (2.0 * PI)
+            };
+            Vector2::new(u, (-normal.y).clamp(-1.0, 1.0).acos() / PI)
+        });
+
+        // Corners on the far side of the seam wrap past 1
+        let max_u = uvs.iter().map(|uv| uv.x).fold(0.0, f32::max);
+        for uv in uvs.iter_mut() {
+            if max_u - uv.x > 0.5 {
+                uv.x += 1.0;
+            }
+        }
+        // A pole has no longitude, so it takes the middle of the other two corners
+        for corner in 0..3 {
+            if corners[corner].y.abs() > 1.0 - 1e-6 {
+                uvs[corner].x = (uvs[(corner + 1) % 3].x + uvs[(corner + 2) % 3].x) / 2.0;
+            }
+        }
+
+        for (normal, uv) in corners.iter().zip(uvs.iter()) {
+            // The mapping is not mirrored, so the bitangent is always cross(normal, tangent)
+            let (sin, cos) = (2.0 * PI * uv.x).sin_cos();
+            vertices.push(MeshVertex {
+                position: radius * normal,
+                normal: *normal,
+                uv: *uv,
+                tangent: Vector4::new(-sin, 0.0, -cos, 1.0),
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity(vertices.len());
+    for triangle in 0..triangles.len() as u32 {
+        push_triangle(
+            &vertices,
+            &mut indices,
+            [triangle * 3, triangle * 3 + 1, triangle * 3 + 2],
+        );
+    }
+    Mesh::new(vertices, indices).weld(1e-6)
+}
+
+/// A closed cylinder along the y axis. The caps are split into rings rings each.
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32, rings: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half = 0.5 * height;
+    let mut mesh = parametric_surface(segments, height_segments.max(1), |u, v| {
+        let (position, normal) = revolve(u, radius, (v - 0.5) * height, (1.0, 0.0));
+        (position, normal, Vector2::new(u, v))
+    });
+    mesh.append(&disc(radius, half, true, segments, rings.max(1)));
+    mesh.append(&disc(radius, -half, false, segments, rings.max(1)));
+    mesh
+}
+
+/// A closed cone along the y axis with its base at -height / 2 and its tip at +height / 2
+pub fn cone(radius: f32, height: f32, segments: u32, height_segments: u32, rings: u32) -> Mesh {
+    let segments = segments.max(3);
+    let slope = Vector3::calc_normalized_vector(&Vector3::new(height, radius, 0.0));
+    let mut mesh = parametric_surface(segments, height_segments.max(1), |u, v| {
+        let (position, normal) = revolve(
+            u,
+            radius * (1.0 - v),
+            (v - 0.5) * height,
+            (slope.x, slope.y),
+        );
+        (position, normal, Vector2::new(u, v))
+    });
+    mesh.append(&disc(radius, -0.5 * height, false, segments, rings.max(1)));
+    mesh
+}
+
+/// A torus around the y axis. major_radius is the distance from the centre to the middle of the
+/// tube and minor_radius the radius of the tube. u runs around the y axis and v around the tube.
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Mesh {
+    parametric_surface(segments.max(3), sides.max(3), |u, v| {
+        let (sin, cos) = turn(v);
+        let (position, normal) = revolve(
+            u,
+            major_radius + minor_radius * cos,
+            minor_radius * sin,
+            (cos, sin),
+        );
+        (position, normal, Vector2::new(u, v))
+    })
+}
+
+/// A cylinder with hemispheres on both ends, along the y axis. height is the length of the
+/// straight part, so the whole capsule is height + 2 * radius tall. Each hemisphere has rings
+/// stacks and v runs from the bottom pole to the top one.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(1);
+    let rows = 2 * rings + 1;
+    let half = 0.5 * height;
+    parametric_surface(segments.max(3), rows, |u, v| {
+        // Which stack of the profile v falls in: the bottom cap, the straight part or the top cap
+        let stack = v * rows as f32;
+        let (distance, y, normal) = if stack <= rings as f32 {
+            let (sin, cos) = polar(0.5 * stack / rings as f32);
+            (radius * sin, -half - radius * cos, (sin, -cos))
+        } else if stack <= (rings + 1) as f32 {
+            (radius, -half + (stack - rings as f32) * height, (1.0, 0.0))
+        } else {
+            let (sin, cos) = polar(0.5 + 0.5 * (stack - (rings + 1) as f32) / rings as f32);
+            (radius * sin, half - radius * cos, (sin, -cos))
+        };
+        let (position, normal) = revolve(u, distance, y, normal);
+        (position, normal, Vector2::new(u, v))
+    })
+}
+
+/// One triangle that covers the whole screen when drawn without transforms, with texture
+/// coordinates running from 0 to 1 across the visible part
+pub fn fullscreen_triangle() -> Mesh {
+    let normal = Vector3::new(0.0, 0.0, 1.0);
+    let tangent = Vector4::new(1.0, 0.0, 0.0, 1.0);
+    let vertex = |x: f32, y: f32| MeshVertex {
+        position: Vector3::new(x, y, 0.0),
+        normal,
+        uv: Vector2::new(0.5 * (x + 1.0), 0.5 * (y + 1.0)),
+        tangent,
+    };
+    Mesh::new(
+        vec![vertex(-1.0, -1.0), vertex(3.0, -1.0), vertex(-1.0, 3.0)],
+        vec![0, 1, 2],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks unit normals, triangles wound to face the way their normals do, and normals
+    /// pointing away from center(position), the point of the shape's core nearest a vertex
+    fn check(name: &str, mesh: &Mesh, center: impl Fn(&Vector3) -> Vector3) {
+        assert!(mesh.triangle_count() > 0, "{}", name);
+        for vertex in &mesh.vertices {
+            assert!(
+                (vertex.normal.magnitude() - 1.0).abs() < 1e-4,
+                "{}: normal of length {}",
+                name,
+                vertex.normal.magnitude()
+            );
+            let outward = vertex.position - center(&vertex.position);
+            assert!(
+                Vector3::dot_product(&vertex.normal, &outward) > 0.0,
+                "{}: normal points inwards at ({}, {}, {})",
+                name,
+                vertex.position.x,
+                vertex.position.y,
+                vertex.position.z
+            );
+        }
+        check_winding(name, mesh);
+    }
+
+    fn check_winding(name: &str, mesh: &Mesh) {
+        for [a, b, c] in mesh.triangles() {
+            let [a, b, c] = [a, b, c].map(|index| mesh.vertices[index as usize]);
+            let face = calc_cross_product(&(b.position - a.position), &(c.position - a.position));
+            let normal = a.normal + b.normal + c.normal;
+            assert!(
+                Vector3::dot_product(&face, &normal) > 0.0,
+                "{}: triangle wound against its normals",
+                name
+            );
+        }
+    }
+
+    fn origin(_: &Vector3) -> Vector3 {
+        Vector3::default()
+    }
+
+    #[test]
+    fn closed_shapes_have_unit_outward_normals() {
+        for detail in [1, 4] {
+            check("cube", &cube(2.0, detail), origin);
+            check("uv_sphere", &uv_sphere(1.5, 8 * detail, 4 * detail), origin);
+            check("icosphere", &icosphere(1.5, detail - 1), origin);
+            check(
+                "cylinder",
+                &cylinder(0.5, 2.0, 8 * detail, detail, detail),
+                origin,
+            );
+            check("cone", &cone(0.5, 2.0, 8 * detail, detail, detail), origin);
+            check(
+                "capsule",
+                &capsule(0.5, 1.0, 8 * detail, 2 * detail),
+                origin,
+            );
+        }
+    }
+
+    #[test]
+    fn torus_normals_point_away_from_the_tube() {
+        let major_radius = 1.0;
+        for (segments, sides) in [(8, 6), (48, 24)] {
+            check(
+                "torus",
+                &torus(major_radius, 0.25, segments, sides),
+                |position| {
+                    // The nearest point on the circle through the middle of the tube
+                    let around = Vector3::new(position.x, 0.0, position.z);
+                    major_radius * Vector3::calc_normalized_vector(&around)
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn flat_shapes_face_their_side() {
+        for (columns, rows) in [(1, 1), (5, 3)] {
+            let plane = plane(2.0, 1.0, columns, rows);
+            assert!(plane.vertices.iter().all(|vertex| vertex.position.y == 0.0
+                && vertex.normal.x == 0.0
+                && vertex.normal.y == 1.0
+                && vertex.normal.z == 0.0));
+            check_winding("plane", &plane);
+        }
+
+        let triangle = fullscreen_triangle();
+        assert!(
+            triangle
+                .vertices
+                .iter()
+                .all(|vertex| vertex.normal.z == 1.0)
+        );
+        check_winding("fullscreen_triangle", &triangle);
+    }
+}
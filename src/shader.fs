@@ -1,13 +1,13 @@
 #version 330 core
-in vec3 ourColor;
 in vec2 TexCoord;
 
 out vec4 FragColor;
 
 uniform sampler2D texture1;
 uniform sampler2D texture2;
+uniform float mixValue;
 
 void main()
 {
-   FragColor = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.2);
+   FragColor = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), mixValue);
 }
\ No newline at end of file
@@ -1,150 +1,697 @@
-use std::{fs::read_to_string, mem::zeroed, path::Path, ptr::null};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::CString,
+    fmt,
+    fs::read_to_string,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use glad_gl::gl::{self, GLchar, GLfloat, GLsizei};
+use glad_gl::gl::{self, GLenum, GLint, GLuint};
 
-use crate::matrix::Matrix4;
+use crate::{
+    gl_backend::{UniformValue, with_backend},
+    gl_objects::Program,
+    matrix::{Matrix3, Matrix4},
+    shader_reflection::{ProgramReflection, base_name, glsl_type_name, glsl_type_size},
+    transform_feedback::TransformFeedbackMode,
+    vector::{Vector2, Vector3, Vector4},
+};
 
 pub struct ShaderProgram {
-    handle: u32,
+    program: Program,
+    uniform_locations: RefCell<HashMap<String, GLint>>,
+    reflection: ProgramReflection,
 }
 
-impl ShaderProgram {
-    pub fn new(vertex_path: &Path, fragment_path: &Path) -> Self {
-        // Build and compile our vertex shader
-        let vertex_shader = unsafe {
-            let mut vertex_shader_source = read_to_string(vertex_path).unwrap();
-            vertex_shader_source.push('\0');
-
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            let vertex_shader_source = {
-                let boxed_source_ptr = Box::new(vertex_shader_source.as_ptr());
-                Box::into_raw(boxed_source_ptr)
-            };
-            gl::ShaderSource(
-                vertex_shader,
-                1,
-                vertex_shader_source as *const *const GLchar,
-                null(),
-            );
-            gl::CompileShader(vertex_shader);
-
-            // Check for compilation errors
-            let mut success: i32 = 0;
-            let mut info_log: [u8; 512] = zeroed();
-            gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success as *mut i32);
-            if success == 0 {
-                gl::GetShaderInfoLog(
-                    vertex_shader,
-                    512,
-                    0 as *mut GLsizei,
-                    info_log.as_mut_ptr() as *mut GLchar,
-                );
-                eprintln!("Vertex shader compilation error");
-                eprintln!("Error info: {}", str::from_utf8(&info_log).unwrap());
-                panic!("panic");
-            }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+}
 
-            vertex_shader
-        };
+impl ShaderStage {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+        }
+    }
 
-        // Build and compile our fragment shader
-        let fragment_shader = unsafe {
-            let mut fragment_shader_source = read_to_string(fragment_path).unwrap();
-            fragment_shader_source.push('\0');
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "Vertex",
+            ShaderStage::Geometry => "Geometry",
+            ShaderStage::Fragment => "Fragment",
+        }
+    }
+}
 
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            let fragment_shader_source = {
-                let boxed_source_ptr = Box::new(fragment_shader_source.as_ptr());
-                Box::into_raw(boxed_source_ptr)
-            };
-            gl::ShaderSource(
-                fragment_shader,
-                1,
-                fragment_shader_source as *const *const GLchar,
-                null(),
-            );
-            gl::CompileShader(fragment_shader);
-
-            // Check for compilation errors
-            let mut success: i32 = 0;
-            let mut info_log: [u8; 512] = zeroed();
-            gl::GetShaderiv(
-                fragment_shader,
-                gl::COMPILE_STATUS,
-                &mut success as *mut i32,
-            );
-            if success == 0 {
-                gl::GetShaderInfoLog(
-                    fragment_shader,
-                    512,
-                    0 as *mut GLsizei,
-                    info_log.as_mut_ptr() as *mut GLchar,
-                );
-                eprintln!("Fragment shader compilation error");
-                eprintln!("Error info: {}", str::from_utf8(&info_log).unwrap());
-                panic!("panic");
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    InvalidSource {
+        label: String,
+    },
+    Compile {
+        stage: ShaderStage,
+        label: String,
+        log: String,
+    },
+    Link {
+        label: String,
+        log: String,
+    },
+    MissingStage(ShaderStage),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => {
+                write!(f, "Failed to read shader {}: {}", path.display(), error)
             }
+            ShaderError::InvalidSource { label } => {
+                write!(f, "Shader source {} contains a null byte", label)
+            }
+            ShaderError::Compile { stage, label, log } => write!(
+                f,
+                "{} shader compilation error ({})\nError info: {}",
+                stage.name(),
+                label,
+                log
+            ),
+            ShaderError::Link { label, log } => {
+                write!(
+                    f,
+                    "Shader program linking error ({})\nError info: {}",
+                    label, log
+                )
+            }
+            ShaderError::MissingStage(stage) => {
+                write!(f, "Shader program has no {} stage", stage.name())
+            }
+        }
+    }
+}
+
+enum StageSource {
+    File(PathBuf),
+    Memory { label: String, source: String },
+}
+
+/// Collects the stages of a program, then compiles and links them in build().
+/// Any combination of stages is accepted as long as there is a vertex and a fragment stage.
+pub struct ShaderProgramBuilder {
+    stages: Vec<(ShaderStage, StageSource)>,
+    feedback_varyings: Vec<String>,
+    feedback_mode: TransformFeedbackMode,
+}
+
+impl ShaderProgramBuilder {
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            feedback_varyings: Vec::new(),
+            feedback_mode: TransformFeedbackMode::Interleaved,
+        }
+    }
+
+    pub fn stage_file(mut self, stage: ShaderStage, path: &Path) -> Self {
+        self.stages
+            .push((stage, StageSource::File(path.to_path_buf())));
+        self
+    }
+
+    /// Adds a stage from GLSL held in memory. The label is only used in error messages.
+    pub fn stage_source(mut self, stage: ShaderStage, label: &str, source: &str) -> Self {
+        self.stages.push((
+            stage,
+            StageSource::Memory {
+                label: label.to_string(),
+                source: source.to_string(),
+            },
+        ));
+        self
+    }
+
+    pub fn vertex_file(self, path: &Path) -> Self {
+        self.stage_file(ShaderStage::Vertex, path)
+    }
 
-            fragment_shader
+    pub fn geometry_file(self, path: &Path) -> Self {
+        self.stage_file(ShaderStage::Geometry, path)
+    }
+
+    pub fn fragment_file(self, path: &Path) -> Self {
+        self.stage_file(ShaderStage::Fragment, path)
+    }
+
+    /// Captures the named outputs of the last vertex processing stage with transform feedback.
+    /// Programs that capture output may omit the fragment stage.
+    pub fn transform_feedback_varyings(
+        mut self,
+        varyings: &[&str],
+        mode: TransformFeedbackMode,
+    ) -> Self {
+        self.feedback_varyings = varyings.iter().map(|name| name.to_string()).collect();
+        self.feedback_mode = mode;
+        self
+    }
+
+    pub fn build(self) -> Result<ShaderProgram, ShaderError> {
+        let required_stages: &[ShaderStage] = if self.feedback_varyings.is_empty() {
+            &[ShaderStage::Vertex, ShaderStage::Fragment]
+        } else {
+            &[ShaderStage::Vertex]
         };
+        for required in required_stages.iter().copied() {
+            if !self.stages.iter().any(|(stage, _)| *stage == required) {
+                return Err(ShaderError::MissingStage(required));
+            }
+        }
+
+        // Load all of the sources before touching GL so that a missing file leaks nothing
+        let mut sources = Vec::with_capacity(self.stages.len());
+        for (stage, stage_source) in self.stages {
+            let (label, source) = match stage_source {
+                StageSource::File(path) => {
+                    let source = read_to_string(&path).map_err(|error| ShaderError::Io {
+                        path: path.clone(),
+                        error,
+                    })?;
+                    let label = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| path.display().to_string());
+                    (label, source)
+                }
+                StageSource::Memory { label, source } => (label, source),
+            };
+            sources.push((stage, label, source));
+        }
+        let program_label = sources
+            .iter()
+            .map(|(_, label, _)| label.as_str())
+            .collect::<Vec<&str>>()
+            .join("+");
+
+        let feedback_varyings = self
+            .feedback_varyings
+            .iter()
+            .map(|name| CString::new(name.as_str()))
+            .collect::<Result<Vec<CString>, _>>()
+            .map_err(|_| ShaderError::InvalidSource {
+                label: program_label.clone(),
+            })?;
+
+        // Compile each stage
+        let mut shaders = Vec::with_capacity(sources.len());
+        for (stage, label, source) in &sources {
+            match compile_shader(*stage, label, source) {
+                Ok(shader) => shaders.push(shader),
+                Err(error) => {
+                    with_backend(|backend| {
+                        for shader in shaders {
+                            backend.delete_shader(shader);
+                        }
+                    });
+                    return Err(error);
+                }
+            }
+        }
 
         // Create the shader program
-        let shader_program = unsafe {
-            let shader_program = gl::CreateProgram();
-
-            gl::AttachShader(shader_program, vertex_shader);
-            gl::AttachShader(shader_program, fragment_shader);
-            gl::LinkProgram(shader_program);
-
-            let mut success: i32 = 0;
-            let mut info_log: [u8; 512] = zeroed();
-            gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success as *mut i32);
-            if success == 0 {
-                gl::GetProgramInfoLog(
+        let shader_program = with_backend(|backend| {
+            let shader_program = backend.create_program();
+            for shader in &shaders {
+                backend.attach_shader(shader_program, *shader);
+            }
+
+            // Captured varyings have to be declared before linking
+            if !feedback_varyings.is_empty() {
+                backend.transform_feedback_varyings(
                     shader_program,
-                    512,
-                    0 as *mut GLsizei,
-                    info_log.as_mut_ptr() as *mut GLchar,
+                    &feedback_varyings,
+                    self.feedback_mode.gl_enum(),
                 );
-                eprintln!("Shader program linking error");
-                eprintln!("Error info: {}", str::from_utf8(&info_log).unwrap());
+            }
+            let linked = backend.link_program(shader_program);
+
+            // The program keeps what it needs after linking
+            for shader in shaders {
+                backend.detach_shader(shader_program, shader);
+                backend.delete_shader(shader);
+            }
+
+            match linked {
+                Ok(()) => Ok(shader_program),
+                Err(log) => {
+                    backend.delete_program(shader_program);
+                    Err(ShaderError::Link {
+                        label: program_label.clone(),
+                        log,
+                    })
+                }
+            }
+        })?;
+
+        Ok(ShaderProgram::from_linked(shader_program, &program_label))
+    }
+}
+
+fn compile_shader(stage: ShaderStage, label: &str, source: &str) -> Result<GLuint, ShaderError> {
+    let source = CString::new(source).map_err(|_| ShaderError::InvalidSource {
+        label: label.to_string(),
+    })?;
+
+    with_backend(|backend| {
+        let shader = backend.create_shader(stage);
+        match backend.compile_shader(shader, &source) {
+            Ok(()) => Ok(shader),
+            Err(log) => {
+                backend.delete_shader(shader);
+                Err(ShaderError::Compile {
+                    stage,
+                    label: label.to_string(),
+                    log,
+                })
+            }
+        }
+    })
+}
+
+impl ShaderProgram {
+    pub fn new(vertex_path: &Path, fragment_path: &Path) -> Self {
+        let result = ShaderProgramBuilder::new()
+            .vertex_file(vertex_path)
+            .fragment_file(fragment_path)
+            .build();
+
+        match result {
+            Ok(program) => program,
+            Err(error) => {
+                eprintln!("{}", error);
                 panic!("panic");
             }
+        }
+    }
 
-            shader_program
-        };
+    pub fn builder() -> ShaderProgramBuilder {
+        ShaderProgramBuilder::new()
+    }
 
-        unsafe {
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+    fn from_linked(shader_program: GLuint, label: &str) -> Self {
+        // Seed the location cache with everything the driver reports as active
+        let reflection = with_backend(|backend| backend.reflect_program(shader_program));
+        let mut uniform_locations = HashMap::new();
+        for uniform in reflection.uniforms.iter().filter(|u| u.location != -1) {
+            uniform_locations.insert(uniform.name.clone(), uniform.location);
+            uniform_locations.insert(base_name(&uniform.name).to_string(), uniform.location);
         }
 
+        let program = Program::from_handle(shader_program);
+        program.set_label(label);
+
         Self {
-            handle: shader_program,
+            program,
+            uniform_locations: RefCell::new(uniform_locations),
+            reflection,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn handle(&self) -> GLuint {
+        self.program.handle()
+    }
+
+    pub fn reflection(&self) -> &ProgramReflection {
+        &self.reflection
+    }
+
+    /// Connects a uniform block of this program to a uniform buffer binding point
+    pub fn bind_uniform_block(&self, block_name: &str, binding: GLuint) {
+        match self.reflection.uniform_block(block_name) {
+            Some(block) => with_backend(|backend| {
+                backend.uniform_block_binding(self.program.handle(), block.index, binding)
+            }),
+            None => eprintln!(
+                "Warning: uniform block \"{}\" is not an active block of the program",
+                block_name
+            ),
         }
     }
 
     pub fn use_program(&self) {
-        unsafe { gl::UseProgram(self.handle) }
+        self.program.bind();
     }
 
-    pub fn set_int(&self, name: &str, value: i32) {
-        unsafe {
-            gl::Uniform1i(
-                gl::GetUniformLocation(self.handle, name.as_ptr() as *const GLchar),
-                value,
+    /// Returns the location of a uniform, looking it up on first use and caching it afterwards.
+    /// Unknown names are reported once and return None so that nothing is written to location -1.
+    fn uniform_location(&self, name: &str) -> Option<GLint> {
+        if let Some(location) = self.uniform_locations.borrow().get(name) {
+            return if *location == -1 {
+                None
+            } else {
+                Some(*location)
+            };
+        }
+
+        let location = match CString::new(name) {
+            Ok(c_name) => {
+                with_backend(|backend| backend.get_uniform_location(self.program.handle(), &c_name))
+            }
+            Err(_) => -1,
+        };
+        if location == -1 {
+            eprintln!(
+                "Warning: uniform \"{}\" is not an active uniform of the program",
+                name
             );
         }
+        self.uniform_locations
+            .borrow_mut()
+            .insert(name.to_string(), location);
+
+        if location == -1 { None } else { Some(location) }
+    }
+
+    /// Uploads a value to the uniform of the program in use, unless the name is unknown
+    fn set_uniform(&self, name: &str, value: UniformValue) {
+        if let Some(location) = self.uniform_location(name) {
+            with_backend(|backend| backend.uniform(location, value));
+        }
+    }
+
+    pub fn set_int(&self, name: &str, value: i32) {
+        self.set_uniform(name, UniformValue::Int(&[value]));
+    }
+
+    pub fn set_uint(&self, name: &str, value: u32) {
+        self.set_uniform(name, UniformValue::UInt(&[value]));
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) {
+        self.set_uniform(name, UniformValue::Float(&[value]));
+    }
+
+    /// Booleans are uploaded as ints, which is how GLSL expects bool uniforms to be set
+    pub fn set_bool(&self, name: &str, value: bool) {
+        self.set_int(name, value as i32);
+    }
+
+    pub fn set_vec2(&self, name: &str, value: &Vector2) {
+        self.set_uniform(name, UniformValue::Vec2(&[value.x, value.y]));
+    }
+
+    pub fn set_vec3(&self, name: &str, value: &Vector3) {
+        self.set_uniform(name, UniformValue::Vec3(&[value.x, value.y, value.z]));
+    }
+
+    pub fn set_vec4(&self, name: &str, value: &Vector4) {
+        self.set_uniform(
+            name,
+            UniformValue::Vec4(&[value.x, value.y, value.z, value.w]),
+        );
+    }
+
+    pub fn set_mat3(&self, name: &str, value: &Matrix3) {
+        self.set_uniform(name, UniformValue::Mat3(value.data.as_flattened()));
     }
 
     pub fn set_mat4(&self, name: &str, value: &Matrix4) {
-        unsafe {
-            let location = gl::GetUniformLocation(self.handle, name.as_ptr() as *const GLchar);
-            let error = gl::GetError();
-            assert_eq!(error, 0);
-            gl::UniformMatrix4fv(location, 1, gl::TRUE, value.data.as_ptr() as *const GLfloat);
-            let error = gl::GetError();
-            assert_eq!(error, 0);
+        self.set_uniform(name, UniformValue::Mat4(value.data.as_flattened()));
+    }
+
+    pub fn set_int_array(&self, name: &str, values: &[i32]) {
+        self.set_uniform(name, UniformValue::Int(values));
+    }
+
+    pub fn set_uint_array(&self, name: &str, values: &[u32]) {
+        self.set_uniform(name, UniformValue::UInt(values));
+    }
+
+    pub fn set_float_array(&self, name: &str, values: &[f32]) {
+        self.set_uniform(name, UniformValue::Float(values));
+    }
+
+    pub fn set_vec3_array(&self, name: &str, values: &[Vector3]) {
+        let data: Vec<f32> = values.iter().flat_map(|v| [v.x, v.y, v.z]).collect();
+        self.set_uniform(name, UniformValue::Vec3(&data));
+    }
+
+    pub fn set_vec4_array(&self, name: &str, values: &[Vector4]) {
+        let data: Vec<f32> = values.iter().flat_map(|v| [v.x, v.y, v.z, v.w]).collect();
+        self.set_uniform(name, UniformValue::Vec4(&data));
+    }
+
+    pub fn set_mat4_array(&self, name: &str, values: &[Matrix4]) {
+        let data: Vec<f32> = values
+            .iter()
+            .flat_map(|m| m.data.iter().flatten().copied())
+            .collect();
+        self.set_uniform(name, UniformValue::Mat4(&data));
+    }
+
+    /// Sets a uniform from text in the type the program declares it with, so that uniforms can
+    /// be edited by name without knowing their types. Values are separated by spaces or commas,
+    /// arrays list every element in turn and matrices are written row by row, e.g. "0.5" for a
+    /// float, "1 0 0" for a vec3 or "true" for a bool.
+    pub fn set_uniform_from_str(&self, name: &str, text: &str) -> Result<(), String> {
+        let Some(uniform) = self.reflection.uniform(name) else {
+            return Err(format!("\"{}\" is not an active uniform", name));
+        };
+        if uniform.block_index != -1 {
+            return Err(format!(
+                "\"{}\" is in a uniform block and is set through its buffer",
+                name
+            ));
+        }
+        let words: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+        let length = uniform.size.max(1) as usize;
+        // Samplers are set to the texture unit they read from
+        let components = (glsl_type_size(uniform.gl_type) / 4).max(1);
+        if words.len() != components * length {
+            return Err(format!(
+                "\"{}\" is {}{} and needs {} values, got {}",
+                name,
+                glsl_type_name(uniform.gl_type),
+                if length > 1 {
+                    format!("[{}]", length)
+                } else {
+                    String::new()
+                },
+                components * length,
+                words.len()
+            ));
+        }
+        let floats = || parse_values::<f32>(name, &words);
+
+        match (uniform.gl_type, length) {
+            (gl::FLOAT, 1) => self.set_float(name, floats()?[0]),
+            (gl::FLOAT, _) => self.set_float_array(name, &floats()?),
+            (gl::FLOAT_VEC2, 1) => {
+                let values = floats()?;
+                self.set_vec2(name, &Vector2::new(values[0], values[1]));
+            }
+            (gl::FLOAT_VEC3, _) => {
+                let values: Vec<Vector3> = floats()?
+                    .chunks(3)
+                    .map(|v| Vector3::new(v[0], v[1], v[2]))
+                    .collect();
+                if length == 1 {
+                    self.set_vec3(name, &values[0]);
+                } else {
+                    self.set_vec3_array(name, &values);
+                }
+            }
+            (gl::FLOAT_VEC4, _) => {
+                let values: Vec<Vector4> = floats()?
+                    .chunks(4)
+                    .map(|v| Vector4::new(v[0], v[1], v[2], v[3]))
+                    .collect();
+                if length == 1 {
+                    self.set_vec4(name, &values[0]);
+                } else {
+                    self.set_vec4_array(name, &values);
+                }
+            }
+            (gl::FLOAT_MAT3, 1) => {
+                let values = floats()?;
+                let data = std::array::from_fn(|row| {
+                    std::array::from_fn(|column| values[3 * row + column])
+                });
+                self.set_mat3(name, &Matrix3 { data });
+            }
+            (gl::FLOAT_MAT4, _) => {
+                let values: Vec<Matrix4> = floats()?
+                    .chunks(16)
+                    .map(|v| Matrix4 {
+                        data: std::array::from_fn(|row| {
+                            std::array::from_fn(|column| v[4 * row + column])
+                        }),
+                    })
+                    .collect();
+                if length == 1 {
+                    self.set_mat4(name, &values[0]);
+                } else {
+                    self.set_mat4_array(name, &values);
+                }
+            }
+            (gl::INT | gl::SAMPLER_2D | gl::SAMPLER_CUBE, 1) => {
+                self.set_int(name, parse_values(name, &words)?[0])
+            }
+            (gl::INT | gl::SAMPLER_2D | gl::SAMPLER_CUBE, _) => {
+                self.set_int_array(name, &parse_values(name, &words)?)
+            }
+            (gl::UNSIGNED_INT, 1) => self.set_uint(name, parse_values(name, &words)?[0]),
+            (gl::UNSIGNED_INT, _) => self.set_uint_array(name, &parse_values(name, &words)?),
+            (gl::BOOL, 1) => match words[0] {
+                "true" | "1" => self.set_bool(name, true),
+                "false" | "0" => self.set_bool(name, false),
+                word => return Err(format!("\"{}\" is a bool, got {}", name, word)),
+            },
+            (gl_type, _) => {
+                return Err(format!(
+                    "\"{}\" is {}{}, which cannot be set from text",
+                    name,
+                    glsl_type_name(gl_type),
+                    if length > 1 { " array" } else { "" }
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses every word as a T, naming the uniform in the error
+fn parse_values<T: FromStr>(name: &str, words: &[&str]) -> Result<Vec<T>, String> {
+    words
+        .iter()
+        .map(|word| {
+            word.parse()
+                .map_err(|_| format!("\"{}\" cannot be set to {}", name, word))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_backend::{RecordingBackend, set_backend};
+
+    const VERTEX: &str = "#version 330 core
+layout (location = 0) in vec3 position;
+out vec3 color;
+void main()
+{
+    gl_Position = vec4(position, 1.0);
+    color = position;
+}
+";
+
+    #[test]
+    fn failed_builds_leave_no_objects_behind() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(recording.clone()));
+
+        let broken = "#version 330 core\nvoid main()\n{\n";
+        let result = ShaderProgram::builder()
+            .stage_source(ShaderStage::Vertex, "vertex", VERTEX)
+            .stage_source(ShaderStage::Fragment, "broken", broken)
+            .build();
+        assert!(matches!(
+            result,
+            Err(ShaderError::Compile {
+                stage: ShaderStage::Fragment,
+                ..
+            })
+        ));
+
+        // The fragment shader reads an input the vertex shader does not write
+        let mismatched = "#version 330 core
+in vec2 uv;
+out vec4 fragment_color;
+void main()
+{
+    fragment_color = vec4(uv, 0.0, 1.0);
+}
+";
+        let result = ShaderProgram::builder()
+            .stage_source(ShaderStage::Vertex, "vertex", VERTEX)
+            .stage_source(ShaderStage::Fragment, "mismatched", mismatched)
+            .build();
+        assert!(matches!(result, Err(ShaderError::Link { .. })));
+
+        assert!(recording.live_objects().is_empty());
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+    }
+
+    #[test]
+    fn uniforms_are_set_from_text_by_their_declared_type() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(recording.clone()));
+
+        let fragment = "#version 330 core
+in vec3 color;
+out vec4 fragment_color;
+uniform float strength;
+uniform vec3 tints[2];
+uniform mat3 rotation;
+uniform bool enabled;
+uniform uint steps;
+uniform sampler2D image;
+uniform vec2 offsets[2];
+void main()
+{
+    vec3 tinted = rotation * (tints[0] + tints[1] + vec3(offsets[0] + offsets[1], 0.0)) * color;
+    vec4 sampled = texture(image, tinted.xy) * float(steps);
+    fragment_color = enabled ? strength * sampled : vec4(0.0);
+}
+";
+        let program = ShaderProgram::builder()
+            .stage_source(ShaderStage::Vertex, "vertex", VERTEX)
+            .stage_source(ShaderStage::Fragment, "fragment", fragment)
+            .build()
+            .unwrap();
+        program.use_program();
+
+        for (name, text) in [
+            ("strength", "0.5"),
+            ("tints", "1 0 0, 0 1 0"),
+            ("rotation", "1 0 0 0 1 0 0 0 1"),
+            ("enabled", "true"),
+            ("steps", "4"),
+            ("image", "2"),
+        ] {
+            assert_eq!(program.set_uniform_from_str(name, text), Ok(()), "{}", name);
+        }
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+
+        let failures = [
+            ("missing", "1"),
+            ("tints", "1 0 0"),
+            ("strength", "half"),
+            ("steps", "-1"),
+            ("enabled", "yes"),
+            ("offsets", "0 0 1 1"),
+        ];
+        for (name, text) in failures {
+            assert!(
+                program.set_uniform_from_str(name, text).is_err(),
+                "{}",
+                name
+            );
         }
     }
 }
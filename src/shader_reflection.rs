@@ -0,0 +1,686 @@
+use std::fmt;
+
+use glad_gl::gl::{self, GLchar, GLenum, GLint, GLsizei, GLuint};
+
+#[cfg(test)]
+use crate::{
+    glsl::{StageInterface, Variable},
+    shader::ShaderStage,
+};
+
+/// An active uniform of a linked program.
+/// location is -1 for uniforms that live inside a uniform block, in which case block_index and
+/// offset describe where the uniform is stored in the block.
+#[derive(Clone)]
+pub struct UniformInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub size: GLint,
+    pub location: GLint,
+    pub block_index: GLint,
+    pub offset: GLint,
+}
+
+/// An active vertex attribute (vertex shader input) of a linked program
+#[derive(Clone)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub size: GLint,
+    pub location: GLint,
+}
+
+/// An active uniform block of a linked program
+#[derive(Clone)]
+pub struct UniformBlockInfo {
+    pub name: String,
+    pub index: GLuint,
+    pub data_size: GLint,
+    pub binding: GLint,
+    pub active_uniforms: GLint,
+}
+
+/// A varying captured by transform feedback, in capture order
+#[derive(Clone)]
+pub struct FeedbackVaryingInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub size: GLint,
+}
+
+/// Everything the driver reports about a program's interface after linking
+#[derive(Clone, Default)]
+pub struct ProgramReflection {
+    pub uniforms: Vec<UniformInfo>,
+    pub attributes: Vec<AttributeInfo>,
+    pub uniform_blocks: Vec<UniformBlockInfo>,
+    pub feedback_varyings: Vec<FeedbackVaryingInfo>,
+}
+
+impl ProgramReflection {
+    /// Queries the active uniforms, attributes and uniform blocks of a linked program
+    pub fn query(program: GLuint) -> Self {
+        Self {
+            uniforms: query_uniforms(program),
+            attributes: query_attributes(program),
+            uniform_blocks: query_uniform_blocks(program),
+            feedback_varyings: query_feedback_varyings(program),
+        }
+    }
+
+    /// The number of bytes written per vertex when capturing in interleaved mode
+    pub fn feedback_stride(&self) -> usize {
+        self.feedback_varyings
+            .iter()
+            .map(|varying| glsl_type_size(varying.gl_type) * varying.size.max(0) as usize)
+            .sum()
+    }
+
+    /// Looks up a uniform by name. Array uniforms can be found with or without the "[0]" suffix.
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms
+            .iter()
+            .find(|uniform| uniform.name == name || base_name(&uniform.name) == name)
+    }
+
+    pub fn uniform_block(&self, name: &str) -> Option<&UniformBlockInfo> {
+        self.uniform_blocks.iter().find(|block| block.name == name)
+    }
+}
+
+/// Writes the type and name of a reflected variable, with the length of arrays
+fn write_declaration(
+    f: &mut fmt::Formatter<'_>,
+    gl_type: GLenum,
+    name: &str,
+    size: GLint,
+) -> fmt::Result {
+    write!(f, "{} {}", glsl_type_name(gl_type), base_name(name))?;
+    if size > 1 {
+        write!(f, "[{}]", size)?;
+    }
+    Ok(())
+}
+
+/// One line per uniform, attribute, uniform block and captured varying
+impl fmt::Display for ProgramReflection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for uniform in &self.uniforms {
+            write!(f, "uniform ")?;
+            write_declaration(f, uniform.gl_type, &uniform.name, uniform.size)?;
+            match self
+                .uniform_blocks
+                .iter()
+                .find(|block| block.index as GLint == uniform.block_index)
+            {
+                Some(block) => writeln!(f, " in {} at offset {}", block.name, uniform.offset)?,
+                None => writeln!(f, " at location {}", uniform.location)?,
+            }
+        }
+        for attribute in &self.attributes {
+            write!(f, "in ")?;
+            write_declaration(f, attribute.gl_type, &attribute.name, attribute.size)?;
+            writeln!(f, " at location {}", attribute.location)?;
+        }
+        for block in &self.uniform_blocks {
+            writeln!(
+                f,
+                "block {}: {} bytes, {} uniforms, binding {}",
+                block.name, block.data_size, block.active_uniforms, block.binding
+            )?;
+        }
+        for varying in &self.feedback_varyings {
+            write!(f, "captured ")?;
+            write_declaration(f, varying.gl_type, &varying.name, varying.size)?;
+            writeln!(f)?;
+        }
+        if !self.feedback_varyings.is_empty() {
+            writeln!(f, "{} bytes captured per vertex", self.feedback_stride())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl ProgramReflection {
+    /// Works out what a driver would report for a program made of these stages from their
+    /// source, for when there is no driver (see RecordingBackend). Uniforms and attributes that
+    /// no function mentions are inactive, locations are handed out in declaration order and
+    /// uniform blocks are laid out as std140. Fails with a link log if a captured varying is
+    /// not an output of the last vertex processing stage.
+    pub fn from_interfaces(
+        stages: &[StageInterface],
+        feedback_varyings: &[String],
+    ) -> Result<Self, String> {
+        let mut uniforms: Vec<UniformInfo> = Vec::new();
+        let mut uniform_blocks: Vec<UniformBlockInfo> = Vec::new();
+        let mut next_location = 0;
+        for stage in stages {
+            for uniform in stage.uniforms.iter().filter(|uniform| uniform.referenced) {
+                if uniforms
+                    .iter()
+                    .any(|known| base_name(&known.name) == uniform.name)
+                {
+                    continue;
+                }
+                let size = array_length(uniform);
+                uniforms.push(UniformInfo {
+                    name: reported_name(uniform),
+                    gl_type: glsl_type_from_name(&uniform.type_name).unwrap_or(0),
+                    size,
+                    location: next_location,
+                    block_index: -1,
+                    offset: -1,
+                });
+                // Array elements take consecutive locations
+                next_location += size;
+            }
+
+            for block in &stage.uniform_blocks {
+                if uniform_blocks
+                    .iter()
+                    .any(|known| known.name == block.block_name)
+                {
+                    continue;
+                }
+                let index = uniform_blocks.len() as GLuint;
+                // Blocks with members of unknown types, such as structs, get no layout
+                let layout: Option<Vec<(usize, usize)>> =
+                    block.members.iter().map(std140_size).collect();
+                let mut offset: usize = 0;
+                for (position, member) in block.members.iter().enumerate() {
+                    let member_offset = match &layout {
+                        Some(layout) => {
+                            let (size, alignment) = layout[position];
+                            offset = offset.next_multiple_of(alignment);
+                            let member_offset = offset as GLint;
+                            offset += size;
+                            member_offset
+                        }
+                        None => -1,
+                    };
+                    // Members of blocks with an instance name are qualified by the block name
+                    let name = match block.instance_name {
+                        Some(_) => format!("{}.{}", block.block_name, reported_name(member)),
+                        None => reported_name(member),
+                    };
+                    uniforms.push(UniformInfo {
+                        name,
+                        gl_type: glsl_type_from_name(&member.type_name).unwrap_or(0),
+                        size: array_length(member),
+                        location: -1,
+                        block_index: index as GLint,
+                        offset: member_offset,
+                    });
+                }
+                uniform_blocks.push(UniformBlockInfo {
+                    name: block.block_name.clone(),
+                    index,
+                    data_size: offset.next_multiple_of(16) as GLint,
+                    binding: 0,
+                    active_uniforms: block.members.len() as GLint,
+                });
+            }
+        }
+
+        let mut attributes: Vec<AttributeInfo> = Vec::new();
+        let vertex_inputs = stages
+            .iter()
+            .filter(|stage| stage.stage == ShaderStage::Vertex)
+            .flat_map(|stage| stage.inputs.iter())
+            .filter(|input| input.referenced);
+        // Inputs without a layout location get the lowest one nobody else declares
+        let mut taken: Vec<GLint> = vertex_inputs
+            .clone()
+            .filter_map(|input| input.location.map(|location| location as GLint))
+            .collect();
+        for input in vertex_inputs {
+            let location = match input.location {
+                Some(location) => location as GLint,
+                None => {
+                    let location = (0..).find(|free| !taken.contains(free)).unwrap_or(0);
+                    taken.push(location);
+                    location
+                }
+            };
+            attributes.push(AttributeInfo {
+                name: reported_name(input),
+                gl_type: glsl_type_from_name(&input.type_name).unwrap_or(0),
+                size: array_length(input),
+                location,
+            });
+        }
+
+        let mut captured = Vec::with_capacity(feedback_varyings.len());
+        if let Some(last) = stages
+            .iter()
+            .rfind(|stage| stage.stage != ShaderStage::Fragment)
+        {
+            for name in feedback_varyings {
+                if name == "gl_Position" {
+                    captured.push(FeedbackVaryingInfo {
+                        name: name.clone(),
+                        gl_type: gl::FLOAT_VEC4,
+                        size: 1,
+                    });
+                    continue;
+                }
+                let Some(output) = last.outputs.iter().find(|output| output.name == *name) else {
+                    return Err(format!(
+                        "transform feedback varying {} is not an output of the {} stage",
+                        name,
+                        last.stage.name()
+                    ));
+                };
+                captured.push(FeedbackVaryingInfo {
+                    name: reported_name(output),
+                    gl_type: glsl_type_from_name(&output.type_name).unwrap_or(0),
+                    size: array_length(output),
+                });
+            }
+        }
+
+        Ok(Self {
+            uniforms,
+            attributes,
+            uniform_blocks,
+            feedback_varyings: captured,
+        })
+    }
+}
+
+/// The element count of a variable, 1 if it is not an array or its size is not a literal
+#[cfg(test)]
+fn array_length(variable: &Variable) -> GLint {
+    variable
+        .array_size
+        .as_deref()
+        .map_or(1, |size| size.parse().unwrap_or(1))
+}
+
+/// Drivers report arrays by their first element
+#[cfg(test)]
+fn reported_name(variable: &Variable) -> String {
+    match variable.array_size {
+        Some(_) => format!("{}[0]", variable.name),
+        None => variable.name.clone(),
+    }
+}
+
+/// The size and alignment of a block member under std140, None for types without a known layout
+#[cfg(test)]
+fn std140_size(variable: &Variable) -> Option<(usize, usize)> {
+    let gl_type = glsl_type_from_name(&variable.type_name)?;
+    let (columns, rows) = match gl_type {
+        gl::FLOAT_MAT2 => (2, 2),
+        gl::FLOAT_MAT2x3 => (2, 3),
+        gl::FLOAT_MAT2x4 => (2, 4),
+        gl::FLOAT_MAT3x2 => (3, 2),
+        gl::FLOAT_MAT3 => (3, 3),
+        gl::FLOAT_MAT3x4 => (3, 4),
+        gl::FLOAT_MAT4x2 => (4, 2),
+        gl::FLOAT_MAT4x3 => (4, 3),
+        gl::FLOAT_MAT4 => (4, 4),
+        // Samplers cannot be block members
+        _ => match glsl_type_size(gl_type) / 4 {
+            0 => return None,
+            rows => (1, rows),
+        },
+    };
+    // Matrix columns are padded to a vec4, a vec3 is aligned like a vec4
+    let (size, alignment) = match (columns, rows) {
+        (1, 1) => (4, 4),
+        (1, 2) => (8, 8),
+        (1, rows) => (rows * 4, 16),
+        (columns, _) => (columns * 16, 16),
+    };
+    match variable.array_size {
+        // Each element is padded to a vec4
+        Some(_) => Some((
+            size.next_multiple_of(16) * array_length(variable).max(0) as usize,
+            16,
+        )),
+        None => Some((size, alignment)),
+    }
+}
+
+/// Strips the "[0]" suffix the driver appends to the names of array variables
+pub fn base_name(name: &str) -> &str {
+    name.strip_suffix("[0]").unwrap_or(name)
+}
+
+fn get_program_int(program: GLuint, pname: GLenum) -> GLint {
+    let mut value: GLint = 0;
+    unsafe { gl::GetProgramiv(program, pname, &mut value as *mut GLint) };
+    value
+}
+
+/// Converts a name buffer filled in by the driver into a String
+fn name_from_buffer(buffer: &[u8], length: GLsizei) -> String {
+    String::from_utf8_lossy(&buffer[..length.max(0) as usize]).into_owned()
+}
+
+fn query_uniforms(program: GLuint) -> Vec<UniformInfo> {
+    let count = get_program_int(program, gl::ACTIVE_UNIFORMS);
+    let max_length = get_program_int(program, gl::ACTIVE_UNIFORM_MAX_LENGTH).max(1);
+
+    let mut uniforms = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count as GLuint {
+        let mut name_buffer = vec![0u8; max_length as usize];
+        let mut length: GLsizei = 0;
+        let mut size: GLint = 0;
+        let mut gl_type: GLenum = 0;
+        let mut block_index: GLint = -1;
+        let mut offset: GLint = -1;
+        let location = unsafe {
+            gl::GetActiveUniform(
+                program,
+                index,
+                max_length,
+                &mut length as *mut GLsizei,
+                &mut size as *mut GLint,
+                &mut gl_type as *mut GLenum,
+                name_buffer.as_mut_ptr() as *mut GLchar,
+            );
+            gl::GetActiveUniformsiv(
+                program,
+                1,
+                &index as *const GLuint,
+                gl::UNIFORM_BLOCK_INDEX,
+                &mut block_index as *mut GLint,
+            );
+            gl::GetActiveUniformsiv(
+                program,
+                1,
+                &index as *const GLuint,
+                gl::UNIFORM_OFFSET,
+                &mut offset as *mut GLint,
+            );
+
+            // The name buffer is null terminated by the driver
+            gl::GetUniformLocation(program, name_buffer.as_ptr() as *const GLchar)
+        };
+
+        uniforms.push(UniformInfo {
+            name: name_from_buffer(&name_buffer, length),
+            gl_type,
+            size,
+            location,
+            block_index,
+            offset,
+        });
+    }
+
+    uniforms
+}
+
+fn query_attributes(program: GLuint) -> Vec<AttributeInfo> {
+    let count = get_program_int(program, gl::ACTIVE_ATTRIBUTES);
+    let max_length = get_program_int(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH).max(1);
+
+    let mut attributes = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count as GLuint {
+        let mut name_buffer = vec![0u8; max_length as usize];
+        let mut length: GLsizei = 0;
+        let mut size: GLint = 0;
+        let mut gl_type: GLenum = 0;
+        let location = unsafe {
+            gl::GetActiveAttrib(
+                program,
+                index,
+                max_length,
+                &mut length as *mut GLsizei,
+                &mut size as *mut GLint,
+                &mut gl_type as *mut GLenum,
+                name_buffer.as_mut_ptr() as *mut GLchar,
+            );
+            gl::GetAttribLocation(program, name_buffer.as_ptr() as *const GLchar)
+        };
+
+        attributes.push(AttributeInfo {
+            name: name_from_buffer(&name_buffer, length),
+            gl_type,
+            size,
+            location,
+        });
+    }
+
+    attributes
+}
+
+fn query_uniform_blocks(program: GLuint) -> Vec<UniformBlockInfo> {
+    let count = get_program_int(program, gl::ACTIVE_UNIFORM_BLOCKS);
+
+    let mut blocks = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count as GLuint {
+        let get_block_int = |pname: GLenum| -> GLint {
+            let mut value: GLint = 0;
+            unsafe { gl::GetActiveUniformBlockiv(program, index, pname, &mut value as *mut GLint) };
+            value
+        };
+
+        let name_length = get_block_int(gl::UNIFORM_BLOCK_NAME_LENGTH).max(1);
+        let mut name_buffer = vec![0u8; name_length as usize];
+        let mut length: GLsizei = 0;
+        unsafe {
+            gl::GetActiveUniformBlockName(
+                program,
+                index,
+                name_length,
+                &mut length as *mut GLsizei,
+                name_buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+
+        blocks.push(UniformBlockInfo {
+            name: name_from_buffer(&name_buffer, length),
+            index,
+            data_size: get_block_int(gl::UNIFORM_BLOCK_DATA_SIZE),
+            binding: get_block_int(gl::UNIFORM_BLOCK_BINDING),
+            active_uniforms: get_block_int(gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS),
+        });
+    }
+
+    blocks
+}
+
+fn query_feedback_varyings(program: GLuint) -> Vec<FeedbackVaryingInfo> {
+    let count = get_program_int(program, gl::TRANSFORM_FEEDBACK_VARYINGS);
+    let max_length = get_program_int(program, gl::TRANSFORM_FEEDBACK_VARYING_MAX_LENGTH).max(1);
+
+    let mut varyings = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count as GLuint {
+        let mut name_buffer = vec![0u8; max_length as usize];
+        let mut length: GLsizei = 0;
+        let mut size: GLsizei = 0;
+        let mut gl_type: GLenum = 0;
+        unsafe {
+            gl::GetTransformFeedbackVarying(
+                program,
+                index,
+                max_length,
+                &mut length as *mut GLsizei,
+                &mut size as *mut GLsizei,
+                &mut gl_type as *mut GLenum,
+                name_buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+
+        varyings.push(FeedbackVaryingInfo {
+            name: name_from_buffer(&name_buffer, length),
+            gl_type,
+            size,
+        });
+    }
+
+    varyings
+}
+
+/// Returns the size in bytes of a value of the given type when tightly packed
+pub fn glsl_type_size(gl_type: GLenum) -> usize {
+    let components = match gl_type {
+        gl::FLOAT | gl::INT | gl::UNSIGNED_INT | gl::BOOL => 1,
+        gl::FLOAT_VEC2 | gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 | gl::BOOL_VEC2 => 2,
+        gl::FLOAT_VEC3 | gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 | gl::BOOL_VEC3 => 3,
+        gl::FLOAT_VEC4 | gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 | gl::BOOL_VEC4 => 4,
+        gl::FLOAT_MAT2 => 4,
+        gl::FLOAT_MAT2x3 | gl::FLOAT_MAT3x2 => 6,
+        gl::FLOAT_MAT2x4 | gl::FLOAT_MAT4x2 => 8,
+        gl::FLOAT_MAT3 => 9,
+        gl::FLOAT_MAT3x4 | gl::FLOAT_MAT4x3 => 12,
+        gl::FLOAT_MAT4 => 16,
+        _ => 0,
+    };
+    components * 4
+}
+
+/// The type enums reflection reports with their GLSL spelling
+const GLSL_TYPES: [(GLenum, &str); 33] = [
+    (gl::FLOAT, "float"),
+    (gl::FLOAT_VEC2, "vec2"),
+    (gl::FLOAT_VEC3, "vec3"),
+    (gl::FLOAT_VEC4, "vec4"),
+    (gl::INT, "int"),
+    (gl::INT_VEC2, "ivec2"),
+    (gl::INT_VEC3, "ivec3"),
+    (gl::INT_VEC4, "ivec4"),
+    (gl::UNSIGNED_INT, "uint"),
+    (gl::UNSIGNED_INT_VEC2, "uvec2"),
+    (gl::UNSIGNED_INT_VEC3, "uvec3"),
+    (gl::UNSIGNED_INT_VEC4, "uvec4"),
+    (gl::BOOL, "bool"),
+    (gl::BOOL_VEC2, "bvec2"),
+    (gl::BOOL_VEC3, "bvec3"),
+    (gl::BOOL_VEC4, "bvec4"),
+    (gl::FLOAT_MAT2, "mat2"),
+    (gl::FLOAT_MAT3, "mat3"),
+    (gl::FLOAT_MAT4, "mat4"),
+    (gl::FLOAT_MAT2x3, "mat2x3"),
+    (gl::FLOAT_MAT2x4, "mat2x4"),
+    (gl::FLOAT_MAT3x2, "mat3x2"),
+    (gl::FLOAT_MAT3x4, "mat3x4"),
+    (gl::FLOAT_MAT4x2, "mat4x2"),
+    (gl::FLOAT_MAT4x3, "mat4x3"),
+    (gl::SAMPLER_1D, "sampler1D"),
+    (gl::SAMPLER_2D, "sampler2D"),
+    (gl::SAMPLER_3D, "sampler3D"),
+    (gl::SAMPLER_CUBE, "samplerCube"),
+    (gl::SAMPLER_2D_SHADOW, "sampler2DShadow"),
+    (gl::SAMPLER_2D_ARRAY, "sampler2DArray"),
+    (gl::INT_SAMPLER_2D, "isampler2D"),
+    (gl::UNSIGNED_INT_SAMPLER_2D, "usampler2D"),
+];
+
+/// Returns the GLSL spelling of a type enum reported by reflection, e.g. "vec3" for FLOAT_VEC3
+pub fn glsl_type_name(gl_type: GLenum) -> &'static str {
+    GLSL_TYPES
+        .iter()
+        .find(|(known, _)| *known == gl_type)
+        .map_or("unknown", |(_, name)| name)
+}
+
+/// The type enum for a GLSL type name, e.g. FLOAT_VEC3 for "vec3", or None for types
+/// reflection cannot report such as structs
+#[cfg(test)]
+pub fn glsl_type_from_name(name: &str) -> Option<GLenum> {
+    GLSL_TYPES
+        .iter()
+        .find(|(_, known)| *known == name)
+        .map(|(gl_type, _)| *gl_type)
+}
+
+/// Returns the number of scalar components and the component type of an attribute type.
+/// Matrix types report the components of a single column.
+pub fn attribute_components(gl_type: GLenum) -> Option<(GLint, GLenum)> {
+    match gl_type {
+        gl::FLOAT => Some((1, gl::FLOAT)),
+        gl::FLOAT_VEC2 => Some((2, gl::FLOAT)),
+        gl::FLOAT_VEC3 | gl::FLOAT_MAT3 => Some((3, gl::FLOAT)),
+        gl::FLOAT_VEC4 | gl::FLOAT_MAT4 => Some((4, gl::FLOAT)),
+        gl::INT => Some((1, gl::INT)),
+        gl::INT_VEC2 => Some((2, gl::INT)),
+        gl::INT_VEC3 => Some((3, gl::INT)),
+        gl::INT_VEC4 => Some((4, gl::INT)),
+        gl::UNSIGNED_INT => Some((1, gl::UNSIGNED_INT)),
+        gl::UNSIGNED_INT_VEC2 => Some((2, gl::UNSIGNED_INT)),
+        gl::UNSIGNED_INT_VEC3 => Some((3, gl::UNSIGNED_INT)),
+        gl::UNSIGNED_INT_VEC4 => Some((4, gl::UNSIGNED_INT)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glsl::lint_stage;
+
+    #[test]
+    fn interfaces_are_reflected_like_a_driver_would() {
+        let (vertex, _) = lint_stage(ShaderStage::Vertex, "vertex", include_str!("shader.vs"));
+        let (fragment, _) = lint_stage(
+            ShaderStage::Fragment,
+            "fragment",
+            "#version 330 core
+out vec4 color;
+uniform vec4 tints[3];
+uniform float unused;
+void main()
+{
+    color = tints[2];
+}
+",
+        );
+        let reflection = ProgramReflection::from_interfaces(&[vertex, fragment], &[]).unwrap();
+
+        let names: Vec<&str> = reflection
+            .uniforms
+            .iter()
+            .map(|u| u.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "model",
+                "view",
+                "projection",
+                "cameraPosition",
+                "lightPosition",
+                "lightColor",
+                "tints[0]",
+            ]
+        );
+        let tints = reflection.uniform("tints").unwrap();
+        assert_eq!((tints.location, tints.size), (1, 3));
+        assert_eq!(tints.gl_type, gl::FLOAT_VEC4);
+
+        // The std140 layout FrameData is written with
+        let offsets: Vec<GLint> = reflection.uniforms[1..6].iter().map(|u| u.offset).collect();
+        assert_eq!(offsets, [0, 64, 128, 144, 160]);
+        assert_eq!(
+            reflection.uniform_block("FrameData").unwrap().data_size,
+            176
+        );
+
+        let locations: Vec<(&str, GLint)> = reflection
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.name.as_str(), attribute.location))
+            .collect();
+        assert_eq!(locations, [("aPos", 0), ("aTexCoord", 1)]);
+
+        let text = reflection.to_string();
+        for line in [
+            "uniform vec4 tints[3] at location 1",
+            "uniform vec3 lightColor in FrameData at offset 160",
+            "in vec2 aTexCoord at location 1",
+            "block FrameData: 176 bytes, 5 uniforms, binding 0",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} missing from\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,595 @@
+//! Mesh simplification by edge collapse with quadric error metrics (Garland and Heckbert).
+//! Each position collects the planes of the faces around it, and the edge whose collapse
+//! moves the surface least is collapsed first. Collapses move one end onto the other, so kept
+//! vertices keep their attributes. Borders and seams, where texture coordinates or normals are
+//! split, are kept in place: vertices on them only slide along them, and corners where they
+//! meet or turn sharply never move.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use crate::{
+    mesh::Mesh,
+    normals::position_key,
+    vector::{Vector3, calc_cross_product},
+};
+
+/// How much more moving off a border or seam costs than moving off a face
+const BOUNDARY_WEIGHT: f64 = 10.0;
+
+/// Cosine of the turn, 45 degrees, above which a vertex on a border or seam is a corner
+const CORNER_COSINE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// The sum of squared distances to a set of planes, as a symmetric 4x4 matrix, and the total
+/// area of the planes so errors can be given as distances
+#[derive(Clone, Copy, Default)]
+struct Quadric {
+    /// a00 a01 a02 a03 a11 a12 a13 a22 a23 a33
+    terms: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+    /// The plane through point with the unit normal, scaled by weight
+    fn from_plane(normal: &Vector3, point: &Vector3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -(a * point.x as f64 + b * point.y as f64 + c * point.z as f64);
+        let terms = [
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ]
+        .map(|term| term * weight);
+        Self { terms, weight }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (term, other) in self.terms.iter_mut().zip(other.terms) {
+            *term += other;
+        }
+        self.weight += other.weight;
+    }
+
+    /// The root mean squared distance of the point to the planes
+    fn error(&self, point: &Vector3) -> f64 {
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+        let q = &self.terms;
+        let sum = q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+        if self.weight > 0.0 {
+            (sum.max(0.0) / self.weight).sqrt()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What a position may do
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// Free to move onto any neighbour
+    Interior,
+    /// On one border or seam line, and may only slide along it
+    Boundary,
+    /// A corner of borders or seams, where one turns sharply or several meet, or part of a
+    /// non-manifold edge
+    Locked,
+}
+
+/// A candidate collapse of position from onto position to
+struct Collapse {
+    error: f64,
+    from: u32,
+    to: u32,
+    /// Versions of both positions when the error was computed, to drop stale entries
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Reversed, so the heap pops the smallest error first. Ties go to the lowest positions so
+    /// the result does not depend on hash order.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .error
+            .total_cmp(&self.error)
+            .then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// The vertices at the ends of an edge, the one at the lower position first, and the triangle
+/// using it
+type EdgeUse = (u32, u32, usize);
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+struct Simplifier<'a> {
+    mesh: &'a Mesh,
+    /// Position of each vertex
+    position_of: Vec<u32>,
+    points: Vec<Vector3>,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    /// Triangles around each position, possibly including dead ones
+    around: Vec<Vec<u32>>,
+    quadrics: Vec<Quadric>,
+    kinds: Vec<Kind>,
+    /// Position pairs on a border or seam
+    boundary_edges: HashSet<(u32, u32)>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(mesh: &'a Mesh) -> Self {
+        let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut points = Vec::new();
+        let position_of: Vec<u32> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                *ids.entry(position_key(&vertex.position))
+                    .or_insert_with(|| {
+                        points.push(vertex.position);
+                        points.len() as u32 - 1
+                    })
+            })
+            .collect();
+        let position_count = points.len();
+
+        // Triangles whose corners share a position have nothing to keep
+        let triangles: Vec<[u32; 3]> = mesh
+            .triangles()
+            .filter(|triangle| {
+                let [a, b, c] = triangle.map(|index| position_of[index as usize]);
+                a != b && b != c && a != c
+            })
+            .collect();
+
+        let mut around = vec![Vec::new(); position_count];
+        let mut quadrics = vec![Quadric::default(); position_count];
+        // How the triangles use each edge between two positions
+        let mut edges: HashMap<(u32, u32), Vec<EdgeUse>> = HashMap::new();
+        for (triangle, corners) in triangles.iter().enumerate() {
+            let [a, b, c] = corners.map(|index| position_of[index as usize]);
+            let [pa, pb, pc] = [a, b, c].map(|position| points[position as usize]);
+            let face = calc_cross_product(&(pb - pa), &(pc - pa));
+            let area = face.magnitude();
+            let plane = if area > 0.0 {
+                Quadric::from_plane(&Vector3::calc_normalized_vector(&face), &pa, area as f64)
+            } else {
+                Quadric::default()
+            };
+            for corner in 0..3 {
+                let (from, to) = (corners[corner], corners[(corner + 1) % 3]);
+                let position = position_of[from as usize];
+                around[position as usize].push(triangle as u32);
+                quadrics[position as usize].add(&plane);
+                let key = edge_key(position, position_of[to as usize]);
+                let (low, high) = if position < position_of[to as usize] {
+                    (from, to)
+                } else {
+                    (to, from)
+                };
+                edges.entry(key).or_default().push((low, high, triangle));
+            }
+        }
+
+        let mut kinds = vec![Kind::Interior; position_count];
+        let mut boundary_neighbours = vec![Vec::new(); position_count];
+        let mut boundary_edges = HashSet::new();
+        // Sorted, so the constraint planes are summed in the same order every time
+        let mut edges: Vec<_> = edges.into_iter().collect();
+        edges.sort_unstable_by_key(|(key, _)| *key);
+        for ((a, b), uses) in &edges {
+            let boundary = match uses.as_slice() {
+                [_] => true,
+                [(a0, b0, _), (a1, b1, _)] => a0 != a1 || b0 != b1,
+                _ => {
+                    kinds[*a as usize] = Kind::Locked;
+                    kinds[*b as usize] = Kind::Locked;
+                    continue;
+                }
+            };
+            if !boundary {
+                continue;
+            }
+            boundary_edges.insert((*a, *b));
+            boundary_neighbours[*a as usize].push(*b);
+            boundary_neighbours[*b as usize].push(*a);
+
+            // A plane through the edge, perpendicular to its face, keeps the edge in place
+            let (pa, pb) = (points[*a as usize], points[*b as usize]);
+            let corners =
+                triangles[uses[0].2].map(|index| points[position_of[index as usize] as usize]);
+            let face = calc_cross_product(&(corners[1] - corners[0]), &(corners[2] - corners[0]));
+            let across = calc_cross_product(&(pb - pa), &face);
+            if across.magnitude() > 0.0 {
+                let length = (pb - pa).magnitude() as f64;
+                let mut plane = Quadric::from_plane(
+                    &Vector3::calc_normalized_vector(&across),
+                    &pa,
+                    BOUNDARY_WEIGHT * length * length,
+                );
+                // The constraint adds error without counting as surface
+                plane.weight = 0.0;
+                quadrics[*a as usize].add(&plane);
+                quadrics[*b as usize].add(&plane);
+            }
+        }
+        for (position, neighbours) in boundary_neighbours.iter().enumerate() {
+            if kinds[position] != Kind::Interior {
+                continue;
+            }
+            kinds[position] = match neighbours.as_slice() {
+                [] => Kind::Interior,
+                [before, after] => {
+                    let point = points[position];
+                    let incoming = point - points[*before as usize];
+                    let outgoing = points[*after as usize] - point;
+                    let lengths = incoming.magnitude() * outgoing.magnitude();
+                    if Vector3::dot_product(&incoming, &outgoing) >= CORNER_COSINE * lengths {
+                        Kind::Boundary
+                    } else {
+                        Kind::Locked
+                    }
+                }
+                _ => Kind::Locked,
+            };
+        }
+
+        Self {
+            mesh,
+            position_of,
+            alive: vec![true; triangles.len()],
+            triangles,
+            around,
+            quadrics,
+            kinds,
+            boundary_edges,
+            versions: vec![0; position_count],
+            removed: vec![false; position_count],
+            points,
+        }
+    }
+
+    fn positions(&self, triangle: u32) -> [u32; 3] {
+        self.triangles[triangle as usize].map(|index| self.position_of[index as usize])
+    }
+
+    fn live_around(&self, position: u32) -> impl Iterator<Item = u32> + '_ {
+        self.around[position as usize]
+            .iter()
+            .copied()
+            .filter(|triangle| self.alive[*triangle as usize])
+    }
+
+    fn neighbours(&self, position: u32) -> HashSet<u32> {
+        self.live_around(position)
+            .flat_map(|triangle| self.positions(triangle))
+            .filter(|other| *other != position)
+            .collect()
+    }
+
+    /// The collapse of from onto to, if the kinds of the two positions allow it
+    fn candidate(&self, from: u32, to: u32) -> Option<Collapse> {
+        match self.kinds[from as usize] {
+            Kind::Locked => return None,
+            Kind::Boundary if !self.boundary_edges.contains(&edge_key(from, to)) => return None,
+            _ => {}
+        }
+        let mut quadric = self.quadrics[from as usize];
+        quadric.add(&self.quadrics[to as usize]);
+        Some(Collapse {
+            error: quadric.error(&self.points[to as usize]),
+            from,
+            to,
+            versions: (self.versions[from as usize], self.versions[to as usize]),
+        })
+    }
+
+    /// The vertex of to that each vertex of from becomes, or None if the collapse would break
+    /// the mesh: tear a seam, fold a triangle over or join surfaces that only touch
+    fn check(&self, from: u32, to: u32) -> Option<HashMap<u32, u32>> {
+        // Positions around both ends may only be shared through the triangles on the edge
+        let shared_triangles = self
+            .live_around(from)
+            .filter(|triangle| self.positions(*triangle).contains(&to))
+            .count();
+        let common = self
+            .neighbours(from)
+            .intersection(&self.neighbours(to))
+            .count();
+        if shared_triangles == 0 || common != shared_triangles {
+            return None;
+        }
+
+        let mut mapping: HashMap<u32, u32> = HashMap::new();
+        for triangle in self.live_around(from) {
+            let corners = self.triangles[triangle as usize];
+            let positions = self.positions(triangle);
+            let Some(to_vertex) = positions
+                .iter()
+                .position(|position| *position == to)
+                .map(|corner| corners[corner])
+            else {
+                continue;
+            };
+            let from_vertex = corners[positions.iter().position(|position| *position == from)?];
+            if *mapping.entry(from_vertex).or_insert(to_vertex) != to_vertex {
+                return None;
+            }
+        }
+
+        let target = &self.points[to as usize];
+        for triangle in self.live_around(from) {
+            let corners = self.triangles[triangle as usize];
+            let positions = self.positions(triangle);
+            if positions.contains(&to) {
+                continue;
+            }
+            // Every vertex of from must have a place to go
+            for (corner, position) in positions.iter().enumerate() {
+                if *position == from && !mapping.contains_key(&corners[corner]) {
+                    return None;
+                }
+            }
+            let before = positions.map(|position| self.points[position as usize]);
+            let after = positions.map(|position| {
+                if position == from {
+                    *target
+                } else {
+                    self.points[position as usize]
+                }
+            });
+            let normal = |p: &[Vector3; 3]| calc_cross_product(&(p[1] - p[0]), &(p[2] - p[0]));
+            let (old_normal, new_normal) = (normal(&before), normal(&after));
+            let lengths = old_normal.magnitude() * new_normal.magnitude();
+            if lengths <= 0.0 || Vector3::dot_product(&old_normal, &new_normal) < 0.2 * lengths {
+                return None;
+            }
+        }
+        Some(mapping)
+    }
+
+    fn collapse(&mut self, from: u32, to: u32, mapping: &HashMap<u32, u32>) -> usize {
+        let mut removed_triangles = 0;
+        let around = std::mem::take(&mut self.around[from as usize]);
+        for triangle in &around {
+            if !self.alive[*triangle as usize] {
+                continue;
+            }
+            if self.positions(*triangle).contains(&to) {
+                self.alive[*triangle as usize] = false;
+                removed_triangles += 1;
+                continue;
+            }
+            for index in &mut self.triangles[*triangle as usize] {
+                if let Some(vertex) = mapping.get(index) {
+                    *index = *vertex;
+                }
+            }
+        }
+        self.around[to as usize].extend(around);
+        self.around[to as usize].retain(|triangle| self.alive[*triangle as usize]);
+
+        let quadric = self.quadrics[from as usize];
+        self.quadrics[to as usize].add(&quadric);
+        let moved: Vec<(u32, u32)> = self
+            .boundary_edges
+            .iter()
+            .filter(|(a, b)| *a == from || *b == from)
+            .copied()
+            .collect();
+        for (a, b) in moved {
+            self.boundary_edges.remove(&(a, b));
+            let other = if a == from { b } else { a };
+            if other != to {
+                self.boundary_edges.insert(edge_key(other, to));
+            }
+        }
+        self.removed[from as usize] = true;
+        self.versions[to as usize] += 1;
+        removed_triangles
+    }
+
+    fn run(&mut self, target_triangles: usize, max_error: f32) -> f32 {
+        let mut heap = BinaryHeap::new();
+        for triangle in 0..self.triangles.len() as u32 {
+            let [a, b, c] = self.positions(triangle);
+            for (from, to) in [(a, b), (b, a), (b, c), (c, b), (c, a), (a, c)] {
+                heap.extend(self.candidate(from, to));
+            }
+        }
+
+        let mut live = self.triangles.len();
+        let mut reached: f64 = 0.0;
+        while live > target_triangles {
+            let Some(collapse) = heap.pop() else {
+                break;
+            };
+            let (from, to) = (collapse.from, collapse.to);
+            if self.removed[from as usize]
+                || self.removed[to as usize]
+                || collapse.versions != (self.versions[from as usize], self.versions[to as usize])
+            {
+                continue;
+            }
+            if collapse.error > max_error as f64 {
+                break;
+            }
+            let Some(mapping) = self.check(from, to) else {
+                continue;
+            };
+            live -= self.collapse(from, to, &mapping);
+            reached = reached.max(collapse.error);
+
+            for neighbour in self.neighbours(to) {
+                heap.extend(self.candidate(to, neighbour));
+                heap.extend(self.candidate(neighbour, to));
+            }
+        }
+        reached as f32
+    }
+
+    /// The live triangles with only the vertices they use
+    fn finish(&self) -> Mesh {
+        let mut remap: Vec<Option<u32>> = vec![None; self.mesh.vertices.len()];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (triangle, corners) in self.triangles.iter().enumerate() {
+            if !self.alive[triangle] {
+                continue;
+            }
+            for index in corners {
+                indices.push(*remap[*index as usize].get_or_insert_with(|| {
+                    vertices.push(self.mesh.vertices[*index as usize]);
+                    vertices.len() as u32 - 1
+                }));
+            }
+        }
+        Mesh::new(vertices, indices)
+    }
+}
+
+/// Collapses edges until the mesh has at most target_triangles triangles or the next collapse
+/// would move the surface by more than max_error. Returns the simplified mesh and the largest
+/// error of a collapse made, as a distance in the mesh's units.
+pub fn simplify(mesh: &Mesh, target_triangles: usize, max_error: f32) -> (Mesh, f32) {
+    let mut simplifier = Simplifier::new(mesh);
+    let error = simplifier.run(target_triangles, max_error);
+    (simplifier.finish(), error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mesh::MeshVertex, primitives};
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| {
+                let position = |index: u32| mesh.vertices[index as usize].position;
+                let (a, b, c) = (position(a), position(b), position(c));
+                0.5 * calc_cross_product(&(b - a), &(c - a)).magnitude()
+            })
+            .sum()
+    }
+
+    /// Collapses only ever move vertices onto others, so every kept vertex is an original one
+    fn assert_vertices_kept(simplified: &Mesh, original: &Mesh) {
+        for vertex in &simplified.vertices {
+            assert!(
+                original
+                    .vertices
+                    .iter()
+                    .any(|other| vertex.approx_eq(other, 0.0))
+            );
+        }
+    }
+
+    fn has_position(mesh: &Mesh, x: f32, y: f32, z: f32) -> bool {
+        mesh.vertices
+            .iter()
+            .any(|vertex| position_key(&vertex.position) == position_key(&Vector3::new(x, y, z)))
+    }
+
+    #[test]
+    fn a_flat_plane_shrinks_to_its_corners() {
+        let plane = primitives::plane(2.0, 1.0, 16, 16);
+        let (half, error) = simplify(&plane, 100, f32::MAX);
+        assert!(half.triangle_count() <= 100);
+        assert!(half.triangle_count() > 0);
+        assert!(error < 1e-4);
+
+        // The corners are locked, so two triangles are all that can be left
+        let (coarsest, _) = simplify(&plane, 0, f32::MAX);
+        assert_eq!(coarsest.triangle_count(), 2);
+        for (x, z) in [(-1.0, -0.5), (1.0, -0.5), (-1.0, 0.5), (1.0, 0.5)] {
+            assert!(has_position(&coarsest, x, 0.0, z));
+        }
+
+        // Borders only slide along themselves, so the outline and the area stay
+        for mesh in [&half, &coarsest] {
+            assert_vertices_kept(mesh, &plane);
+            assert!((area(mesh) - 2.0).abs() < 1e-4);
+            let bounds = mesh.bounds().unwrap();
+            assert_eq!((bounds.min.x, bounds.max.x), (-1.0, 1.0));
+            assert_eq!((bounds.min.z, bounds.max.z), (-0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn cube_seams_stay_on_the_edges() {
+        let cube = primitives::cube(1.0, 4);
+        assert_eq!(cube.triangle_count(), 192);
+        let (simplified, error) = simplify(&cube, 0, f32::MAX);
+        // Every face ends up as two triangles between its locked corners
+        assert_eq!(simplified.triangle_count(), 12);
+        assert!(error < 1e-4);
+        assert_vertices_kept(&simplified, &cube);
+        assert!((area(&simplified) - 6.0).abs() < 1e-4);
+
+        // No triangle leaves its face by cutting across an edge
+        for [a, b, c] in simplified.triangles() {
+            let normal = simplified.vertices[a as usize].normal;
+            for index in [b, c] {
+                let other = simplified.vertices[index as usize].normal;
+                assert!(Vector3::dot_product(&normal, &other) > 0.999);
+            }
+        }
+    }
+
+    #[test]
+    fn texture_seams_are_not_torn() {
+        let sphere = primitives::uv_sphere(1.0, 24, 12);
+        let (simplified, _) = simplify(&sphere, sphere.triangle_count() / 4, f32::MAX);
+        assert!(simplified.triangle_count() <= sphere.triangle_count() / 4);
+        assert_vertices_kept(&simplified, &sphere);
+
+        // Every vertex on one side of the seam still has its twin on the other side
+        let on_seam = |vertex: &MeshVertex, u: f32| vertex.uv.x == u && vertex.uv.y > 0.0;
+        let seam_count = simplified
+            .vertices
+            .iter()
+            .filter(|vertex| on_seam(vertex, 0.0))
+            .inspect(|vertex| {
+                assert!(simplified.vertices.iter().any(|twin| on_seam(twin, 1.0)
+                    && position_key(&twin.position) == position_key(&vertex.position)));
+            })
+            .count();
+        assert!(seam_count > 0);
+    }
+}
@@ -0,0 +1,363 @@
+//! STL files, the triangle soup format of CAD tools and 3D printers, in ASCII and binary form.
+//! STL has no shared vertices, so corners that match exactly are welded after reading. Normals
+//! are flat and recomputed from the winding, falling back to the stored facet normal for
+//! triangles without area.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mesh::{Aabb, Mesh, MeshVertex},
+    vector::{Vector2, Vector3, calc_cross_product},
+};
+
+#[derive(Debug)]
+pub enum StlError {
+    Io { path: PathBuf, error: io::Error },
+    Invalid(String),
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            StlError::Invalid(message) => write!(f, "Invalid STL: {}", message),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StlFormat {
+    Ascii,
+    Binary,
+}
+
+pub struct StlModel {
+    /// The solid name of an ASCII file or the header text of a binary one
+    pub name: String,
+    pub mesh: Mesh,
+    pub bounds: Option<Aabb>,
+}
+
+pub fn load_stl(path: &Path) -> Result<StlModel, StlError> {
+    let bytes = fs::read(path).map_err(|error| StlError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    parse_stl(&bytes)
+}
+
+/// Parses either form. Binary files are recognised by their size, since many of them start
+/// with "solid" too.
+pub fn parse_stl(bytes: &[u8]) -> Result<StlModel, StlError> {
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        if bytes.len() == 84 + count * 50 {
+            return Ok(parse_binary(bytes, count));
+        }
+    }
+    if bytes.trim_ascii_start().starts_with(b"solid") {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| StlError::Invalid("ASCII STL is not valid UTF-8".to_string()))?;
+        return parse_ascii(text);
+    }
+    Err(StlError::Invalid(
+        "neither ASCII nor a binary file of the size its triangle count needs".to_string(),
+    ))
+}
+
+fn read_vector(bytes: &[u8]) -> Vector3 {
+    let component = |offset: usize| {
+        f32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    };
+    Vector3::new(component(0), component(4), component(8))
+}
+
+/// Turns triangles of (facet normal, corners) into a welded mesh with flat normals
+fn build_model(name: String, triangles: &[(Vector3, [Vector3; 3])]) -> StlModel {
+    let mut vertices = Vec::with_capacity(triangles.len() * 3);
+    for (stored_normal, corners) in triangles {
+        let face = calc_cross_product(&(corners[1] - corners[0]), &(corners[2] - corners[0]));
+        let normal = if face.magnitude() > 0.0 {
+            Vector3::calc_normalized_vector(&face)
+        } else if stored_normal.magnitude() > 0.0 {
+            Vector3::calc_normalized_vector(stored_normal)
+        } else {
+            Vector3::default()
+        };
+        for corner in corners {
+            vertices.push(MeshVertex::new(*corner, normal, Vector2::default()));
+        }
+    }
+    let mesh = Mesh::from_triangle_list(vertices).weld(0.0);
+    let bounds = mesh.bounds();
+    StlModel { name, mesh, bounds }
+}
+
+fn parse_binary(bytes: &[u8], count: usize) -> StlModel {
+    let name = String::from_utf8_lossy(&bytes[..80])
+        .trim_end_matches(['\0', ' '])
+        .to_string();
+    let triangles: Vec<_> = (0..count)
+        .map(|triangle| {
+            // Normal, three corners and a 2 byte attribute nobody agrees on
+            let record = &bytes[84 + triangle * 50..84 + (triangle + 1) * 50];
+            (
+                read_vector(&record[0..12]),
+                [
+                    read_vector(&record[12..24]),
+                    read_vector(&record[24..36]),
+                    read_vector(&record[36..48]),
+                ],
+            )
+        })
+        .collect();
+    build_model(name, &triangles)
+}
+
+fn parse_vector<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Vector3> {
+    let mut component = || tokens.next()?.parse::<f32>().ok();
+    Some(Vector3::new(component()?, component()?, component()?))
+}
+
+fn parse_ascii(text: &str) -> Result<StlModel, StlError> {
+    let mut name = None;
+    let mut triangles = Vec::new();
+    let mut normal = Vector3::default();
+    let mut corners: Vec<Vector3> = Vec::new();
+    let mut in_facet = false;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let error =
+            |message: String| StlError::Invalid(format!("line {}: {}", line_index + 1, message));
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+
+        match keyword {
+            "solid" => {
+                if name.is_none() {
+                    name = Some(line.trim()["solid".len()..].trim().to_string());
+                }
+            }
+            "facet" => {
+                if in_facet {
+                    return Err(error("facet inside a facet".to_string()));
+                }
+                if tokens.next() != Some("normal") {
+                    return Err(error("facet without normal".to_string()));
+                }
+                normal = parse_vector(&mut tokens)
+                    .ok_or_else(|| error("facet normal needs three numbers".to_string()))?;
+                corners.clear();
+                in_facet = true;
+            }
+            "vertex" => {
+                if !in_facet {
+                    return Err(error("vertex outside a facet".to_string()));
+                }
+                corners.push(
+                    parse_vector(&mut tokens)
+                        .ok_or_else(|| error("vertex needs three numbers".to_string()))?,
+                );
+            }
+            "endfacet" => {
+                if !in_facet || corners.len() != 3 {
+                    return Err(error(format!(
+                        "facet has {} vertices instead of 3",
+                        corners.len()
+                    )));
+                }
+                triangles.push((normal, [corners[0], corners[1], corners[2]]));
+                in_facet = false;
+            }
+            "outer" | "endloop" | "endsolid" => {}
+            _ => return Err(error(format!("unexpected \"{}\"", keyword))),
+        }
+    }
+    if in_facet {
+        return Err(StlError::Invalid("file ends inside a facet".to_string()));
+    }
+    Ok(build_model(name.unwrap_or_default(), &triangles))
+}
+
+fn face_normal(mesh: &Mesh, [a, b, c]: [u32; 3]) -> Vector3 {
+    let position = |index: u32| mesh.vertices[index as usize].position;
+    let face = calc_cross_product(&(position(b) - position(a)), &(position(c) - position(a)));
+    if face.magnitude() > 0.0 {
+        Vector3::calc_normalized_vector(&face)
+    } else {
+        face
+    }
+}
+
+/// Writes every triangle of the mesh with its flat normal. Binary headers cannot start with
+/// "solid", so such names are prefixed.
+pub fn write_stl(
+    writer: &mut impl Write,
+    mesh: &Mesh,
+    name: &str,
+    format: StlFormat,
+) -> io::Result<()> {
+    match format {
+        StlFormat::Ascii => {
+            writeln!(writer, "solid {}", name)?;
+            for triangle in mesh.triangles() {
+                let normal = face_normal(mesh, triangle);
+                writeln!(
+                    writer,
+                    "  facet normal {:e} {:e} {:e}",
+                    normal.x, normal.y, normal.z
+                )?;
+                writeln!(writer, "    outer loop")?;
+                for index in triangle {
+                    let position = &mesh.vertices[index as usize].position;
+                    writeln!(
+                        writer,
+                        "      vertex {:e} {:e} {:e}",
+                        position.x, position.y, position.z
+                    )?;
+                }
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+            writeln!(writer, "endsolid {}", name)
+        }
+        StlFormat::Binary => {
+            let mut header = [0u8; 80];
+            let name = if name.starts_with("solid") {
+                format!("binary {}", name)
+            } else {
+                name.to_string()
+            };
+            let length = name.len().min(80);
+            header[..length].copy_from_slice(&name.as_bytes()[..length]);
+            writer.write_all(&header)?;
+            writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+            for triangle in mesh.triangles() {
+                let normal = face_normal(mesh, triangle);
+                let positions = triangle.map(|index| mesh.vertices[index as usize].position);
+                for vector in [normal, positions[0], positions[1], positions[2]] {
+                    for component in [vector.x, vector.y, vector.z] {
+                        writer.write_all(&component.to_le_bytes())?;
+                    }
+                }
+                writer.write_all(&[0, 0])?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn save_stl(path: &Path, mesh: &Mesh, name: &str, format: StlFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_stl(&mut writer, mesh, name, format)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives;
+
+    fn triangle_positions(mesh: &Mesh) -> Vec<[f32; 9]> {
+        mesh.triangles()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|index| mesh.vertices[index as usize].position);
+                [a.x, a.y, a.z, b.x, b.y, b.z, c.x, c.y, c.z]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_keep_every_triangle() {
+        // Sphere coordinates are not round numbers, which checks that ASCII loses no precision
+        let mesh = primitives::uv_sphere(1.5, 12, 8);
+        for format in [StlFormat::Ascii, StlFormat::Binary] {
+            let mut bytes = Vec::new();
+            write_stl(&mut bytes, &mesh, "sphere", format).unwrap();
+            let model = parse_stl(&bytes).unwrap();
+
+            assert_eq!(model.name, "sphere", "{:?}", format);
+            assert_eq!(
+                model.mesh.triangle_count(),
+                mesh.triangle_count(),
+                "{:?}",
+                format
+            );
+            assert_eq!(
+                triangle_positions(&model.mesh),
+                triangle_positions(&mesh),
+                "{:?}",
+                format
+            );
+            let bounds = model.bounds.unwrap();
+            let expected = mesh.bounds().unwrap();
+            for (actual, expected) in [(bounds.min, expected.min), (bounds.max, expected.max)] {
+                assert_eq!(
+                    (actual.x, actual.y, actual.z),
+                    (expected.x, expected.y, expected.z)
+                );
+            }
+            // Exactly matching corners are shared again
+            assert!(model.mesh.vertices.len() < model.mesh.triangle_count() * 3);
+        }
+    }
+
+    #[test]
+    fn binary_files_may_start_with_solid() {
+        let mut bytes = vec![0u8; 80];
+        bytes[..10].copy_from_slice(b"solid fake");
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        for component in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            bytes.extend_from_slice(&component.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0, 0]);
+
+        let model = parse_stl(&bytes).unwrap();
+        assert_eq!(model.name, "solid fake");
+        assert_eq!(model.mesh.triangle_count(), 1);
+        let normal = model.mesh.vertices[0].normal;
+        assert_eq!((normal.x, normal.y, normal.z), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn truncated_files_are_invalid() {
+        let mesh = primitives::cube(1.0, 1);
+
+        let mut binary = Vec::new();
+        write_stl(&mut binary, &mesh, "cube", StlFormat::Binary).unwrap();
+        binary.truncate(binary.len() - 10);
+        assert!(matches!(parse_stl(&binary), Err(StlError::Invalid(_))));
+
+        let mut ascii = Vec::new();
+        write_stl(&mut ascii, &mesh, "cube", StlFormat::Ascii).unwrap();
+        let text = String::from_utf8(ascii).unwrap();
+        let cut = text.rfind("endfacet").unwrap();
+        assert!(matches!(
+            parse_stl(&text.as_bytes()[..cut]),
+            Err(StlError::Invalid(message)) if message == "file ends inside a facet"
+        ));
+
+        let short_facet = "solid broken\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\n\
+                           vertex 1 0 0\nendloop\nendfacet\nendsolid broken\n";
+        assert!(matches!(
+            parse_stl(short_facet.as_bytes()),
+            Err(StlError::Invalid(message)) if message == "line 7: facet has 2 vertices instead of 3"
+        ));
+    }
+}
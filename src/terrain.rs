@@ -0,0 +1,456 @@
+//! Terrain from a grid of heights, read from a grayscale image or made by a noise function.
+//! The grid is centred on the origin in the xz plane with samples spacing apart; image rows run
+//! from -z to +z so the image looks upright from above. Meshes are built in square chunks that
+//! can be culled on their own, each with a skirt hanging from its border to hide cracks.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, ImageError, ImageReader};
+
+use crate::{
+    mesh::{Aabb, Mesh, MeshVertex},
+    normals::{fallback_tangent, orthogonalize},
+    vector::{Vector2, Vector3, calc_cross_product},
+};
+
+#[derive(Debug)]
+pub enum TerrainError {
+    Io { path: PathBuf, error: io::Error },
+    Decode { path: PathBuf, error: ImageError },
+    Invalid(String),
+}
+
+impl fmt::Display for TerrainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerrainError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            TerrainError::Decode { path, error } => {
+                write!(f, "Failed to decode {}: {}", path.display(), error)
+            }
+            TerrainError::Invalid(message) => write!(f, "Invalid heightmap: {}", message),
+        }
+    }
+}
+
+/// Heights on a regular grid of columns x rows samples
+#[derive(Clone)]
+pub struct Heightmap {
+    columns: usize,
+    rows: usize,
+    spacing: f32,
+    /// Row by row, in world units
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Samples height(x, z) at every grid point, with x and z in world units
+    pub fn from_fn(
+        columns: usize,
+        rows: usize,
+        spacing: f32,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Self {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "a heightmap needs at least 2 x 2 samples"
+        );
+        let mut heightmap = Self {
+            columns,
+            rows,
+            spacing,
+            heights: Vec::with_capacity(columns * rows),
+        };
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, z) = heightmap.grid_position(column, row);
+                heightmap.heights.push(height(x, z));
+            }
+        }
+        heightmap
+    }
+
+    /// One sample per pixel, black at height 0 and white at height_scale. 16-bit images keep
+    /// their precision.
+    pub fn from_image(
+        image: &DynamicImage,
+        spacing: f32,
+        height_scale: f32,
+    ) -> Result<Self, TerrainError> {
+        let luma = image.to_luma16();
+        let (columns, rows) = (luma.width() as usize, luma.height() as usize);
+        if columns < 2 || rows < 2 {
+            return Err(TerrainError::Invalid(format!(
+                "{} x {} pixels is too small, at least 2 x 2 are needed",
+                columns, rows
+            )));
+        }
+        let heights = luma
+            .pixels()
+            .map(|pixel| pixel.0[0] as f32 / u16::MAX as f32 * height_scale)
+            .collect();
+        Ok(Self {
+            columns,
+            rows,
+            spacing,
+            heights,
+        })
+    }
+
+    /// Decodes a grayscale image file, see from_image
+    pub fn load(path: &Path, spacing: f32, height_scale: f32) -> Result<Self, TerrainError> {
+        let image = ImageReader::open(path)
+            .map_err(|error| TerrainError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?
+            .decode()
+            .map_err(|error| TerrainError::Decode {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        Self::from_image(&image, spacing, height_scale)
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Distance between neighbouring samples
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    /// Extent of the grid along x and z
+    pub fn size(&self) -> Vector2 {
+        Vector2::new(
+            (self.columns - 1) as f32 * self.spacing,
+            (self.rows - 1) as f32 * self.spacing,
+        )
+    }
+
+    /// The height of a sample, with coordinates outside the grid clamped to its edge
+    pub fn sample(&self, column: isize, row: isize) -> f32 {
+        let column = column.clamp(0, self.columns as isize - 1) as usize;
+        let row = row.clamp(0, self.rows as isize - 1) as usize;
+        self.heights[row * self.columns + column]
+    }
+
+    /// The x and z of a sample
+    fn grid_position(&self, column: usize, row: usize) -> (f32, f32) {
+        let size = self.size();
+        (
+            column as f32 * self.spacing - 0.5 * size.x,
+            row as f32 * self.spacing - 0.5 * size.y,
+        )
+    }
+
+    /// Where a sample is in the world
+    pub fn sample_position(&self, column: usize, row: usize) -> Vector3 {
+        let (x, z) = self.grid_position(column, row);
+        Vector3::new(x, self.heights[row * self.columns + column], z)
+    }
+
+    /// The normal at a sample from central differences, one-sided along the edges
+    pub fn sample_normal(&self, column: usize, row: usize) -> Vector3 {
+        let (column, row) = (column as isize, row as isize);
+        let (left, right) = (
+            column.max(1) - 1,
+            (column + 1).min(self.columns as isize - 1),
+        );
+        let (back, front) = (row.max(1) - 1, (row + 1).min(self.rows as isize - 1));
+        let slope_x = (self.sample(right, row) - self.sample(left, row))
+            / ((right - left) as f32 * self.spacing);
+        let slope_z = (self.sample(column, front) - self.sample(column, back))
+            / ((front - back) as f32 * self.spacing);
+        Vector3::calc_normalized_vector(&Vector3::new(-slope_x, 1.0, -slope_z))
+    }
+
+    /// The ground height below a point, interpolated bilinearly between the four surrounding
+    /// samples. Points beyond the grid get the height of its nearest edge. The mesh splits each
+    /// cell into two flat triangles, so between samples it can differ slightly from this.
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        let size = self.size();
+        let grid_x = ((x + 0.5 * size.x) / self.spacing).clamp(0.0, (self.columns - 1) as f32);
+        let grid_z = ((z + 0.5 * size.y) / self.spacing).clamp(0.0, (self.rows - 1) as f32);
+        // The last cell also covers the far edge
+        let column = (grid_x as usize).min(self.columns - 2);
+        let row = (grid_z as usize).min(self.rows - 2);
+        let (tx, tz) = (grid_x - column as f32, grid_z - row as f32);
+
+        let (column, row) = (column as isize, row as isize);
+        let back = lerp(self.sample(column, row), self.sample(column + 1, row), tx);
+        let front = lerp(
+            self.sample(column, row + 1),
+            self.sample(column + 1, row + 1),
+            tx,
+        );
+        lerp(back, front, tz)
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> MeshVertex {
+        let position = self.sample_position(column, row);
+        let normal = self.sample_normal(column, row);
+        // u follows +x and v follows -z, like primitives::plane
+        let uv = Vector2::new(
+            column as f32 / (self.columns - 1) as f32,
+            1.0 - row as f32 / (self.rows - 1) as f32,
+        );
+        let tangent = orthogonalize(
+            &normal,
+            &Vector3::new(1.0, 0.0, 0.0),
+            &Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap_or_else(|| fallback_tangent(&normal));
+        MeshVertex {
+            position,
+            normal,
+            uv,
+            tangent,
+        }
+    }
+
+    /// Splits the grid into chunks of up to chunk_size x chunk_size cells, the last ones along
+    /// each axis taking what is left. Neighbouring chunks share their border samples, and
+    /// normals come from the whole grid, so the seams between them are invisible. A skirt
+    /// skirt_depth deep is added below each chunk's border; 0 leaves it out.
+    pub fn build_chunks(&self, chunk_size: usize, skirt_depth: f32) -> Vec<TerrainChunk> {
+        let chunk_size = chunk_size.max(1);
+        let mut chunks = Vec::new();
+        for (chunk_row, first_row) in (0..self.rows - 1).step_by(chunk_size).enumerate() {
+            for (chunk_column, first_column) in
+                (0..self.columns - 1).step_by(chunk_size).enumerate()
+            {
+                let last_column = (first_column + chunk_size).min(self.columns - 1);
+                let last_row = (first_row + chunk_size).min(self.rows - 1);
+                let mesh = self.chunk_mesh(
+                    (first_column, last_column),
+                    (first_row, last_row),
+                    skirt_depth,
+                );
+                let bounds = mesh.bounds().expect("chunks always have vertices");
+                chunks.push(TerrainChunk {
+                    mesh,
+                    bounds,
+                    column: chunk_column,
+                    row: chunk_row,
+                });
+            }
+        }
+        chunks
+    }
+
+    fn chunk_mesh(
+        &self,
+        (first_column, last_column): (usize, usize),
+        (first_row, last_row): (usize, usize),
+        skirt_depth: f32,
+    ) -> Mesh {
+        let stride = (last_column - first_column + 1) as u32;
+        let mut vertices = Vec::new();
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                vertices.push(self.vertex(column, row));
+            }
+        }
+
+        // Counter-clockwise seen from above
+        let mut indices = Vec::new();
+        for row in 0..(last_row - first_row) as u32 {
+            for column in 0..stride - 1 {
+                let corner = row * stride + column;
+                let (right, front) = (corner + 1, corner + stride);
+                indices.extend_from_slice(&[corner, front, front + 1]);
+                indices.extend_from_slice(&[corner, front + 1, right]);
+            }
+        }
+
+        if skirt_depth > 0.0 {
+            let rows = (last_row - first_row) as u32 + 1;
+            let index = |column: u32, row: u32| row * stride + column;
+            // Around the border once, every corner visited a single time
+            let mut border: Vec<u32> = (0..stride).map(|column| index(column, 0)).collect();
+            border.extend((1..rows).map(|row| index(stride - 1, row)));
+            border.extend((0..stride - 1).rev().map(|column| index(column, rows - 1)));
+            border.extend((1..rows - 1).rev().map(|row| index(0, row)));
+
+            let center = 0.5 * (vertices[0].position + vertices[vertices.len() - 1].position);
+            let first_skirt = vertices.len() as u32;
+            for (position, top) in border.iter().enumerate() {
+                let mut vertex = vertices[*top as usize];
+                vertex.position.y -= skirt_depth;
+                vertices.push(vertex);
+
+                let next = (position + 1) % border.len();
+                let (a, b) = (*top, border[next]);
+                let (lower_a, lower_b) = (first_skirt + position as u32, first_skirt + next as u32);
+                // Face away from the chunk so back-face culling keeps the side seen from outside
+                let midpoint =
+                    0.5 * (vertices[a as usize].position + vertices[b as usize].position);
+                let outward = midpoint - center;
+                let face = calc_cross_product(
+                    &(vertices[b as usize].position - vertices[a as usize].position),
+                    &Vector3::new(0.0, -1.0, 0.0),
+                );
+                if Vector3::dot_product(&face, &outward) >= 0.0 {
+                    indices.extend_from_slice(&[a, b, lower_b, a, lower_b, lower_a]);
+                } else {
+                    indices.extend_from_slice(&[a, lower_a, lower_b, a, lower_b, b]);
+                }
+            }
+        }
+        Mesh::new(vertices, indices)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// A piece of terrain and the box around it, skirt included, for culling
+pub struct TerrainChunk {
+    pub mesh: Mesh,
+    pub bounds: Aabb,
+    /// Position among the chunks, counted along x and z
+    pub column: usize,
+    pub row: usize,
+}
+
+/// A well mixed 32-bit hash of a lattice point
+fn hash(x: i32, z: i32, seed: u32) -> u32 {
+    let mut hash = seed ^ (x as u32).wrapping_mul(0x27d4eb2d) ^ (z as u32).wrapping_mul(0x165667b1);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^ (hash >> 16)
+}
+
+/// Value noise in [0, 1]: random values on the integer lattice, blended smoothly in between
+pub fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    let (cell_x, cell_z) = (x.floor(), z.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - cell_x), smooth(z - cell_z));
+    let (cell_x, cell_z) = (cell_x as i32, cell_z as i32);
+    let corner = |dx: i32, dz: i32| {
+        hash(cell_x.wrapping_add(dx), cell_z.wrapping_add(dz), seed) as f32 / u32::MAX as f32
+    };
+    lerp(
+        lerp(corner(0, 0), corner(1, 0), tx),
+        lerp(corner(0, 1), corner(1, 1), tx),
+        tz,
+    )
+}
+
+/// Octaves of value noise, each at twice the frequency and half the amplitude of the one
+/// before, normalized to [0, 1]
+pub fn fractal_noise(x: f32, z: f32, seed: u32, octaves: u32) -> f32 {
+    let (mut sum, mut amplitude, mut total, mut frequency) = (0.0, 1.0, 0.0, 1.0);
+    for octave in 0..octaves.max(1) {
+        sum += amplitude * value_noise(x * frequency, z * frequency, seed.wrapping_add(octave));
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 5 x 4 samples two units apart, x from -4 to 4 and z from -3 to 3
+    fn bowl() -> Heightmap {
+        Heightmap::from_fn(5, 4, 2.0, |x, z| x * x + z)
+    }
+
+    #[test]
+    fn heights_are_bilinear_between_samples() {
+        let heightmap = bowl();
+        assert_eq!(heightmap.size().x, 8.0);
+        assert_eq!(heightmap.size().y, 6.0);
+        for (column, row) in [(0, 0), (2, 1), (4, 3)] {
+            let position = heightmap.sample_position(column, row);
+            assert_eq!(heightmap.height(position.x, position.z), position.y);
+            assert_eq!(position.y, position.x * position.x + position.z);
+        }
+        // Halfway along a row, then in the middle of the cell between -1, 3, 1 and 5
+        assert_eq!(heightmap.height(1.0, -1.0), 1.0);
+        assert_eq!(heightmap.height(1.0, 0.0), 2.0);
+        assert_eq!(heightmap.height(-3.0, 2.0), 12.0);
+
+        // Beyond the grid the nearest edge holds
+        assert_eq!(heightmap.height(100.0, 3.0), 19.0);
+        assert_eq!(heightmap.height(100.0, 100.0), 19.0);
+        assert_eq!(heightmap.height(-100.0, -1.0), 15.0);
+        assert_eq!(heightmap.sample(-1, 10), heightmap.sample(0, 3));
+    }
+
+    #[test]
+    fn chunks_cover_the_grid_and_share_their_borders() {
+        let heightmap = Heightmap::from_fn(11, 8, 1.0, |x, z| (x * 0.3).sin() + z * 0.1);
+        // 10 x 7 cells in chunks of 4: 3 along x, the last 2 wide, and 2 along z
+        let chunks = heightmap.build_chunks(4, 0.0);
+        let places: Vec<(usize, usize)> = chunks
+            .iter()
+            .map(|chunk| (chunk.column, chunk.row))
+            .collect();
+        assert_eq!(places, [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        let triangles: Vec<usize> = chunks
+            .iter()
+            .map(|chunk| chunk.mesh.triangle_count())
+            .collect();
+        assert_eq!(triangles, [32, 32, 16, 24, 24, 12]);
+        assert_eq!(triangles.iter().sum::<usize>(), 2 * 10 * 7);
+
+        // The column between the first two chunks is in both, identical down to the normals
+        let border_x = heightmap.sample_position(4, 0).x;
+        let on_border = |chunk: &TerrainChunk| -> Vec<MeshVertex> {
+            chunk
+                .mesh
+                .vertices
+                .iter()
+                .filter(|vertex| vertex.position.x == border_x)
+                .copied()
+                .collect()
+        };
+        let (left, right) = (on_border(&chunks[0]), on_border(&chunks[1]));
+        assert_eq!(left.len(), 5);
+        assert_eq!(right.len(), 5);
+        for (a, b) in left.iter().zip(&right) {
+            assert!(a.approx_eq(b, 0.0));
+        }
+    }
+
+    #[test]
+    fn chunk_bounds_include_the_skirt() {
+        let heightmap = bowl();
+        let plain = heightmap.build_chunks(2, 0.0);
+        let skirted = heightmap.build_chunks(2, 1.5);
+        assert_eq!(plain.len(), skirted.len());
+        for (plain, skirted) in plain.iter().zip(&skirted) {
+            assert_eq!(skirted.bounds.min.y, plain.bounds.min.y - 1.5);
+            assert_eq!(skirted.bounds.max.y, plain.bounds.max.y);
+            assert_eq!(
+                (skirted.bounds.min.x, skirted.bounds.max.z),
+                (plain.bounds.min.x, plain.bounds.max.z)
+            );
+            // A lowered copy of each of the 3 x 3 or 3 x 2 border samples, and a wall quad below
+            // each border edge
+            let border = skirted.mesh.vertices.len() - plain.mesh.vertices.len();
+            assert!(border == 8 || border == 6);
+            assert_eq!(
+                skirted.mesh.triangle_count(),
+                plain.mesh.triangle_count() + 2 * border
+            );
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use glad_gl::gl::{self, GLenum, GLuint};
+
+use crate::{
+    gl_backend::with_backend,
+    gl_objects::{Buffer, BufferTarget, BufferUsage, Pod},
+};
+
+/// How captured varyings are laid out in the feedback buffers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformFeedbackMode {
+    /// All varyings are written one after another into a single buffer
+    Interleaved,
+    /// Each varying is written into its own buffer, bound at the varying's index
+    Separate,
+}
+
+impl TransformFeedbackMode {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            TransformFeedbackMode::Interleaved => gl::INTERLEAVED_ATTRIBS,
+            TransformFeedbackMode::Separate => gl::SEPARATE_ATTRIBS,
+        }
+    }
+}
+
+/// The primitive type recorded during capture. Draw calls inside the capture must use a
+/// compatible mode (e.g. TRIANGLES, TRIANGLE_STRIP or TRIANGLE_FAN for Triangles).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeedbackPrimitive {
+    Points,
+    Lines,
+    Triangles,
+}
+
+impl FeedbackPrimitive {
+    /// The primitive to capture from draws with mode, e.g. Lines for LINE_STRIP
+    pub fn for_draw_mode(mode: GLenum) -> Option<Self> {
+        match mode {
+            gl::POINTS => Some(FeedbackPrimitive::Points),
+            gl::LINES | gl::LINE_STRIP | gl::LINE_LOOP => Some(FeedbackPrimitive::Lines),
+            gl::TRIANGLES | gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => {
+                Some(FeedbackPrimitive::Triangles)
+            }
+            _ => None,
+        }
+    }
+
+    fn gl_enum(&self) -> GLenum {
+        match self {
+            FeedbackPrimitive::Points => gl::POINTS,
+            FeedbackPrimitive::Lines => gl::LINES,
+            FeedbackPrimitive::Triangles => gl::TRIANGLES,
+        }
+    }
+}
+
+/// A buffer object that receives captured vertex shader (or geometry shader) output
+pub struct TransformFeedbackBuffer {
+    buffer: Buffer,
+}
+
+impl TransformFeedbackBuffer {
+    /// Allocates a buffer able to hold capacity bytes of captured output
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Buffer::with_size(
+                BufferTarget::TransformFeedback,
+                capacity,
+                BufferUsage::DynamicRead,
+            ),
+        }
+    }
+
+    /// Copies the first count captured values back to the CPU
+    pub fn read<T: Pod + Default>(&self, count: usize) -> Vec<T> {
+        self.buffer.read(0, count)
+    }
+}
+
+/// Records the output of the draw calls issued by draw into the given buffers.
+/// The currently used program must have been linked with transform feedback varyings. Buffers are
+/// bound to consecutive indices starting at 0, one per varying in Separate mode.
+/// When discard_rasterizer is set, nothing reaches the framebuffer, which is what is wanted for
+/// pure GPU computation such as particle updates.
+/// Returns the number of primitives that were written.
+pub fn capture<F: FnOnce()>(
+    buffers: &[&TransformFeedbackBuffer],
+    primitive: FeedbackPrimitive,
+    discard_rasterizer: bool,
+    draw: F,
+) -> u32 {
+    for (index, buffer) in buffers.iter().enumerate() {
+        buffer.buffer.bind_base(index as GLuint);
+    }
+    let query = with_backend(|backend| {
+        if discard_rasterizer {
+            backend.enable(gl::RASTERIZER_DISCARD);
+        }
+        let query = backend.gen_query();
+        backend.begin_query(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, query);
+        backend.begin_transform_feedback(primitive.gl_enum());
+        query
+    });
+
+    draw();
+
+    with_backend(|backend| {
+        backend.end_transform_feedback();
+        backend.end_query(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+        if discard_rasterizer {
+            backend.disable(gl::RASTERIZER_DISCARD);
+        }
+        for index in 0..buffers.len() {
+            backend.bind_buffer_base(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint, 0);
+        }
+
+        // Waits for the GPU to finish the captured draws
+        let primitives_written = backend.query_result(query);
+        backend.delete_query(query);
+        primitives_written
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gl_backend::{RecordingBackend, set_backend},
+        primitives,
+        shader::{ShaderProgram, ShaderStage},
+    };
+
+    const VERTEX: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+out vec3 worldPosition;
+void main()
+{
+    worldPosition = aPos * 2.0;
+    gl_Position = vec4(worldPosition, 1.0);
+}
+";
+
+    #[test]
+    fn capture_counts_the_primitives_drawn() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(recording.clone()));
+
+        let program = ShaderProgram::builder()
+            .stage_source(ShaderStage::Vertex, "capture", VERTEX)
+            .transform_feedback_varyings(
+                &["worldPosition", "gl_Position"],
+                TransformFeedbackMode::Separate,
+            )
+            .build()
+            .unwrap();
+        let mesh = primitives::cube(1.0, 1);
+        let gpu_mesh = mesh.upload();
+        let positions = TransformFeedbackBuffer::new(mesh.triangle_count() * 3 * 12);
+        let clip_positions = TransformFeedbackBuffer::new(mesh.triangle_count() * 3 * 16);
+        assert_eq!(positions.buffer.size(), 12 * 36);
+
+        program.use_program();
+        let written = capture(
+            &[&positions, &clip_positions],
+            FeedbackPrimitive::Triangles,
+            true,
+            || gpu_mesh.draw(),
+        );
+        assert_eq!(written as usize, mesh.triangle_count());
+        assert!(!recording.is_enabled(gl::RASTERIZER_DISCARD));
+
+        // Drawing twice inside one capture counts both draws
+        let written = capture(&[&positions], FeedbackPrimitive::Triangles, false, || {
+            gpu_mesh.draw();
+            gpu_mesh.draw();
+        });
+        assert_eq!(written as usize, 2 * mesh.triangle_count());
+        assert!(recording.errors().is_empty(), "{:?}", recording.errors());
+
+        // Points cannot be captured from a triangle draw
+        let written = capture(&[&positions], FeedbackPrimitive::Points, true, || {
+            gpu_mesh.draw()
+        });
+        assert_eq!(written, 0);
+        assert_eq!(recording.errors().len(), 1);
+    }
+}
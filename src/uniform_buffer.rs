@@ -0,0 +1,341 @@
+use std::{fmt, marker::PhantomData};
+
+use glad_gl::gl::GLuint;
+
+use crate::{
+    capabilities::Capabilities,
+    gl_backend::{allocate_uniform_binding, release_uniform_binding},
+    gl_objects::{Buffer, BufferTarget, BufferUsage},
+    matrix::Matrix4,
+    shader::ShaderProgram,
+    vector::Vector3,
+};
+
+#[derive(Debug)]
+pub enum UniformBufferError {
+    /// Every binding point the context supports is taken
+    OutOfBindingPoints { block: &'static str, limit: usize },
+}
+
+impl fmt::Display for UniformBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniformBufferError::OutOfBindingPoints { block, limit } => write!(
+                f,
+                "No binding point left for uniform block {}, the context supports {}",
+                block, limit
+            ),
+        }
+    }
+}
+
+/// Serializes values into a byte buffer following the std140 layout rules.
+/// Scalars are aligned to 4 bytes, vec2 to 8, vec3 and vec4 to 16. Array elements and matrix
+/// columns are each padded out to 16 bytes.
+pub struct Std140Writer {
+    data: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    /// The offset (in bytes) that the next value would be written at before alignment
+    #[cfg(test)]
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let padded = self.data.len().next_multiple_of(alignment);
+        self.data.resize(padded, 0);
+    }
+
+    fn push_f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[cfg(test)]
+    pub fn write_float(&mut self, value: f32) {
+        self.align(4);
+        self.push_f32(value);
+    }
+
+    /// A vec3 takes 12 bytes but is aligned like a vec4, so a following float can fill the gap
+    pub fn write_vec3(&mut self, value: &Vector3) {
+        self.align(16);
+        self.push_f32(value.x);
+        self.push_f32(value.y);
+        self.push_f32(value.z);
+    }
+
+    /// Matrices are stored as column vectors, each padded to a vec4
+    #[cfg(test)]
+    pub fn write_mat3(&mut self, value: &crate::matrix::Matrix3) {
+        for column in 0..3 {
+            self.align(16);
+            for row in 0..3 {
+                self.push_f32(value.data[row][column]);
+            }
+            self.push_f32(0.0);
+        }
+    }
+
+    pub fn write_mat4(&mut self, value: &Matrix4) {
+        for column in 0..4 {
+            self.align(16);
+            for row in 0..4 {
+                self.push_f32(value.data[row][column]);
+            }
+        }
+    }
+
+    /// Each element of a scalar array has a stride of 16 bytes
+    #[cfg(test)]
+    pub fn write_float_array(&mut self, values: &[f32]) {
+        for value in values {
+            self.align(16);
+            self.push_f32(*value);
+        }
+        self.align(16);
+    }
+
+    /// Returns the block's bytes, padded to a multiple of a vec4 like the driver does
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(16);
+        self.data
+    }
+}
+
+/// A Rust type that mirrors a std140 uniform block declared in GLSL
+pub trait UniformBlock {
+    /// The block name as declared in the shader
+    const NAME: &'static str;
+
+    /// Writes the members in the same order as they are declared in the shader
+    fn write_std140(&self, writer: &mut Std140Writer);
+
+    fn std140_bytes(&self) -> Vec<u8> {
+        let mut writer = Std140Writer::new();
+        self.write_std140(&mut writer);
+        writer.finish()
+    }
+}
+
+/// A uniform buffer object holding one instance of a block, attached to its own binding point
+pub struct UniformBuffer<T: UniformBlock> {
+    buffer: Buffer,
+    binding: GLuint,
+    _block: PhantomData<T>,
+}
+
+impl<T: UniformBlock> UniformBuffer<T> {
+    pub fn new(initial: &T, capabilities: &Capabilities) -> Result<Self, UniformBufferError> {
+        let limit = capabilities.max_uniform_buffer_bindings.max(0) as usize;
+        let binding =
+            allocate_uniform_binding(limit).ok_or(UniformBufferError::OutOfBindingPoints {
+                block: T::NAME,
+                limit,
+            })?;
+
+        // Data is rewritten every frame
+        let buffer = Buffer::with_data(
+            BufferTarget::Uniform,
+            &initial.std140_bytes(),
+            BufferUsage::DynamicDraw,
+        );
+        buffer.set_label(T::NAME);
+        buffer.bind_base(binding);
+
+        Ok(Self {
+            buffer,
+            binding,
+            _block: PhantomData,
+        })
+    }
+
+    /// Uploads new contents for the block
+    pub fn update(&self, value: &T) {
+        let data = value.std140_bytes();
+        assert_eq!(
+            data.len(),
+            self.buffer.size(),
+            "uniform block {} changed size between updates",
+            T::NAME
+        );
+        self.buffer.set_sub_data(0, &data);
+    }
+
+    /// Points the program's block of the same name at this buffer's binding point.
+    /// Also warns if the driver's view of the block size disagrees with the Rust layout.
+    pub fn attach(&self, program: &ShaderProgram) {
+        if let Some(block) = program.reflection().uniform_block(T::NAME)
+            && block.data_size as usize != self.buffer.size()
+        {
+            eprintln!(
+                "Warning: uniform block {} is {} bytes in the shader but {} bytes in Rust",
+                T::NAME,
+                block.data_size,
+                self.buffer.size()
+            );
+        }
+        program.bind_uniform_block(T::NAME, self.binding);
+    }
+}
+
+impl<T: UniformBlock> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        release_uniform_binding(self.binding);
+    }
+}
+
+/// Per-frame data shared by every program that declares the FrameData block
+pub struct FrameData {
+    pub view: Matrix4,
+    pub projection: Matrix4,
+    pub camera_position: Vector3,
+    pub light_position: Vector3,
+    pub light_color: Vector3,
+}
+
+impl UniformBlock for FrameData {
+    const NAME: &'static str = "FrameData";
+
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.write_mat4(&self.view);
+        writer.write_mat4(&self.projection);
+        writer.write_vec3(&self.camera_position);
+        writer.write_vec3(&self.light_position);
+        writer.write_vec3(&self.light_color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gl_backend::{RecordingBackend, set_backend},
+        matrix::Matrix3,
+    };
+
+    #[test]
+    fn running_out_of_binding_points_is_an_error() {
+        let recording = RecordingBackend::new();
+        set_backend(Box::new(recording.clone()));
+
+        let capabilities = Capabilities {
+            max_uniform_buffer_bindings: 0,
+            ..Capabilities::minimum()
+        };
+        let frame_data = FrameData {
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            camera_position: Vector3::new(0.0, 0.0, 0.0),
+            light_position: Vector3::new(0.0, 0.0, 0.0),
+            light_color: Vector3::new(1.0, 1.0, 1.0),
+        };
+        let result = UniformBuffer::new(&frame_data, &capabilities);
+        assert!(matches!(
+            result,
+            Err(UniformBufferError::OutOfBindingPoints {
+                block: "FrameData",
+                limit: 0
+            })
+        ));
+        assert!(recording.live_objects().is_empty());
+
+        // The buffer takes the first binding point of the backend and gives it back when dropped
+        let buffer = UniformBuffer::new(&frame_data, &Capabilities::minimum()).unwrap();
+        assert_eq!(allocate_uniform_binding(36), Some(1));
+        drop(buffer);
+        assert!(recording.live_objects().is_empty());
+        assert_eq!(allocate_uniform_binding(36), Some(0));
+    }
+
+    #[test]
+    fn binding_points_belong_to_the_backend() {
+        set_backend(Box::new(RecordingBackend::new()));
+        assert_eq!(allocate_uniform_binding(2), Some(0));
+        assert_eq!(allocate_uniform_binding(2), Some(1));
+        assert_eq!(allocate_uniform_binding(2), None);
+        release_uniform_binding(0);
+        assert_eq!(allocate_uniform_binding(2), Some(0));
+
+        // A new backend is a new context with every point free
+        set_backend(Box::new(RecordingBackend::new()));
+        assert_eq!(allocate_uniform_binding(2), Some(0));
+    }
+
+    fn frame_data() -> FrameData {
+        FrameData {
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            camera_position: Vector3::new(1.0, 2.0, 3.0),
+            light_position: Vector3::new(4.0, 5.0, 6.0),
+            light_color: Vector3::new(7.0, 8.0, 9.0),
+        }
+    }
+
+    /// The f32 stored at a byte offset
+    fn float_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn frame_data_follows_std140() {
+        let bytes = frame_data().std140_bytes();
+        assert_eq!(bytes.len(), 176);
+        // Matrices are written column by column, so the diagonal is every fifth float
+        assert_eq!(float_at(&bytes, 0), 1.0);
+        assert_eq!(float_at(&bytes, 4), 0.0);
+        assert_eq!(float_at(&bytes, 64 + 20), 1.0);
+        // Each vec3 starts on a 16 byte boundary
+        let vec3_at = |offset| {
+            [
+                float_at(&bytes, offset),
+                float_at(&bytes, offset + 4),
+                float_at(&bytes, offset + 8),
+            ]
+        };
+        assert_eq!(vec3_at(128), [1.0, 2.0, 3.0]);
+        assert_eq!(vec3_at(144), [4.0, 5.0, 6.0]);
+        assert_eq!(vec3_at(160), [7.0, 8.0, 9.0]);
+    }
+
+    #[test]
+    fn scalars_fill_gaps_and_arrays_are_padded() {
+        // A float packs into the fourth component of a vec3
+        let mut writer = Std140Writer::new();
+        writer.write_vec3(&Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(writer.offset(), 12);
+        writer.write_float(4.0);
+        assert_eq!(writer.offset(), 16);
+        let bytes = writer.finish();
+        assert_eq!(float_at(&bytes, 12), 4.0);
+
+        // Every array element takes 16 bytes
+        let mut writer = Std140Writer::new();
+        writer.write_float(0.5);
+        writer.write_float_array(&[1.0, 2.0, 3.0]);
+        assert_eq!(writer.offset(), 64);
+        let bytes = writer.finish();
+        assert_eq!(
+            [16, 32, 48].map(|offset| float_at(&bytes, offset)),
+            [1.0, 2.0, 3.0]
+        );
+
+        // So does every column of a mat3
+        let mut writer = Std140Writer::new();
+        writer.write_mat3(&Matrix3 {
+            data: [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]],
+        });
+        assert_eq!(writer.offset(), 48);
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(
+            [0, 4, 8, 16, 32].map(|offset| float_at(&bytes, offset)),
+            [1.0, 4.0, 7.0, 2.0, 3.0]
+        );
+    }
+}
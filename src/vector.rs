@@ -1,12 +1,19 @@
 use std::ops;
 
+use crate::gl_objects::Pod;
+
 #[derive(Clone, Default, Copy)]
+#[repr(C)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
 }
 
 impl Vector2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
     pub fn from_vector4(v: &Vector4) -> Self {
         Self { x: v.x, y: v.y }
     }
@@ -132,6 +139,7 @@ impl Vector2i {
 }
 
 #[derive(Clone, Default, Copy)]
+#[repr(C)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -139,6 +147,10 @@ pub struct Vector3 {
 }
 
 impl Vector3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
     pub fn from_vector2(v: &Vector2) -> Self {
         Self {
             x: v.x,
@@ -313,6 +325,7 @@ pub fn calc_cross_product(a: &Vector3, b: &Vector3) -> Vector3 {
 }
 
 #[derive(Clone, Default, PartialEq, Copy)]
+#[repr(C)]
 pub struct Vector4 {
     pub x: f32,
     pub y: f32,
@@ -321,6 +334,10 @@ pub struct Vector4 {
 }
 
 impl Vector4 {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
     pub fn from_vector3(v: &Vector3) -> Self {
         Vector4 {
             x: v.x,
@@ -443,3 +460,8 @@ impl Vector2i {
         }
     }
 }
+
+// The vectors are repr(C) structs of f32s, so they can be uploaded to GL directly
+unsafe impl Pod for Vector2 {}
+unsafe impl Pod for Vector3 {}
+unsafe impl Pod for Vector4 {}
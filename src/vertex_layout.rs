@@ -0,0 +1,272 @@
+use glad_gl::gl::{self, GLenum, GLint, GLuint};
+
+use crate::{
+    gl_objects::Pod,
+    shader_reflection::{ProgramReflection, attribute_components},
+};
+
+/// The type of each component of an attribute as it is stored in the buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComponentType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl ComponentType {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            ComponentType::Float => gl::FLOAT,
+            ComponentType::Byte => gl::BYTE,
+            ComponentType::UnsignedByte => gl::UNSIGNED_BYTE,
+            ComponentType::Short => gl::SHORT,
+            ComponentType::UnsignedShort => gl::UNSIGNED_SHORT,
+            ComponentType::Int => gl::INT,
+            ComponentType::UnsignedInt => gl::UNSIGNED_INT,
+        }
+    }
+
+    pub fn from_gl_enum(value: GLenum) -> Option<Self> {
+        match value {
+            gl::FLOAT => Some(ComponentType::Float),
+            gl::BYTE => Some(ComponentType::Byte),
+            gl::UNSIGNED_BYTE => Some(ComponentType::UnsignedByte),
+            gl::SHORT => Some(ComponentType::Short),
+            gl::UNSIGNED_SHORT => Some(ComponentType::UnsignedShort),
+            gl::INT => Some(ComponentType::Int),
+            gl::UNSIGNED_INT => Some(ComponentType::UnsignedInt),
+            _ => None,
+        }
+    }
+
+    /// Size of one component in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::Byte | ComponentType::UnsignedByte => 1,
+            ComponentType::Short | ComponentType::UnsignedShort => 2,
+            ComponentType::Float | ComponentType::Int | ComponentType::UnsignedInt => 4,
+        }
+    }
+}
+
+/// How the shader sees the stored components
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttributeKind {
+    /// Converted to float as is (an integer 255 becomes 255.0)
+    Float,
+    /// Integers are mapped to [0, 1] (unsigned) or [-1, 1] (signed)
+    Normalized,
+    /// Integers stay integers, for int/uint/ivec/uvec shader inputs
+    Integer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub location: GLuint,
+    pub component_type: ComponentType,
+    pub count: GLint,
+    pub kind: AttributeKind,
+    /// Byte offset of the attribute from the start of a vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    /// Size of the whole attribute in bytes
+    pub fn size(&self) -> usize {
+        self.component_type.size() * self.count as usize
+    }
+}
+
+/// Describes how the attributes of a vertex are laid out in a buffer.
+/// Attributes added with attribute() are packed one after another and the stride grows to fit;
+/// attribute_at() places an attribute at an explicit offset, e.g. a field of a Rust struct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self {
+            attributes: Vec::new(),
+            stride: 0,
+        }
+    }
+
+    /// Starts a layout for vertices that are stride bytes apart
+    pub fn with_stride(stride: usize) -> Self {
+        Self {
+            attributes: Vec::new(),
+            stride,
+        }
+    }
+
+    /// Appends an attribute directly after the previous one
+    pub fn attribute(self, location: GLuint, component_type: ComponentType, count: GLint) -> Self {
+        self.packed(location, component_type, count, AttributeKind::Float)
+    }
+
+    /// Appends an integer attribute whose values are normalized to [0, 1] or [-1, 1]
+    pub fn normalized_attribute(
+        self,
+        location: GLuint,
+        component_type: ComponentType,
+        count: GLint,
+    ) -> Self {
+        self.packed(location, component_type, count, AttributeKind::Normalized)
+    }
+
+    fn packed(
+        self,
+        location: GLuint,
+        component_type: ComponentType,
+        count: GLint,
+        kind: AttributeKind,
+    ) -> Self {
+        let offset = self
+            .attributes
+            .iter()
+            .map(|attribute| attribute.offset + attribute.size())
+            .max()
+            .unwrap_or(0);
+        self.attribute_at(location, component_type, count, kind, offset)
+    }
+
+    /// Adds an attribute at an explicit byte offset
+    pub fn attribute_at(
+        mut self,
+        location: GLuint,
+        component_type: ComponentType,
+        count: GLint,
+        kind: AttributeKind,
+        offset: usize,
+    ) -> Self {
+        assert!(
+            (1..=4).contains(&count),
+            "attributes have 1 to 4 components"
+        );
+        assert!(
+            self.attributes.iter().all(|a| a.location != location),
+            "location {} is used twice",
+            location
+        );
+
+        let attribute = VertexAttribute {
+            location,
+            component_type,
+            count,
+            kind,
+            offset,
+        };
+        self.stride = self.stride.max(offset + attribute.size());
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    /// Number of bytes between the starts of consecutive vertices
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn attribute_at_location(&self, location: GLuint) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|a| a.location == location)
+    }
+
+    /// Compares the layout with the vertex inputs of a linked program and describes every
+    /// mismatch. Inputs without an attribute, component count differences and int/float
+    /// disagreements are reported. Attributes the program does not read are fine, since one
+    /// vertex format usually feeds several programs.
+    pub fn validate(&self, reflection: &ProgramReflection) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for input in &reflection.attributes {
+            // Built-ins such as gl_VertexID are not sourced from buffers
+            if input.location < 0 || input.name.starts_with("gl_") {
+                continue;
+            }
+            let Some(attribute) = self.attribute_at_location(input.location as GLuint) else {
+                problems.push(format!(
+                    "shader input {} (location {}) has no attribute in the vertex layout",
+                    input.name, input.location
+                ));
+                continue;
+            };
+            let Some((components, shader_type)) = attribute_components(input.gl_type) else {
+                continue;
+            };
+
+            if components != attribute.count {
+                problems.push(format!(
+                    "shader input {} has {} components but the layout provides {}",
+                    input.name, components, attribute.count
+                ));
+            }
+            let shader_wants_integer = shader_type != gl::FLOAT;
+            let layout_gives_integer = attribute.kind == AttributeKind::Integer;
+            if shader_wants_integer != layout_gives_integer {
+                problems.push(format!(
+                    "shader input {} is {} but the layout provides {} values",
+                    input.name,
+                    if shader_wants_integer {
+                        "an integer"
+                    } else {
+                        "a float"
+                    },
+                    if layout_gives_integer {
+                        "integer"
+                    } else {
+                        "float"
+                    }
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// A vertex struct that knows its own layout
+pub trait Vertex: Pod {
+    fn layout() -> VertexLayout;
+}
+
+/// Implements Vertex for a repr(C) struct by listing its fields with a location, a component
+/// type and a component count, optionally followed by Normalized or Integer:
+///
+/// impl_vertex!(ColoredVertex {
+///     position: 0, Float, 3;
+///     color: 1, UnsignedByte, 4, Normalized;
+/// });
+macro_rules! impl_vertex {
+    ($vertex:ty { $($field:ident : $location:expr, $component:ident, $count:expr $(, $kind:ident)?;)* }) => {
+        impl $crate::vertex_layout::Vertex for $vertex {
+            fn layout() -> $crate::vertex_layout::VertexLayout {
+                $crate::vertex_layout::VertexLayout::with_stride(size_of::<$vertex>())
+                    $(.attribute_at(
+                        $location,
+                        $crate::vertex_layout::ComponentType::$component,
+                        $count,
+                        impl_vertex!(@kind $($kind)?),
+                        std::mem::offset_of!($vertex, $field),
+                    ))*
+            }
+        }
+    };
+    (@kind) => {
+        $crate::vertex_layout::AttributeKind::Float
+    };
+    (@kind $kind:ident) => {
+        $crate::vertex_layout::AttributeKind::$kind
+    };
+}
+
+pub(crate) use impl_vertex;